@@ -0,0 +1,648 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::{
+    Alignment, BlockQuoteKind, CodeBlockKind, CowStr, Event, HeadingLevel, Tag, TagEnd,
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use wasm_bindgen::prelude::*;
+
+use crate::format::is_inline;
+use crate::gfm;
+use crate::headings;
+use crate::options::{HtmlPolicy, MarkdownOptions};
+use crate::plain_text::needs_url;
+use crate::raw_html;
+use crate::render::{self, Spanned};
+use crate::toc;
+
+/// Settings for `render_ansi`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TerminalOptions {
+    /// Column to wrap text at, 0 for no wrapping.
+    pub width: usize,
+    /// Style text with SGR escape sequences. Without them links are
+    /// followed by their URL.
+    pub color: bool,
+    /// Make links clickable with OSC 8 escape sequences, which terminals
+    /// without support ignore. Needs `color`.
+    pub hyperlinks: bool,
+}
+
+impl Default for TerminalOptions {
+    fn default() -> Self {
+        TerminalOptions {
+            width: 80,
+            color: true,
+            hyperlinks: true,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl TerminalOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TerminalOptions {
+        TerminalOptions::default()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Bold,
+    Dim,
+    Italic,
+    Underline,
+    Strikethrough,
+    Code,
+    Link,
+    Heading,
+}
+
+impl Style {
+    fn codes(self) -> &'static str {
+        match self {
+            Style::Bold => "1",
+            Style::Dim => "2",
+            Style::Italic => "3",
+            Style::Underline => "4",
+            Style::Strikethrough => "9",
+            Style::Code => "33",
+            Style::Link => "4;34",
+            Style::Heading => "1;35",
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const LINK_END: &str = "\x1b]8;;\x1b\\";
+
+/// Renders `input` for a terminal: styled, wrapped to `terminal.width`,
+/// with boxed code blocks and drawn tables. Raw HTML is left out unless
+/// `options.html` escapes it.
+pub(crate) fn convert(
+    input: &str,
+    options: &MarkdownOptions,
+    terminal: &TerminalOptions,
+) -> String {
+    let mut events = render::parse(input, options);
+    if options.toc {
+        events = toc::insert(headings::assign_ids(events), options);
+    }
+    let html = match options.html {
+        HtmlPolicy::Escape => HtmlPolicy::Escape,
+        _ => HtmlPolicy::Strip,
+    };
+    events = raw_html::apply(events, html);
+    if options.autolinks {
+        events = gfm::autolink(events);
+    }
+    let mut out = String::new();
+    push_ansi(&mut out, events, terminal);
+    out
+}
+
+/// Writes an event stream as ANSI-styled terminal text, the counterpart of
+/// `html_writer::push_html`.
+pub(crate) fn push_ansi(out: &mut String, events: Vec<Spanned<'_>>, options: &TerminalOptions) {
+    let mut renderer = Renderer {
+        events,
+        position: 0,
+        options,
+        numbers: HashMap::new(),
+    };
+    let width = match options.width {
+        0 => usize::MAX / 2,
+        width => width,
+    };
+    for line in renderer.blocks(width, true) {
+        out.push_str(line.trim_end_matches(' '));
+        out.push('\n');
+    }
+}
+
+struct Renderer<'a, 'o> {
+    events: Vec<Spanned<'a>>,
+    position: usize,
+    options: &'o TerminalOptions,
+    numbers: HashMap<CowStr<'a>, usize>,
+}
+
+impl<'a> Renderer<'a, '_> {
+    fn next(&mut self) -> Option<Event<'a>> {
+        let (event, _) = self.events.get(self.position)?;
+        self.position += 1;
+        Some(event.clone())
+    }
+
+    fn peek(&self) -> Option<&Event<'a>> {
+        self.events.get(self.position).map(|(event, _)| event)
+    }
+
+    /// The blocks up to the end of the enclosing container, `loose` ones
+    /// separated by blank lines.
+    fn blocks(&mut self, width: usize, loose: bool) -> Vec<String> {
+        let mut lines = Vec::new();
+        while let Some(event) = self.peek() {
+            if matches!(event, Event::End(_)) {
+                self.position += 1;
+                break;
+            }
+            let block = self.block(width);
+            if block.is_empty() {
+                continue;
+            }
+            if loose && !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.extend(block);
+        }
+        lines
+    }
+
+    fn block(&mut self, width: usize) -> Vec<String> {
+        if self.peek().is_some_and(is_inline) {
+            return wrap(&self.inline(&[]), width);
+        }
+        let Some(event) = self.next() else {
+            return Vec::new();
+        };
+        match event {
+            Event::Start(Tag::Paragraph) => {
+                let text = self.inline(&[]);
+                self.position += 1;
+                wrap(&text, width)
+            }
+            Event::Start(Tag::Heading { level, .. }) => {
+                let styles: &[Style] = match level {
+                    HeadingLevel::H1 => &[Style::Heading, Style::Underline],
+                    _ => &[Style::Heading],
+                };
+                let text = self.inline(styles);
+                self.position += 1;
+                wrap(&text, width)
+            }
+            Event::Start(Tag::BlockQuote(kind)) => {
+                let mut lines = Vec::new();
+                if let Some(kind) = kind {
+                    lines.push(self.styled(&[Style::Bold], alert(kind)));
+                }
+                lines.extend(self.blocks(width.saturating_sub(2).max(1), true));
+                let bar = self.styled(&[Style::Dim], "│");
+                lines
+                    .into_iter()
+                    .map(|line| format!("{} {}", bar, line))
+                    .collect()
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let mut code = String::new();
+                while let Some(event) = self.next() {
+                    match event {
+                        Event::Text(text) => code.push_str(&text),
+                        Event::End(_) => break,
+                        _ => {}
+                    }
+                }
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code_block(&code, &language, width)
+            }
+            Event::Start(Tag::List(start)) => self.list(start, width),
+            Event::Start(Tag::Table(alignments)) => self.table(&alignments),
+            Event::Start(Tag::FootnoteDefinition(name)) => {
+                let label = format!("[{}] ", self.number(name));
+                let lines = self.blocks(width.saturating_sub(label.width()).max(1), false);
+                hang(lines, &self.styled(&[Style::Dim], &label), label.width())
+            }
+            Event::Start(Tag::DefinitionList) => {
+                let mut lines = Vec::new();
+                while let Some(event) = self.next() {
+                    match event {
+                        Event::Start(Tag::DefinitionListTitle) => {
+                            let title = self.inline(&[Style::Bold]);
+                            self.position += 1;
+                            lines.extend(wrap(&title, width));
+                        }
+                        Event::Start(Tag::DefinitionListDefinition) => {
+                            let definition = self.blocks(width.saturating_sub(4).max(1), false);
+                            lines.extend(hang(definition, "    ", 4));
+                        }
+                        Event::End(_) => break,
+                        _ => {}
+                    }
+                }
+                lines
+            }
+            Event::Rule => vec![self.styled(&[Style::Dim], &"─".repeat(width.min(80)))],
+            Event::Start(_) => {
+                // HTML and metadata blocks.
+                let mut depth = 1;
+                while depth > 0 {
+                    match self.next() {
+                        Some(Event::Start(_)) => depth += 1,
+                        Some(Event::End(_)) => depth -= 1,
+                        Some(_) => {}
+                        None => break,
+                    }
+                }
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn list(&mut self, start: Option<u64>, width: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut number = start;
+        while let Some(event) = self.next() {
+            match event {
+                Event::Start(Tag::Item) => {
+                    let bullet = match number.as_mut() {
+                        Some(number) => {
+                            *number += 1;
+                            format!("{}. ", *number - 1)
+                        }
+                        None => "• ".to_string(),
+                    };
+                    let checkbox = match self.peek() {
+                        Some(Event::TaskListMarker(checked)) => Some(*checked),
+                        _ => None,
+                    };
+                    let bullet = match checkbox {
+                        Some(checked) => {
+                            self.position += 1;
+                            format!("{}{} ", bullet, if checked { "☑" } else { "☐" })
+                        }
+                        None => bullet,
+                    };
+                    let indent = bullet.width();
+                    let item = self.blocks(width.saturating_sub(indent).max(1), false);
+                    lines.extend(hang(item, &bullet, indent));
+                }
+                Event::End(_) => break,
+                _ => {}
+            }
+        }
+        lines
+    }
+
+    fn code_block(&self, code: &str, language: &str, width: usize) -> Vec<String> {
+        let code = code.trim_end_matches('\n').replace('\t', "    ");
+        let inner = code.lines().map(UnicodeWidthStr::width).max().unwrap_or(0);
+        let inner = inner
+            .max(language.width() + 2)
+            .min(width.saturating_sub(4).max(1));
+        let border = |text: String| self.styled(&[Style::Dim], &text);
+
+        // The language label is cut to fit in the top border.
+        let mut label = String::new();
+        for c in language.chars() {
+            if label.width() + c.width().unwrap_or(0) >= inner {
+                break;
+            }
+            label.push(c);
+        }
+
+        let mut lines = Vec::new();
+        let top = match label.is_empty() {
+            true => format!("┌{}┐", "─".repeat(inner + 2)),
+            false => format!(
+                "┌─ {} {}┐",
+                label,
+                "─".repeat(inner.saturating_sub(label.width() + 1))
+            ),
+        };
+        lines.push(border(top));
+        for line in code.lines() {
+            for chunk in split_width(line, inner) {
+                let padding = " ".repeat(inner.saturating_sub(chunk.width()));
+                lines.push(format!(
+                    "{} {}{} {}",
+                    border("│".into()),
+                    chunk,
+                    padding,
+                    border("│".into())
+                ));
+            }
+        }
+        lines.push(border(format!("└{}┘", "─".repeat(inner + 2))));
+        lines
+    }
+
+    fn table(&mut self, alignments: &[Alignment]) -> Vec<String> {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut head = true;
+        while let Some(event) = self.next() {
+            match event {
+                Event::Start(Tag::TableHead | Tag::TableRow) => rows.push(Vec::new()),
+                Event::End(TagEnd::TableHead) => head = false,
+                Event::Start(Tag::TableCell) => {
+                    let styles: &[Style] = if head { &[Style::Bold] } else { &[] };
+                    let cell = self.inline(styles).replace('\n', " ");
+                    self.position += 1;
+                    rows.last_mut().expect("cells are inside rows").push(cell);
+                }
+                Event::End(TagEnd::Table) => break,
+                _ => {}
+            }
+        }
+        let mut widths = vec![0; alignments.len()];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(visible_width(cell));
+            }
+        }
+        let rule = |left: &str, middle: &str, right: &str| {
+            let segments: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+            self.styled(
+                &[Style::Dim],
+                &format!("{}{}{}", left, segments.join(middle), right),
+            )
+        };
+        let bar = self.styled(&[Style::Dim], "│");
+
+        let mut lines = vec![rule("┌", "┬", "┐")];
+        for (index, row) in rows.iter().enumerate() {
+            if index == 1 {
+                lines.push(rule("├", "┼", "┤"));
+            }
+            let mut line = bar.clone();
+            for ((cell, width), alignment) in row.iter().zip(&widths).zip(alignments) {
+                let padding = width - visible_width(cell);
+                let (left, right) = match alignment {
+                    Alignment::Right => (padding, 0),
+                    Alignment::Center => (padding / 2, padding - padding / 2),
+                    _ => (0, padding),
+                };
+                line.push_str(&format!(
+                    " {}{}{} {}",
+                    " ".repeat(left),
+                    cell,
+                    " ".repeat(right),
+                    bar
+                ));
+            }
+            lines.push(line);
+        }
+        lines.push(rule("└", "┴", "┘"));
+        lines
+    }
+
+    /// Inline content up to the next block event, styled on top of `base`.
+    /// Hard breaks are kept as `\n`.
+    fn inline(&mut self, base: &[Style]) -> String {
+        let mut out = String::new();
+        let mut styles = base.to_vec();
+        let mut links: Vec<(usize, CowStr<'a>)> = Vec::new();
+        if !base.is_empty() {
+            out.push_str(&self.sgr(&styles));
+        }
+        while self.peek().is_some_and(is_inline) {
+            let event = self.next().expect("peeked");
+            match event {
+                Event::Text(text) => out.push_str(&text.replace('\n', " ")),
+                Event::Code(text) | Event::InlineMath(text) | Event::DisplayMath(text) => {
+                    styles.push(Style::Code);
+                    out.push_str(&self.sgr(&styles));
+                    out.push_str(&text);
+                    styles.pop();
+                    out.push_str(&self.sgr(&styles));
+                }
+                Event::InlineHtml(_) => {}
+                Event::SoftBreak => out.push(' '),
+                Event::HardBreak => out.push('\n'),
+                Event::FootnoteReference(name) => {
+                    let number = self.number(name);
+                    out.push_str(&format!("[{}]", number));
+                }
+                Event::TaskListMarker(checked) => {
+                    out.push_str(if checked { "☑ " } else { "☐ " })
+                }
+                Event::Start(tag) => {
+                    let image = matches!(tag, Tag::Image { .. });
+                    let style = match tag {
+                        Tag::Emphasis => Style::Italic,
+                        Tag::Strong => Style::Bold,
+                        Tag::Strikethrough => Style::Strikethrough,
+                        Tag::Link { dest_url, .. } => {
+                            self.open_link(&mut out, &links, &dest_url);
+                            links.push((out.len(), dest_url));
+                            Style::Link
+                        }
+                        Tag::Image { dest_url, .. } => {
+                            self.open_link(&mut out, &links, &dest_url);
+                            links.push((out.len(), dest_url));
+                            Style::Link
+                        }
+                        _ => continue,
+                    };
+                    styles.push(style);
+                    out.push_str(&self.sgr(&styles));
+                    if image {
+                        out.push_str("[image: ");
+                    }
+                }
+                Event::End(tag) => {
+                    if matches!(tag, TagEnd::Superscript | TagEnd::Subscript) {
+                        continue;
+                    }
+                    if tag == TagEnd::Image {
+                        out.push(']');
+                    }
+                    styles.pop();
+                    out.push_str(&self.sgr(&styles));
+                    if let TagEnd::Link | TagEnd::Image = tag {
+                        let (start, url) = links.pop().expect("links are open");
+                        if self.hyperlinks() && links.is_empty() {
+                            out.push_str(LINK_END);
+                        } else if !self.hyperlinks() && needs_url(plain(&out[start..]).trim(), &url)
+                        {
+                            out.push_str(&format!(
+                                " {}",
+                                self.styled(&[Style::Dim], &format!("({})", url))
+                            ));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Closing every span switched back to `base`.
+        if !base.is_empty() {
+            out.push_str(&self.sgr(&[]));
+        }
+        out
+    }
+
+    fn open_link(&self, out: &mut String, links: &[(usize, CowStr<'a>)], url: &str) {
+        if self.hyperlinks() && links.is_empty() {
+            out.push_str(&format!("\x1b]8;;{}\x1b\\", url));
+        }
+    }
+
+    fn hyperlinks(&self) -> bool {
+        self.options.color && self.options.hyperlinks
+    }
+
+    /// The escape sequence switching to exactly `styles`.
+    fn sgr(&self, styles: &[Style]) -> String {
+        if !self.options.color {
+            return String::new();
+        }
+        let mut sequence = "\x1b[0".to_string();
+        for style in styles {
+            sequence.push(';');
+            sequence.push_str(style.codes());
+        }
+        sequence.push('m');
+        sequence
+    }
+
+    fn styled(&self, styles: &[Style], text: &str) -> String {
+        match self.options.color {
+            true => format!("{}{}{}", self.sgr(styles), text, RESET),
+            false => text.to_string(),
+        }
+    }
+
+    /// The number of a footnote, counting from 1 in order of first mention
+    /// like the HTML renderer does.
+    fn number(&mut self, name: CowStr<'a>) -> usize {
+        let len = self.numbers.len() + 1;
+        *self.numbers.entry(name).or_insert(len)
+    }
+}
+
+fn alert(kind: BlockQuoteKind) -> &'static str {
+    match kind {
+        BlockQuoteKind::Note => "Note",
+        BlockQuoteKind::Tip => "Tip",
+        BlockQuoteKind::Important => "Important",
+        BlockQuoteKind::Warning => "Warning",
+        BlockQuoteKind::Caution => "Caution",
+    }
+}
+
+/// Puts `first` before the first line and indents the rest by `indent`.
+fn hang(lines: Vec<String>, first: &str, indent: usize) -> Vec<String> {
+    if lines.is_empty() {
+        return vec![first.to_string()];
+    }
+    let rest = " ".repeat(indent);
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(index, line)| match (index, line.is_empty()) {
+            (0, _) => format!("{}{}", first, line),
+            (_, true) => line,
+            (_, false) => format!("{}{}", rest, line),
+        })
+        .collect()
+}
+
+/// `text` without escape sequences.
+fn plain(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('\x1b') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + escape_len(&rest[start..])..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The length of the CSI or OSC escape sequence at the start of `text`.
+fn escape_len(text: &str) -> usize {
+    let end = match text.as_bytes().get(1) {
+        Some(b'[') => text
+            .find(|c: char| c.is_ascii_alphabetic())
+            .map(|end| end + 1),
+        Some(b']') => text.find("\x1b\\").map(|end| end + 2),
+        _ => None,
+    };
+    end.unwrap_or(1)
+}
+
+fn visible_width(text: &str) -> usize {
+    plain(text).width()
+}
+
+/// Fills styled `text` into lines of at most `width` columns, breaking at
+/// spaces and at `\n`. Styles and links open at a break are closed at the
+/// end of the line and reopened on the next, so prefixes added in front of
+/// lines stay unstyled.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0;
+    // The last style switch and hyperlink opened, to carry over breaks.
+    let mut style = String::new();
+    let mut link = String::new();
+    let mut break_line = |line: &mut String, line_width: &mut usize, style: &str, link: &str| {
+        if !style.is_empty() && style != RESET {
+            line.push_str(RESET);
+        }
+        if !link.is_empty() {
+            line.push_str(LINK_END);
+        }
+        lines.push(std::mem::take(line));
+        line.push_str(link);
+        if style != RESET {
+            line.push_str(style);
+        }
+        *line_width = 0;
+    };
+    for (index, paragraph) in text.split('\n').enumerate() {
+        if index > 0 {
+            break_line(&mut line, &mut line_width, &style, &link);
+        }
+        for (index, word) in paragraph.split(' ').enumerate() {
+            let word_width = visible_width(word);
+            if index > 0 {
+                if line_width > 0 && line_width + 1 + word_width > width {
+                    break_line(&mut line, &mut line_width, &style, &link);
+                } else {
+                    line.push(' ');
+                    line_width += 1;
+                }
+            }
+            line.push_str(word);
+            line_width += word_width;
+            let mut rest = word;
+            while let Some(start) = rest.find('\x1b') {
+                let sequence = &rest[start..start + escape_len(&rest[start..])];
+                if sequence.starts_with("\x1b[") {
+                    style = sequence.to_string();
+                } else if sequence == LINK_END {
+                    link.clear();
+                } else if sequence.starts_with("\x1b]") {
+                    link = sequence.to_string();
+                }
+                rest = &rest[start + sequence.len()..];
+            }
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+/// Splits `text` into pieces at most `width` columns wide.
+fn split_width(text: &str, width: usize) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut current = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if current + char_width > width && current > 0 {
+            pieces.push(String::new());
+            current = 0;
+        }
+        pieces.last_mut().expect("there is a piece").push(c);
+        current += char_width;
+    }
+    pieces
+}
@@ -0,0 +1,442 @@
+use std::collections::BTreeMap;
+
+use pulldown_cmark::{
+    Alignment, BlockQuoteKind, CodeBlockKind, Event, LinkType, MetadataBlockKind, Tag,
+};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::gfm;
+use crate::options::MarkdownOptions;
+use crate::render;
+use crate::source_map::SourceIndex;
+
+#[wasm_bindgen(typescript_custom_section)]
+const AST_SCHEMA: &'static str = r#"
+/** Source range of a node: UTF-16 offsets into the input and 1-based lines. */
+export interface MarkdownPosition {
+  start: number;
+  end: number;
+  line: number;
+  endLine: number;
+}
+
+/** A node of the tree returned by `parse_markdown_ast`. */
+export type MarkdownNode = { position: MarkdownPosition; children?: MarkdownNode[] } & (
+  | { type: "document" }
+  | { type: "paragraph" }
+  | { type: "heading"; level: number; id?: string; classes?: string[]; attributes?: Record<string, string | null> }
+  | { type: "blockquote"; alert?: "note" | "tip" | "important" | "warning" | "caution" }
+  | { type: "code"; fenced: boolean; lang?: string; meta?: string; value: string }
+  | { type: "html"; value: string }
+  | { type: "inlineHtml"; value: string }
+  | { type: "list"; ordered: boolean; start?: number }
+  | { type: "listItem"; checked?: boolean }
+  | { type: "footnoteDefinition"; label: string }
+  | { type: "footnoteReference"; label: string }
+  | { type: "definitionList" }
+  | { type: "definitionTerm" }
+  | { type: "definitionDescription" }
+  | { type: "table"; align: ("left" | "center" | "right" | null)[] }
+  | { type: "tableHead" }
+  | { type: "tableRow" }
+  | { type: "tableCell" }
+  | { type: "emphasis" }
+  | { type: "strong" }
+  | { type: "delete" }
+  | { type: "superscript" }
+  | { type: "subscript" }
+  | { type: "link"; url: string; title?: string; linkType: string; identifier?: string }
+  | { type: "image"; url: string; title?: string; alt: string; linkType: string; identifier?: string }
+  | { type: "metadata"; format: "yaml" | "toml"; value: string }
+  | { type: "text"; value: string }
+  | { type: "inlineCode"; value: string }
+  | { type: "inlineMath"; value: string }
+  | { type: "displayMath"; value: string }
+  | { type: "softBreak" }
+  | { type: "hardBreak" }
+  | { type: "thematicBreak" }
+);
+"#;
+
+/// Source range of a node. Offsets are UTF-16 code units, lines 1-based.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Position {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub end_line: usize,
+}
+
+impl Position {
+    /// The position of byte `range` in the text behind `index`.
+    pub(crate) fn of(index: &SourceIndex, range: std::ops::Range<usize>) -> Self {
+        Position {
+            start: index.utf16(range.start),
+            end: index.utf16(range.end),
+            line: index.line(range.start),
+            end_line: index.line(range.end.saturating_sub(1).max(range.start)),
+        }
+    }
+}
+
+/// One node of the document tree. The schema is mirrored by the
+/// `MarkdownNode` TypeScript type above; keep the two in sync.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Node {
+    #[serde(flatten)]
+    pub kind: NodeKind,
+    pub position: Position,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Node>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum NodeKind {
+    Document,
+    Paragraph,
+    Heading {
+        level: u8,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        classes: Vec<String>,
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        attributes: BTreeMap<String, Option<String>>,
+    },
+    #[serde(rename = "blockquote")]
+    BlockQuote {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        alert: Option<&'static str>,
+    },
+    Code {
+        fenced: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        lang: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        meta: Option<String>,
+        value: String,
+    },
+    Html {
+        value: String,
+    },
+    InlineHtml {
+        value: String,
+    },
+    List {
+        ordered: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start: Option<u64>,
+    },
+    ListItem {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        checked: Option<bool>,
+    },
+    FootnoteDefinition {
+        label: String,
+    },
+    FootnoteReference {
+        label: String,
+    },
+    DefinitionList,
+    DefinitionTerm,
+    DefinitionDescription,
+    Table {
+        align: Vec<Option<&'static str>>,
+    },
+    TableHead,
+    TableRow,
+    TableCell,
+    Emphasis,
+    Strong,
+    Delete,
+    Superscript,
+    Subscript,
+    #[serde(rename_all = "camelCase")]
+    Link {
+        url: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        link_type: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        identifier: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Image {
+        url: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        alt: String,
+        link_type: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        identifier: Option<String>,
+    },
+    Metadata {
+        format: &'static str,
+        value: String,
+    },
+    Text {
+        value: String,
+    },
+    InlineCode {
+        value: String,
+    },
+    InlineMath {
+        value: String,
+    },
+    DisplayMath {
+        value: String,
+    },
+    SoftBreak,
+    HardBreak,
+    ThematicBreak,
+}
+
+impl Node {
+    /// The concatenated text content of this node and its descendants.
+    pub(crate) fn text_content(&self) -> String {
+        let mut text = String::new();
+        self.collect_text(&mut text);
+        text
+    }
+
+    fn collect_text(&self, out: &mut String) {
+        match &self.kind {
+            NodeKind::Text { value }
+            | NodeKind::InlineCode { value }
+            | NodeKind::InlineMath { value }
+            | NodeKind::DisplayMath { value } => out.push_str(value),
+            NodeKind::SoftBreak | NodeKind::HardBreak => out.push(' '),
+            _ => self
+                .children
+                .iter()
+                .for_each(|child| child.collect_text(out)),
+        }
+    }
+}
+
+/// Parses `input` into a document tree. Adjacent text is merged into a
+/// single node, and with `autolinks` enabled bare URLs become link nodes.
+pub(crate) fn build(input: &str, options: &MarkdownOptions) -> Node {
+    let index = SourceIndex::new(input);
+    let position = |range| Position::of(&index, range);
+
+    let mut events = render::parse(input, options);
+    if options.autolinks {
+        events = gfm::autolink(events);
+    }
+
+    let mut stack = vec![Node {
+        kind: NodeKind::Document,
+        position: position(0..input.len()),
+        children: Vec::new(),
+    }];
+    for (event, range) in events {
+        let top = stack.last_mut().expect("the document node is never popped");
+        let leaf = match event {
+            Event::Start(tag) => {
+                stack.push(Node {
+                    kind: start_kind(tag),
+                    position: position(range),
+                    children: Vec::new(),
+                });
+                continue;
+            }
+            Event::End(_) => {
+                let mut node = stack.pop().expect("events are balanced");
+                if let NodeKind::Image { alt, .. } = &mut node.kind {
+                    *alt = node.children.iter().map(Node::text_content).collect();
+                }
+                stack
+                    .last_mut()
+                    .expect("the document node is never popped")
+                    .children
+                    .push(node);
+                continue;
+            }
+            Event::Text(text) => match &mut top.kind {
+                NodeKind::Code { value, .. } | NodeKind::Metadata { value, .. } => {
+                    value.push_str(&text);
+                    continue;
+                }
+                _ => NodeKind::Text {
+                    value: text.to_string(),
+                },
+            },
+            Event::Html(html) => match &mut top.kind {
+                NodeKind::Html { value } => {
+                    value.push_str(&html);
+                    continue;
+                }
+                _ => NodeKind::InlineHtml {
+                    value: html.to_string(),
+                },
+            },
+            Event::InlineHtml(html) => NodeKind::InlineHtml {
+                value: html.to_string(),
+            },
+            Event::Code(code) => NodeKind::InlineCode {
+                value: code.to_string(),
+            },
+            Event::InlineMath(math) => NodeKind::InlineMath {
+                value: math.to_string(),
+            },
+            Event::DisplayMath(math) => NodeKind::DisplayMath {
+                value: math.to_string(),
+            },
+            Event::FootnoteReference(label) => NodeKind::FootnoteReference {
+                label: label.to_string(),
+            },
+            Event::SoftBreak => NodeKind::SoftBreak,
+            Event::HardBreak => NodeKind::HardBreak,
+            Event::Rule => NodeKind::ThematicBreak,
+            Event::TaskListMarker(done) => {
+                if let NodeKind::ListItem { checked } = &mut top.kind {
+                    *checked = Some(done);
+                }
+                continue;
+            }
+        };
+
+        let position = position(range);
+        if let (NodeKind::Text { value }, Some(previous)) = (&leaf, top.children.last_mut()) {
+            if let NodeKind::Text { value: merged } = &mut previous.kind {
+                merged.push_str(value);
+                previous.position.end = position.end;
+                previous.position.end_line = position.end_line;
+                continue;
+            }
+        }
+        top.children.push(Node {
+            kind: leaf,
+            position,
+            children: Vec::new(),
+        });
+    }
+    stack.pop().expect("the document node is never popped")
+}
+
+fn start_kind(tag: Tag) -> NodeKind {
+    match tag {
+        Tag::Paragraph => NodeKind::Paragraph,
+        Tag::Heading {
+            level,
+            id,
+            classes,
+            attrs,
+        } => NodeKind::Heading {
+            level: level as u8,
+            id: id.map(|id| id.to_string()),
+            classes: classes.into_iter().map(|class| class.to_string()).collect(),
+            attributes: attrs
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.map(|value| value.to_string())))
+                .collect(),
+        },
+        Tag::BlockQuote(kind) => NodeKind::BlockQuote {
+            alert: kind.map(|kind| match kind {
+                BlockQuoteKind::Note => "note",
+                BlockQuoteKind::Tip => "tip",
+                BlockQuoteKind::Important => "important",
+                BlockQuoteKind::Warning => "warning",
+                BlockQuoteKind::Caution => "caution",
+            }),
+        },
+        Tag::CodeBlock(CodeBlockKind::Fenced(info)) => {
+            let info = info.trim();
+            let (lang, meta) = match info.split_once(char::is_whitespace) {
+                Some((lang, meta)) => (lang, Some(meta.trim().to_string())),
+                None => (info, None),
+            };
+            NodeKind::Code {
+                fenced: true,
+                lang: (!lang.is_empty()).then(|| lang.to_string()),
+                meta,
+                value: String::new(),
+            }
+        }
+        Tag::CodeBlock(CodeBlockKind::Indented) => NodeKind::Code {
+            fenced: false,
+            lang: None,
+            meta: None,
+            value: String::new(),
+        },
+        Tag::HtmlBlock => NodeKind::Html {
+            value: String::new(),
+        },
+        Tag::List(start) => NodeKind::List {
+            ordered: start.is_some(),
+            start,
+        },
+        Tag::Item => NodeKind::ListItem { checked: None },
+        Tag::FootnoteDefinition(label) => NodeKind::FootnoteDefinition {
+            label: label.to_string(),
+        },
+        Tag::DefinitionList => NodeKind::DefinitionList,
+        Tag::DefinitionListTitle => NodeKind::DefinitionTerm,
+        Tag::DefinitionListDefinition => NodeKind::DefinitionDescription,
+        Tag::Table(alignments) => NodeKind::Table {
+            align: alignments
+                .into_iter()
+                .map(|alignment| match alignment {
+                    Alignment::None => None,
+                    Alignment::Left => Some("left"),
+                    Alignment::Center => Some("center"),
+                    Alignment::Right => Some("right"),
+                })
+                .collect(),
+        },
+        Tag::TableHead => NodeKind::TableHead,
+        Tag::TableRow => NodeKind::TableRow,
+        Tag::TableCell => NodeKind::TableCell,
+        Tag::Emphasis => NodeKind::Emphasis,
+        Tag::Strong => NodeKind::Strong,
+        Tag::Strikethrough => NodeKind::Delete,
+        Tag::Superscript => NodeKind::Superscript,
+        Tag::Subscript => NodeKind::Subscript,
+        Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        } => NodeKind::Link {
+            url: dest_url.to_string(),
+            title: (!title.is_empty()).then(|| title.to_string()),
+            link_type: link_type_name(link_type),
+            identifier: (!id.is_empty()).then(|| id.to_string()),
+        },
+        Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        } => NodeKind::Image {
+            url: dest_url.to_string(),
+            title: (!title.is_empty()).then(|| title.to_string()),
+            alt: String::new(),
+            link_type: link_type_name(link_type),
+            identifier: (!id.is_empty()).then(|| id.to_string()),
+        },
+        Tag::MetadataBlock(kind) => NodeKind::Metadata {
+            format: match kind {
+                MetadataBlockKind::YamlStyle => "yaml",
+                MetadataBlockKind::PlusesStyle => "toml",
+            },
+            value: String::new(),
+        },
+    }
+}
+
+pub(crate) fn link_type_name(link_type: LinkType) -> &'static str {
+    match link_type {
+        LinkType::Inline => "inline",
+        LinkType::Reference | LinkType::ReferenceUnknown => "reference",
+        LinkType::Collapsed | LinkType::CollapsedUnknown => "collapsed",
+        LinkType::Shortcut | LinkType::ShortcutUnknown => "shortcut",
+        LinkType::Autolink => "autolink",
+        LinkType::Email => "email",
+        LinkType::WikiLink { .. } => "wikilink",
+    }
+}
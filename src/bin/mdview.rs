@@ -0,0 +1,66 @@
+//! Renders a Markdown file, or standard input, to the terminal.
+
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::ExitCode;
+
+use markdown_wasm::{render_ansi, MarkdownOptions, TerminalOptions};
+
+const USAGE: &str =
+    "usage: mdview [--width COLUMNS] [--commonmark] [--no-color] [--no-links] [FILE]";
+
+fn main() -> ExitCode {
+    let mut options = MarkdownOptions::gfm();
+    let mut terminal = TerminalOptions {
+        width: std::env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.parse().ok())
+            .unwrap_or(80),
+        color: io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        ..TerminalOptions::new()
+    };
+    let mut path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" | "-w" => match args.next().and_then(|width| width.parse().ok()) {
+                Some(width) => terminal.width = width,
+                None => return usage(),
+            },
+            "--commonmark" => options = MarkdownOptions::commonmark(),
+            "--no-color" => terminal.color = false,
+            "--color" => terminal.color = true,
+            "--no-links" => terminal.hyperlinks = false,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            "-" => path = None,
+            _ if arg.starts_with('-') => return usage(),
+            _ => path = Some(arg),
+        }
+    }
+
+    let input = match &path {
+        Some(path) => std::fs::read_to_string(path),
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).map(|_| input)
+        }
+    };
+    let input = match input {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("mdview: {}: {}", path.as_deref().unwrap_or("stdin"), error);
+            return ExitCode::FAILURE;
+        }
+    };
+    let output = render_ansi(&input, &options, &terminal);
+    // A closed pipe, as with `mdview README.md | head`, is not an error.
+    let _ = io::stdout().lock().write_all(output.as_bytes());
+    ExitCode::SUCCESS
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::from(2)
+}
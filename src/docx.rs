@@ -0,0 +1,929 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::{Alignment, CowStr, Event, LinkType, Tag, TagEnd};
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::front_matter;
+use crate::gfm;
+use crate::headings;
+use crate::options::{HtmlPolicy, MarkdownOptions};
+use crate::raw_html;
+use crate::render;
+use crate::toc;
+use crate::xml::escape;
+use crate::zip::Zip;
+
+/// Settings for `render_docx`, and the images to embed.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocxOptions {
+    /// The font of code spans and blocks.
+    #[wasm_bindgen(getter_with_clone)]
+    pub code_font: String,
+    images: HashMap<String, Vec<u8>>,
+}
+
+impl Default for DocxOptions {
+    fn default() -> Self {
+        DocxOptions {
+            code_font: "Consolas".to_string(),
+            images: HashMap::new(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl DocxOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> DocxOptions {
+        DocxOptions::default()
+    }
+
+    /// Supplies the bytes of the image that `src` refers to. PNG, JPEG and
+    /// GIF images are embedded; other images, and those without bytes, are
+    /// written as their alt text.
+    pub fn add_image(&mut self, src: &str, bytes: &[u8]) {
+        self.images.insert(src.to_string(), bytes.to_vec());
+    }
+}
+
+const W: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const R: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const RELATIONSHIPS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+const HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+/// The width between the page margins, in twentieths of a point.
+const TEXT_WIDTH: u32 = 9360;
+/// English Metric Units per pixel at 96 DPI, and per twentieth of a point.
+const EMU_PER_PIXEL: u64 = 9525;
+const EMU_PER_TWIP: u64 = 635;
+
+/// Renders `input` as a Word document and returns the bytes of the
+/// `.docx` file. With `options.front_matter`, its `title` starts the
+/// document and, with `author`, fills in the document properties. Raw HTML
+/// is left out unless `options.html` escapes it.
+pub(crate) fn convert(input: &str, options: &MarkdownOptions, docx: &DocxOptions) -> Vec<u8> {
+    let mut events = headings::assign_ids(render::parse(input, options));
+    if options.toc {
+        events = toc::insert(events, options);
+    }
+    let html = match options.html {
+        HtmlPolicy::Escape => HtmlPolicy::Escape,
+        _ => HtmlPolicy::Strip,
+    };
+    events = raw_html::apply(events, html);
+    if options.autolinks {
+        events = gfm::autolink(events);
+    }
+
+    let fields = match options.front_matter {
+        true => front_matter::fields(input),
+        false => Default::default(),
+    };
+    let title = fields.get("title").map(metadata).unwrap_or_default();
+    let author = match fields.get("author").or_else(|| fields.get("authors")) {
+        Some(Value::Array(authors)) => authors.iter().map(metadata).collect::<Vec<_>>().join("; "),
+        Some(author) => metadata(author),
+        None => String::new(),
+    };
+
+    let mut writer = Writer::new(docx);
+    if !title.is_empty() {
+        writer
+            .body
+            .push_str("<w:p><w:pPr><w:pStyle w:val=\"Title\"/></w:pPr>");
+        writer.body.push_str(&runs(&title, ""));
+        writer.body.push_str("</w:p>");
+    }
+    for (event, _) in events {
+        writer.event(event);
+    }
+    writer.close();
+    // Word wants a paragraph after every table.
+    if writer.body.is_empty() || writer.body.ends_with("</w:tbl>") {
+        writer.body.push_str("<w:p/>");
+    }
+
+    let mut zip = Zip::new();
+    let mut types = String::from(HEADER);
+    types.push_str(
+        "<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+         <Default Extension=\"rels\" \
+         ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+         <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+         <Default Extension=\"png\" ContentType=\"image/png\"/>\
+         <Default Extension=\"jpeg\" ContentType=\"image/jpeg\"/>\
+         <Default Extension=\"gif\" ContentType=\"image/gif\"/>",
+    );
+    let main = "application/vnd.openxmlformats-officedocument.wordprocessingml";
+    for (part, content_type) in [
+        ("/word/document.xml", format!("{}.document.main+xml", main)),
+        ("/word/styles.xml", format!("{}.styles+xml", main)),
+        ("/word/numbering.xml", format!("{}.numbering+xml", main)),
+        (
+            "/docProps/core.xml",
+            "application/vnd.openxmlformats-package.core-properties+xml".to_string(),
+        ),
+    ] {
+        types.push_str(&format!(
+            "<Override PartName=\"{}\" ContentType=\"{}\"/>",
+            part, content_type
+        ));
+    }
+    types.push_str("</Types>");
+    zip.add("[Content_Types].xml", types.as_bytes());
+
+    let package = format!(
+        "{}<Relationships xmlns=\"{}\">\
+         <Relationship Id=\"rId1\" Type=\"{}/officeDocument\" Target=\"word/document.xml\"/>\
+         <Relationship Id=\"rId2\" Type=\"{}/metadata/core-properties\" Target=\"docProps/core.xml\"/>\
+         </Relationships>",
+        HEADER, RELATIONSHIPS, R, RELATIONSHIPS
+    );
+    zip.add("_rels/.rels", package.as_bytes());
+
+    let mut core = format!(
+        "{}<cp:coreProperties \
+         xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\">",
+        HEADER
+    );
+    if !title.is_empty() {
+        core.push_str(&format!("<dc:title>{}</dc:title>", escape(&title)));
+    }
+    if !author.is_empty() {
+        core.push_str(&format!("<dc:creator>{}</dc:creator>", escape(&author)));
+    }
+    core.push_str("</cp:coreProperties>");
+    zip.add("docProps/core.xml", core.as_bytes());
+
+    let document = format!(
+        "{}<w:document xmlns:w=\"{}\" xmlns:r=\"{}\" \
+         xmlns:wp=\"http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing\" \
+         xmlns:a=\"http://schemas.openxmlformats.org/drawingml/2006/main\" \
+         xmlns:pic=\"http://schemas.openxmlformats.org/drawingml/2006/picture\"><w:body>{}\
+         <w:sectPr><w:pgSz w:w=\"12240\" w:h=\"15840\"/><w:pgMar w:top=\"1440\" w:right=\"1440\" \
+         w:bottom=\"1440\" w:left=\"1440\" w:header=\"720\" w:footer=\"720\" w:gutter=\"0\"/></w:sectPr>\
+         </w:body></w:document>",
+        HEADER, W, R, writer.body
+    );
+    zip.add("word/document.xml", document.as_bytes());
+
+    let mut relationships = format!(
+        "{}<Relationships xmlns=\"{}\">\
+         <Relationship Id=\"rId1\" Type=\"{}/styles\" Target=\"styles.xml\"/>\
+         <Relationship Id=\"rId2\" Type=\"{}/numbering\" Target=\"numbering.xml\"/>",
+        HEADER, RELATIONSHIPS, R, R
+    );
+    for (index, (kind, target)) in writer.relationships.iter().enumerate() {
+        let external = if *kind == "hyperlink" {
+            " TargetMode=\"External\""
+        } else {
+            ""
+        };
+        relationships.push_str(&format!(
+            "<Relationship Id=\"rId{}\" Type=\"{}/{}\" Target=\"{}\"{}/>",
+            index + 3,
+            R,
+            kind,
+            escape(target),
+            external
+        ));
+    }
+    relationships.push_str("</Relationships>");
+    zip.add("word/_rels/document.xml.rels", relationships.as_bytes());
+
+    zip.add("word/styles.xml", styles(&docx.code_font).as_bytes());
+    zip.add("word/numbering.xml", numbering(&writer.lists).as_bytes());
+
+    for (path, bytes) in &writer.media {
+        zip.add(&format!("word/{}", path), bytes);
+    }
+    zip.finish()
+}
+
+/// A front matter value as plain text.
+fn metadata(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Paragraph styles for headings, quotes, list items and code, and
+/// character styles for code spans and links, named as Word names its own
+/// so they pick up a template's look.
+fn styles(code_font: &str) -> String {
+    let mut out = format!(
+        "{}<w:styles xmlns:w=\"{}\"><w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii=\"Calibri\" \
+         w:hAnsi=\"Calibri\" w:eastAsia=\"Calibri\" w:cs=\"Calibri\"/><w:sz w:val=\"22\"/>\
+         <w:szCs w:val=\"22\"/></w:rPr></w:rPrDefault><w:pPrDefault><w:pPr><w:spacing w:after=\"160\" \
+         w:line=\"259\" \
+         w:lineRule=\"auto\"/></w:pPr></w:pPrDefault></w:docDefaults>",
+        HEADER, W
+    );
+    let code = format!(
+        "<w:rFonts w:ascii=\"{0}\" w:hAnsi=\"{0}\" w:cs=\"{0}\"/><w:sz w:val=\"20\"/><w:szCs w:val=\"20\"/>",
+        escape(code_font)
+    );
+    let border = "w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"";
+    let mut styles = vec![
+        ("paragraph", "Normal", "Normal", "<w:qFormat/>".to_string()),
+        (
+            "paragraph",
+            "Title",
+            "Title",
+            "<w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:spacing \
+             w:after=\"240\"/></w:pPr><w:rPr><w:sz w:val=\"56\"/><w:szCs w:val=\"56\"/></w:rPr>"
+                .to_string(),
+        ),
+    ];
+    let headings = [
+        "Heading1", "Heading2", "Heading3", "Heading4", "Heading5", "Heading6",
+    ];
+    let names = [
+        "heading 1",
+        "heading 2",
+        "heading 3",
+        "heading 4",
+        "heading 5",
+        "heading 6",
+    ];
+    for (level, size) in [32, 28, 26, 24, 22, 22].into_iter().enumerate() {
+        let heading = format!(
+            "<w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/>\
+             <w:spacing w:before=\"240\" w:after=\"80\"/><w:outlineLvl w:val=\"{}\"/></w:pPr><w:rPr><w:b/>\
+             <w:color \
+             w:val=\"1F3864\"/><w:sz w:val=\"{}\"/><w:szCs w:val=\"{}\"/></w:rPr>",
+            level, size, size
+        );
+        styles.push(("paragraph", headings[level], names[level], heading));
+    }
+    styles.extend([
+        (
+            "paragraph",
+            "Quote",
+            "Quote",
+            "<w:basedOn w:val=\"Normal\"/><w:qFormat/><w:pPr><w:pBdr><w:left w:val=\"single\" w:sz=\"18\" \
+             w:space=\"8\" w:color=\"D0D7DE\"/></w:pBdr><w:ind w:left=\"720\"/></w:pPr><w:rPr><w:i/><w:color \
+             w:val=\"595959\"/></w:rPr>"
+                .to_string(),
+        ),
+        (
+            "paragraph",
+            "ListParagraph",
+            "List Paragraph",
+            "<w:basedOn w:val=\"Normal\"/><w:qFormat/><w:pPr><w:spacing w:after=\"60\"/><w:ind \
+             w:left=\"720\"/></w:pPr>"
+                .to_string(),
+        ),
+        (
+            "paragraph",
+            "SourceCode",
+            "Source Code",
+            format!(
+                "<w:basedOn w:val=\"Normal\"/><w:pPr><w:shd w:val=\"clear\" w:color=\"auto\" \
+                 w:fill=\"F6F8FA\"/><w:spacing w:after=\"0\" w:line=\"240\" w:lineRule=\"auto\"/></w:pPr>\
+                 <w:rPr>{}</w:rPr>",
+                code
+            ),
+        ),
+        (
+            "character",
+            "DefaultParagraphFont",
+            "Default Paragraph Font",
+            "<w:uiPriority w:val=\"1\"/><w:semiHidden/>".to_string(),
+        ),
+        (
+            "character",
+            "VerbatimChar",
+            "Verbatim Char",
+            format!(
+                "<w:basedOn w:val=\"DefaultParagraphFont\"/><w:rPr>{}<w:shd w:val=\"clear\" w:color=\"auto\" \
+                 w:fill=\"F6F8FA\"/></w:rPr>",
+                code
+            ),
+        ),
+        (
+            "character",
+            "Hyperlink",
+            "Hyperlink",
+            "<w:basedOn w:val=\"DefaultParagraphFont\"/><w:rPr><w:color w:val=\"0563C1\"/><w:u \
+             w:val=\"single\"/></w:rPr>"
+                .to_string(),
+        ),
+        (
+            "table",
+            "TableNormal",
+            "Normal Table",
+            "<w:semiHidden/><w:tblPr><w:tblInd w:w=\"0\" w:type=\"dxa\"/><w:tblCellMar><w:top w:w=\"0\" \
+             w:type=\"dxa\"/><w:left w:w=\"108\" w:type=\"dxa\"/><w:bottom w:w=\"0\" w:type=\"dxa\"/>\
+             <w:right w:w=\"108\" w:type=\"dxa\"/></w:tblCellMar></w:tblPr>"
+                .to_string(),
+        ),
+        (
+            "table",
+            "Table",
+            "Table",
+            format!(
+                "<w:basedOn w:val=\"TableNormal\"/><w:pPr><w:spacing w:before=\"40\" w:after=\"40\"/></w:pPr>\
+                 <w:tblPr><w:tblBorders><w:top {0}/><w:left {0}/><w:bottom {0}/><w:right {0}/>\
+                 <w:insideH {0}/><w:insideV {0}/></w:tblBorders></w:tblPr>",
+                border
+            ),
+        ),
+    ]);
+    for (kind, id, name, body) in styles {
+        let default = match id {
+            "Normal" | "DefaultParagraphFont" | "TableNormal" => " w:default=\"1\"",
+            _ => "",
+        };
+        out.push_str(&format!(
+            "<w:style w:type=\"{}\"{} w:styleId=\"{}\"><w:name w:val=\"{}\"/>{}</w:style>",
+            kind, default, id, name, body
+        ));
+    }
+    out.push_str("</w:styles>");
+    out
+}
+
+/// Two multilevel lists, bullets and decimal numbers, and an instance of
+/// one for every list in the document. Each ordered list restarts at its
+/// own start number.
+fn numbering(lists: &[(Option<u64>, usize)]) -> String {
+    let mut out = format!("{}<w:numbering xmlns:w=\"{}\">", HEADER, W);
+    for (id, ordered) in [(0, false), (1, true)] {
+        out.push_str(&format!(
+            "<w:abstractNum w:abstractNumId=\"{}\"><w:multiLevelType w:val=\"hybridMultilevel\"/>",
+            id
+        ));
+        for level in 0..9 {
+            let (format, text) = match ordered {
+                true => (
+                    ["decimal", "lowerLetter", "lowerRoman"][level % 3],
+                    format!("%{}.", level + 1),
+                ),
+                false => ("bullet", ["•", "◦", "▪"][level % 3].to_string()),
+            };
+            out.push_str(&format!(
+                "<w:lvl w:ilvl=\"{}\"><w:start w:val=\"1\"/><w:numFmt w:val=\"{}\"/><w:lvlText w:val=\"{}\"/>\
+                 <w:lvlJc w:val=\"left\"/><w:pPr><w:ind w:left=\"{}\" w:hanging=\"360\"/></w:pPr></w:lvl>",
+                level,
+                format,
+                text,
+                720 * (level + 1)
+            ));
+        }
+        out.push_str("</w:abstractNum>");
+    }
+    for (index, (start, level)) in lists.iter().enumerate() {
+        out.push_str(&format!(
+            "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"{}\"/>",
+            index + 1,
+            usize::from(start.is_some())
+        ));
+        if let Some(start) = start {
+            out.push_str(&format!(
+                "<w:lvlOverride w:ilvl=\"{}\"><w:startOverride w:val=\"{}\"/></w:lvlOverride>",
+                level, start
+            ));
+        }
+        out.push_str("</w:num>");
+    }
+    out.push_str("</w:numbering>");
+    out
+}
+
+/// The file extension and pixel size of a PNG, JPEG or GIF image.
+fn image_size(bytes: &[u8]) -> Option<(&'static str, u32, u32)> {
+    let be16 = |at: usize| Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let be32 = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let le16 = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(("png", be32(16)?, be32(20)?));
+    }
+    if bytes.starts_with(b"GIF8") {
+        return Some(("gif", le16(6)?, le16(8)?));
+    }
+    if !bytes.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+    let mut at = 2;
+    loop {
+        while bytes.get(at) == Some(&0xff) && bytes.get(at + 1) == Some(&0xff) {
+            at += 1;
+        }
+        if bytes.get(at) != Some(&0xff) {
+            return None;
+        }
+        let marker = *bytes.get(at + 1)?;
+        match marker {
+            // Start of frame, other than the huffman, arithmetic coding
+            // and JPEG extension markers that share the range.
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                return Some(("jpeg", be16(at + 7)?, be16(at + 5)?));
+            }
+            0xd0..=0xd9 | 0x01 => at += 2,
+            _ => at += 2 + be16(at + 2)? as usize,
+        }
+    }
+}
+
+/// A bookmark name for the heading with `id`: Word only allows letters,
+/// digits and underscores, at most 40 of them, and hides bookmarks that
+/// start with an underscore.
+fn bookmark(id: &str) -> String {
+    let name: String = id
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("_{}", name).chars().take(40).collect()
+}
+
+struct Writer<'a, 'o> {
+    body: String,
+    docx: &'o DocxOptions,
+    /// Relationships of the main document after the styles and numbering,
+    /// which take the first two ids: their type and target.
+    relationships: Vec<(&'static str, String)>,
+    targets: HashMap<(&'static str, String), usize>,
+    /// Embedded images by source, with their relationship id and size in
+    /// EMUs; `None` for those written as alt text.
+    images: HashMap<String, Option<(usize, u64, u64)>>,
+    media: Vec<(String, &'o [u8])>,
+    /// Every list so far: its start number, `None` for bullet lists, and
+    /// its nesting level.
+    lists: Vec<(Option<u64>, usize)>,
+    /// The numbering instance of every open list.
+    open_lists: Vec<usize>,
+    /// Whether each open list item has had its numbered paragraph.
+    items: Vec<bool>,
+    /// A paragraph is open, waiting for more runs.
+    paragraph: bool,
+    heading: Option<(usize, Option<String>)>,
+    quotes: usize,
+    definitions: usize,
+    code: Option<String>,
+    /// The alignment of every column of the current table, and the column
+    /// of the current cell.
+    table: Option<(Vec<Alignment>, usize)>,
+    bold: usize,
+    italic: usize,
+    strikethrough: usize,
+    superscript: usize,
+    subscript: usize,
+    links: usize,
+    code_span: bool,
+    /// The source and alt text of the image being read.
+    image: Option<(CowStr<'a>, String)>,
+    /// The number of the footnote whose definition's first paragraph is
+    /// next, which starts with it.
+    footnote: Option<usize>,
+    hidden: usize,
+    bookmarks: usize,
+    drawings: usize,
+    numbers: HashMap<CowStr<'a>, usize>,
+}
+
+impl<'a, 'o> Writer<'a, 'o> {
+    fn new(docx: &'o DocxOptions) -> Self {
+        Writer {
+            body: String::new(),
+            docx,
+            relationships: Vec::new(),
+            targets: HashMap::new(),
+            images: HashMap::new(),
+            media: Vec::new(),
+            lists: Vec::new(),
+            open_lists: Vec::new(),
+            items: Vec::new(),
+            paragraph: false,
+            heading: None,
+            quotes: 0,
+            definitions: 0,
+            code: None,
+            table: None,
+            bold: 0,
+            italic: 0,
+            strikethrough: 0,
+            superscript: 0,
+            subscript: 0,
+            links: 0,
+            code_span: false,
+            image: None,
+            footnote: None,
+            hidden: 0,
+            bookmarks: 0,
+            drawings: 0,
+            numbers: HashMap::new(),
+        }
+    }
+
+    fn event(&mut self, event: Event<'a>) {
+        if self.hidden > 0 {
+            match event {
+                Event::Start(Tag::MetadataBlock(_)) => self.hidden += 1,
+                Event::End(TagEnd::MetadataBlock(_)) => self.hidden -= 1,
+                _ => {}
+            }
+            return;
+        }
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) | Event::InlineMath(text) | Event::DisplayMath(text) => {
+                match (self.code.as_mut(), self.image.as_mut()) {
+                    (Some(code), _) => code.push_str(&text),
+                    (_, Some((_, alt))) => alt.push_str(&text),
+                    _ => self.run(&text),
+                }
+            }
+            Event::Code(code) => {
+                self.code_span = true;
+                self.run(&code);
+                self.code_span = false;
+            }
+            Event::SoftBreak => self.run(" "),
+            Event::HardBreak => {
+                self.open();
+                self.body.push_str("<w:r><w:br/></w:r>");
+            }
+            Event::Rule => {
+                self.close();
+                self.body.push_str(
+                    "<w:p><w:pPr><w:pBdr><w:bottom w:val=\"single\" w:sz=\"6\" w:space=\"1\" \
+                     w:color=\"auto\"/></w:pBdr></w:pPr></w:p>",
+                );
+            }
+            Event::FootnoteReference(name) => {
+                let number = self.number(name);
+                self.superscript += 1;
+                self.run(&number.to_string());
+                self.superscript -= 1;
+            }
+            Event::TaskListMarker(checked) => self.run(if checked { "☒ " } else { "☐ " }),
+            Event::Html(_) | Event::InlineHtml(_) => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'a>) {
+        match tag {
+            Tag::Paragraph => self.close(),
+            Tag::Heading { level, id, .. } => {
+                self.close();
+                self.heading = Some((level as usize, id.map(|id| bookmark(&id))));
+            }
+            Tag::BlockQuote(_) => {
+                self.close();
+                self.quotes += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.close();
+                self.code = Some(String::new());
+            }
+            Tag::List(start) => {
+                self.close();
+                self.lists.push((start, self.open_lists.len().min(8)));
+                self.open_lists.push(self.lists.len());
+            }
+            Tag::Item => {
+                self.close();
+                self.items.push(false);
+            }
+            Tag::FootnoteDefinition(name) => {
+                self.close();
+                self.footnote = Some(self.number(name));
+            }
+            Tag::DefinitionListTitle => {
+                self.close();
+                self.bold += 1;
+            }
+            Tag::DefinitionListDefinition => {
+                self.close();
+                self.definitions += 1;
+            }
+            Tag::Table(alignments) => {
+                self.close();
+                let width = TEXT_WIDTH / alignments.len().max(1) as u32;
+                self.body.push_str(
+                    "<w:tbl><w:tblPr><w:tblStyle w:val=\"Table\"/><w:tblW w:w=\"0\" w:type=\"auto\"/>\
+                     <w:tblLook w:val=\"0020\" w:firstRow=\"1\"/></w:tblPr><w:tblGrid>",
+                );
+                for _ in &alignments {
+                    self.body
+                        .push_str(&format!("<w:gridCol w:w=\"{}\"/>", width));
+                }
+                self.body.push_str("</w:tblGrid>");
+                self.table = Some((alignments, 0));
+            }
+            Tag::TableHead => {
+                self.body.push_str("<w:tr><w:trPr><w:tblHeader/></w:trPr>");
+                self.bold += 1;
+            }
+            Tag::TableRow => self.body.push_str("<w:tr>"),
+            Tag::TableCell => self
+                .body
+                .push_str("<w:tc><w:tcPr><w:tcW w:w=\"0\" w:type=\"auto\"/></w:tcPr>"),
+            Tag::Emphasis => self.italic += 1,
+            Tag::Strong => self.bold += 1,
+            Tag::Strikethrough => self.strikethrough += 1,
+            Tag::Superscript => self.superscript += 1,
+            Tag::Subscript => self.subscript += 1,
+            Tag::Link {
+                link_type,
+                dest_url,
+                ..
+            } => {
+                self.open();
+                match dest_url.strip_prefix('#') {
+                    Some(id) => self.body.push_str(&format!(
+                        "<w:hyperlink w:anchor=\"{}\">",
+                        escape(&bookmark(id))
+                    )),
+                    None => {
+                        let url = match link_type {
+                            LinkType::Email => format!("mailto:{}", dest_url),
+                            _ => dest_url.to_string(),
+                        };
+                        let id = self.relationship("hyperlink", &url);
+                        self.body
+                            .push_str(&format!("<w:hyperlink r:id=\"rId{}\">", id));
+                    }
+                }
+                self.links += 1;
+            }
+            Tag::Image { dest_url, .. } => self.image = Some((dest_url, String::new())),
+            Tag::MetadataBlock(_) => self.hidden = 1,
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => self.close(),
+            TagEnd::Heading(_) => {
+                self.close();
+                self.heading = None;
+            }
+            TagEnd::BlockQuote(_) => {
+                self.close();
+                self.quotes -= 1;
+            }
+            TagEnd::CodeBlock => {
+                let code = self.code.clone().unwrap_or_default();
+                for line in code.strip_suffix('\n').unwrap_or(&code).split('\n') {
+                    self.open();
+                    self.body.push_str(&runs(line, ""));
+                    self.close();
+                }
+                self.code = None;
+            }
+            TagEnd::List(_) => {
+                self.close();
+                self.open_lists.pop();
+            }
+            TagEnd::Item => {
+                self.close();
+                self.items.pop();
+            }
+            TagEnd::FootnoteDefinition => self.close(),
+            TagEnd::DefinitionListTitle => {
+                self.close();
+                self.bold -= 1;
+            }
+            TagEnd::DefinitionListDefinition => {
+                self.close();
+                self.definitions -= 1;
+            }
+            TagEnd::Table => {
+                self.body.push_str("</w:tbl>");
+                self.table = None;
+            }
+            TagEnd::TableHead => {
+                self.body.push_str("</w:tr>");
+                self.bold -= 1;
+                if let Some((_, column)) = self.table.as_mut() {
+                    *column = 0;
+                }
+            }
+            TagEnd::TableRow => {
+                self.body.push_str("</w:tr>");
+                if let Some((_, column)) = self.table.as_mut() {
+                    *column = 0;
+                }
+            }
+            TagEnd::TableCell => {
+                // Every cell needs a paragraph, even an empty one.
+                self.open();
+                self.close();
+                self.body.push_str("</w:tc>");
+                if let Some((_, column)) = self.table.as_mut() {
+                    *column += 1;
+                }
+            }
+            TagEnd::Emphasis => self.italic -= 1,
+            TagEnd::Strong => self.bold -= 1,
+            TagEnd::Strikethrough => self.strikethrough -= 1,
+            TagEnd::Superscript => self.superscript -= 1,
+            TagEnd::Subscript => self.subscript -= 1,
+            TagEnd::Link => {
+                self.body.push_str("</w:hyperlink>");
+                self.links -= 1;
+            }
+            TagEnd::Image => {
+                if let Some((src, alt)) = self.image.take() {
+                    self.picture(&src, &alt);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Opens a paragraph if none is, styled for where it is.
+    fn open(&mut self) {
+        if self.paragraph {
+            return;
+        }
+        self.paragraph = true;
+        let mut properties = String::new();
+        let style = match (&self.heading, self.code.is_some()) {
+            (Some((level, _)), _) => Some(format!("Heading{}", level)),
+            (None, true) => Some("SourceCode".to_string()),
+            _ if !self.items.is_empty() => Some("ListParagraph".to_string()),
+            _ if self.quotes > 0 => Some("Quote".to_string()),
+            _ => None,
+        };
+        if let Some(style) = style {
+            properties.push_str(&format!("<w:pStyle w:val=\"{}\"/>", style));
+        }
+        match self.items.last_mut() {
+            Some(numbered @ false) => {
+                *numbered = true;
+                let list = *self.open_lists.last().expect("items are inside lists");
+                properties.push_str(&format!(
+                    "<w:numPr><w:ilvl w:val=\"{}\"/><w:numId w:val=\"{}\"/></w:numPr>",
+                    self.lists[list - 1].1,
+                    list
+                ));
+            }
+            // Later paragraphs line up with the item's text.
+            Some(true) => properties.push_str(&format!(
+                "<w:ind w:left=\"{}\"/>",
+                720 * self.open_lists.len().min(9)
+            )),
+            None if self.definitions > 0 => properties.push_str("<w:ind w:left=\"720\"/>"),
+            None => {}
+        }
+        if let Some((alignments, column)) = &self.table {
+            let justification = match alignments.get(*column) {
+                Some(Alignment::Center) => "center",
+                Some(Alignment::Right) => "right",
+                _ => "left",
+            };
+            properties.push_str(&format!("<w:jc w:val=\"{}\"/>", justification));
+        }
+        self.body.push_str("<w:p>");
+        if !properties.is_empty() {
+            self.body
+                .push_str(&format!("<w:pPr>{}</w:pPr>", properties));
+        }
+        if let Some((_, Some(name))) = &self.heading {
+            self.bookmarks += 1;
+            self.body.push_str(&format!(
+                "<w:bookmarkStart w:id=\"{}\" w:name=\"{}\"/><w:bookmarkEnd w:id=\"{}\"/>",
+                self.bookmarks,
+                escape(name),
+                self.bookmarks
+            ));
+        }
+        if let Some(number) = self.footnote.take() {
+            self.body.push_str(&runs(
+                &number.to_string(),
+                "<w:vertAlign w:val=\"superscript\"/>",
+            ));
+            self.body.push_str(&runs(" ", ""));
+        }
+    }
+
+    fn close(&mut self) {
+        if self.paragraph {
+            self.body.push_str("</w:p>");
+            self.paragraph = false;
+        }
+    }
+
+    fn run(&mut self, text: &str) {
+        self.open();
+        let mut properties = String::new();
+        if self.code_span {
+            properties.push_str("<w:rStyle w:val=\"VerbatimChar\"/>");
+        } else if self.links > 0 {
+            properties.push_str("<w:rStyle w:val=\"Hyperlink\"/>");
+        }
+        if self.bold > 0 {
+            properties.push_str("<w:b/>");
+        }
+        if self.italic > 0 {
+            properties.push_str("<w:i/>");
+        }
+        if self.strikethrough > 0 {
+            properties.push_str("<w:strike/>");
+        }
+        if self.superscript > 0 {
+            properties.push_str("<w:vertAlign w:val=\"superscript\"/>");
+        } else if self.subscript > 0 {
+            properties.push_str("<w:vertAlign w:val=\"subscript\"/>");
+        }
+        self.body.push_str(&runs(text, &properties));
+    }
+
+    /// Embeds the image from `src`, or writes its alt text when there are
+    /// no usable bytes for it.
+    fn picture(&mut self, src: &str, alt: &str) {
+        let embedded = match self.images.get(src) {
+            Some(embedded) => *embedded,
+            None => {
+                let docx = self.docx;
+                let embedded = docx.images.get(src).and_then(|bytes| {
+                    let (extension, width, height) = image_size(bytes)?;
+                    let path = format!("media/image{}.{}", self.media.len() + 1, extension);
+                    self.media.push((path.clone(), bytes));
+                    let id = self.relationship("image", &path);
+                    // Shrink to the text width, keeping the aspect ratio.
+                    let (mut cx, mut cy) =
+                        (width as u64 * EMU_PER_PIXEL, height as u64 * EMU_PER_PIXEL);
+                    let max = TEXT_WIDTH as u64 * EMU_PER_TWIP;
+                    if cx > max {
+                        cy = cy * max / cx;
+                        cx = max;
+                    }
+                    Some((id, cx, cy))
+                });
+                self.images.insert(src.to_string(), embedded);
+                embedded
+            }
+        };
+        let Some((id, cx, cy)) = embedded else {
+            self.run(alt);
+            return;
+        };
+        self.open();
+        self.drawings += 1;
+        self.body.push_str(&format!(
+            "<w:r><w:drawing><wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\">\
+             <wp:extent cx=\"{cx}\" cy=\"{cy}\"/><wp:docPr id=\"{n}\" name=\"Picture {n}\" descr=\"{alt}\"/>\
+             <wp:cNvGraphicFramePr><a:graphicFrameLocks noChangeAspect=\"1\"/></wp:cNvGraphicFramePr>\
+             <a:graphic><a:graphicData uri=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">\
+             <pic:pic><pic:nvPicPr><pic:cNvPr id=\"{n}\" name=\"Picture {n}\"/><pic:cNvPicPr/></pic:nvPicPr>\
+             <pic:blipFill><a:blip r:embed=\"rId{id}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill>\
+             <pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm>\
+             <a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic></a:graphicData>\
+             </a:graphic>\
+             </wp:inline></w:drawing></w:r>",
+            n = self.drawings,
+            alt = escape(alt),
+        ));
+    }
+
+    /// The id of the relationship to `target`, added the first time.
+    fn relationship(&mut self, kind: &'static str, target: &str) -> usize {
+        let key = (kind, target.to_string());
+        if let Some(&id) = self.targets.get(&key) {
+            return id;
+        }
+        self.relationships.push(key.clone());
+        let id = self.relationships.len() + 2;
+        self.targets.insert(key, id);
+        id
+    }
+
+    /// The number of a footnote, counting from 1 in order of first mention
+    /// like the HTML renderer does.
+    fn number(&mut self, name: CowStr<'a>) -> usize {
+        let len = self.numbers.len() + 1;
+        *self.numbers.entry(name).or_insert(len)
+    }
+}
+
+/// `text` as runs with `properties`, tabs written as tab characters.
+fn runs(text: &str, properties: &str) -> String {
+    let mut out = String::from("<w:r>");
+    if !properties.is_empty() {
+        out.push_str(&format!("<w:rPr>{}</w:rPr>", properties));
+    }
+    for (index, part) in text.split('\t').enumerate() {
+        if index > 0 {
+            out.push_str("<w:tab/>");
+        }
+        if !part.is_empty() {
+            out.push_str(&format!(
+                "<w:t xml:space=\"preserve\">{}</w:t>",
+                escape(part)
+            ));
+        }
+    }
+    out.push_str("</w:r>");
+    out
+}
@@ -0,0 +1,501 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::{CowStr, Event, HeadingLevel, Tag, TagEnd};
+use pulldown_cmark_escape::escape_href;
+use serde::Deserialize;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::front_matter;
+use crate::highlight;
+use crate::options::{HighlightStyle, MarkdownOptions};
+use crate::render;
+use crate::xml::escape;
+use crate::zip::Zip;
+
+#[wasm_bindgen(typescript_custom_section)]
+const EPUB_SCHEMA: &'static str = r#"
+/** A chapter of the book made by `build_epub`. */
+export interface EpubChapter {
+  /** The chapter's file name, e.g. "intro.md". Links to it from other chapters lead to the chapter. */
+  name: string;
+  markdown: string;
+}
+"#;
+
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct Chapter {
+    pub name: String,
+    pub markdown: String,
+}
+
+/// Settings for `build_epub`, and the files the chapters refer to.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpubOptions {
+    /// When the book was last changed, as `YYYY-MM-DDThh:mm:ssZ`. Empty to
+    /// use the front matter `date` at midnight; one of the two is needed.
+    #[wasm_bindgen(getter_with_clone)]
+    pub modified: String,
+    resources: Vec<Resource>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Resource {
+    href: String,
+    media_type: String,
+    bytes: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl EpubOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> EpubOptions {
+        EpubOptions::default()
+    }
+
+    /// Adds a file such as an image at `href`, relative to the chapters,
+    /// with its media type. Every local `src` in the chapters needs one.
+    pub fn add_resource(&mut self, href: &str, media_type: &str, bytes: &[u8]) {
+        let href = href.trim_start_matches("./");
+        self.resources.retain(|resource| resource.href != href);
+        self.resources.push(Resource {
+            href: href.to_string(),
+            media_type: media_type.to_string(),
+            bytes: bytes.to_vec(),
+        });
+    }
+}
+
+const STYLESHEET: &str = "body { font-family: serif; line-height: 1.5; }
+pre, code { font-family: monospace; }
+pre { white-space: pre-wrap; }
+blockquote { margin-left: 1.5em; padding-left: 1em; border-left: 3px solid #ccc; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #999; padding: 0.2em 0.5em; }
+img { max-width: 100%; }
+";
+
+/// Bundles `chapters`, in reading order, into an EPUB 3 book. Each chapter
+/// is rendered with `options` into its own XHTML file; its title is its
+/// first level 1 heading, else its front matter `title`, else its name.
+/// The book's `title`, `author`, `language`, `identifier` and `date` come
+/// from the first chapter whose front matter has them. Chapters are
+/// written in the XHTML mode, so raw HTML is re-serialized as XML. Fails
+/// without a modification time, or when a chapter refers to a file that is
+/// not one of `epub`'s resources.
+pub(crate) fn build(
+    chapters: &[Chapter],
+    options: &MarkdownOptions,
+    epub: &EpubOptions,
+) -> Result<Vec<u8>, String> {
+    let options = MarkdownOptions {
+        front_matter: true,
+        source_positions: false,
+        xhtml: true,
+        ..options.clone()
+    };
+    let files: HashMap<&str, String> = chapters
+        .iter()
+        .enumerate()
+        .map(|(index, chapter)| (chapter.name.trim_start_matches("./"), file(index)))
+        .collect();
+    let fields: Vec<_> = chapters
+        .iter()
+        .map(|chapter| front_matter::fields(&chapter.markdown))
+        .collect();
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find_map(|fields| fields.get(key).filter(|value| !value.is_null()))
+    };
+    let text = |value: &Value| match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    };
+    let language = field("language")
+        .or_else(|| field("lang"))
+        .map(text)
+        .unwrap_or_else(|| "en".to_string());
+    let date = field("date").map(text);
+    let modified = modified(&epub.modified, date.as_deref())?;
+    let reserved = |href: &str| {
+        ["nav.xhtml", "style.css", "content.opf"].contains(&href)
+            || (0..chapters.len()).any(|index| file(index) == href)
+    };
+    for resource in &epub.resources {
+        let href = &resource.href;
+        if href.is_empty()
+            || href.starts_with('/')
+            || href.contains(':')
+            || href.split('/').any(|part| part == "..")
+        {
+            return Err(format!(
+                "resource {:?} is not a relative path inside the book",
+                href
+            ));
+        }
+        if reserved(href) {
+            return Err(format!(
+                "resource {:?} has the name of a file the book is made of",
+                href
+            ));
+        }
+        if resource.media_type.is_empty() {
+            return Err(format!("resource {:?} has no media type", href));
+        }
+    }
+
+    let mut zip = Zip::new();
+    zip.add("mimetype", b"application/epub+zip");
+    zip.add(
+        "META-INF/container.xml",
+        b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+          <container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\
+          <rootfiles><rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/>\
+          </rootfiles></container>",
+    );
+
+    let mut stylesheet = STYLESHEET.to_string();
+    if options.highlight && options.highlight_style == HighlightStyle::Classes {
+        stylesheet.push_str(&highlight::stylesheet());
+    }
+    zip.add("OEBPS/style.css", stylesheet.as_bytes());
+
+    let mut titles = Vec::new();
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    for (index, chapter) in chapters.iter().enumerate() {
+        let events = render::parse(&chapter.markdown, &options);
+        let title = first_heading(&events)
+            .or_else(|| fields[index].get("title").map(text))
+            .unwrap_or_else(|| chapter.name.clone());
+        let events = events
+            .into_iter()
+            .map(|(event, range)| match event {
+                Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }) => {
+                    let dest_url = link(&files, dest_url);
+                    (
+                        Event::Start(Tag::Link {
+                            link_type,
+                            dest_url,
+                            title,
+                            id,
+                        }),
+                        range,
+                    )
+                }
+                event => (event, range),
+            })
+            .collect();
+        let body = render::render_events(events, &options, None);
+        let document = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+             <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" \
+             xml:lang=\"{0}\" lang=\"{0}\">\n<head>\n<meta charset=\"UTF-8\"/>\n<title>{1}</title>\n\
+             <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n\
+             <body>\n{2}</body>\n</html>\n",
+            escape(&language),
+            escape(&title),
+            body
+        );
+        zip.add(&format!("OEBPS/{}", file(index)), document.as_bytes());
+
+        let mut properties = Vec::new();
+        if body.contains("<math") {
+            properties.push("mathml");
+        }
+        let mut remote = false;
+        for source in sources(&body) {
+            if source.starts_with("http://") || source.starts_with("https://") {
+                remote = true;
+            } else if !source.starts_with("data:") {
+                let path = decode(source.split('#').next().unwrap_or_default());
+                let path = path.trim_start_matches("./");
+                if !epub.resources.iter().any(|resource| resource.href == path) {
+                    return Err(format!(
+                        "{} refers to {:?}, which is not one of the resources",
+                        chapter.name, source
+                    ));
+                }
+            }
+        }
+        if remote {
+            properties.push("remote-resources");
+        }
+        let properties = match properties.is_empty() {
+            true => String::new(),
+            false => format!(" properties=\"{}\"", properties.join(" ")),
+        };
+        manifest.push_str(&format!(
+            "<item id=\"chapter-{0}\" href=\"{1}\" media-type=\"application/xhtml+xml\"{2}/>\n",
+            index + 1,
+            file(index),
+            properties
+        ));
+        spine.push_str(&format!("<itemref idref=\"chapter-{}\"/>\n", index + 1));
+        titles.push(title);
+    }
+
+    let title = field("title")
+        .map(text)
+        .or_else(|| titles.first().cloned())
+        .unwrap_or_default();
+    let mut toc = String::new();
+    for (index, chapter) in titles.iter().enumerate() {
+        toc.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            file(index),
+            escape(chapter)
+        ));
+    }
+    let nav = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" \
+         xml:lang=\"{0}\" lang=\"{0}\">\n<head>\n<meta charset=\"UTF-8\"/>\n<title>{1}</title>\n</head>\n\
+         <body>\n<nav epub:type=\"toc\" id=\"toc\">\n<h1>{1}</h1>\n<ol>\n{2}</ol>\n</nav>\n\
+         </body>\n</html>\n",
+        escape(&language),
+        escape(&title),
+        toc
+    );
+    zip.add("OEBPS/nav.xhtml", nav.as_bytes());
+
+    for (index, resource) in epub.resources.iter().enumerate() {
+        let mut href = String::new();
+        escape_href(&mut href, &resource.href).expect("writing to a string cannot fail");
+        manifest.push_str(&format!(
+            "<item id=\"resource-{}\" href=\"{}\" media-type=\"{}\"/>\n",
+            index + 1,
+            href,
+            escape(&resource.media_type)
+        ));
+        zip.add(&format!("OEBPS/{}", resource.href), &resource.bytes);
+    }
+
+    let authors = match field("author").or_else(|| field("authors")) {
+        Some(Value::Array(authors)) => authors.iter().map(text).collect(),
+        Some(author) => vec![text(author)],
+        None => Vec::new(),
+    };
+    let mut metadata = format!(
+        "<dc:identifier id=\"book-id\">{}</dc:identifier>\n<dc:title>{}</dc:title>\n\
+         <dc:language>{}</dc:language>\n",
+        escape(
+            &field("identifier")
+                .map(text)
+                .unwrap_or_else(|| identifier(chapters))
+        ),
+        escape(&title),
+        escape(&language)
+    );
+    for author in authors {
+        metadata.push_str(&format!("<dc:creator>{}</dc:creator>\n", escape(&author)));
+    }
+    if let Some(date) = &date {
+        metadata.push_str(&format!("<dc:date>{}</dc:date>\n", escape(date)));
+    }
+    metadata.push_str(&format!(
+        "<meta property=\"dcterms:modified\">{}</meta>\n",
+        modified
+    ));
+    let package = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" \
+         xml:lang=\"{}\">\n<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n{}</metadata>\n\
+         <manifest>\n<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" \
+         properties=\"nav\"/>\n<item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n\
+         {}</manifest>\n\
+         <spine>\n{}</spine>\n</package>\n",
+        escape(&language),
+        metadata,
+        manifest,
+        spine
+    );
+    zip.add("OEBPS/content.opf", package.as_bytes());
+    Ok(zip.finish())
+}
+
+fn file(index: usize) -> String {
+    format!("chapter-{}.xhtml", index + 1)
+}
+
+/// `url` pointed at the chapter file when it names one of the chapters.
+fn link<'a>(files: &HashMap<&str, String>, url: CowStr<'a>) -> CowStr<'a> {
+    let (path, fragment) = match url.find('#') {
+        Some(index) => (&url[..index], &url[index..]),
+        None => (&*url, ""),
+    };
+    match files.get(path.trim_start_matches("./")) {
+        Some(file) => format!("{}{}", file, fragment).into(),
+        None => url,
+    }
+}
+
+fn first_heading(events: &[render::Spanned]) -> Option<String> {
+    let start = events.iter().position(|(event, _)| {
+        matches!(
+            event,
+            Event::Start(Tag::Heading {
+                level: HeadingLevel::H1,
+                ..
+            })
+        )
+    })?;
+    let mut text = String::new();
+    for (event, _) in &events[start + 1..] {
+        match event {
+            Event::Text(part) | Event::Code(part) => text.push_str(part),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            Event::End(TagEnd::Heading(_)) => break,
+            _ => {}
+        }
+    }
+    Some(text.trim().to_string())
+}
+
+/// A stable identifier for a book without one: a hash of its chapters, so
+/// rebuilding the same book gives the same id.
+fn identifier(chapters: &[Chapter]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for chapter in chapters {
+        for byte in chapter
+            .name
+            .bytes()
+            .chain([0])
+            .chain(chapter.markdown.bytes())
+            .chain([0])
+        {
+            hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("urn:md:{:016x}", hash)
+}
+
+/// `dcterms:modified`, which must be a UTC timestamp: `modified`, or else
+/// midnight of a front matter `date`.
+fn modified(modified: &str, date: Option<&str>) -> Result<String, String> {
+    let matches = |text: &str, pattern: &str| {
+        text.len() == pattern.len()
+            && text
+                .bytes()
+                .zip(pattern.bytes())
+                .all(|(byte, expected)| match expected {
+                    b'0' => byte.is_ascii_digit(),
+                    expected => byte == expected,
+                })
+    };
+    match (modified, date) {
+        ("", None) => Err("the book needs a modification time or a front matter date".to_string()),
+        ("", Some(date)) => match date.get(..10).filter(|day| matches(day, "0000-00-00")) {
+            Some(day) => Ok(format!("{}T00:00:00Z", day)),
+            None => Err(format!(
+                "the front matter date {:?} does not start with YYYY-MM-DD",
+                date
+            )),
+        },
+        (modified, _) if matches(modified, "0000-00-00T00:00:00Z") => Ok(modified.to_string()),
+        (modified, _) => Err(format!(
+            "the modification time {:?} is not YYYY-MM-DDThh:mm:ssZ",
+            modified
+        )),
+    }
+}
+
+/// The `src` of every element in a chapter's XHTML, with character
+/// references resolved. Text and attribute values have their `<` escaped,
+/// so every other `<` starts a tag or a comment.
+fn sources(xhtml: &str) -> Vec<String> {
+    let mut sources = Vec::new();
+    let mut rest = xhtml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.split_once("-->").map_or("", |(_, after)| after);
+            continue;
+        }
+        // Attribute values are double-quoted, with `"` escaped.
+        let mut tag = rest.trim_start_matches(|c: char| !c.is_whitespace() && c != '>');
+        while let Some((name, after)) = tag.trim_start().split_once("=\"") {
+            if name.contains('>') {
+                break;
+            }
+            let Some((value, after)) = after.split_once('"') else {
+                break;
+            };
+            if name == "src" {
+                sources.push(unescape(value));
+            }
+            tag = after;
+        }
+        rest = tag;
+    }
+    sources
+}
+
+/// `text` with the character references the XHTML writer uses resolved.
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let reference = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            name => match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => name
+                    .strip_prefix('#')
+                    .and_then(|decimal| decimal.parse().ok())
+                    .and_then(char::from_u32),
+            },
+        };
+        match reference {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `url` with its percent escapes decoded, as a resource's path.
+fn decode(url: &str) -> String {
+    let bytes = url.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = url
+            .get(index + 1..index + 3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
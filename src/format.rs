@@ -0,0 +1,1431 @@
+use std::ops::Range;
+
+use pulldown_cmark::{
+    Alignment, BlockQuoteKind, BrokenLink, CodeBlockKind, Event, LinkType, MetadataBlockKind,
+    Parser, Tag, TagEnd,
+};
+use unicode_width::UnicodeWidthStr;
+use wasm_bindgen::prelude::*;
+
+use crate::front_matter;
+use crate::options::MarkdownOptions;
+use crate::render::Spanned;
+
+/// What `format_markdown` does with the line breaks inside paragraphs.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProseWrap {
+    /// Keep the source's line breaks.
+    #[default]
+    Preserve,
+    /// Refill paragraphs to `line_width` columns.
+    Always,
+    /// Put every paragraph on a single line.
+    Never,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BulletMarker {
+    #[default]
+    Dash,
+    Asterisk,
+    Plus,
+}
+
+/// The delimiter written around emphasis or strong emphasis. Underscores
+/// cannot emphasize part of a word, so such spans keep asterisks.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EmphasisMarker {
+    #[default]
+    Asterisk,
+    Underscore,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FenceStyle {
+    #[default]
+    Backtick,
+    Tilde,
+}
+
+/// Output style for `format_markdown`.
+///
+/// Headings are always written ATX style, code blocks fenced and thematic
+/// breaks as `---`. Where two adjacent lists would merge if written with the
+/// same marker, the second one uses another.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Column limit for `ProseWrap.Always`, counting container indentation.
+    pub line_width: usize,
+    pub prose_wrap: ProseWrap,
+    pub bullet: BulletMarker,
+    pub emphasis: EmphasisMarker,
+    pub strong: EmphasisMarker,
+    pub fence: FenceStyle,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            line_width: 80,
+            prose_wrap: ProseWrap::default(),
+            bullet: BulletMarker::default(),
+            emphasis: EmphasisMarker::default(),
+            strong: EmphasisMarker::default(),
+            fence: FenceStyle::default(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl FormatOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> FormatOptions {
+        FormatOptions::default()
+    }
+}
+
+// Inline content is assembled with markers the block writer resolves once it
+// knows where lines break. NULs from the input are markers too, so every NUL
+// starts one.
+/// A space a line may break at.
+const SPACE: &str = "\0 ";
+/// A soft line break from the source.
+const SOFT_BREAK: &str = "\0/";
+/// Escape the next character if it ends up starting a line.
+const LINE_START: &str = "\0!";
+/// A NUL from the input.
+const NUL: &str = "\0N";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Paragraph,
+    /// The bare inline content of a tight list item.
+    Text,
+    List {
+        ordered: bool,
+        marker: char,
+    },
+    Rule,
+    /// An HTML block only a blank line ends, which `interrupts` a
+    /// paragraph or else needs a blank line before it too.
+    Html {
+        interrupts: bool,
+    },
+    Other,
+}
+
+type Block = (Kind, Vec<String>);
+
+/// Where inline content is written, which decides what needs escaping.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Inline {
+    Paragraph,
+    Heading,
+    TableCell,
+}
+
+/// Re-serializes `input` as normalized Markdown that renders to the same
+/// HTML, apart from whitespace between words.
+pub(crate) fn format(input: &str, options: &MarkdownOptions, format: &FormatOptions) -> String {
+    let offset = match options.front_matter {
+        true => front_matter::block_len(input),
+        false => 0,
+    };
+    let parser = Parser::new_ext(&input[offset..], options.into()).into_offset_iter();
+    let mut definitions: Vec<(usize, String)> = parser
+        .reference_definitions()
+        .iter()
+        .map(|(label, definition)| {
+            let mut line = format!("[{}]: {}", label, destination(&definition.dest));
+            if let Some(title) = &definition.title {
+                line.push(' ');
+                line.push_str(&quote(title));
+            }
+            (offset + definition.span.start, line)
+        })
+        .collect();
+    definitions.sort();
+    // Text comes split at escapes and entity references; escaping needs to
+    // see it whole.
+    let mut events: Vec<Spanned> = Vec::new();
+    for (event, range) in parser {
+        let range = range.start + offset..range.end + offset;
+        match (events.last_mut(), event) {
+            (Some((Event::Text(text), previous)), Event::Text(more)) => {
+                *text = format!("{}{}", text, more).into();
+                previous.end = range.end;
+            }
+            (_, event) => events.push((event, range)),
+        }
+    }
+
+    let mut formatter = Formatter {
+        input,
+        events,
+        position: 0,
+        options,
+        format,
+        definitions: definitions.into_iter().rev().collect(),
+        inlines: Vec::new(),
+        quotes: 0,
+        source_markers: false,
+    };
+    let mut blocks = formatter.blocks(format.line_width, true);
+    let rest: Vec<String> = formatter
+        .definitions
+        .drain(..)
+        .rev()
+        .map(|(_, line)| line)
+        .collect();
+    if !rest.is_empty() {
+        blocks.push((Kind::Other, rest));
+    }
+
+    let mut output = input[..offset].to_string();
+    let lines = join(blocks, true);
+    if !lines.is_empty() {
+        output.push_str(&lines.join("\n"));
+        output.push('\n');
+    }
+    output
+}
+
+struct Formatter<'a, 'o> {
+    input: &'a str,
+    events: Vec<Spanned<'a>>,
+    position: usize,
+    options: &'o MarkdownOptions,
+    format: &'o FormatOptions,
+    /// Link reference definitions still to write, last first.
+    definitions: Vec<(usize, String)>,
+    inlines: Vec<Span>,
+    /// How many block quotes the current block is in.
+    quotes: usize,
+    /// Whether emphasis is written with its delimiters from the source.
+    source_markers: bool,
+}
+
+/// An inline span being written.
+struct Span {
+    range: Range<usize>,
+    /// The length of its delimiters in the source.
+    source_length: usize,
+    /// The delimiter character written.
+    marker: char,
+    close: String,
+    /// Where its content starts in the output.
+    content_start: usize,
+    /// A reference link's type and label.
+    reference: Option<(LinkType, String)>,
+}
+
+impl<'a> Formatter<'a, '_> {
+    fn peek(&self) -> Option<&Spanned<'a>> {
+        self.events.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Spanned<'a>> {
+        let event = self.events.get(self.position).cloned();
+        self.position += 1;
+        event
+    }
+
+    /// The blocks up to the end of the enclosing container. At the top
+    /// level, link reference definitions are written where they were.
+    fn blocks(&mut self, width: usize, top: bool) -> Vec<Block> {
+        let mut blocks: Vec<Block> = Vec::new();
+        while let Some((event, range)) = self.peek() {
+            if let Event::End(_) = event {
+                break;
+            }
+            let (inline, start) = (is_inline(event), range.start);
+            if top {
+                let mut lines = Vec::new();
+                while self
+                    .definitions
+                    .last()
+                    .is_some_and(|(offset, _)| *offset < start)
+                {
+                    lines.push(self.definitions.pop().expect("a definition").1);
+                }
+                if !lines.is_empty() {
+                    blocks.push((Kind::Other, lines));
+                }
+            }
+            let block = match inline {
+                // The content of a tight list item.
+                true => (Kind::Text, self.paragraph(width)),
+                false => self.block(width, blocks.last().map(|block| block.0)),
+            };
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    fn block(&mut self, width: usize, previous: Option<Kind>) -> Block {
+        let (event, _) = self.next().expect("a block event");
+        let tag = match event {
+            Event::Start(tag) => tag,
+            Event::Rule => return (Kind::Rule, vec!["---".to_string()]),
+            _ => return (Kind::Other, Vec::new()),
+        };
+        let block = match tag {
+            Tag::Paragraph => (Kind::Paragraph, self.paragraph(width)),
+            Tag::Heading {
+                level,
+                id,
+                classes,
+                attrs,
+            } => {
+                let content = self.inline(Inline::Heading);
+                let mut attributes: Vec<String> = Vec::new();
+                if let Some(id) = id {
+                    attributes.push(format!("#{}", id));
+                }
+                attributes.extend(classes.iter().map(|class| format!(".{}", class)));
+                for (key, value) in &attrs {
+                    attributes.push(match value {
+                        Some(value) => format!("{}={}", key, value),
+                        None => key.to_string(),
+                    });
+                }
+                (Kind::Other, heading(&content, level as usize, &attributes))
+            }
+            Tag::BlockQuote(kind) => {
+                let mut lines: Vec<String> =
+                    kind.map(alert).into_iter().map(String::from).collect();
+                self.quotes += 1;
+                lines.extend(join(self.blocks(width.saturating_sub(2), false), true));
+                self.quotes -= 1;
+                if lines.is_empty() {
+                    lines.push(String::new());
+                }
+                let lines = lines
+                    .into_iter()
+                    .map(|line| match line.is_empty() {
+                        true => ">".to_string(),
+                        false => format!("> {}", line),
+                    })
+                    .collect();
+                (Kind::Other, lines)
+            }
+            Tag::CodeBlock(kind) => {
+                let info = match kind {
+                    CodeBlockKind::Fenced(info) => info.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                (Kind::Other, self.code_block(&info))
+            }
+            Tag::HtmlBlock => {
+                let mut html = String::new();
+                while let Some((Event::Html(text) | Event::Text(text), _)) = self.peek() {
+                    html.push_str(text);
+                    self.position += 1;
+                }
+                let kind = match ends_at_blank_line(&html) {
+                    true => Kind::Html {
+                        interrupts: interrupts_paragraph(&html),
+                    },
+                    false => Kind::Other,
+                };
+                // Trailing whitespace goes; a bare `\r` left there would end
+                // the line in the next pass.
+                (
+                    kind,
+                    html.trim_end_matches([' ', '\t', '\r', '\n'])
+                        .lines()
+                        .map(|line| line.trim_end_matches([' ', '\t', '\r']).to_string())
+                        .collect(),
+                )
+            }
+            Tag::List(start) => self.list(start, width, previous),
+            Tag::Table(alignments) => (Kind::Other, self.table(&alignments)),
+            Tag::FootnoteDefinition(label) => {
+                let blocks = self.blocks(width.saturating_sub(4), false);
+                // Only a paragraph can start on the label's line.
+                let inline = blocks
+                    .first()
+                    .is_some_and(|block| block.0 == Kind::Paragraph);
+                let mut lines = join(blocks, true);
+                if !inline {
+                    lines.insert(0, String::new());
+                }
+                (
+                    Kind::Other,
+                    indent(lines, &format!("[^{}]: ", label), "    "),
+                )
+            }
+            Tag::DefinitionList => (Kind::Other, self.definition_list(width)),
+            Tag::MetadataBlock(kind) => {
+                let fence = match kind {
+                    MetadataBlockKind::YamlStyle => "---",
+                    MetadataBlockKind::PlusesStyle => "+++",
+                };
+                let mut lines = vec![fence.to_string()];
+                while let Some((Event::Text(text), _)) = self.peek() {
+                    lines.extend(text.lines().map(String::from));
+                    self.position += 1;
+                }
+                lines.push(fence.to_string());
+                (Kind::Other, lines)
+            }
+            _ => {
+                self.skip();
+                return (Kind::Other, Vec::new());
+            }
+        };
+        self.next();
+        block
+    }
+
+    /// Skips past the end of the element just started.
+    fn skip(&mut self) {
+        let mut depth = 1;
+        while let Some((event, _)) = self.next() {
+            match event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return;
+            }
+        }
+    }
+
+    fn paragraph(&mut self, width: usize) -> Vec<String> {
+        let content = self.inline(Inline::Paragraph);
+        fill(&content, width, self.format.prose_wrap)
+    }
+
+    fn code_block(&mut self, info: &str) -> Vec<String> {
+        let mut code = String::new();
+        while let Some((Event::Text(text), _)) = self.peek() {
+            code.push_str(text);
+            self.position += 1;
+        }
+        let fence = match (self.format.fence, info.contains('`')) {
+            (FenceStyle::Backtick, false) => '`',
+            _ => '~',
+        };
+        let longest = code
+            .lines()
+            .map(|line| {
+                let line = line.trim_start_matches(' ');
+                line.len() - line.trim_start_matches(fence).len()
+            })
+            .max()
+            .unwrap_or(0);
+        let fence = fence.to_string().repeat(longest.max(2) + 1);
+        // An info string starting with the fence character would lengthen it.
+        let space = match info.starts_with(&fence[..1]) {
+            true => " ",
+            false => "",
+        };
+        let mut lines = vec![format!("{}{}{}", fence, space, info_string(info))];
+        // A CR left at the end of a line would read as part of its line
+        // ending.
+        lines.extend(
+            code.strip_suffix('\n')
+                .unwrap_or(&code)
+                .split('\n')
+                .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string()),
+        );
+        if code.is_empty() {
+            lines.pop();
+        }
+        lines.push(fence);
+        lines
+    }
+
+    fn list(&mut self, start: Option<u64>, width: usize, previous: Option<Kind>) -> Block {
+        let ordered = start.is_some();
+        let markers = match (ordered, self.format.bullet) {
+            (true, _) => ['.', ')', ')'],
+            (false, BulletMarker::Dash) => ['-', '*', '+'],
+            (false, BulletMarker::Asterisk) => ['*', '-', '+'],
+            (false, BulletMarker::Plus) => ['+', '-', '*'],
+        };
+        let prefix = |index: usize, marker: char| match start {
+            Some(start) => format!("{}{} ", start + index as u64, marker),
+            None => format!("{} ", marker),
+        };
+        let mut items: Vec<Vec<Block>> = Vec::new();
+        let mut loose = false;
+        while let Some((Event::Start(Tag::Item), _)) = self.peek() {
+            self.position += 1;
+            // Whether the item's own blocks are paragraphs rather than bare
+            // text is what makes a list loose.
+            let mut depth = 0;
+            for (event, _) in &self.events[self.position..] {
+                match event {
+                    Event::Start(Tag::Paragraph) if depth == 0 => loose = true,
+                    Event::Start(_) => depth += 1,
+                    Event::End(TagEnd::Item) if depth == 0 => break,
+                    Event::End(_) => depth -= 1,
+                    _ => {}
+                }
+            }
+            let indent = prefix(items.len(), markers[0]).len();
+            let blocks = self.blocks(width.saturating_sub(indent), false);
+            self.next();
+            items.push(blocks);
+        }
+        // Adjacent lists with the same marker would merge into one, and an
+        // item that starts with empty nested items can read as a thematic
+        // break.
+        let marker = markers
+            .into_iter()
+            .find(|&marker| {
+                previous != Some(Kind::List { ordered, marker })
+                    && !items.iter().enumerate().any(|(index, blocks)| {
+                        let first = blocks.first().and_then(|block| block.1.first());
+                        first.is_some_and(|line| is_rule(&(prefix(index, marker) + line)))
+                    })
+            })
+            .unwrap_or(markers[0]);
+        let items: Vec<(String, Vec<Block>)> = items
+            .into_iter()
+            .enumerate()
+            .map(|(index, blocks)| (prefix(index, marker), blocks))
+            .collect();
+        let mut lines = Vec::new();
+        for (index, (prefix, mut blocks)) in items.into_iter().enumerate() {
+            if loose && index > 0 {
+                lines.push(String::new());
+            }
+            // `---` right after a marker or a line of text would read as a
+            // list item or a setext underline.
+            for block in &mut blocks {
+                if block.0 == Kind::Rule {
+                    block.1 = vec!["___".to_string()];
+                }
+            }
+            let item = join(blocks, loose);
+            let rest = " ".repeat(prefix.len());
+            match item.first() {
+                None => lines.push(prefix.trim_end_matches(' ').to_string()),
+                // Spaces after the marker would be taken for its width, so
+                // an indented HTML block starts on the next line.
+                Some(first) if first.starts_with([' ', '\t']) => {
+                    lines.push(prefix.trim_end_matches(' ').to_string());
+                    lines.extend(indent(item, &rest, &rest));
+                }
+                Some(_) => lines.extend(indent(item, &prefix, &rest)),
+            }
+        }
+        (Kind::List { ordered, marker }, lines)
+    }
+
+    fn table(&mut self, alignments: &[Alignment]) -> Vec<String> {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        while let Some((event, _)) = self.next() {
+            match event {
+                Event::Start(Tag::TableHead | Tag::TableRow) => rows.push(Vec::new()),
+                Event::Start(Tag::TableCell) => {
+                    let cell = flatten(&self.inline(Inline::TableCell));
+                    self.next();
+                    rows.last_mut().expect("cells are inside rows").push(cell);
+                }
+                Event::End(TagEnd::Table) => {
+                    self.position -= 1;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let columns = alignments.len();
+        let mut widths = vec![3; columns];
+        for row in &mut rows {
+            row.resize(columns, String::new());
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.width());
+            }
+        }
+        let row_line = |row: &Vec<String>| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .zip(alignments)
+                .map(|((cell, width), alignment)| {
+                    let padding = width - cell.width();
+                    let (left, right) = match alignment {
+                        Alignment::Right => (padding, 0),
+                        Alignment::Center => (padding / 2, padding - padding / 2),
+                        _ => (0, padding),
+                    };
+                    format!("{}{}{}", " ".repeat(left), cell, " ".repeat(right))
+                })
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let delimiters: Vec<String> = widths
+            .iter()
+            .zip(alignments)
+            .map(|(width, alignment)| match alignment {
+                Alignment::None => "-".repeat(*width),
+                Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+                Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+                Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+            })
+            .collect();
+        let mut lines = Vec::new();
+        for (index, row) in rows.iter().enumerate() {
+            lines.push(row_line(row));
+            if index == 0 {
+                lines.push(format!("| {} |", delimiters.join(" | ")));
+            }
+        }
+        lines
+    }
+
+    fn definition_list(&mut self, width: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        while let Some((event, _)) = self.next() {
+            match event {
+                Event::Start(Tag::DefinitionListTitle) => {
+                    if !lines.is_empty() {
+                        lines.push(String::new());
+                    }
+                    let title = self.inline(Inline::Paragraph);
+                    lines.extend(fill(&title, 0, ProseWrap::Never));
+                    self.next();
+                }
+                Event::Start(Tag::DefinitionListDefinition) => {
+                    let blocks = self.blocks(width.saturating_sub(2), false);
+                    self.next();
+                    let loose = blocks.iter().any(|block| block.0 == Kind::Paragraph);
+                    if loose {
+                        lines.push(String::new());
+                    }
+                    let mut definition = join(blocks, loose);
+                    // An empty definition is a bare colon.
+                    if definition.is_empty() {
+                        definition.push(String::new());
+                    }
+                    lines.extend(indent(definition, ": ", "  "));
+                }
+                Event::End(TagEnd::DefinitionList) => {
+                    self.position -= 1;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        lines
+    }
+
+    /// Inline content up to the next block event, with the markers `fill`
+    /// resolves. Where the preferred emphasis delimiters would pair up
+    /// differently, the source's are kept.
+    fn inline(&mut self, context: Inline) -> String {
+        let start = self.position;
+        let out = self.inline_content(context);
+        if emphasis(&self.events[start..self.position]) == self.reparse_emphasis(&out) {
+            return out;
+        }
+        self.position = start;
+        self.source_markers = true;
+        let out = self.inline_content(context);
+        self.source_markers = false;
+        out
+    }
+
+    /// The emphasis in inline content as it would parse on its own.
+    fn reparse_emphasis(&self, content: &str) -> String {
+        let text = fill(content, 0, ProseWrap::Never).join("\n");
+        // Link references resolve elsewhere in the document.
+        let callback = |_: BrokenLink| Some(("".into(), "".into()));
+        let events: Vec<Spanned> =
+            Parser::new_with_broken_link_callback(&text, self.options.into(), Some(callback))
+                .into_offset_iter()
+                .collect();
+        emphasis(&events)
+    }
+
+    fn inline_content(&mut self, context: Inline) -> String {
+        let mut out = String::new();
+        while let Some((event, range)) = self.peek().cloned() {
+            if !is_inline(&event) {
+                break;
+            }
+            self.position += 1;
+            let start = out.len();
+            let (marked, opening) = (
+                matches!(event, Event::Text(_) | Event::SoftBreak),
+                matches!(event, Event::Start(_)),
+            );
+            match event {
+                Event::Text(text) => {
+                    let following = self.peek().map(|(event, _)| event);
+                    self.text(&mut out, &text, range, context, following);
+                }
+                Event::Code(code) => out.push_str(&code_span(&code)),
+                Event::InlineMath(tex) => out.push_str(&format!("${}$", tex)),
+                Event::DisplayMath(tex) => out.push_str(&format!("$${}$$", tex)),
+                // The raw HTML keeps the block quote markers and
+                // indentation of its continuation lines, which are written
+                // again with the line. Lines that could start a block are
+                // indented instead, and a lazy line, which has no markers to
+                // write again, joins the one before.
+                Event::InlineHtml(html) | Event::Html(html) => {
+                    for (index, mut line) in html.split('\n').enumerate() {
+                        if index > 0 {
+                            let mut lazy = false;
+                            for _ in 0..self.quotes {
+                                line = line.trim_start_matches([' ', '\t']);
+                                lazy |= !line.starts_with('>');
+                                line = line.strip_prefix('>').unwrap_or(line);
+                            }
+                            line = line.trim_start_matches([' ', '\t']);
+                            if lazy {
+                                out.push(' ');
+                            } else {
+                                out.push('\n');
+                                if !line.starts_with(char::is_alphabetic) {
+                                    out.push_str("    ");
+                                }
+                            }
+                        }
+                        out.push_str(line);
+                    }
+                }
+                Event::FootnoteReference(label) => out.push_str(&format!("[^{}]", label)),
+                Event::SoftBreak => out.push_str(SOFT_BREAK),
+                Event::HardBreak => out.push_str("\\\n"),
+                Event::TaskListMarker(checked) => {
+                    out.push_str(if checked { "[x] " } else { "[ ] " })
+                }
+                Event::Start(Tag::Link {
+                    link_type: LinkType::Autolink | LinkType::Email,
+                    dest_url,
+                    ..
+                }) => {
+                    out.push_str(&format!("<{}>", dest_url));
+                    self.skip();
+                }
+                // A wiki link's text is its target, unparsed.
+                Event::Start(Tag::Link {
+                    link_type: LinkType::WikiLink { has_pothole: false },
+                    dest_url,
+                    ..
+                }) if !dest_url.contains('\n') => {
+                    out.push_str(&format!("[[{}]]", dest_url));
+                    self.skip();
+                }
+                Event::Start(tag) => {
+                    let span = self.open(tag, range, &mut out);
+                    self.inlines.push(span);
+                }
+                Event::End(_) => {
+                    let span = self.inlines.pop().expect("inline spans are balanced");
+                    match span.reference {
+                        // Shortcut and collapsed references need their text
+                        // to match the label.
+                        Some((LinkType::Shortcut, label)) if out[span.content_start..] == label => {
+                            out.push(']')
+                        }
+                        Some((LinkType::Collapsed, label))
+                            if out[span.content_start..] == label =>
+                        {
+                            out.push_str("][]")
+                        }
+                        Some((_, label)) => out.push_str(&format!("][{}]", label)),
+                        None => out.push_str(&span.close),
+                    }
+                }
+                Event::Rule => {}
+            }
+            // Pipes end table cells even inside code spans and link
+            // destinations; text escapes its own, and its NULs.
+            let escaped = match context {
+                Inline::TableCell => ['\0', '|'].as_slice(),
+                _ => ['\0'].as_slice(),
+            };
+            if !marked && out[start..].contains(escaped) {
+                let mut piece = out.split_off(start).replace('\0', NUL);
+                if context == Inline::TableCell {
+                    piece = piece.replace('|', "\\|");
+                }
+                out.push_str(&piece);
+                if let Some(span) = self.inlines.last_mut().filter(|_| opening) {
+                    span.content_start = out.len();
+                }
+            }
+        }
+        out
+    }
+
+    /// Writes the opening delimiter of an inline span.
+    fn open(&self, tag: Tag<'a>, range: Range<usize>, out: &mut String) -> Span {
+        let mut span = Span {
+            range,
+            source_length: 0,
+            marker: ' ',
+            close: String::new(),
+            content_start: 0,
+            reference: None,
+        };
+        let (open, close) = match tag {
+            Tag::Emphasis | Tag::Strong => {
+                let strong = matches!(tag, Tag::Strong);
+                let preferred = match (strong, self.format.emphasis, self.format.strong) {
+                    (false, EmphasisMarker::Underscore, _)
+                    | (true, _, EmphasisMarker::Underscore) => '_',
+                    _ => '*',
+                };
+                span.source_length = if strong { 2 } else { 1 };
+                span.marker = match self.input[span.range.start..].chars().next() {
+                    Some(marker @ ('*' | '_')) if self.source_markers => marker,
+                    _ => self.emphasis_marker(preferred, &span, out),
+                };
+                let delimiter = span.marker.to_string().repeat(span.source_length);
+                (delimiter.clone(), delimiter)
+            }
+            Tag::Strikethrough => {
+                let source = &self.input[span.range.clone()];
+                span.source_length = source.len() - source.trim_start_matches('~').len();
+                ("~~".to_string(), "~~".to_string())
+            }
+            Tag::Superscript => ("^".to_string(), "^".to_string()),
+            Tag::Subscript => ("~".to_string(), "~".to_string()),
+            Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }
+            | Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            } => {
+                let image = self
+                    .input
+                    .get(span.range.start..)
+                    .is_some_and(|source| source.starts_with('!'));
+                let bang = if image { "!" } else { "" };
+                // Wiki link targets are written raw, which a line break
+                // would end.
+                let link_type = match link_type {
+                    LinkType::WikiLink { .. } if dest_url.contains('\n') => LinkType::Inline,
+                    link_type => link_type,
+                };
+                if matches!(
+                    link_type,
+                    LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut
+                ) {
+                    span.reference = Some((link_type, id.to_string()));
+                }
+                link(bang, link_type, &dest_url, &title)
+            }
+            _ => (String::new(), String::new()),
+        };
+        out.push_str(&open);
+        span.close = close;
+        span.content_start = out.len();
+        span
+    }
+
+    /// The emphasis delimiter for `span`: `preferred`, unless underscores
+    /// would fall inside a word or the delimiters would merge with an
+    /// enclosing or preceding span's into different emphasis. `***x***` is
+    /// emphasis around strong emphasis and `****x****` strong around strong,
+    /// but `**x**` is not emphasis around emphasis, and strong around
+    /// emphasis would read as the first.
+    fn emphasis_marker(&self, preferred: char, span: &Span, out: &str) -> char {
+        let range = &span.range;
+        // Spans directly inside another share its surroundings.
+        let (mut start, mut end) = (range.start, range.end);
+        for parent in self.inlines.iter().rev() {
+            if start == parent.range.start + parent.source_length {
+                start = parent.range.start;
+            }
+            if end + parent.source_length == parent.range.end {
+                end = parent.range.end;
+            }
+        }
+        let intraword = self.input[..start].ends_with(char::is_alphanumeric)
+            || self.input[end..].starts_with(char::is_alphanumeric);
+        let preferred = match intraword {
+            true => '*',
+            false => preferred,
+        };
+        let merges = self.inlines.last().is_some_and(|parent| {
+            let first = range.start == parent.range.start + parent.source_length;
+            let last = range.end + parent.source_length == parent.range.end;
+            parent.marker == preferred
+                && match (parent.source_length, span.source_length) {
+                    (1, 1) => first || last,
+                    (2, 1) => first && last,
+                    _ => false,
+                }
+        });
+        // Right after another span's closing delimiter.
+        let follows = out.ends_with(preferred)
+            && self
+                .inlines
+                .last()
+                .is_none_or(|parent| parent.content_start != out.len());
+        match (preferred, merges || follows) {
+            ('*', true) if !intraword => '_',
+            ('_', true) => '*',
+            _ => preferred,
+        }
+    }
+
+    /// Appends `text`, read from `range` of the source, escaping what would
+    /// otherwise read as markup. Unless the `following` event is a line
+    /// break, what follows it is unknown.
+    fn text(
+        &self,
+        out: &mut String,
+        text: &str,
+        range: Range<usize>,
+        context: Inline,
+        following: Option<&Event>,
+    ) {
+        let options = self.options;
+        let source = &self.input[range.clone()];
+        let before = self.input[..range.start].chars().next_back();
+        let after = self.input[range.end..].chars().next();
+        let broken = matches!(following, Some(Event::SoftBreak | Event::HardBreak));
+        // Whether the line goes on after the text.
+        let followed = !broken && following.is_some_and(is_inline);
+        let chars: Vec<char> = text.chars().collect();
+        let first_word = out.is_empty()
+            || out.ends_with(SPACE)
+            || out.ends_with(SOFT_BREAK)
+            || out.ends_with('\n');
+        let mut word_start = first_word;
+        // Where a `1.` or `1)` at the start of a word has its delimiter.
+        let mut ordinal = None;
+        for (index, &c) in chars.iter().enumerate() {
+            let previous = index.checked_sub(1).map(|index| chars[index]);
+            let next = match chars.get(index + 1) {
+                None if broken => Some(' '),
+                next => next.copied(),
+            };
+            if c == '\0' {
+                out.push_str(NUL);
+                word_start = false;
+                continue;
+            }
+            if c == ' ' && context == Inline::Paragraph {
+                out.push_str(SPACE);
+                word_start = true;
+                continue;
+            }
+            // Other ASCII whitespace is stripped at the ends of a line, so it
+            // is only kept between two other characters. Next to a
+            // delimiter, whitespace is written as it was in the source: a
+            // reference reads as punctuation there.
+            let kept = |neighbour: Option<char>, edge: bool, in_source: bool| match neighbour {
+                Some(neighbour) => !c.is_ascii() || !neighbour.is_whitespace(),
+                None if edge => !c.is_ascii(),
+                None => in_source,
+            };
+            let raw = kept(previous, first_word, source.starts_with(c))
+                && kept(next, !followed, source.ends_with(c));
+            if c.is_whitespace() && c != ' ' && (c == '\n' || c == '\r' || !raw) {
+                out.push_str(&format!("&#{};", c as u32));
+                continue;
+            }
+            let escape = match c {
+                '\\' | '`' | '*' | '[' | ']' => true,
+                '_' => {
+                    !(previous.is_some_and(char::is_alphanumeric)
+                        && next.is_some_and(char::is_alphanumeric))
+                }
+                '<' => next.is_none_or(|next| next.is_ascii_alphabetic() || "/!?".contains(next)),
+                '&' => next.is_none_or(|next| next.is_ascii_alphanumeric() || next == '#'),
+                '!' => next.is_none_or(|next| next == '['),
+                // Right after a link, `(` would start a destination.
+                '(' | ':' => index == 0 && out.ends_with(']'),
+                '~' => options.strikethrough || options.subscript,
+                '^' => options.superscript,
+                '$' => options.math || options.mathml,
+                '}' => context == Inline::Heading && options.heading_attributes,
+                '|' => context == Inline::TableCell,
+                '\'' | '"' => options.smart_punctuation,
+                '.' => options.smart_punctuation && next == Some('.'),
+                '-' => options.smart_punctuation && next == Some('-'),
+                _ => false,
+            };
+            if word_start && !escape {
+                let digits = chars[index..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit())
+                    .count();
+                let delimiter = chars.get(index + digits).copied();
+                let after = chars.get(index + digits + 1).copied();
+                if (1..=9).contains(&digits)
+                    && matches!(delimiter, Some('.' | ')'))
+                    && after.is_none_or(|after| after == ' ')
+                {
+                    ordinal = Some(index + digits);
+                } else if "#>-+=~".contains(c)
+                    || (c == ':' && (options.definition_list || options.tables))
+                    || (c == '|' && options.tables)
+                {
+                    out.push_str(LINE_START);
+                }
+            }
+            if ordinal == Some(index) {
+                out.push_str(LINE_START);
+            }
+            // With the source's delimiters, the rest of a delimiter run
+            // stays unescaped so that the run pairs up as it did.
+            let in_run = self.source_markers && matches!(c, '*' | '_') && {
+                let leading = source.len() - source.trim_start_matches(c).len();
+                let rest = source.trim_end_matches(c);
+                let escaped = rest.chars().next_back().or(before) == Some('\\');
+                (before == Some(c) && index < leading)
+                    || (after == Some(c)
+                        && chars.len() - index <= source.len() - rest.len()
+                        && !escaped)
+            };
+            if escape && !in_run {
+                out.push('\\');
+            }
+            out.push(c);
+            word_start = false;
+        }
+    }
+}
+
+/// The nesting of emphasis-like spans in inline events, with the letters and
+/// digits between them.
+fn emphasis(events: &[Spanned]) -> String {
+    let mut out = String::new();
+    for (event, _) in events {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Emphasis => out.push_str("<em>"),
+                Tag::Strong => out.push_str("<strong>"),
+                Tag::Strikethrough => out.push_str("<del>"),
+                Tag::Superscript => out.push_str("<sup>"),
+                Tag::Subscript => out.push_str("<sub>"),
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Emphasis => out.push_str("</em>"),
+                TagEnd::Strong => out.push_str("</strong>"),
+                TagEnd::Strikethrough => out.push_str("</del>"),
+                TagEnd::Superscript => out.push_str("</sup>"),
+                TagEnd::Subscript => out.push_str("</sub>"),
+                _ => {}
+            },
+            Event::Text(text) | Event::Code(text) | Event::FootnoteReference(text) => {
+                out.extend(text.chars().filter(|c| c.is_alphanumeric()))
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn alert(kind: BlockQuoteKind) -> &'static str {
+    match kind {
+        BlockQuoteKind::Note => "[!NOTE]",
+        BlockQuoteKind::Tip => "[!TIP]",
+        BlockQuoteKind::Important => "[!IMPORTANT]",
+        BlockQuoteKind::Warning => "[!WARNING]",
+        BlockQuoteKind::Caution => "[!CAUTION]",
+    }
+}
+
+pub(crate) fn is_inline(event: &Event) -> bool {
+    match event {
+        Event::Start(tag) => matches!(
+            tag,
+            Tag::Emphasis
+                | Tag::Strong
+                | Tag::Strikethrough
+                | Tag::Superscript
+                | Tag::Subscript
+                | Tag::Link { .. }
+                | Tag::Image { .. }
+        ),
+        Event::End(tag) => matches!(
+            tag,
+            TagEnd::Emphasis
+                | TagEnd::Strong
+                | TagEnd::Strikethrough
+                | TagEnd::Superscript
+                | TagEnd::Subscript
+                | TagEnd::Link
+                | TagEnd::Image
+        ),
+        Event::Rule | Event::Html(_) => false,
+        _ => true,
+    }
+}
+
+/// Whether `line` is a thematic break.
+fn is_rule(line: &str) -> bool {
+    let mut marks = line.chars().filter(|c| !matches!(c, ' ' | '\t'));
+    let first = marks.next();
+    first.is_some_and(|first| "-*_".contains(first))
+        && marks.clone().all(|c| Some(c) == first)
+        && marks.count() >= 2
+}
+
+/// Joins blocks, with blank lines between them if `loose`. An HTML block
+/// only a blank line ends gets one either way.
+fn join(blocks: Vec<Block>, loose: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut previous = None;
+    for (kind, block) in blocks {
+        let html =
+            matches!(previous, Some(Kind::Html { .. })) || kind == Kind::Html { interrupts: false };
+        if previous.is_some() && (loose || html) {
+            lines.push(String::new());
+        }
+        lines.extend(block);
+        previous = Some(kind);
+    }
+    lines
+}
+
+/// Whether the HTML block starting with `html` runs to a blank line rather
+/// than to an end marker of its own.
+fn ends_at_blank_line(html: &str) -> bool {
+    let html = html.trim_start_matches(' ').to_ascii_lowercase();
+    let raw_text = ["<pre", "<script", "<style", "<textarea"]
+        .iter()
+        .any(|start| {
+            html.strip_prefix(start).is_some_and(|rest| {
+                rest.is_empty() || rest.starts_with(|c: char| c.is_ascii_whitespace() || c == '>')
+            })
+        });
+    let declaration = html
+        .strip_prefix("<!")
+        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_alphabetic()));
+    !(raw_text
+        || declaration
+        || html.starts_with("<!--")
+        || html.starts_with("<?")
+        || html.starts_with("<![cdata["))
+}
+
+/// Whether an HTML block that runs to a blank line starts with one of the
+/// block-level tags that can interrupt a paragraph.
+fn interrupts_paragraph(html: &str) -> bool {
+    const TAGS: &[&str] = &[
+        "address",
+        "article",
+        "aside",
+        "base",
+        "basefont",
+        "blockquote",
+        "body",
+        "caption",
+        "center",
+        "col",
+        "colgroup",
+        "dd",
+        "details",
+        "dialog",
+        "dir",
+        "div",
+        "dl",
+        "dt",
+        "fieldset",
+        "figcaption",
+        "figure",
+        "footer",
+        "form",
+        "frame",
+        "frameset",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "head",
+        "header",
+        "hr",
+        "html",
+        "iframe",
+        "legend",
+        "li",
+        "link",
+        "main",
+        "menu",
+        "menuitem",
+        "nav",
+        "noframes",
+        "ol",
+        "optgroup",
+        "option",
+        "p",
+        "param",
+        "search",
+        "section",
+        "summary",
+        "table",
+        "tbody",
+        "td",
+        "tfoot",
+        "th",
+        "thead",
+        "title",
+        "tr",
+        "track",
+        "ul",
+    ];
+    let html = html.trim_start_matches(' ').to_ascii_lowercase();
+    let Some(rest) = html.strip_prefix("</").or(html.strip_prefix('<')) else {
+        return false;
+    };
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(rest.len());
+    let after = &rest[end..];
+    TAGS.contains(&&rest[..end])
+        && (after.is_empty()
+            || after.starts_with(|c: char| c.is_ascii_whitespace() || c == '>')
+            || after.starts_with("/>"))
+}
+
+/// Prefixes the first line with `first` and the others with `rest`.
+fn indent(lines: Vec<String>, first: &str, rest: &str) -> Vec<String> {
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(index, line)| match (index, line.is_empty()) {
+            (0, _) => format!("{}{}", first, line)
+                .trim_end_matches([' ', '\t'])
+                .to_string(),
+            (_, true) => line,
+            (_, false) => format!("{}{}", rest, line),
+        })
+        .collect()
+}
+
+fn heading(content: &str, level: usize, attributes: &[String]) -> Vec<String> {
+    // ATX headings are one line; keep a setext heading with hard breaks or
+    // a task list marker.
+    let task = content.starts_with("[x] ") || content.starts_with("[ ] ");
+    if (content.contains('\n') || task) && level <= 2 {
+        let mut lines = fill(content, 0, ProseWrap::Never);
+        let underline = if level == 1 { "===" } else { "---" };
+        lines.push(underline.to_string());
+        return lines;
+    }
+    let mut content = flatten(&content.replace("\\\n", " "))
+        .trim_end_matches([' ', '\t'])
+        .to_string();
+    // A closing sequence of `#`s would be dropped.
+    if content.ends_with('#') {
+        content.insert(content.len() - 1, '\\');
+    }
+    if !attributes.is_empty() {
+        content = format!("{} {{{}}}", content, attributes.join(" "));
+    }
+    let hashes = "#".repeat(level);
+    match content.is_empty() {
+        true => vec![hashes],
+        false => vec![format!("{} {}", hashes, content)],
+    }
+}
+
+/// Inline content on one line, with its markers resolved.
+fn flatten(content: &str) -> String {
+    content
+        .replace(SPACE, " ")
+        .replace(SOFT_BREAK, " ")
+        .replace(LINE_START, "")
+        .replace(NUL, "\0")
+}
+
+/// Breaks inline content into lines per `wrap`, escaping what would read as
+/// a block marker at the start of a line.
+fn fill(content: &str, width: usize, wrap: ProseWrap) -> Vec<String> {
+    let mut lines = Vec::new();
+    for hard_line in content.split('\n') {
+        let mut line = String::new();
+        let mut line_words = 0;
+        for (word, separator) in words(hard_line) {
+            let breakable = !word.is_empty() && !word.starts_with(['<', '`']);
+            // A tag alone on the first line would start an HTML block.
+            let lone_tag = lines.is_empty() && line_words == 1 && line.starts_with('<');
+            if wrap == ProseWrap::Always
+                && breakable
+                && !lone_tag
+                && !line.trim_end_matches([' ', '\t']).is_empty()
+                && display_width(&line) + display_width(word) > width
+            {
+                lines.push(finish_line(line.trim_end_matches([' ', '\t'])));
+                line.clear();
+                line_words = 0;
+            }
+            // Runs of spaces are written as one, or they would count
+            // towards the width of one pass and not the next.
+            if wrap == ProseWrap::Always && word.is_empty() && separator != Some(SOFT_BREAK) {
+                continue;
+            }
+            line.push_str(word);
+            line_words += usize::from(!word.is_empty());
+            match separator {
+                Some(SOFT_BREAK) if wrap == ProseWrap::Preserve => {
+                    lines.push(finish_line(&line));
+                    line.clear();
+                    line_words = 0;
+                }
+                Some(_) => line.push(' '),
+                None => {}
+            }
+        }
+        lines.push(finish_line(&line));
+    }
+    indent_html(&mut lines);
+    lines
+}
+
+/// Indents the continuation lines of a paragraph that start with raw HTML,
+/// which could start an HTML block there.
+fn indent_html(lines: &mut [String]) {
+    for line in lines.iter_mut().skip(1) {
+        if line.starts_with('<') {
+            line.insert_str(0, "    ");
+        }
+    }
+}
+
+/// Splits inline content at its breakable spaces and soft breaks.
+fn words(content: &str) -> Vec<(&str, Option<&'static str>)> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut search = 0;
+    while let Some(index) = content[search..].find('\0').map(|index| search + index) {
+        let separator = [SPACE, SOFT_BREAK]
+            .into_iter()
+            .find(|separator| content[index..].starts_with(separator));
+        search = index + 2;
+        if let Some(separator) = separator {
+            words.push((&content[start..index], Some(separator)));
+            start = search;
+        }
+    }
+    words.push((&content[start..], None));
+    words
+}
+
+/// Resolves the `LINE_START` markers of a finished line.
+fn finish_line(line: &str) -> String {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let mut line = line.to_string();
+    if line[digits..].starts_with(LINE_START) {
+        line.replace_range(digits..digits + 2, "\\");
+    }
+    line.replace(LINE_START, "").replace(NUL, "\0")
+}
+
+/// The width of `text` in columns, not counting markers.
+fn display_width(text: &str) -> usize {
+    // Markers are a NUL and one ASCII character, neither of which is written.
+    text.replace('\0', "").width() - text.matches('\0').count()
+}
+
+fn code_span(code: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in code.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let ticks = "`".repeat(longest + 1);
+    // One space of padding is stripped from each side when both have one.
+    let padded = code.starts_with('`')
+        || code.ends_with('`')
+        || (code.starts_with(' ') && code.ends_with(' ') && !code.trim_matches(' ').is_empty());
+    match padded {
+        true => format!("{} {} {}", ticks, code, ticks),
+        false => format!("{}{}{}", ticks, code, ticks),
+    }
+}
+
+/// The opening and closing of a link or image; reference links close when
+/// their text is known.
+fn link(bang: &str, link_type: LinkType, url: &str, title: &str) -> (String, String) {
+    match link_type {
+        LinkType::WikiLink { has_pothole: true } => {
+            (format!("{}[[{}|", bang, url), "]]".to_string())
+        }
+        LinkType::WikiLink { has_pothole: false } => (format!("{}[[", bang), "]]".to_string()),
+        LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut => {
+            (format!("{}[", bang), String::new())
+        }
+        _ => {
+            let mut close = format!("]({}", destination(url));
+            if !title.is_empty() {
+                close.push(' ');
+                close.push_str(&quote(title));
+            }
+            close.push(')');
+            (format!("{}[", bang), close)
+        }
+    }
+}
+
+/// A link destination, in angle brackets if it has spaces or is empty. Line
+/// breaks, which only a wiki link target can hold, are percent-encoded as
+/// the href is.
+fn destination(url: &str) -> String {
+    let url = &url.replace('\n', "%0A");
+    let escaped = escape_entities(&url.replace('\\', "\\\\"));
+    match url.is_empty() || url.contains([' ', '<', '>']) {
+        true => format!("<{}>", escaped.replace('<', "\\<").replace('>', "\\>")),
+        false => escaped.replace('(', "\\(").replace(')', "\\)"),
+    }
+}
+
+/// A code block's info string, which has escapes and references decoded and
+/// its surrounding whitespace stripped.
+fn info_string(info: &str) -> String {
+    let escaped = escape_entities(&info.replace('\\', "\\\\"));
+    let inner = escaped.trim_matches(char::is_whitespace);
+    let start = escaped.len() - escaped.trim_start_matches(char::is_whitespace).len();
+    let reference =
+        |text: &str| -> String { text.chars().map(|c| format!("&#{};", c as u32)).collect() };
+    match inner.is_empty() {
+        true => reference(&escaped),
+        false => format!(
+            "{}{}{}",
+            reference(&escaped[..start]),
+            inner,
+            reference(&escaped[start + inner.len()..])
+        ),
+    }
+}
+
+fn quote(title: &str) -> String {
+    let title = title.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", escape_entities(&title).replace('\n', "&#10;"))
+}
+
+/// Escapes `&` where it would start an entity reference.
+fn escape_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '&'
+            && chars
+                .peek()
+                .is_some_and(|next| next.is_ascii_alphanumeric() || *next == '#')
+        {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
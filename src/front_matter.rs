@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+use toml::de::{DeTable, DeValue};
+use wasm_bindgen::prelude::*;
+use yaml_rust2::parser::{Event as YamlEvent, Parser as YamlParser};
+use yaml_rust2::scanner::{Marker, ScanError, TScalarStyle};
+use yaml_rust2::Yaml;
+
+#[wasm_bindgen(typescript_custom_section)]
+const FRONT_MATTER_SCHEMA: &'static str = r#"
+/** A front matter block that failed to parse. Lines and columns are 1-based. */
+export interface FrontMatterError {
+  message: string;
+  line: number;
+  column: number;
+}
+
+/** The result of `parse_markdown_with_front_matter`. */
+export interface MarkdownWithFrontMatter {
+  html: string;
+  /** The parsed front matter; undefined when there is none or it is invalid. */
+  frontMatter?: unknown;
+  frontMatterFormat?: "yaml" | "toml";
+  frontMatterError?: FrontMatterError;
+}
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Format {
+    Yaml,
+    Toml,
+}
+
+/// Why front matter failed to parse, at a 1-based line of the document.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct FrontMatterError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The metadata block at the very start of a document.
+#[derive(Clone, Debug)]
+pub(crate) struct FrontMatter {
+    pub format: Format,
+    pub value: Result<Value, FrontMatterError>,
+    /// The 1-based document line of each value, keyed by JSON pointer.
+    /// Object members are located at their key.
+    pub lines: HashMap<String, usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WithFrontMatter {
+    pub html: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub front_matter: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub front_matter_format: Option<Format>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub front_matter_error: Option<FrontMatterError>,
+}
+
+impl WithFrontMatter {
+    pub(crate) fn new(html: String, front_matter: Option<FrontMatter>) -> Self {
+        let mut result = WithFrontMatter {
+            html,
+            front_matter: None,
+            front_matter_format: None,
+            front_matter_error: None,
+        };
+        if let Some(front_matter) = front_matter {
+            result.front_matter_format = Some(front_matter.format);
+            match front_matter.value {
+                Ok(value) => result.front_matter = Some(value),
+                Err(error) => result.front_matter_error = Some(error),
+            }
+        }
+        result
+    }
+}
+
+/// The length of the front matter block at the start of `input`,
+/// delimiters and line breaks included, or 0 if there is none.
+pub(crate) fn block_len(input: &str) -> usize {
+    block(input).map_or(0, |(_, range, _)| range.end)
+}
+
+/// Finds a `---` YAML or `+++` TOML block at the start of `input`, with the
+/// same rules as pulldown-cmark's metadata blocks: the content may not
+/// start with a blank line, and YAML may also close with `...`. Returns the
+/// block's format, range and content range.
+fn block(input: &str) -> Option<(Format, Range<usize>, Range<usize>)> {
+    let (fence, format) = match input.get(..3)? {
+        "---" => ("---", Format::Yaml),
+        "+++" => ("+++", Format::Toml),
+        _ => return None,
+    };
+    let mut lines = input.split_inclusive('\n');
+    let opening = lines.next()?;
+    if !opening[3..].trim().is_empty() {
+        return None;
+    }
+    let body_start = opening.len();
+    let mut offset = body_start;
+    for (index, line) in lines.enumerate() {
+        let content = line.trim_end_matches(['\n', '\r']).trim_end_matches(' ');
+        let closing = content == fence || (format == Format::Yaml && content == "...");
+        if index == 0 && (closing || content.trim().is_empty()) {
+            return None;
+        }
+        if closing {
+            return Some((format, 0..offset + line.len(), body_start..offset));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Finds and parses the front matter block at the start of `input`.
+pub(crate) fn extract(input: &str) -> Option<FrontMatter> {
+    let (format, _, body) = block(input)?;
+    let text = &input[body.clone()];
+    let mut lines = HashMap::new();
+    let value = match format {
+        Format::Yaml => parse_yaml(text, &mut lines),
+        Format::Toml => parse_toml(text, &mut lines),
+    };
+    // Parser positions are relative to the block's content, which starts on
+    // the line after the opening delimiter.
+    for line in lines.values_mut() {
+        *line += 1;
+    }
+    lines.insert(String::new(), 1);
+    Some(FrontMatter {
+        format,
+        value: value.map_err(|error| FrontMatterError {
+            line: error.line + 1,
+            ..error
+        }),
+        lines,
+    })
+}
+
+/// The members of the front matter at the start of `input`; empty when
+/// there is none, or it is invalid or not a mapping.
+pub(crate) fn fields(input: &str) -> Map<String, Value> {
+    match extract(input).map(|front_matter| front_matter.value) {
+        Some(Ok(Value::Object(fields))) => fields,
+        _ => Map::new(),
+    }
+}
+
+/// The JSON pointer to member `key` of the value at `pointer`.
+pub(crate) fn pointer_child(pointer: &str, key: &str) -> String {
+    format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// The most values a YAML document may expand to. Aliases repeat the value
+/// they refer to, so a few lines of nested aliases could otherwise grow into
+/// gigabytes (the "billion laughs" attack).
+const MAX_YAML_VALUES: usize = 10_000;
+
+fn parse_yaml(text: &str, lines: &mut HashMap<String, usize>) -> Result<Value, FrontMatterError> {
+    let mut loader = YamlLoader {
+        parser: YamlParser::new_from_str(text),
+        anchors: HashMap::new(),
+        values: 0,
+        lines,
+    };
+    let mut document = None;
+    loop {
+        let (event, marker) = loader.next()?;
+        match event {
+            YamlEvent::StreamStart | YamlEvent::DocumentEnd => {}
+            YamlEvent::DocumentStart if document.is_some() => {
+                return Err(yaml_error("front matter must be a single document", marker));
+            }
+            YamlEvent::DocumentStart => {
+                let (event, marker) = loader.next()?;
+                document = Some(loader.node(event, marker, String::new())?);
+            }
+            YamlEvent::StreamEnd => return Ok(document.unwrap_or(Value::Null)),
+            _ => return Err(yaml_error("unexpected YAML event", marker)),
+        }
+    }
+}
+
+/// Builds JSON values from YAML parser events.
+struct YamlLoader<'a> {
+    parser: YamlParser<std::str::Chars<'a>>,
+    /// Anchored values and how many values each one counts for.
+    anchors: HashMap<usize, (Value, usize)>,
+    /// Values built so far, counting every copy made by an alias.
+    values: usize,
+    lines: &'a mut HashMap<String, usize>,
+}
+
+impl YamlLoader<'_> {
+    fn next(&mut self) -> Result<(YamlEvent, Marker), FrontMatterError> {
+        self.parser
+            .next_token()
+            .map_err(|error: ScanError| yaml_error(error.info(), *error.marker()))
+    }
+
+    fn node(
+        &mut self,
+        event: YamlEvent,
+        marker: Marker,
+        pointer: String,
+    ) -> Result<Value, FrontMatterError> {
+        self.lines.entry(pointer.clone()).or_insert(marker.line());
+        let first = self.values;
+        self.count(1, marker)?;
+        let (value, anchor) = match event {
+            YamlEvent::Scalar(value, style, anchor, tag) => {
+                let value = match (style, tag) {
+                    (TScalarStyle::Plain, None) => scalar(&value),
+                    _ => Value::String(value),
+                };
+                (value, anchor)
+            }
+            YamlEvent::SequenceStart(anchor, _) => {
+                let mut items = Vec::new();
+                loop {
+                    match self.next()? {
+                        (YamlEvent::SequenceEnd, _) => break,
+                        (event, marker) => {
+                            let item = pointer_child(&pointer, &items.len().to_string());
+                            items.push(self.node(event, marker, item)?);
+                        }
+                    }
+                }
+                (Value::Array(items), anchor)
+            }
+            YamlEvent::MappingStart(anchor, _) => {
+                let mut entries = Map::new();
+                loop {
+                    let (key, key_line) = match self.next()? {
+                        (YamlEvent::MappingEnd, _) => break,
+                        (event, marker) => match self.node(event, marker, String::new())? {
+                            Value::String(key) => (key, marker.line()),
+                            key => (key.to_string(), marker.line()),
+                        },
+                    };
+                    let member = pointer_child(&pointer, &key);
+                    self.lines.insert(member.clone(), key_line);
+                    let (event, marker) = self.next()?;
+                    let value = self.node(event, marker, member)?;
+                    entries.insert(key, value);
+                }
+                (Value::Object(entries), anchor)
+            }
+            YamlEvent::Alias(anchor) => {
+                let Some(&(_, count)) = self.anchors.get(&anchor) else {
+                    return Err(yaml_error("unknown alias", marker));
+                };
+                // Counted before copying, so the copy is within the limit.
+                self.count(count - 1, marker)?;
+                (self.anchors[&anchor].0.clone(), 0)
+            }
+            _ => return Err(yaml_error("unexpected YAML event", marker)),
+        };
+        if anchor != 0 {
+            self.anchors
+                .insert(anchor, (value.clone(), self.values - first));
+        }
+        Ok(value)
+    }
+
+    fn count(&mut self, values: usize, marker: Marker) -> Result<(), FrontMatterError> {
+        self.values += values;
+        match self.values > MAX_YAML_VALUES {
+            true => Err(yaml_error(
+                "front matter expands to too many values",
+                marker,
+            )),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Resolves a plain scalar with the YAML 1.2 core schema.
+fn scalar(value: &str) -> Value {
+    match Yaml::from_str(value) {
+        Yaml::Integer(integer) => Value::Number(integer.into()),
+        Yaml::Real(real) => Yaml::Real(real.clone())
+            .as_f64()
+            .and_then(Number::from_f64)
+            .map_or(Value::String(real), Value::Number),
+        Yaml::Boolean(boolean) => Value::Bool(boolean),
+        Yaml::Null => Value::Null,
+        _ => Value::String(value.to_string()),
+    }
+}
+
+fn yaml_error(message: &str, marker: Marker) -> FrontMatterError {
+    FrontMatterError {
+        message: message.to_string(),
+        line: marker.line(),
+        column: marker.col() + 1,
+    }
+}
+
+fn parse_toml(text: &str, lines: &mut HashMap<String, usize>) -> Result<Value, FrontMatterError> {
+    match DeTable::parse(text) {
+        Ok(table) => Ok(toml_table(table.get_ref(), "", &mut |pointer, offset| {
+            lines.insert(pointer, text[..offset].matches('\n').count() + 1);
+        })),
+        Err(error) => {
+            let offset = error.span().map_or(0, |span| span.start).min(text.len());
+            let before = &text[..offset];
+            let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+            Err(FrontMatterError {
+                message: error.message().trim().to_string(),
+                line: before.matches('\n').count() + 1,
+                column: before[line_start..].chars().count() + 1,
+            })
+        }
+    }
+}
+
+/// Converts a parsed table, passing the JSON pointer and source offset of
+/// every value to `locate`.
+fn toml_table(table: &DeTable, pointer: &str, locate: &mut impl FnMut(String, usize)) -> Value {
+    Value::Object(
+        table
+            .iter()
+            .map(|(key, value)| {
+                let member = pointer_child(pointer, key.get_ref());
+                locate(member.clone(), key.span().start);
+                let value = toml_value(value.get_ref(), &member, locate);
+                (key.get_ref().to_string(), value)
+            })
+            .collect(),
+    )
+}
+
+fn toml_value(value: &DeValue, pointer: &str, locate: &mut impl FnMut(String, usize)) -> Value {
+    match value {
+        DeValue::String(string) => Value::String(string.to_string()),
+        DeValue::Integer(integer) => i64::from_str_radix(integer.as_str(), integer.radix())
+            .map_or_else(
+                |_| Value::String(integer.to_string()),
+                |integer| integer.into(),
+            ),
+        DeValue::Float(float) => float
+            .as_str()
+            .parse()
+            .ok()
+            .and_then(Number::from_f64)
+            .map_or_else(|| Value::String(float.to_string()), Value::Number),
+        DeValue::Boolean(boolean) => Value::Bool(*boolean),
+        DeValue::Datetime(datetime) => Value::String(datetime.to_string()),
+        DeValue::Array(array) => Value::Array(
+            array
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    let item_pointer = pointer_child(pointer, &index.to_string());
+                    locate(item_pointer.clone(), item.span().start);
+                    toml_value(item.get_ref(), &item_pointer, locate)
+                })
+                .collect(),
+        ),
+        DeValue::Table(table) => toml_table(table, pointer, locate),
+    }
+}
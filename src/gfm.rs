@@ -0,0 +1,274 @@
+use std::ops::Range;
+
+use pulldown_cmark::{Event, LinkType, Tag, TagEnd};
+
+use crate::render::Spanned;
+
+/// Tags GitHub refuses to pass through from raw HTML.
+const FILTERED_TAGS: [&str; 9] = [
+    "title",
+    "textarea",
+    "style",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "script",
+    "plaintext",
+];
+
+/// Applies the GFM tagfilter: the `<` of every disallowed tag in raw HTML
+/// is replaced with `&lt;` so the browser shows it as text.
+pub(crate) fn tagfilter(events: &mut [Spanned]) {
+    for (event, _) in events.iter_mut() {
+        if let Event::Html(html) | Event::InlineHtml(html) = event {
+            if let Some(filtered) = filter_tags(html) {
+                *html = filtered.into();
+            }
+        }
+    }
+}
+
+fn filter_tags(html: &str) -> Option<String> {
+    let mut out = String::with_capacity(html.len());
+    let mut changed = false;
+    let mut rest = html;
+    while let Some(pos) = rest.find('<') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        if is_filtered_tag(rest) {
+            out.push_str("&lt;");
+            changed = true;
+        } else {
+            out.push('<');
+        }
+    }
+    out.push_str(rest);
+    changed.then_some(out)
+}
+
+fn is_filtered_tag(tail: &str) -> bool {
+    let name = tail.strip_prefix('/').unwrap_or(tail).as_bytes();
+    FILTERED_TAGS.iter().any(|tag| {
+        name.len() >= tag.len()
+            && name[..tag.len()].eq_ignore_ascii_case(tag.as_bytes())
+            && matches!(
+                name.get(tag.len()),
+                None | Some(b'>' | b'/' | b' ' | b'\t' | b'\n' | b'\r' | b'\x0c')
+            )
+    })
+}
+
+/// Turns bare `www.`, `http(s)://` and email addresses in text into links,
+/// following the GFM extended autolink rules. Text inside links, images and
+/// code blocks is left alone.
+pub(crate) fn autolink(events: Vec<Spanned<'_>>) -> Vec<Spanned<'_>> {
+    let mut out = Vec::with_capacity(events.len());
+    let mut pending: Option<(String, Range<usize>)> = None;
+    let mut skip = 0usize;
+    for (event, range) in events {
+        if let Event::Text(text) = &event {
+            if skip == 0 {
+                let (merged, span) = pending.get_or_insert_with(|| (String::new(), range.clone()));
+                merged.push_str(text);
+                span.end = range.end;
+                continue;
+            }
+        }
+        if let Some((text, span)) = pending.take() {
+            push_linkified(&mut out, text, span);
+        }
+        match &event {
+            Event::Start(
+                Tag::Link { .. } | Tag::Image { .. } | Tag::CodeBlock(_) | Tag::MetadataBlock(_),
+            ) => skip += 1,
+            Event::End(
+                TagEnd::Link | TagEnd::Image | TagEnd::CodeBlock | TagEnd::MetadataBlock(_),
+            ) => skip -= 1,
+            _ => {}
+        }
+        out.push((event, range));
+    }
+    if let Some((text, span)) = pending.take() {
+        push_linkified(&mut out, text, span);
+    }
+    out
+}
+
+struct Autolink {
+    start: usize,
+    end: usize,
+    href: String,
+    email: bool,
+}
+
+/// Splits merged text into text and link events. The generated events all
+/// share the span of the merged text.
+fn push_linkified(out: &mut Vec<Spanned<'_>>, text: String, span: Range<usize>) {
+    let links = find_autolinks(&text);
+    let mut push = |event| out.push((event, span.clone()));
+    if links.is_empty() {
+        push(Event::Text(text.into()));
+        return;
+    }
+    let mut last = 0;
+    for link in links {
+        if link.start > last {
+            push(Event::Text(text[last..link.start].to_string().into()));
+        }
+        push(Event::Start(Tag::Link {
+            link_type: if link.email {
+                LinkType::Email
+            } else {
+                LinkType::Autolink
+            },
+            dest_url: link.href.into(),
+            title: "".into(),
+            id: "".into(),
+        }));
+        push(Event::Text(text[link.start..link.end].to_string().into()));
+        push(Event::End(TagEnd::Link));
+        last = link.end;
+    }
+    if last < text.len() {
+        push(Event::Text(text[last..].to_string().into()));
+    }
+}
+
+fn find_autolinks(text: &str) -> Vec<Autolink> {
+    let mut links = Vec::new();
+    let mut last_end = 0;
+    let mut i = 0;
+    while i < text.len() {
+        if at_boundary(text, i) {
+            if let Some((end, href)) = scan_url(text, i) {
+                links.push(Autolink {
+                    start: i,
+                    end,
+                    href,
+                    email: false,
+                });
+                i = end;
+                last_end = end;
+                continue;
+            }
+        }
+        if text.as_bytes()[i] == b'@' {
+            if let Some((start, end)) = scan_email(text, i, last_end) {
+                links.push(Autolink {
+                    start,
+                    end,
+                    href: text[start..end].to_string(),
+                    email: true,
+                });
+                i = end;
+                last_end = end;
+                continue;
+            }
+        }
+        i += text[i..].chars().next().map_or(1, char::len_utf8);
+    }
+    links
+}
+
+fn at_boundary(text: &str, i: usize) -> bool {
+    match text[..i].chars().next_back() {
+        None => true,
+        Some(c) => c.is_whitespace() || matches!(c, '*' | '_' | '~' | '('),
+    }
+}
+
+fn starts_with_ignore_case(s: &str, prefix: &str) -> bool {
+    s.len() >= prefix.len() && s.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+fn scan_url(text: &str, start: usize) -> Option<(usize, String)> {
+    let rest = &text[start..];
+    let (scheme_len, href_prefix) = if starts_with_ignore_case(rest, "https://") {
+        (8, "")
+    } else if starts_with_ignore_case(rest, "http://") {
+        (7, "")
+    } else if starts_with_ignore_case(rest, "www.") {
+        (0, "http://")
+    } else {
+        return None;
+    };
+    let domain_end = scheme_len + scan_domain(&rest[scheme_len..])?;
+    let end = rest[domain_end..]
+        .find(|c: char| c.is_whitespace() || c == '<')
+        .map_or(rest.len(), |pos| domain_end + pos);
+    let end = trim_trailing(&rest[..end]);
+    if end <= scheme_len {
+        return None;
+    }
+    Some((start + end, format!("{}{}", href_prefix, &rest[..end])))
+}
+
+/// Returns the length of a valid domain at the start of `s`: period
+/// separated segments of alphanumerics, `-` and `_`, with at least one
+/// period and no underscore in the last two segments.
+fn scan_domain(s: &str) -> Option<usize> {
+    let len = s
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(s.len());
+    let domain = s[..len].trim_end_matches('.');
+    let segments: Vec<&str> = domain.split('.').collect();
+    if segments.len() < 2 || segments.iter().any(|segment| segment.is_empty()) {
+        return None;
+    }
+    if segments[segments.len() - 2..]
+        .iter()
+        .any(|segment| segment.contains('_'))
+    {
+        return None;
+    }
+    Some(len)
+}
+
+/// Drops trailing punctuation, unbalanced closing parentheses and
+/// entity-like suffixes from a candidate link, returning its new length.
+fn trim_trailing(link: &str) -> usize {
+    let mut end = link.len();
+    loop {
+        let candidate = &link[..end];
+        let Some(last) = candidate.chars().next_back() else {
+            return end;
+        };
+        let unbalanced =
+            || last == ')' && candidate.matches(')').count() > candidate.matches('(').count();
+        if matches!(last, '?' | '!' | '.' | ',' | ':' | '*' | '_' | '~') || unbalanced() {
+            end -= 1;
+        } else if last == ';' {
+            let body = &candidate[..end - 1];
+            let name_start = body
+                .trim_end_matches(|c: char| c.is_ascii_alphanumeric())
+                .len();
+            if name_start < body.len() && body[..name_start].ends_with('&') {
+                end = name_start - 1;
+            } else {
+                return end;
+            }
+        } else {
+            return end;
+        }
+    }
+}
+
+fn scan_email(text: &str, at: usize, min_start: usize) -> Option<(usize, usize)> {
+    let is_local = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-' | '_');
+    let start = text[min_start..at]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_local(c))
+        .last()
+        .map(|(pos, _)| min_start + pos)?;
+
+    let is_domain = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_');
+    let domain = &text[at + 1..];
+    let len = domain.find(|c: char| !is_domain(c)).unwrap_or(domain.len());
+    let domain = domain[..len].trim_end_matches('.');
+    if !domain.contains('.') || domain.ends_with(['-', '_']) || domain.contains("..") {
+        return None;
+    }
+    Some((start, at + 1 + domain.len()))
+}
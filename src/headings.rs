@@ -0,0 +1,105 @@
+use std::collections::{HashMap, HashSet};
+
+use pulldown_cmark::{Event, Tag, TagEnd};
+
+use crate::render::Spanned;
+
+/// Generates GitHub-compatible heading slugs, de-duplicating repeats with
+/// `-1`, `-2`, ... suffixes.
+#[derive(Default)]
+pub(crate) struct Slugger {
+    taken: HashSet<String>,
+    counts: HashMap<String, usize>,
+}
+
+impl Slugger {
+    /// Marks an explicit id as used so generated slugs avoid it.
+    pub(crate) fn reserve(&mut self, id: &str) {
+        self.taken.insert(id.to_string());
+    }
+
+    pub(crate) fn slug(&mut self, text: &str) -> String {
+        let base = slugify(text);
+        let mut slug = base.clone();
+        while self.taken.contains(&slug) {
+            let count = self.counts.entry(base.clone()).or_insert(0);
+            *count += 1;
+            slug = format!("{}-{}", base, count);
+        }
+        self.taken.insert(slug.clone());
+        slug
+    }
+}
+
+/// Lowercases `text`, drops punctuation and symbols, and turns spaces into
+/// hyphens, the way GitHub derives heading anchors.
+pub(crate) fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The plain text of the heading starting at `events[start]`.
+pub(crate) fn heading_text(events: &[Spanned], start: usize) -> String {
+    let mut text = String::new();
+    for (event, _) in &events[start + 1..] {
+        match event {
+            Event::End(TagEnd::Heading(_)) => break,
+            Event::Text(value) | Event::Code(value) => text.push_str(value),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Gives every heading without an explicit `{#id}` a generated one.
+pub(crate) fn assign_ids(mut events: Vec<Spanned<'_>>) -> Vec<Spanned<'_>> {
+    let mut slugger = Slugger::default();
+    for (event, _) in &events {
+        if let Event::Start(Tag::Heading { id: Some(id), .. }) = event {
+            slugger.reserve(id);
+        }
+    }
+    for index in 0..events.len() {
+        if let Event::Start(Tag::Heading { id: None, .. }) = &events[index].0 {
+            let slug = slugger.slug(&heading_text(&events, index));
+            if let Event::Start(Tag::Heading { id, .. }) = &mut events[index].0 {
+                *id = Some(slug.into());
+            }
+        }
+    }
+    events
+}
+
+/// Prepends a hover anchor link to every heading that has an id.
+pub(crate) fn insert_anchors(events: Vec<Spanned<'_>>) -> Vec<Spanned<'_>> {
+    let mut out = Vec::with_capacity(events.len());
+    for (event, range) in events {
+        let anchor = match &event {
+            Event::Start(Tag::Heading { id: Some(id), .. }) => Some(format!(
+                "<a class=\"heading-anchor\" href=\"#{}\" aria-hidden=\"true\">#</a>",
+                escape_attribute(id)
+            )),
+            _ => None,
+        };
+        out.push((event, range.clone()));
+        if let Some(anchor) = anchor {
+            out.push((Event::InlineHtml(anchor.into()), range));
+        }
+    }
+    out
+}
+
+fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    pulldown_cmark_escape::escape_href(&mut escaped, value).unwrap();
+    escaped
+}
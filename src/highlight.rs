@@ -0,0 +1,953 @@
+use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
+use pulldown_cmark_escape::escape_html_body_text;
+
+use crate::options::HighlightStyle;
+use crate::render::Spanned;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token {
+    Comment,
+    Keyword,
+    Literal,
+    Number,
+    String,
+    Type,
+    Function,
+    Property,
+    Variable,
+    Attribute,
+}
+
+impl Token {
+    const ALL: [Token; 10] = [
+        Token::Comment,
+        Token::Keyword,
+        Token::Literal,
+        Token::Number,
+        Token::String,
+        Token::Type,
+        Token::Function,
+        Token::Property,
+        Token::Variable,
+        Token::Attribute,
+    ];
+
+    fn class(self) -> &'static str {
+        match self {
+            Token::Comment => "hl-comment",
+            Token::Keyword => "hl-keyword",
+            Token::Literal => "hl-literal",
+            Token::Number => "hl-number",
+            Token::String => "hl-string",
+            Token::Type => "hl-type",
+            Token::Function => "hl-function",
+            Token::Property => "hl-property",
+            Token::Variable => "hl-variable",
+            Token::Attribute => "hl-attribute",
+        }
+    }
+
+    /// The built-in palette, used for inline styles and the stylesheet.
+    fn style(self) -> &'static str {
+        match self {
+            Token::Comment => "color:#6a737d;font-style:italic",
+            Token::Keyword => "color:#d73a49",
+            Token::Literal | Token::Number | Token::Property => "color:#005cc5",
+            Token::String => "color:#032f62",
+            Token::Type | Token::Function => "color:#6f42c1",
+            Token::Variable => "color:#e36209",
+            Token::Attribute => "color:#22863a",
+        }
+    }
+}
+
+/// A quoted string syntax.
+struct Quote {
+    open: &'static str,
+    close: &'static str,
+    /// Backslash escapes the next character.
+    escapes: bool,
+    multiline: bool,
+}
+
+const fn quote(open: &'static str, close: &'static str, escapes: bool, multiline: bool) -> Quote {
+    Quote {
+        open,
+        close,
+        escapes,
+        multiline,
+    }
+}
+
+#[derive(PartialEq, Eq)]
+enum Keys {
+    None,
+    /// A string followed by `:` is an object key.
+    Json,
+    /// `key =` at the start of a line is a key, `[table]` a table header.
+    Toml,
+}
+
+/// The lexical grammar of a language, as far as highlighting needs it.
+struct Language {
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    literals: &'static [&'static str],
+    builtins: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// Longer delimiters first.
+    quotes: &'static [Quote],
+    /// Letters that may prefix a string literal, as in `b"..."` or `f'...'`.
+    string_prefixes: &'static str,
+    /// `r"..."`, `r#"..."#`, `br##"..."##` raw strings, closed by a quote
+    /// and as many `#` as opened them.
+    raw_strings: bool,
+    ignore_case: bool,
+    /// Identifiers starting with an uppercase letter are types.
+    capitalized_types: bool,
+    /// Identifiers directly followed by `(` are function calls.
+    calls: bool,
+    /// Identifiers directly followed by `!` are macro calls.
+    macros: bool,
+    /// `@name` decorators.
+    decorators: bool,
+    /// `#[...]` attributes.
+    attributes: bool,
+    /// `'a` lifetimes, told apart from `'a'` character literals.
+    lifetimes: bool,
+    /// `$name`, `$1` and `${...}` expansions.
+    dollar_variables: bool,
+    /// `$` is an identifier character.
+    dollar_identifiers: bool,
+    /// Line comments only start at the beginning of a word.
+    word_comments: bool,
+    /// `-` joins words, as in `--flag` or `my-key`.
+    dashed_words: bool,
+    keys: Keys,
+}
+
+const NONE: Language = Language {
+    names: &[],
+    keywords: &[],
+    types: &[],
+    literals: &[],
+    builtins: &[],
+    line_comments: &[],
+    block_comment: None,
+    quotes: &[],
+    string_prefixes: "",
+    raw_strings: false,
+    ignore_case: false,
+    capitalized_types: false,
+    calls: false,
+    macros: false,
+    decorators: false,
+    attributes: false,
+    lifetimes: false,
+    dollar_variables: false,
+    dollar_identifiers: false,
+    word_comments: false,
+    dashed_words: false,
+    keys: Keys::None,
+};
+
+const TYPESCRIPT: Language = Language {
+    names: &[
+        "typescript",
+        "ts",
+        "tsx",
+        "javascript",
+        "js",
+        "jsx",
+        "mjs",
+        "cjs",
+    ],
+    keywords: &[
+        "abstract",
+        "as",
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "debugger",
+        "declare",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "enum",
+        "export",
+        "extends",
+        "finally",
+        "for",
+        "from",
+        "function",
+        "get",
+        "if",
+        "implements",
+        "import",
+        "in",
+        "infer",
+        "instanceof",
+        "interface",
+        "is",
+        "keyof",
+        "let",
+        "namespace",
+        "new",
+        "of",
+        "private",
+        "protected",
+        "public",
+        "readonly",
+        "return",
+        "satisfies",
+        "set",
+        "static",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "try",
+        "type",
+        "typeof",
+        "var",
+        "void",
+        "while",
+        "with",
+        "yield",
+    ],
+    types: &[
+        "any", "bigint", "boolean", "never", "number", "object", "string", "symbol", "unknown",
+    ],
+    literals: &["true", "false", "null", "undefined", "NaN", "Infinity"],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &[
+        quote("`", "`", true, true),
+        quote("\"", "\"", true, false),
+        quote("'", "'", true, false),
+    ],
+    capitalized_types: true,
+    calls: true,
+    decorators: true,
+    dollar_identifiers: true,
+    ..NONE
+};
+
+const RUST: Language = Language {
+    names: &["rust", "rs"],
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "type",
+        "unsafe", "use", "where", "while",
+    ],
+    types: &[
+        "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "str", "u8",
+        "u16", "u32", "u64", "u128", "usize",
+    ],
+    literals: &["true", "false"],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &[quote("\"", "\"", true, true)],
+    string_prefixes: "br",
+    raw_strings: true,
+    capitalized_types: true,
+    calls: true,
+    macros: true,
+    attributes: true,
+    lifetimes: true,
+    ..NONE
+};
+
+const JSON: Language = Language {
+    names: &["json", "jsonc", "json5"],
+    literals: &["true", "false", "null"],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &[quote("\"", "\"", true, false)],
+    keys: Keys::Json,
+    ..NONE
+};
+
+const TOML: Language = Language {
+    names: &["toml"],
+    literals: &["true", "false", "inf", "nan"],
+    line_comments: &["#"],
+    quotes: &[
+        quote("\"\"\"", "\"\"\"", true, true),
+        quote("'''", "'''", false, true),
+        quote("\"", "\"", true, false),
+        quote("'", "'", false, false),
+    ],
+    dashed_words: true,
+    keys: Keys::Toml,
+    ..NONE
+};
+
+const SHELL: Language = Language {
+    names: &["sh", "bash", "shell", "zsh", "shellscript"],
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "fi", "for", "function", "if", "in", "local",
+        "return", "select", "then", "time", "until", "while",
+    ],
+    builtins: &[
+        "alias", "cd", "echo", "eval", "exec", "exit", "export", "printf", "pwd", "read", "set",
+        "shift", "source", "test", "trap", "unset", "wait",
+    ],
+    line_comments: &["#"],
+    quotes: &[quote("\"", "\"", true, true), quote("'", "'", false, true)],
+    dollar_variables: true,
+    word_comments: true,
+    dashed_words: true,
+    ..NONE
+};
+
+const PYTHON: Language = Language {
+    names: &["python", "py", "python3"],
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "case", "class", "continue", "def",
+        "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+        "is", "lambda", "match", "nonlocal", "not", "or", "pass", "raise", "return", "try",
+        "while", "with", "yield",
+    ],
+    literals: &["True", "False", "None"],
+    builtins: &[
+        "abs",
+        "all",
+        "any",
+        "bool",
+        "dict",
+        "enumerate",
+        "filter",
+        "float",
+        "int",
+        "isinstance",
+        "len",
+        "list",
+        "map",
+        "max",
+        "min",
+        "open",
+        "print",
+        "range",
+        "repr",
+        "set",
+        "sorted",
+        "str",
+        "sum",
+        "super",
+        "tuple",
+        "type",
+        "zip",
+    ],
+    line_comments: &["#"],
+    quotes: &[
+        quote("\"\"\"", "\"\"\"", true, true),
+        quote("'''", "'''", true, true),
+        quote("\"", "\"", true, false),
+        quote("'", "'", true, false),
+    ],
+    string_prefixes: "rbfuRBFU",
+    capitalized_types: true,
+    calls: true,
+    decorators: true,
+    ..NONE
+};
+
+const SQL: Language = Language {
+    names: &["sql", "postgresql", "postgres", "mysql", "sqlite"],
+    keywords: &[
+        "add",
+        "all",
+        "alter",
+        "and",
+        "as",
+        "asc",
+        "begin",
+        "between",
+        "by",
+        "case",
+        "column",
+        "commit",
+        "constraint",
+        "create",
+        "cross",
+        "default",
+        "delete",
+        "desc",
+        "distinct",
+        "drop",
+        "else",
+        "end",
+        "exists",
+        "foreign",
+        "from",
+        "full",
+        "group",
+        "having",
+        "if",
+        "in",
+        "index",
+        "inner",
+        "insert",
+        "into",
+        "is",
+        "join",
+        "key",
+        "left",
+        "like",
+        "limit",
+        "not",
+        "offset",
+        "on",
+        "or",
+        "order",
+        "outer",
+        "primary",
+        "references",
+        "returning",
+        "right",
+        "rollback",
+        "select",
+        "set",
+        "table",
+        "then",
+        "transaction",
+        "union",
+        "unique",
+        "update",
+        "values",
+        "view",
+        "when",
+        "where",
+        "with",
+    ],
+    types: &[
+        "bigint",
+        "blob",
+        "boolean",
+        "char",
+        "date",
+        "decimal",
+        "double",
+        "float",
+        "int",
+        "integer",
+        "json",
+        "jsonb",
+        "numeric",
+        "real",
+        "serial",
+        "smallint",
+        "text",
+        "timestamp",
+        "timestamptz",
+        "uuid",
+        "varchar",
+    ],
+    literals: &["null", "true", "false"],
+    line_comments: &["--"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &[quote("'", "'", false, true)],
+    ignore_case: true,
+    calls: true,
+    ..NONE
+};
+
+const LANGUAGES: [&Language; 7] = [&TYPESCRIPT, &RUST, &JSON, &TOML, &SHELL, &PYTHON, &SQL];
+
+const YAML_NAMES: [&str; 2] = ["yaml", "yml"];
+
+enum Grammar {
+    Generic(&'static Language),
+    Yaml,
+}
+
+impl Grammar {
+    fn find(name: &str) -> Option<Grammar> {
+        let name = name.to_ascii_lowercase();
+        if YAML_NAMES.contains(&name.as_str()) {
+            return Some(Grammar::Yaml);
+        }
+        LANGUAGES
+            .into_iter()
+            .find(|language| language.names.contains(&name.as_str()))
+            .map(Grammar::Generic)
+    }
+
+    fn highlight(&self, code: &str, style: HighlightStyle) -> String {
+        let mut painter = Painter {
+            out: String::with_capacity(code.len() * 2),
+            style,
+        };
+        match self {
+            Grammar::Generic(language) => tokenize(language, code, &mut painter),
+            Grammar::Yaml => tokenize_yaml(code, &mut painter),
+        }
+        painter.out
+    }
+}
+
+struct Painter {
+    out: String,
+    style: HighlightStyle,
+}
+
+impl Painter {
+    fn paint(&mut self, token: Option<Token>, text: &str) {
+        let Some(token) = token.filter(|_| !text.is_empty()) else {
+            escape_html_body_text(&mut self.out, text).unwrap();
+            return;
+        };
+        match self.style {
+            HighlightStyle::Classes => {
+                self.out.push_str("<span class=\"");
+                self.out.push_str(token.class());
+            }
+            HighlightStyle::Inline => {
+                self.out.push_str("<span style=\"");
+                self.out.push_str(token.style());
+            }
+        }
+        self.out.push_str("\">");
+        escape_html_body_text(&mut self.out, text).unwrap();
+        self.out.push_str("</span>");
+    }
+}
+
+/// Highlights `code` written in `language`, or `None` if the language is not
+/// one of the bundled ones.
+pub(crate) fn highlight(code: &str, language: &str, style: HighlightStyle) -> Option<String> {
+    Grammar::find(language).map(|grammar| grammar.highlight(code, style))
+}
+
+/// CSS rules for the classes emitted with `HighlightStyle::Classes`.
+pub(crate) fn stylesheet() -> String {
+    Token::ALL
+        .iter()
+        .map(|token| {
+            format!(
+                ".{} {{ {}; }}\n",
+                token.class(),
+                token.style().replace(';', "; ")
+            )
+        })
+        .collect()
+}
+
+/// Replaces the text of fenced code blocks in a bundled language with
+/// highlighted HTML.
+pub(crate) fn apply(events: Vec<Spanned<'_>>, style: HighlightStyle) -> Vec<Spanned<'_>> {
+    let mut out = Vec::with_capacity(events.len());
+    let mut pending: Option<(Grammar, String)> = None;
+    for (event, range) in events {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref info))) => {
+                let language = info.split(' ').next().unwrap_or_default();
+                pending = Grammar::find(language).map(|grammar| (grammar, String::new()));
+                out.push((event, range));
+            }
+            Event::Text(text) if pending.is_some() => {
+                if let Some((_, code)) = &mut pending {
+                    code.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((grammar, code)) = pending.take() {
+                    let html = grammar.highlight(&code, style);
+                    out.push((Event::Html(html.into()), range.clone()));
+                }
+                out.push((event, range));
+            }
+            event => out.push((event, range)),
+        }
+    }
+    out
+}
+
+fn is_word_char(language: &Language, c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || (c == '$' && language.dollar_identifiers)
+}
+
+fn tokenize(language: &Language, code: &str, painter: &mut Painter) {
+    let mut pos = 0;
+    let mut line_start = true;
+    while pos < code.len() {
+        let (token, end) = scan(language, code, pos, line_start);
+        painter.paint(token, &code[pos..end]);
+        let text = &code[pos..end];
+        if let Some(newline) = text.rfind('\n') {
+            line_start = text[newline + 1..].trim().is_empty();
+        } else if !text.trim().is_empty() {
+            line_start = false;
+        }
+        pos = end;
+    }
+}
+
+/// Scans one token (or one run of plain text) starting at `pos`, returning
+/// its kind and end.
+fn scan(language: &Language, code: &str, pos: usize, line_start: bool) -> (Option<Token>, usize) {
+    let rest = &code[pos..];
+    let c = rest.chars().next().unwrap();
+    let previous = code[..pos].chars().next_back();
+
+    if let Some((open, close)) = language.block_comment {
+        if let Some(body) = rest.strip_prefix(open) {
+            let end = body
+                .find(close)
+                .map_or(code.len(), |index| pos + open.len() + index + close.len());
+            return (Some(Token::Comment), end);
+        }
+    }
+    let word_start = previous.is_none_or(char::is_whitespace);
+    if (word_start || !language.word_comments)
+        && language
+            .line_comments
+            .iter()
+            .any(|comment| rest.starts_with(comment))
+    {
+        return (Some(Token::Comment), line_end(code, pos));
+    }
+    if language.keys == Keys::Toml && line_start {
+        if c == '[' {
+            return (Some(Token::Type), line_end(code, pos));
+        }
+        if let Some(key) = toml_key(rest) {
+            return (Some(Token::Property), pos + key);
+        }
+    }
+    if language.lifetimes && c == '\'' {
+        let mut chars = rest[1..].chars();
+        let first = chars.next();
+        let is_char = first == Some('\\') || chars.next() == Some('\'');
+        if is_char {
+            return (
+                Some(Token::String),
+                string_end(code, pos, &quote("'", "'", true, false)),
+            );
+        }
+        if first.is_some_and(|first| is_word_char(language, first)) {
+            return (Some(Token::Variable), word_end(language, code, pos + 1));
+        }
+    }
+    if let Some(quote) = language
+        .quotes
+        .iter()
+        .find(|quote| rest.starts_with(quote.open))
+    {
+        let end = string_end(code, pos, quote);
+        if language.keys == Keys::Json && code[end..].trim_start().starts_with(':') {
+            return (Some(Token::Property), end);
+        }
+        return (Some(Token::String), end);
+    }
+    if language.attributes && (rest.starts_with("#[") || rest.starts_with("#![")) {
+        let mut depth = 0;
+        for (index, c) in rest.char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' if depth == 1 => return (Some(Token::Attribute), pos + index + 1),
+                ']' => depth -= 1,
+                '\n' => break,
+                _ => {}
+            }
+        }
+    }
+    if language.decorators && c == '@' && rest[1..].starts_with(|c| is_word_char(language, c)) {
+        let mut end = word_end(language, code, pos + 1);
+        while code[end..].starts_with('.') && code[end + 1..].starts_with(char::is_alphabetic) {
+            end = word_end(language, code, end + 1);
+        }
+        return (Some(Token::Attribute), end);
+    }
+    if language.dollar_variables && c == '$' {
+        if rest[1..].starts_with('{') {
+            let end = rest
+                .find('}')
+                .map_or(line_end(code, pos), |index| pos + index + 1);
+            return (Some(Token::Variable), end);
+        }
+        if rest[1..].starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            return (Some(Token::Variable), word_end(language, code, pos + 1));
+        }
+        if rest[1..].starts_with(|c: char| c.is_ascii_digit() || "?@#*!$-".contains(c)) {
+            return (Some(Token::Variable), pos + 2);
+        }
+    }
+    if c.is_ascii_digit() {
+        return (Some(Token::Number), number_end(code, pos));
+    }
+    if is_word_char(language, c) {
+        let end = word_end(language, code, pos);
+        let word = &code[pos..end];
+        let after = &code[end..];
+        let prefixed = !language.string_prefixes.is_empty()
+            && word.len() <= 2
+            && word.chars().all(|c| language.string_prefixes.contains(c));
+        if language.raw_strings && matches!(word, "r" | "br") {
+            if let Some(end) = raw_string_end(code, end) {
+                return (Some(Token::String), end);
+            }
+        }
+        if prefixed {
+            if let Some(quote) = language
+                .quotes
+                .iter()
+                .find(|quote| after.starts_with(quote.open))
+            {
+                return (Some(Token::String), string_end(code, end, quote));
+            }
+        }
+        return (classify(language, word, after), end);
+    }
+    (None, pos + c.len_utf8())
+}
+
+fn classify(language: &Language, word: &str, after: &str) -> Option<Token> {
+    let matches = |list: &[&str]| {
+        list.iter().any(|entry| match language.ignore_case {
+            true => entry.eq_ignore_ascii_case(word),
+            false => *entry == word,
+        })
+    };
+    if matches(language.keywords) {
+        Some(Token::Keyword)
+    } else if matches(language.literals) {
+        Some(Token::Literal)
+    } else if matches(language.types) {
+        Some(Token::Type)
+    } else if matches(language.builtins)
+        || (language.calls && after.starts_with('('))
+        || (language.macros && after.starts_with('!') && !after.starts_with("!="))
+    {
+        Some(Token::Function)
+    } else if language.capitalized_types && word.starts_with(|c: char| c.is_uppercase()) {
+        Some(Token::Type)
+    } else {
+        None
+    }
+}
+
+fn line_end(code: &str, pos: usize) -> usize {
+    code[pos..]
+        .find('\n')
+        .map_or(code.len(), |index| pos + index)
+}
+
+fn word_end(language: &Language, code: &str, pos: usize) -> usize {
+    let mut chars = code[pos..].char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let joins = language.dashed_words
+            && c == '-'
+            && chars
+                .peek()
+                .is_some_and(|&(_, next)| next.is_alphanumeric());
+        if !is_word_char(language, c) && !joins {
+            return pos + index;
+        }
+    }
+    code.len()
+}
+
+fn number_end(code: &str, pos: usize) -> usize {
+    let rest = &code[pos..];
+    let hex = rest.starts_with("0x") || rest.starts_with("0X");
+    let mut previous = '0';
+    for (index, c) in rest.char_indices() {
+        let continues = c.is_alphanumeric()
+            || c == '_'
+            || (c == '.' && rest[index + 1..].starts_with(|c: char| c.is_ascii_digit()))
+            || ((c == '+' || c == '-') && !hex && matches!(previous, 'e' | 'E'));
+        if !continues {
+            return pos + index;
+        }
+        previous = c;
+    }
+    code.len()
+}
+
+/// The end of the string opening with `quote` at `pos`, or of the line or
+/// code for an unterminated one.
+fn string_end(code: &str, pos: usize, quote: &Quote) -> usize {
+    let start = pos + quote.open.len();
+    let mut chars = code[start..].char_indices();
+    while let Some((index, c)) = chars.next() {
+        if code[start + index..].starts_with(quote.close) {
+            return start + index + quote.close.len();
+        }
+        match c {
+            '\\' if quote.escapes => {
+                chars.next();
+            }
+            '\n' if !quote.multiline => return start + index,
+            _ => {}
+        }
+    }
+    code.len()
+}
+
+/// The end of the raw string whose `#`s or quote start at `pos`, or of the
+/// code for an unterminated one; `None` when no raw string starts there.
+fn raw_string_end(code: &str, pos: usize) -> Option<usize> {
+    let rest = &code[pos..];
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    if !rest[hashes..].starts_with('"') {
+        return None;
+    }
+    let close = format!("\"{}", "#".repeat(hashes));
+    let start = pos + hashes + 1;
+    Some(
+        code[start..]
+            .find(&close)
+            .map_or(code.len(), |index| start + index + close.len()),
+    )
+}
+
+/// The length of a `key =` key at the start of `rest`, without the
+/// whitespace before `=`.
+fn toml_key(rest: &str) -> Option<usize> {
+    let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+    let key = line[..line.find('=')?].trim_end();
+    let bare = |c: char| c.is_alphanumeric() || "_-. \"'".contains(c);
+    (!key.is_empty() && key.chars().all(bare)).then_some(key.len())
+}
+
+/// YAML is highlighted line by line: keys, scalars after them, comments and
+/// the indented content of `|` and `>` block scalars.
+fn tokenize_yaml(code: &str, painter: &mut Painter) {
+    let mut block_indent: Option<usize> = None;
+    for line in code.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let indent = content.len() - content.trim_start().len();
+        if let Some(block) = block_indent {
+            if content.trim().is_empty() || indent > block {
+                painter.paint(Some(Token::String), content);
+                painter.paint(None, &line[content.len()..]);
+                continue;
+            }
+            block_indent = None;
+        }
+        if content == "---" || content == "..." {
+            painter.paint(Some(Token::Keyword), content);
+            painter.paint(None, &line[content.len()..]);
+            continue;
+        }
+
+        let mut pos = indent;
+        while content[pos..].starts_with("- ") || content[pos..].starts_with("-\t") {
+            pos += 2;
+            pos += content[pos..].len() - content[pos..].trim_start().len();
+        }
+        painter.paint(None, &content[..pos]);
+        let mut rest = &content[pos..];
+        if let Some(key) = yaml_key(rest) {
+            painter.paint(Some(Token::Property), &rest[..key]);
+            painter.paint(None, ":");
+            rest = &rest[key + 1..];
+        }
+        let value = rest.trim_start();
+        painter.paint(None, &rest[..rest.len() - value.len()]);
+        if value.starts_with('|') || value.starts_with('>') {
+            block_indent = Some(indent);
+        }
+        yaml_value(value, painter);
+        painter.paint(None, &line[content.len()..]);
+    }
+}
+
+/// The length of the mapping key at the start of `rest`, if there is one.
+fn yaml_key(rest: &str) -> Option<usize> {
+    if rest.starts_with('#') {
+        return None;
+    }
+    let start = match rest.chars().next()? {
+        quote @ ('"' | '\'') => rest[1..].find(quote)? + 2,
+        _ => 0,
+    };
+    let mut previous = ' ';
+    for (index, c) in rest[start..].char_indices() {
+        let index = start + index;
+        if c == '#' && previous.is_whitespace() {
+            return None;
+        }
+        if c == ':'
+            && rest[index + 1..]
+                .chars()
+                .next()
+                .is_none_or(char::is_whitespace)
+        {
+            return (index > 0).then_some(index);
+        }
+        previous = c;
+    }
+    None
+}
+
+fn yaml_value(value: &str, painter: &mut Painter) {
+    if value.starts_with('#') {
+        painter.paint(Some(Token::Comment), value);
+        return;
+    }
+    let (scalar, comment) = match value.find(" #") {
+        Some(index) if !value.starts_with(['"', '\'']) => value.split_at(index),
+        _ => (value, ""),
+    };
+    let token = match scalar.chars().next() {
+        None => None,
+        Some('"') => Some(Token::String),
+        Some('\'') => Some(Token::String),
+        Some('&' | '*') => Some(Token::Variable),
+        Some('!') => Some(Token::Type),
+        Some(_) => yaml_scalar(scalar.trim_end()),
+    };
+    if let Some(Token::String) = token {
+        let quote = match scalar.starts_with('"') {
+            true => quote("\"", "\"", true, false),
+            false => quote("'", "'", false, false),
+        };
+        let end = string_end(scalar, 0, &quote);
+        painter.paint(Some(Token::String), &scalar[..end]);
+        yaml_value(&scalar[end..], painter);
+    } else if let Some(Token::Variable | Token::Type) = token {
+        let end = scalar.find(char::is_whitespace).unwrap_or(scalar.len());
+        painter.paint(token, &scalar[..end]);
+        let rest = scalar[end..].trim_start();
+        painter.paint(None, &scalar[end..scalar.len() - rest.len()]);
+        yaml_value(rest, painter);
+    } else {
+        painter.paint(token, scalar);
+    }
+    if !comment.is_empty() {
+        let trimmed = comment.trim_start();
+        painter.paint(None, &comment[..comment.len() - trimmed.len()]);
+        painter.paint(Some(Token::Comment), trimmed);
+    }
+}
+
+/// Plain scalars that YAML resolves to booleans, null or numbers.
+fn yaml_scalar(scalar: &str) -> Option<Token> {
+    const LITERALS: [&str; 10] = [
+        "true", "false", "yes", "no", "on", "off", "null", "~", ".inf", ".nan",
+    ];
+    if LITERALS
+        .iter()
+        .any(|literal| literal.eq_ignore_ascii_case(scalar))
+    {
+        Some(Token::Literal)
+    } else if scalar.parse::<f64>().is_ok()
+        || (scalar.starts_with("0x") && i64::from_str_radix(&scalar[2..], 16).is_ok())
+    {
+        Some(Token::Number)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use pulldown_cmark::{
+    Alignment, BlockQuoteKind, CodeBlockKind, CowStr, Event, LinkType, Tag, TagEnd,
+};
+use pulldown_cmark_escape::{escape_href, escape_html, escape_html_body_text};
+
+use crate::render::Spanned;
+use crate::source_map::{block_element, SourceIndex};
+
+enum TableState {
+    Head,
+    Body,
+}
+
+/// Renders events to HTML the way `pulldown_cmark::html::push_html` does,
+/// optionally tagging every block element with the source range it came
+/// from.
+struct HtmlWriter<'a, 's, I> {
+    iter: I,
+    out: &'s mut String,
+    source: Option<&'s SourceIndex<'s>>,
+    end_newline: bool,
+    in_non_writing_block: bool,
+    table_state: TableState,
+    table_alignments: Vec<Alignment>,
+    table_cell_index: usize,
+    numbers: HashMap<CowStr<'a>, usize>,
+}
+
+/// Appends the HTML for `events` to `out`. With a `source`, block elements
+/// get `data-source-start`/`data-source-end` (UTF-16 offsets) and
+/// `data-source-line` attributes.
+pub(crate) fn push_html<'a>(
+    out: &mut String,
+    events: impl Iterator<Item = Spanned<'a>>,
+    source: Option<&SourceIndex>,
+) {
+    HtmlWriter {
+        iter: events,
+        out,
+        source,
+        end_newline: true,
+        in_non_writing_block: false,
+        table_state: TableState::Head,
+        table_alignments: Vec::new(),
+        table_cell_index: 0,
+        numbers: HashMap::new(),
+    }
+    .run();
+}
+
+// Writing into a `String` cannot fail.
+fn escape(out: &mut String, text: &str) {
+    escape_html(out, text).unwrap();
+}
+
+fn escape_body(out: &mut String, text: &str) {
+    escape_html_body_text(out, text).unwrap();
+}
+
+fn escape_url(out: &mut String, text: &str) {
+    escape_href(out, text).unwrap();
+}
+
+impl<'a, I> HtmlWriter<'a, '_, I>
+where
+    I: Iterator<Item = Spanned<'a>>,
+{
+    fn write(&mut self, s: &str) {
+        self.out.push_str(s);
+        if !s.is_empty() {
+            self.end_newline = s.ends_with('\n');
+        }
+    }
+
+    fn write_newline(&mut self) {
+        self.end_newline = true;
+        self.out.push('\n');
+    }
+
+    /// Starts a block on a fresh line.
+    fn block_break(&mut self) {
+        if !self.end_newline {
+            self.write_newline();
+        }
+    }
+
+    fn source_attributes(&mut self, range: &Range<usize>) {
+        if let Some(source) = self.source {
+            let attributes = format!(
+                " data-source-start=\"{}\" data-source-end=\"{}\" data-source-line=\"{}\"",
+                source.utf16(range.start),
+                source.utf16(range.end),
+                source.line(range.start),
+            );
+            self.write(&attributes);
+        }
+    }
+
+    fn run(mut self) {
+        while let Some((event, range)) = self.iter.next() {
+            match event {
+                Event::Start(tag) => self.start_tag(tag, range),
+                Event::End(tag) => self.end_tag(tag),
+                Event::Text(text) => {
+                    if !self.in_non_writing_block {
+                        escape_body(self.out, &text);
+                        self.end_newline = text.ends_with('\n');
+                    }
+                }
+                Event::Code(text) => {
+                    self.write("<code>");
+                    escape_body(self.out, &text);
+                    self.write("</code>");
+                }
+                Event::InlineMath(text) => {
+                    self.write(r#"<span class="math math-inline">"#);
+                    escape(self.out, &text);
+                    self.write("</span>");
+                }
+                Event::DisplayMath(text) => {
+                    self.write(r#"<span class="math math-display">"#);
+                    escape(self.out, &text);
+                    self.write("</span>");
+                }
+                Event::Html(html) | Event::InlineHtml(html) => self.write(&html),
+                Event::SoftBreak => self.write_newline(),
+                Event::HardBreak => self.write("<br />\n"),
+                Event::Rule => {
+                    self.block_break();
+                    self.write("<hr");
+                    self.source_attributes(&range);
+                    self.write(" />\n");
+                }
+                Event::FootnoteReference(name) => {
+                    let len = self.numbers.len() + 1;
+                    self.write("<sup class=\"footnote-reference\"><a href=\"#");
+                    escape(self.out, &name);
+                    self.write("\">");
+                    let number = *self.numbers.entry(name).or_insert(len);
+                    self.write(&number.to_string());
+                    self.write("</a></sup>");
+                }
+                Event::TaskListMarker(true) => {
+                    self.write("<input disabled=\"\" type=\"checkbox\" checked=\"\"/>\n")
+                }
+                Event::TaskListMarker(false) => {
+                    self.write("<input disabled=\"\" type=\"checkbox\"/>\n")
+                }
+            }
+        }
+    }
+
+    /// Writes `<element` plus source attributes, on a fresh line.
+    fn open_block(&mut self, tag: &Tag, range: &Range<usize>) {
+        self.block_break();
+        self.write("<");
+        self.write(block_element(tag).unwrap_or_default());
+        self.source_attributes(range);
+    }
+
+    fn start_tag(&mut self, tag: Tag<'a>, range: Range<usize>) {
+        match tag {
+            Tag::HtmlBlock => {}
+            Tag::Paragraph
+            | Tag::Item
+            | Tag::DefinitionListTitle
+            | Tag::DefinitionListDefinition => {
+                self.open_block(&tag, &range);
+                self.write(">");
+            }
+            Tag::Heading {
+                ref id,
+                ref classes,
+                ref attrs,
+                ..
+            } => {
+                self.open_block(&tag, &range);
+                if let Some(id) = id {
+                    self.write(" id=\"");
+                    escape(self.out, id);
+                    self.write("\"");
+                }
+                if !classes.is_empty() {
+                    self.write(" class=\"");
+                    for (index, class) in classes.iter().enumerate() {
+                        if index > 0 {
+                            self.write(" ");
+                        }
+                        escape(self.out, class);
+                    }
+                    self.write("\"");
+                }
+                for (attr, value) in attrs {
+                    self.write(" ");
+                    escape(self.out, attr);
+                    self.write("=\"");
+                    if let Some(value) = value {
+                        escape(self.out, value);
+                    }
+                    self.write("\"");
+                }
+                self.write(">");
+            }
+            Tag::Table(ref alignments) => {
+                self.table_alignments = alignments.clone();
+                self.write("<table");
+                self.source_attributes(&range);
+                self.write(">");
+            }
+            Tag::TableHead => {
+                self.table_state = TableState::Head;
+                self.table_cell_index = 0;
+                self.write("<thead><tr");
+                self.source_attributes(&range);
+                self.write(">");
+            }
+            Tag::TableRow => {
+                self.table_cell_index = 0;
+                self.write("<tr");
+                self.source_attributes(&range);
+                self.write(">");
+            }
+            Tag::TableCell => {
+                match self.table_state {
+                    TableState::Head => self.write("<th"),
+                    TableState::Body => self.write("<td"),
+                }
+                match self.table_alignments.get(self.table_cell_index) {
+                    Some(&Alignment::Left) => self.write(" style=\"text-align: left\">"),
+                    Some(&Alignment::Center) => self.write(" style=\"text-align: center\">"),
+                    Some(&Alignment::Right) => self.write(" style=\"text-align: right\">"),
+                    _ => self.write(">"),
+                }
+            }
+            Tag::BlockQuote(kind) => {
+                self.open_block(&tag, &range);
+                let class = match kind {
+                    None => "",
+                    Some(BlockQuoteKind::Note) => " class=\"markdown-alert-note\"",
+                    Some(BlockQuoteKind::Tip) => " class=\"markdown-alert-tip\"",
+                    Some(BlockQuoteKind::Important) => " class=\"markdown-alert-important\"",
+                    Some(BlockQuoteKind::Warning) => " class=\"markdown-alert-warning\"",
+                    Some(BlockQuoteKind::Caution) => " class=\"markdown-alert-caution\"",
+                };
+                self.write(class);
+                self.write(">\n");
+            }
+            Tag::CodeBlock(ref info) => {
+                let lang = match info {
+                    CodeBlockKind::Fenced(info) => info.split(' ').next().unwrap_or_default(),
+                    CodeBlockKind::Indented => "",
+                };
+                let lang = lang.to_string();
+                self.open_block(&tag, &range);
+                if lang.is_empty() {
+                    self.write("><code>");
+                } else {
+                    self.write("><code class=\"language-");
+                    escape(self.out, &lang);
+                    self.write("\">");
+                }
+            }
+            Tag::List(start) => {
+                self.open_block(&tag, &range);
+                match start {
+                    Some(1) | None => {}
+                    Some(start) => self.write(&format!(" start=\"{}\"", start)),
+                }
+                self.write(">\n");
+            }
+            Tag::DefinitionList => {
+                self.open_block(&tag, &range);
+                self.write(">\n");
+            }
+            Tag::Subscript => self.write("<sub>"),
+            Tag::Superscript => self.write("<sup>"),
+            Tag::Emphasis => self.write("<em>"),
+            Tag::Strong => self.write("<strong>"),
+            Tag::Strikethrough => self.write("<del>"),
+            Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                ..
+            } => {
+                self.write("<a href=\"");
+                if link_type == LinkType::Email {
+                    self.write("mailto:");
+                }
+                escape_url(self.out, &dest_url);
+                if !title.is_empty() {
+                    self.write("\" title=\"");
+                    escape(self.out, &title);
+                }
+                self.write("\">");
+            }
+            Tag::Image {
+                dest_url, title, ..
+            } => {
+                self.write("<img src=\"");
+                escape_url(self.out, &dest_url);
+                self.write("\" alt=\"");
+                self.raw_text();
+                if !title.is_empty() {
+                    self.write("\" title=\"");
+                    escape(self.out, &title);
+                }
+                self.write("\" />");
+            }
+            Tag::FootnoteDefinition(ref name) => {
+                let name = name.clone();
+                self.open_block(&tag, &range);
+                self.write(" class=\"footnote-definition\" id=\"");
+                escape(self.out, &name);
+                self.write("\"><sup class=\"footnote-definition-label\">");
+                let len = self.numbers.len() + 1;
+                let number = *self.numbers.entry(name).or_insert(len);
+                self.write(&number.to_string());
+                self.write("</sup>");
+            }
+            Tag::MetadataBlock(_) => self.in_non_writing_block = true,
+        }
+    }
+
+    fn end_tag(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::HtmlBlock => {}
+            TagEnd::Paragraph => self.write("</p>\n"),
+            TagEnd::Heading(level) => self.write(&format!("</{}>\n", level)),
+            TagEnd::Table => self.write("</tbody></table>\n"),
+            TagEnd::TableHead => {
+                self.write("</tr></thead><tbody>\n");
+                self.table_state = TableState::Body;
+            }
+            TagEnd::TableRow => self.write("</tr>\n"),
+            TagEnd::TableCell => {
+                match self.table_state {
+                    TableState::Head => self.write("</th>"),
+                    TableState::Body => self.write("</td>"),
+                }
+                self.table_cell_index += 1;
+            }
+            TagEnd::BlockQuote(_) => self.write("</blockquote>\n"),
+            TagEnd::CodeBlock => self.write("</code></pre>\n"),
+            TagEnd::List(true) => self.write("</ol>\n"),
+            TagEnd::List(false) => self.write("</ul>\n"),
+            TagEnd::Item => self.write("</li>\n"),
+            TagEnd::DefinitionList => self.write("</dl>\n"),
+            TagEnd::DefinitionListTitle => self.write("</dt>\n"),
+            TagEnd::DefinitionListDefinition => self.write("</dd>\n"),
+            TagEnd::Emphasis => self.write("</em>"),
+            TagEnd::Superscript => self.write("</sup>"),
+            TagEnd::Subscript => self.write("</sub>"),
+            TagEnd::Strong => self.write("</strong>"),
+            TagEnd::Strikethrough => self.write("</del>"),
+            TagEnd::Link => self.write("</a>"),
+            TagEnd::Image => {}
+            TagEnd::FootnoteDefinition => self.write("</div>\n"),
+            TagEnd::MetadataBlock(_) => self.in_non_writing_block = false,
+        }
+    }
+
+    /// Writes the text of an image description as an attribute value,
+    /// consuming events up to and including the image's end tag.
+    fn raw_text(&mut self) {
+        let mut nest = 0;
+        while let Some((event, _)) = self.iter.next() {
+            match event {
+                Event::Start(_) => nest += 1,
+                Event::End(_) => {
+                    if nest == 0 {
+                        break;
+                    }
+                    nest -= 1;
+                }
+                Event::Html(_) => {}
+                Event::InlineHtml(text) | Event::Code(text) | Event::Text(text) => {
+                    escape(self.out, &text);
+                    self.end_newline = text.ends_with('\n');
+                }
+                Event::InlineMath(text) => {
+                    self.write("$");
+                    escape(self.out, &text);
+                    self.write("$");
+                }
+                Event::DisplayMath(text) => {
+                    self.write("$$");
+                    escape(self.out, &text);
+                    self.write("$$");
+                }
+                Event::SoftBreak | Event::HardBreak | Event::Rule => self.write(" "),
+                Event::FootnoteReference(name) => {
+                    let len = self.numbers.len() + 1;
+                    let number = *self.numbers.entry(name).or_insert(len);
+                    self.write(&format!("[{}]", number));
+                }
+                Event::TaskListMarker(true) => self.write("[x]"),
+                Event::TaskListMarker(false) => self.write("[ ]"),
+            }
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use pulldown_cmark::{BrokenLink, CowStr, Event, Parser};
+use wasm_bindgen::prelude::*;
+
+use crate::front_matter;
+use crate::options::MarkdownOptions;
+use crate::render::{self, Spanned};
+
+/// The rendered HTML of one top-level block, keyed by an id that stays the
+/// same for as long as the block exists.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct RenderedBlock {
+    pub id: u32,
+    #[wasm_bindgen(getter_with_clone)]
+    pub html: String,
+}
+
+/// What changed in the preview after `MarkdownDocument::edit`.
+///
+/// Elements listed in `removed` go away. `blocks` are the new or re-rendered
+/// blocks in document order: ids the preview already knows get their HTML
+/// replaced, unknown ids are inserted before the block `next` (or appended
+/// when `next` is undefined).
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct DocumentPatch {
+    #[wasm_bindgen(getter_with_clone)]
+    pub removed: Vec<u32>,
+    #[wasm_bindgen(getter_with_clone)]
+    pub blocks: Vec<RenderedBlock>,
+    pub next: Option<u32>,
+}
+
+#[derive(Clone, Debug)]
+struct Block {
+    id: u32,
+    range: Range<usize>,
+    html: String,
+}
+
+/// A Markdown document kept in sync with an editor.
+///
+/// Edits re-parse only the top-level blocks around the changed text.
+/// Link reference definitions are tracked document-wide, and an edit that
+/// adds, changes or removes one falls back to re-rendering every block.
+/// Footnotes are numbered and heading ids de-duplicated per block, a `toc`
+/// placeholder only lists the headings parsed along with it, and
+/// `source_positions` is ignored since offsets baked into the HTML would go
+/// stale; use `block_at` instead.
+#[wasm_bindgen]
+pub struct MarkdownDocument {
+    text: String,
+    options: MarkdownOptions,
+    blocks: Vec<Block>,
+    refs: HashMap<String, (String, String)>,
+    def_spans: Vec<Range<usize>>,
+    next_id: u32,
+}
+
+#[wasm_bindgen]
+impl MarkdownDocument {
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str, options: &MarkdownOptions) -> MarkdownDocument {
+        let mut document = MarkdownDocument {
+            text: text.to_string(),
+            options: options.clone(),
+            blocks: Vec::new(),
+            refs: HashMap::new(),
+            def_spans: Vec::new(),
+            next_id: 0,
+        };
+        document.rerender_all();
+        document
+    }
+
+    pub fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    /// The HTML of the whole document.
+    pub fn html(&self) -> String {
+        self.blocks
+            .iter()
+            .map(|block| block.html.as_str())
+            .collect()
+    }
+
+    /// Every block in document order, for the initial render of the preview.
+    pub fn blocks(&self) -> Vec<RenderedBlock> {
+        self.blocks
+            .iter()
+            .map(|block| RenderedBlock {
+                id: block.id,
+                html: block.html.clone(),
+            })
+            .collect()
+    }
+
+    /// The id of the top-level block containing UTF-16 offset `offset`.
+    pub fn block_at(&self, offset: usize) -> Option<u32> {
+        let offset = utf16_to_byte(&self.text, 0, offset);
+        self.blocks
+            .iter()
+            .find(|block| block.range.start <= offset && offset < block.range.end)
+            .map(|block| block.id)
+    }
+
+    /// Replaces `deleted` characters at `offset` with `inserted`. Offsets
+    /// and lengths are in UTF-16 code units, as reported by a textarea.
+    pub fn edit(&mut self, offset: usize, deleted: usize, inserted: &str) -> DocumentPatch {
+        let start = utf16_to_byte(&self.text, 0, offset);
+        let end = utf16_to_byte(&self.text, start, deleted);
+        let old_len = self.text.len();
+        let old_front_matter = self.front_matter_len();
+        self.text.replace_range(start..end, inserted);
+        let delta = self.text.len() as isize - old_len as isize;
+        // Whether the document starts with front matter can depend on a line
+        // far from the edit, such as a closing `---` being typed.
+        let front_matter_moved = match old_front_matter > start {
+            true => shift(old_front_matter, delta) != self.front_matter_len(),
+            false => old_front_matter != self.front_matter_len(),
+        };
+
+        let mut lo = self
+            .blocks
+            .iter()
+            .position(|block| block.range.end >= start)
+            .unwrap_or(self.blocks.len())
+            .saturating_sub(1);
+        // A block can depend on the lines above it up to the last blank line:
+        // a line after a link reference definition continues the paragraph
+        // the definition was parsed from.
+        while lo > 0 && !follows_blank_line(&self.text, self.blocks[lo].range.start) {
+            lo -= 1;
+        }
+        let mut hi = self
+            .blocks
+            .iter()
+            .position(|block| block.range.start > end)
+            .map_or(self.blocks.len(), |index| index + 1)
+            .min(self.blocks.len());
+        for block in self
+            .blocks
+            .iter_mut()
+            .filter(|block| block.range.start > end)
+        {
+            block.range = shift(block.range.start, delta)..shift(block.range.end, delta);
+        }
+
+        let region_start = match lo {
+            0 => 0,
+            _ => line_start(&self.text, self.blocks[lo].range.start),
+        };
+        loop {
+            let region_end = match self.blocks.get(hi) {
+                Some(block) => line_start(&self.text, block.range.start),
+                None => self.text.len(),
+            };
+            let (parsed, has_defs) = self.parse_region(region_start..region_end);
+            // A block running into the next one (an unclosed fence, a
+            // paragraph continued by the following line, a list item or
+            // indented code continued after a blank line) must be re-parsed
+            // together with it.
+            let open_ended = parsed
+                .last()
+                .is_some_and(|(range, _)| range.end >= region_end)
+                || self.blocks.get(hi).is_some_and(|block| {
+                    let line = line_start(&self.text, block.range.start);
+                    !follows_blank_line(&self.text, line)
+                        || self.text[line..].starts_with([' ', '\t'])
+                });
+            if open_ended && hi < self.blocks.len() {
+                hi += 1;
+                continue;
+            }
+            let old_region = region_start..shift(region_end, -delta);
+            let had_defs = self
+                .def_spans
+                .iter()
+                .any(|span| span.start < old_region.end && old_region.start < span.end);
+            if had_defs || has_defs || front_matter_moved {
+                // Link reference definitions or the front matter changed,
+                // which can change the rendering of any block.
+                self.collect_refs();
+                let (parsed, _) = self.parse_region(0..self.text.len());
+                let count = self.blocks.len();
+                return self.splice(0..count, parsed);
+            }
+            for span in self.def_spans.iter_mut().filter(|span| span.start >= end) {
+                *span = shift(span.start, delta)..shift(span.end, delta);
+            }
+            return self.splice(lo..hi, parsed);
+        }
+    }
+}
+
+impl MarkdownDocument {
+    fn rerender_all(&mut self) {
+        self.collect_refs();
+        let (parsed, _) = self.parse_region(0..self.text.len());
+        let count = self.blocks.len();
+        self.splice(0..count, parsed);
+    }
+
+    fn front_matter_len(&self) -> usize {
+        match self.options.front_matter {
+            true => front_matter::block_len(&self.text),
+            false => 0,
+        }
+    }
+
+    fn collect_refs(&mut self) {
+        let parser = Parser::new_ext(&self.text, (&self.options).into());
+        let defs = parser.reference_definitions();
+        self.def_spans = defs.iter().map(|(_, def)| def.span.clone()).collect();
+        self.refs = defs
+            .iter()
+            .map(|(label, def)| {
+                let title = def.title.as_deref().unwrap_or_default().to_string();
+                (normalize_label(label), (def.dest.to_string(), title))
+            })
+            .collect();
+    }
+
+    /// Parses `range` of the text as a standalone document and renders each
+    /// top-level block in it. Ranges in the result are document offsets; the
+    /// flag tells whether the range defines any link references.
+    fn parse_region(&self, range: Range<usize>) -> (Vec<(Range<usize>, String)>, bool) {
+        let refs = &self.refs;
+        let callback = |link: BrokenLink| {
+            refs.get(&normalize_label(&link.reference))
+                .map(|(dest, title)| (CowStr::from(dest.clone()), CowStr::from(title.clone())))
+        };
+        // Front matter renders to nothing but is kept as a block of its own,
+        // so edits inside it are handled like any other.
+        let mut blocks = Vec::new();
+        let mut range = range;
+        if range.start == 0 && self.options.front_matter {
+            let len = front_matter::block_len(&self.text[range.clone()]);
+            if len > 0 {
+                blocks.push((0..len, String::new()));
+                range.start = len;
+            }
+        }
+        let source = &self.text[range.clone()];
+        let parser =
+            Parser::new_with_broken_link_callback(source, (&self.options).into(), Some(callback));
+
+        let mut events: Vec<Spanned> = Vec::new();
+        let mut block_start = 0;
+        let mut depth = 0usize;
+        let mut iter = parser.into_offset_iter();
+        for (event, span) in iter.by_ref() {
+            match &event {
+                Event::Start(_) => {
+                    if depth == 0 {
+                        block_start = span.start;
+                    }
+                    depth += 1;
+                }
+                Event::End(_) => depth -= 1,
+                _ if depth == 0 => block_start = span.start,
+                _ => {}
+            }
+            events.push((event, span.clone()));
+            if depth == 0 {
+                let events = std::mem::take(&mut events);
+                let html = render::render_events(events, &self.options, None);
+                blocks.push((range.start + block_start..range.start + span.end, html));
+            }
+        }
+        let has_defs = iter.reference_definitions().iter().next().is_some();
+        (blocks, has_defs)
+    }
+
+    /// Replaces `self.blocks[replaced]` with freshly `parsed` blocks. Blocks
+    /// whose HTML is unchanged at either end keep their ids, the ones in
+    /// between reuse the replaced ids in order and get fresh ids after that.
+    fn splice(
+        &mut self,
+        replaced: Range<usize>,
+        parsed: Vec<(Range<usize>, String)>,
+    ) -> DocumentPatch {
+        let old = &self.blocks[replaced.clone()];
+        let prefix = old
+            .iter()
+            .zip(&parsed)
+            .take_while(|(block, (_, html))| block.html == *html)
+            .count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(parsed[prefix..].iter().rev())
+            .take_while(|(block, (_, html))| block.html == *html)
+            .count();
+        let old_middle = &old[prefix..old.len() - suffix];
+        let new_middle = prefix..parsed.len() - suffix;
+
+        let mut patch = DocumentPatch {
+            removed: old_middle
+                .iter()
+                .skip(new_middle.len())
+                .map(|block| block.id)
+                .collect(),
+            blocks: Vec::new(),
+            next: self.blocks.get(replaced.end - suffix).map(|block| block.id),
+        };
+        let mut new_blocks = Vec::with_capacity(parsed.len());
+        for (index, (range, html)) in parsed.into_iter().enumerate() {
+            let id = if index < prefix {
+                old[index].id
+            } else if index >= new_middle.end {
+                old[index + old.len() - new_middle.end - suffix].id
+            } else if let Some(block) = old_middle.get(index - prefix) {
+                block.id
+            } else {
+                self.next_id += 1;
+                self.next_id
+            };
+            if new_middle.contains(&index) {
+                patch.blocks.push(RenderedBlock {
+                    id,
+                    html: html.clone(),
+                });
+            }
+            new_blocks.push(Block { id, range, html });
+        }
+        self.blocks.splice(replaced, new_blocks);
+        patch
+    }
+}
+
+fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Converts `units` UTF-16 code units, counted from byte `from`, into a byte
+/// offset, clamped to the end of `text`.
+pub(crate) fn utf16_to_byte(text: &str, from: usize, units: usize) -> usize {
+    let mut remaining = units;
+    for (index, c) in text[from..].char_indices() {
+        if remaining == 0 {
+            return from + index;
+        }
+        remaining = remaining.saturating_sub(c.len_utf16());
+    }
+    text.len()
+}
+
+/// The start of the line containing `offset`. Lines end at `\n`, `\r` or
+/// `\r\n`; an offset inside a `\r\n` belongs to the line it ends.
+fn line_start(text: &str, offset: usize) -> usize {
+    let end = match text[..offset].ends_with('\r') && text[offset..].starts_with('\n') {
+        true => offset - 1,
+        false => offset,
+    };
+    text[..end].rfind(['\n', '\r']).map_or(0, |pos| pos + 1)
+}
+
+/// Whether the line containing `offset` is the first line of the text or
+/// comes after a blank line.
+fn follows_blank_line(text: &str, offset: usize) -> bool {
+    match line_start(text, offset) {
+        0 => true,
+        line => text[line_start(text, line - 1)..line].trim().is_empty(),
+    }
+}
+
+fn shift(offset: usize, delta: isize) -> usize {
+    offset.saturating_add_signed(delta)
+}
@@ -0,0 +1,708 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::{
+    Alignment, BlockQuoteKind, CodeBlockKind, CowStr, Event, HeadingLevel, LinkType, Tag, TagEnd,
+};
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::front_matter;
+use crate::gfm;
+use crate::headings;
+use crate::options::{HtmlPolicy, MarkdownOptions};
+use crate::raw_html;
+use crate::render::{self, Spanned};
+use crate::toc;
+
+/// How `render_latex` typesets code blocks.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CodeEnvironment {
+    /// `lstlisting`, with the language set when listings knows it.
+    #[default]
+    Listings,
+    /// `minted`, highlighted by Pygments. Needs `-shell-escape`.
+    Minted,
+    /// Plain `verbatim`.
+    Verbatim,
+}
+
+/// Settings for `render_latex`.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatexOptions {
+    /// Write a whole document; otherwise only the body, for `\input`.
+    pub standalone: bool,
+    pub code: CodeEnvironment,
+    /// With `book`, `report` and their KOMA-Script counterparts, level 1
+    /// headings become chapters.
+    #[wasm_bindgen(getter_with_clone)]
+    pub document_class: String,
+    /// Extra preamble, written after the packages the output needs.
+    #[wasm_bindgen(getter_with_clone)]
+    pub preamble: String,
+    /// Replaces the built-in document. `$body$`, `$packages$`,
+    /// `$preamble$`, `$documentclass$`, `$title$`, `$author$` and `$date$`
+    /// are substituted; the last three from front matter.
+    #[wasm_bindgen(getter_with_clone)]
+    pub template: Option<String>,
+}
+
+impl Default for LatexOptions {
+    fn default() -> Self {
+        LatexOptions {
+            standalone: true,
+            code: CodeEnvironment::default(),
+            document_class: "article".to_string(),
+            preamble: String::new(),
+            template: None,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl LatexOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> LatexOptions {
+        LatexOptions::default()
+    }
+}
+
+const PACKAGES: &str = r"\usepackage{iftex}
+\ifPDFTeX
+  \usepackage[T1]{fontenc}
+  \usepackage[utf8]{inputenc}
+  \usepackage{lmodern}
+\else
+  \usepackage{fontspec}
+\fi
+\usepackage{amsmath,amssymb}
+\usepackage{graphicx}
+\usepackage[export]{adjustbox}
+\usepackage{longtable,booktabs,array}
+\usepackage[normalem]{ulem}
+";
+
+const TEMPLATE: &str = r"\documentclass{$documentclass$}
+$packages$$preamble$
+\title{$title$}
+\author{$author$}
+\date{$date$}
+
+\begin{document}
+$maketitle$$body$
+\end{document}
+";
+
+/// Renders `input` as LaTeX: a whole document, or only its body when
+/// `latex.standalone` is off. Raw HTML is left out unless `options.html`
+/// escapes it.
+pub(crate) fn convert(input: &str, options: &MarkdownOptions, latex: &LatexOptions) -> String {
+    let mut events = headings::assign_ids(render::parse(input, options));
+    if options.toc {
+        events = toc::insert(events, options);
+    }
+    let html = match options.html {
+        HtmlPolicy::Escape => HtmlPolicy::Escape,
+        _ => HtmlPolicy::Strip,
+    };
+    events = raw_html::apply(events, html);
+    if options.autolinks {
+        events = gfm::autolink(events);
+    }
+
+    let chapters = matches!(
+        latex.document_class.as_str(),
+        "book" | "report" | "scrbook" | "scrreprt" | "memoir"
+    );
+    let footnotes = footnotes(&events, latex, chapters);
+    let mut writer = Writer::new(latex, chapters, &footnotes);
+    writer.write(events.into_iter().map(|(event, _)| event));
+    let body = writer.out.trim_end().to_string();
+    if !latex.standalone && latex.template.is_none() {
+        return body + "\n";
+    }
+
+    let fields = match options.front_matter {
+        true => front_matter::fields(input),
+        false => Default::default(),
+    };
+    let title = fields.get("title").map(metadata).unwrap_or_default();
+    let author = match fields.get("author").or_else(|| fields.get("authors")) {
+        Some(Value::Array(authors)) => authors
+            .iter()
+            .map(metadata)
+            .collect::<Vec<_>>()
+            .join(r" \and "),
+        Some(author) => metadata(author),
+        None => String::new(),
+    };
+    let date = fields.get("date").map(metadata).unwrap_or_default();
+    let mut packages = PACKAGES.to_string();
+    match latex.code {
+        CodeEnvironment::Listings => {
+            packages.push_str("\\usepackage{listings}\n");
+            packages.push_str(
+                "\\lstset{basicstyle=\\ttfamily\\small,breaklines,columns=fullflexible}\n",
+            );
+        }
+        CodeEnvironment::Minted => packages.push_str("\\usepackage{minted}\n"),
+        CodeEnvironment::Verbatim => {}
+    }
+    packages.push_str("\\usepackage{hyperref}\n");
+    let preamble = match latex.preamble.is_empty() || latex.preamble.ends_with('\n') {
+        true => latex.preamble.clone(),
+        false => format!("{}\n", latex.preamble),
+    };
+    let maketitle = if title.is_empty() {
+        ""
+    } else {
+        "\\maketitle\n\n"
+    };
+    let template = latex.template.as_deref().unwrap_or(TEMPLATE);
+    // One pass, so substituted text is never searched for placeholders.
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let placeholders = [
+            ("$body$", body.as_str()),
+            ("$packages$", &packages),
+            ("$preamble$", &preamble),
+            ("$documentclass$", &latex.document_class),
+            ("$title$", &title),
+            ("$author$", &author),
+            ("$date$", &date),
+            ("$maketitle$", maketitle),
+        ];
+        match placeholders
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+        {
+            Some((placeholder, value)) => {
+                output.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                output.push('$');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// A front matter value as escaped text.
+fn metadata(value: &Value) -> String {
+    match value {
+        Value::String(text) => escape(text),
+        Value::Null => String::new(),
+        value => escape(&value.to_string()),
+    }
+}
+
+/// The rendered content of every footnote definition, to be written where
+/// the footnote is referenced.
+fn footnotes<'a>(
+    events: &[Spanned<'a>],
+    latex: &LatexOptions,
+    chapters: bool,
+) -> HashMap<CowStr<'a>, String> {
+    let mut footnotes = HashMap::new();
+    let empty = HashMap::new();
+    let mut index = 0;
+    while index < events.len() {
+        if let Event::Start(Tag::FootnoteDefinition(name)) = &events[index].0 {
+            let start = index + 1;
+            let mut depth = 1;
+            while depth > 0 && index + 1 < events.len() {
+                index += 1;
+                match &events[index].0 {
+                    Event::Start(Tag::FootnoteDefinition(_)) => depth += 1,
+                    Event::End(TagEnd::FootnoteDefinition) => depth -= 1,
+                    _ => {}
+                }
+            }
+            // Footnotes cannot nest, so references inside one are written
+            // as plain marks.
+            let mut writer = Writer::new(latex, chapters, &empty);
+            writer.footnote = true;
+            writer.write(events[start..index].iter().map(|(event, _)| event.clone()));
+            footnotes
+                .entry(name.clone())
+                .or_insert_with(|| writer.out.trim().to_string());
+        }
+        index += 1;
+    }
+    footnotes
+}
+
+struct Writer<'a, 'o> {
+    out: String,
+    options: &'o LatexOptions,
+    chapters: bool,
+    footnotes: &'o HashMap<CowStr<'a>, String>,
+    /// The label of every footnote written so far, for repeated references.
+    labels: HashMap<CowStr<'a>, usize>,
+    /// Whether each open list is numbered.
+    lists: Vec<bool>,
+    /// Set after a list item's marker, so a task list marker can replace it.
+    item: bool,
+    alignments: Vec<Alignment>,
+    cell: usize,
+    code: Option<(String, String)>,
+    /// Depth of content that is not written: image descriptions, the text
+    /// of autolinks, metadata and footnote definitions.
+    hidden: usize,
+    /// Whether each open link wrote a closing brace.
+    links: Vec<bool>,
+    /// Definitions written for the current definition list term.
+    definitions: usize,
+    /// The id of the open heading, written as its label.
+    heading: Option<CowStr<'a>>,
+    /// Whether this is the content of a footnote.
+    footnote: bool,
+}
+
+impl<'a, 'o> Writer<'a, 'o> {
+    fn new(
+        options: &'o LatexOptions,
+        chapters: bool,
+        footnotes: &'o HashMap<CowStr<'a>, String>,
+    ) -> Self {
+        Writer {
+            out: String::new(),
+            options,
+            chapters,
+            footnotes,
+            labels: HashMap::new(),
+            lists: Vec::new(),
+            item: false,
+            alignments: Vec::new(),
+            cell: 0,
+            code: None,
+            hidden: 0,
+            links: Vec::new(),
+            definitions: 0,
+            heading: None,
+            footnote: false,
+        }
+    }
+
+    fn write(&mut self, events: impl Iterator<Item = Event<'a>>) {
+        for event in events {
+            if self.hidden > 0 {
+                match event {
+                    Event::Start(
+                        Tag::Image { .. } | Tag::MetadataBlock(_) | Tag::FootnoteDefinition(_),
+                    ) => self.hidden += 1,
+                    Event::End(
+                        TagEnd::Image | TagEnd::MetadataBlock(_) | TagEnd::FootnoteDefinition,
+                    ) => self.hidden -= 1,
+                    Event::End(TagEnd::Link)
+                        if self.hidden == 1 && self.links.last() == Some(&false) =>
+                    {
+                        self.links.pop();
+                        self.hidden = 0;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if let Some((_, code)) = self.code.as_mut() {
+                match event {
+                    Event::Text(text) => code.push_str(&text),
+                    _ => self.end_code(),
+                }
+                continue;
+            }
+            if self.item && !matches!(event, Event::TaskListMarker(_)) {
+                self.out.push_str("\\item ");
+                self.item = false;
+            }
+            match event {
+                Event::Start(tag) => self.start(tag),
+                Event::End(tag) => self.end(tag),
+                Event::Text(text) => self.out.push_str(&escape(&text)),
+                Event::Code(code) => self.out.push_str(&format!("\\texttt{{{}}}", escape(&code))),
+                Event::InlineMath(tex) => self.out.push_str(&format!("${}$", tex)),
+                Event::DisplayMath(tex) => self.out.push_str(&format!("\\[{}\\]", tex)),
+                Event::Html(_) | Event::InlineHtml(_) => {}
+                Event::SoftBreak => self.out.push('\n'),
+                Event::HardBreak => self.out.push_str("\\\\\n"),
+                Event::Rule => {
+                    self.line();
+                    self.out
+                        .push_str("\\begin{center}\\rule{0.5\\linewidth}{0.4pt}\\end{center}\n\n");
+                }
+                Event::FootnoteReference(name) => self.footnote(name),
+                Event::TaskListMarker(checked) => {
+                    let box_ = if checked {
+                        "$\\boxtimes$"
+                    } else {
+                        "$\\square$"
+                    };
+                    self.out.push_str(&format!("\\item[{}] ", box_));
+                    self.item = false;
+                }
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'a>) {
+        let environment = matches!(
+            tag,
+            Tag::BlockQuote(_)
+                | Tag::List(_)
+                | Tag::Table(_)
+                | Tag::DefinitionList
+                | Tag::Heading { .. }
+        );
+        if environment {
+            self.line();
+        }
+        match tag {
+            Tag::Paragraph => {}
+            Tag::Heading { level, id, .. } => {
+                let command = match (level, self.chapters) {
+                    (HeadingLevel::H1, true) => "chapter",
+                    (HeadingLevel::H1, false) | (HeadingLevel::H2, true) => "section",
+                    (HeadingLevel::H2, false) | (HeadingLevel::H3, true) => "subsection",
+                    (HeadingLevel::H3, false) | (HeadingLevel::H4, true) => "subsubsection",
+                    (HeadingLevel::H4, false) | (HeadingLevel::H5, true) => "paragraph",
+                    _ => "subparagraph",
+                };
+                self.out.push_str(&format!("\\{}{{", command));
+                self.heading = id;
+            }
+            Tag::BlockQuote(kind) => {
+                self.out.push_str("\\begin{quote}\n");
+                if let Some(kind) = kind {
+                    self.out.push_str(&format!("\\textbf{{{}.}} ", alert(kind)));
+                }
+            }
+            Tag::CodeBlock(kind) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_lowercase()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((language, String::new()));
+            }
+            Tag::List(start) => {
+                let numbered = start.is_some();
+                self.lists.push(numbered);
+                match start {
+                    Some(start) => {
+                        self.out.push_str("\\begin{enumerate}\n");
+                        if start != 1 {
+                            let counter = ["i", "ii", "iii", "iv"][(self.depth() - 1).min(3)];
+                            self.out.push_str(&format!(
+                                "\\setcounter{{enum{}}}{{{}}}\n",
+                                counter,
+                                start - 1
+                            ));
+                        }
+                    }
+                    None => self.out.push_str("\\begin{itemize}\n"),
+                }
+            }
+            Tag::Item => self.item = true,
+            Tag::Table(alignments) => {
+                let columns: String = alignments
+                    .iter()
+                    .map(|alignment| match alignment {
+                        Alignment::Center => 'c',
+                        Alignment::Right => 'r',
+                        _ => 'l',
+                    })
+                    .collect();
+                self.out.push_str(&format!(
+                    "\\begin{{longtable}}[]{{@{{}}{}@{{}}}}\n\\toprule\n",
+                    columns
+                ));
+                self.alignments = alignments;
+            }
+            Tag::TableHead | Tag::TableRow => self.cell = 0,
+            Tag::TableCell => {
+                if self.cell > 0 {
+                    self.out.push_str(" & ");
+                }
+                self.cell += 1;
+            }
+            Tag::Emphasis => self.out.push_str("\\emph{"),
+            Tag::Strong => self.out.push_str("\\textbf{"),
+            Tag::Strikethrough => self.out.push_str("\\sout{"),
+            Tag::Superscript => self.out.push_str("\\textsuperscript{"),
+            Tag::Subscript => self.out.push_str("\\textsubscript{"),
+            Tag::Link {
+                link_type,
+                dest_url,
+                ..
+            } => match (link_type, dest_url.strip_prefix('#')) {
+                (LinkType::Autolink, _) => {
+                    self.out.push_str(&format!("\\url{{{}}}", url(&dest_url)));
+                    self.links.push(false);
+                    self.hidden = 1;
+                }
+                (LinkType::Email, _) => {
+                    let address = dest_url.strip_prefix("mailto:").unwrap_or(&dest_url);
+                    self.out.push_str(&format!(
+                        "\\href{{mailto:{}}}{{{}}}",
+                        url(address),
+                        escape(address)
+                    ));
+                    self.links.push(false);
+                    self.hidden = 1;
+                }
+                (_, Some(label)) => {
+                    self.out.push_str(&format!("\\hyperref[{}]{{", label));
+                    self.links.push(true);
+                }
+                _ => {
+                    self.out
+                        .push_str(&format!("\\href{{{}}}{{", url(&dest_url)));
+                    self.links.push(true);
+                }
+            },
+            Tag::Image { dest_url, .. } => {
+                // Remote images cannot be included, so they become links.
+                if dest_url.contains("://") {
+                    self.out.push_str(&format!("\\url{{{}}}", url(&dest_url)));
+                } else {
+                    let path = url(&dest_url);
+                    self.out.push_str(&format!(
+                        "\\includegraphics[max width=\\linewidth]{{{}}}",
+                        path
+                    ));
+                }
+                self.hidden = 1;
+            }
+            Tag::FootnoteDefinition(_) | Tag::MetadataBlock(_) => self.hidden = 1,
+            Tag::DefinitionList => self.out.push_str("\\begin{description}\n"),
+            Tag::DefinitionListTitle => {
+                self.out.push_str("\\item[{");
+                self.definitions = 0;
+            }
+            Tag::DefinitionListDefinition => {
+                if self.definitions > 0 {
+                    self.out.push_str("\\par\n");
+                }
+                self.definitions += 1;
+            }
+            Tag::HtmlBlock => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => self.out.push_str("\n\n"),
+            TagEnd::Heading(_) => {
+                self.out.push('}');
+                if let Some(id) = self.heading.take() {
+                    self.out.push_str(&format!("\\label{{{}}}", id));
+                }
+                self.out.push_str("\n\n");
+            }
+            TagEnd::BlockQuote(_) => self.out.push_str("\\end{quote}\n\n"),
+            TagEnd::CodeBlock => self.end_code(),
+            TagEnd::List(_) => {
+                self.lists.pop();
+                let environment = if tag == TagEnd::List(true) {
+                    "enumerate"
+                } else {
+                    "itemize"
+                };
+                self.out.push_str(&format!("\\end{{{}}}\n\n", environment));
+            }
+            TagEnd::Item => self.line(),
+            TagEnd::Table => {
+                self.out.push_str("\\bottomrule\n\\end{longtable}\n\n");
+            }
+            TagEnd::TableHead => self.out.push_str(" \\\\\n\\midrule\n\\endhead\n"),
+            TagEnd::TableRow => self.out.push_str(" \\\\\n"),
+            TagEnd::TableCell => {}
+            TagEnd::Emphasis
+            | TagEnd::Strong
+            | TagEnd::Strikethrough
+            | TagEnd::Superscript
+            | TagEnd::Subscript => self.out.push('}'),
+            TagEnd::Link => {
+                let braced = self.links.pop() == Some(true);
+                if braced {
+                    self.out.push('}');
+                }
+            }
+            TagEnd::DefinitionList => self.out.push_str("\\end{description}\n\n"),
+            TagEnd::DefinitionListTitle => self.out.push_str("}] "),
+            TagEnd::DefinitionListDefinition => self.line(),
+            _ => {}
+        }
+    }
+
+    fn depth(&self) -> usize {
+        self.lists.iter().filter(|&&numbered| numbered).count()
+    }
+
+    fn end_code(&mut self) {
+        let Some((language, code)) = self.code.take() else {
+            return;
+        };
+        let (begin, end) = match self.options.code {
+            CodeEnvironment::Listings => match listings_language(&language) {
+                Some(language) => (
+                    format!("\\begin{{lstlisting}}[language={}]", language),
+                    "\\end{lstlisting}",
+                ),
+                None => ("\\begin{lstlisting}".to_string(), "\\end{lstlisting}"),
+            },
+            CodeEnvironment::Minted => {
+                let language: String = language
+                    .chars()
+                    .filter(|&c| c.is_ascii_alphanumeric() || "+-_.".contains(c))
+                    .collect();
+                let language = if language.is_empty() {
+                    "text".to_string()
+                } else {
+                    language
+                };
+                (
+                    format!("\\begin{{minted}}{{{}}}", language),
+                    "\\end{minted}",
+                )
+            }
+            CodeEnvironment::Verbatim => ("\\begin{verbatim}".to_string(), "\\end{verbatim}"),
+        };
+        let code = code.trim_end_matches('\n');
+        self.line();
+        // Verbatim environments don't work in footnotes and end at the
+        // first `\end` of their name, so those blocks become escaped lines.
+        if self.footnote || code.contains(end) {
+            let lines: Vec<String> = code
+                .split('\n')
+                .map(|line| {
+                    format!(
+                        "\\texttt{{{}}}",
+                        escape(&line.replace('\t', "    ")).replace(' ', "~")
+                    )
+                })
+                .collect();
+            self.out
+                .push_str(&format!("\\noindent{}\n\n", lines.join("\\\\\n")));
+            return;
+        }
+        self.out
+            .push_str(&format!("{}\n{}\n{}\n\n", begin, code, end));
+    }
+
+    /// Ends the current line, so an environment starts on a line of its own.
+    fn line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    /// Writes the footnote `name` where it is first referenced; later
+    /// references point back to it.
+    fn footnote(&mut self, name: CowStr<'a>) {
+        match (self.labels.get(&name), self.footnotes.get(&name)) {
+            (Some(number), _) => self.out.push_str(&format!("\\footref{{fn:{}}}", number)),
+            (None, Some(content)) => {
+                let number = self.labels.len() + 1;
+                self.out.push_str(&format!(
+                    "\\footnote{{{}\\label{{fn:{}}}}}",
+                    content, number
+                ));
+                self.labels.insert(name, number);
+            }
+            (None, None) => self
+                .out
+                .push_str(&format!("\\textsuperscript{{{}}}", escape(&name))),
+        }
+    }
+}
+
+fn alert(kind: BlockQuoteKind) -> &'static str {
+    match kind {
+        BlockQuoteKind::Note => "Note",
+        BlockQuoteKind::Tip => "Tip",
+        BlockQuoteKind::Important => "Important",
+        BlockQuoteKind::Warning => "Warning",
+        BlockQuoteKind::Caution => "Caution",
+    }
+}
+
+/// The listings name of a fenced code block's language, for the languages
+/// listings knows.
+fn listings_language(language: &str) -> Option<&'static str> {
+    Some(match language {
+        "c" => "C",
+        "c++" | "cpp" | "cxx" => "C++",
+        "java" => "Java",
+        "python" | "py" => "Python",
+        "sh" | "bash" | "shell" | "zsh" => "bash",
+        "sql" => "SQL",
+        "html" => "HTML",
+        "xml" => "XML",
+        "ruby" | "rb" => "Ruby",
+        "perl" => "Perl",
+        "php" => "PHP",
+        "haskell" | "hs" => "Haskell",
+        "lua" => "Lua",
+        "r" => "R",
+        "make" | "makefile" => "make",
+        "tex" | "latex" => "TeX",
+        "go" => "Go",
+        _ => return None,
+    })
+}
+
+/// Escapes text for LaTeX. Brackets are braced so they are never taken for
+/// the optional argument of a preceding `\item` or `\\`.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '^' => out.push_str("\\textasciicircum{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            '<' => out.push_str("\\textless{}"),
+            '>' => out.push_str("\\textgreater{}"),
+            '|' => out.push_str("\\textbar{}"),
+            '[' => out.push_str("{[}"),
+            ']' => out.push_str("{]}"),
+            '\u{a0}' => out.push('~'),
+            // `--` and `---` would become dashes.
+            '-' if chars.peek() == Some(&'-') => out.push_str("-{}"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A URL as the argument of `\href` or `\url`.
+fn url(url: &str) -> String {
+    let mut out = String::with_capacity(url.len());
+    for c in url.chars() {
+        match c {
+            // `&` would end a table cell.
+            '%' | '#' | '&' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\\' => out.push_str("%5C"),
+            '{' => out.push_str("%7B"),
+            '}' => out.push_str("%7D"),
+            c => out.push(c),
+        }
+    }
+    out
+}
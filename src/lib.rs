@@ -1,10 +1,317 @@
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
-use pulldown_cmark::{Parser, html};
 
+mod ansi;
+mod ast;
+mod docx;
+mod epub;
+mod format;
+mod front_matter;
+mod gfm;
+mod headings;
+mod highlight;
+mod html_writer;
+mod incremental;
+mod latex;
+mod lint;
+mod man;
+mod math;
+mod options;
+mod plain_text;
+mod raw_html;
+mod render;
+mod result;
+mod sanitize;
+mod schema;
+mod source_map;
+mod table;
+mod toc;
+mod xml;
+mod zip;
+
+pub use ansi::TerminalOptions;
+pub use docx::DocxOptions;
+pub use epub::EpubOptions;
+pub use format::{BulletMarker, EmphasisMarker, FenceStyle, FormatOptions, ProseWrap};
+pub use incremental::{DocumentPatch, MarkdownDocument, RenderedBlock};
+pub use latex::{CodeEnvironment, LatexOptions};
+pub use options::{HighlightStyle, HtmlPolicy, MarkdownOptions};
+pub use plain_text::PlainTextOptions;
+pub use result::{RenderResult, RenderTiming};
+pub use sanitize::SanitizerPolicy;
+pub use schema::FrontMatterSchema;
+pub use source_map::{SourceBlock, SourceMap};
+pub use table::{TableEdit, TableOperation};
+
+/// Renders strict CommonMark. Same as
+/// `parse_markdown_with_options(input, new MarkdownOptions())`.
 #[wasm_bindgen]
 pub fn parse_markdown(input: &str) -> String {
-    let parser = Parser::new(input);
-    let mut html_output = String::new();
-    html::push_html(&mut html_output, parser);
-    html_output
-}
\ No newline at end of file
+    render::render_html(input, &MarkdownOptions::default())
+}
+
+#[wasm_bindgen]
+pub fn parse_markdown_with_options(input: &str, options: &MarkdownOptions) -> String {
+    render::render_html(input, options)
+}
+
+#[wasm_bindgen]
+pub fn parse_markdown_gfm(input: &str) -> String {
+    render::render_html(input, &MarkdownOptions::gfm())
+}
+
+/// Renders `input` and collects its title, headings, links, front matter
+/// and diagnostics in one pass.
+#[wasm_bindgen]
+pub fn render(input: &str, options: &MarkdownOptions) -> RenderResult {
+    result::render(input, options)
+}
+
+/// Renders `input` as readable plain text, for search snippets,
+/// notifications and `aria-label`s.
+#[wasm_bindgen]
+pub fn render_plain_text(
+    input: &str,
+    options: &MarkdownOptions,
+    plain: &PlainTextOptions,
+) -> String {
+    plain_text::convert(input, options, plain)
+}
+
+/// Renders `input` as ANSI-styled text for a terminal (or a terminal
+/// emulator such as xterm.js), wrapped to `terminal.width`.
+#[wasm_bindgen]
+pub fn render_ansi(input: &str, options: &MarkdownOptions, terminal: &TerminalOptions) -> String {
+    ansi::convert(input, options, terminal)
+}
+
+/// Renders `input` as a LaTeX document, or only its body; see
+/// `LatexOptions`.
+#[wasm_bindgen]
+pub fn render_latex(input: &str, options: &MarkdownOptions, latex: &LatexOptions) -> String {
+    latex::convert(input, options, latex)
+}
+
+/// Renders `input` as a `man(7)` page, named by the front matter `name`
+/// and `section`. Front matter is read whatever `options.front_matter` says.
+#[wasm_bindgen]
+pub fn render_man(input: &str, options: &MarkdownOptions) -> String {
+    man::convert(input, options)
+}
+
+/// Renders `input` as a Word document, returning the bytes of the `.docx`
+/// file. Images are embedded from the bytes given to `docx.add_image`.
+#[wasm_bindgen]
+pub fn render_docx(input: &str, options: &MarkdownOptions, docx: &DocxOptions) -> Vec<u8> {
+    docx::convert(input, options, docx)
+}
+
+/// Bundles `chapters`, in reading order, into an EPUB 3 book and returns
+/// the bytes of the `.epub` file. Book metadata comes from the chapters'
+/// front matter: `title`, `author`, `language`, `identifier` and `date`.
+/// Images and other files the chapters refer to are packed from the bytes
+/// given to `epub.add_resource`; a missing one is an error.
+#[wasm_bindgen]
+pub fn build_epub(
+    #[wasm_bindgen(unchecked_param_type = "EpubChapter[]")] chapters: JsValue,
+    options: &MarkdownOptions,
+    epub: &EpubOptions,
+) -> Result<Vec<u8>, JsValue> {
+    let chapters: Vec<epub::Chapter> = serde_wasm_bindgen::from_value(chapters)?;
+    epub::build(&chapters, options, epub).map_err(|message| JsValue::from_str(&message))
+}
+
+/// Same as `build_epub`, with the chapters given as a JSON string.
+#[wasm_bindgen]
+pub fn build_epub_json(
+    chapters: &str,
+    options: &MarkdownOptions,
+    epub: &EpubOptions,
+) -> Result<Vec<u8>, String> {
+    let chapters: Vec<epub::Chapter> =
+        serde_json::from_str(chapters).map_err(|error| error.to_string())?;
+    epub::build(&chapters, options, epub)
+}
+
+/// Parses `input` into a document tree; see the `MarkdownNode` type for the
+/// schema.
+#[wasm_bindgen(unchecked_return_type = "MarkdownNode")]
+pub fn parse_markdown_ast(input: &str, options: &MarkdownOptions) -> Result<JsValue, JsValue> {
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(ast::build(input, options).serialize(&serializer)?)
+}
+
+/// Same tree as `parse_markdown_ast`, serialized as a JSON string.
+#[wasm_bindgen]
+pub fn parse_markdown_ast_json(input: &str, options: &MarkdownOptions) -> String {
+    serde_json::to_string(&ast::build(input, options)).expect("the tree serializes to JSON")
+}
+
+/// Renders `input` like `parse_markdown_with_options` with `front_matter`
+/// enabled, returning the parsed front matter alongside the HTML.
+#[wasm_bindgen(unchecked_return_type = "MarkdownWithFrontMatter")]
+pub fn parse_markdown_with_front_matter(
+    input: &str,
+    options: &MarkdownOptions,
+) -> Result<JsValue, JsValue> {
+    let options = MarkdownOptions {
+        front_matter: true,
+        ..options.clone()
+    };
+    let result = front_matter::WithFrontMatter::new(
+        render::render_html(input, &options),
+        front_matter::extract(input),
+    );
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(result.serialize(&serializer)?)
+}
+
+/// Same as `parse_markdown_with_front_matter`, serialized as a JSON string.
+#[wasm_bindgen]
+pub fn parse_markdown_with_front_matter_json(input: &str, options: &MarkdownOptions) -> String {
+    let options = MarkdownOptions {
+        front_matter: true,
+        ..options.clone()
+    };
+    let result = front_matter::WithFrontMatter::new(
+        render::render_html(input, &options),
+        front_matter::extract(input),
+    );
+    serde_json::to_string(&result).expect("the result serializes to JSON")
+}
+
+/// The nested heading outline of `input`, with the slugs used as heading ids.
+#[wasm_bindgen(unchecked_return_type = "TocEntry[]")]
+pub fn heading_outline(input: &str, options: &MarkdownOptions) -> Result<JsValue, JsValue> {
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(toc::outline(input, options).serialize(&serializer)?)
+}
+
+/// Same outline as `heading_outline`, serialized as a JSON string.
+#[wasm_bindgen]
+pub fn heading_outline_json(input: &str, options: &MarkdownOptions) -> String {
+    serde_json::to_string(&toc::outline(input, options)).expect("the outline serializes to JSON")
+}
+
+/// Rewrites `input` as normalized Markdown in the style set by `format`,
+/// rendering the same as the original.
+#[wasm_bindgen]
+pub fn format_markdown(input: &str, options: &MarkdownOptions, format: &FormatOptions) -> String {
+    format::format(input, options, format)
+}
+
+/// Rewrites every pipe table in `input` with padded columns, honoring their
+/// alignment and East Asian wide characters. Cell contents are kept as
+/// written.
+#[wasm_bindgen]
+pub fn format_tables(input: &str, options: &MarkdownOptions) -> String {
+    table::format(input, options)
+}
+
+/// Applies `operation` to the table at UTF-16 offset `cursor` and realigns
+/// it; `undefined` when the cursor is not in a table.
+#[wasm_bindgen]
+pub fn edit_table(
+    input: &str,
+    options: &MarkdownOptions,
+    cursor: usize,
+    operation: TableOperation,
+) -> Option<TableEdit> {
+    table::edit(input, options, cursor, operation)
+}
+
+/// Highlights `code` as `language`, returning `undefined` for languages that
+/// are not bundled.
+#[wasm_bindgen]
+pub fn highlight_code(code: &str, language: &str, style: HighlightStyle) -> Option<String> {
+    highlight::highlight(code, language, style)
+}
+
+/// CSS for the classes written with `HighlightStyle.Classes`.
+#[wasm_bindgen]
+pub fn highlight_stylesheet() -> String {
+    highlight::stylesheet()
+}
+
+/// Translates a LaTeX formula into a `<math>` element, or an error
+/// `<span class="math-error">` marking the unsupported part.
+#[wasm_bindgen]
+pub fn latex_to_mathml(tex: &str, display: bool) -> String {
+    math::to_mathml(tex, display)
+}
+
+/// Checks `input` against markdownlint-style rules, all enabled unless
+/// `config` says otherwise. Diagnostics are sorted by position; fixable ones
+/// carry the edits that fix them.
+#[wasm_bindgen(unchecked_return_type = "LintDiagnostic[]")]
+pub fn lint_markdown(
+    input: &str,
+    options: &MarkdownOptions,
+    #[wasm_bindgen(unchecked_param_type = "LintConfig | undefined")] config: JsValue,
+) -> Result<JsValue, JsValue> {
+    let config = lint_config(config)?;
+    let diagnostics =
+        lint::lint(input, options, &config).map_err(|message| JsValue::from_str(&message))?;
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(diagnostics.serialize(&serializer)?)
+}
+
+/// Same as `lint_markdown`, with the config and the diagnostics as JSON
+/// strings. An empty config string enables every rule.
+#[wasm_bindgen]
+pub fn lint_markdown_json(
+    input: &str,
+    options: &MarkdownOptions,
+    config: &str,
+) -> Result<String, String> {
+    let diagnostics = lint::lint(input, options, &lint_config_json(config)?)?;
+    Ok(serde_json::to_string(&diagnostics).expect("diagnostics serialize to JSON"))
+}
+
+/// Applies every fix `lint_markdown` would offer for `input`, returning the
+/// fixed document.
+#[wasm_bindgen]
+pub fn fix_markdown(
+    input: &str,
+    options: &MarkdownOptions,
+    #[wasm_bindgen(unchecked_param_type = "LintConfig | undefined")] config: JsValue,
+) -> Result<String, JsValue> {
+    let config = lint_config(config)?;
+    lint::fix(input, options, &config).map_err(|message| JsValue::from_str(&message))
+}
+
+/// Same as `fix_markdown`, with the config as a JSON string. An empty
+/// config string enables every rule.
+#[wasm_bindgen]
+pub fn fix_markdown_json(
+    input: &str,
+    options: &MarkdownOptions,
+    config: &str,
+) -> Result<String, String> {
+    lint::fix(input, options, &lint_config_json(config)?)
+}
+
+fn lint_config(config: JsValue) -> Result<serde_json::Map<String, serde_json::Value>, JsValue> {
+    if config.is_undefined() || config.is_null() {
+        return Ok(serde_json::Map::new());
+    }
+    match serde_wasm_bindgen::from_value(config)? {
+        serde_json::Value::Object(config) => Ok(config),
+        _ => Err(JsValue::from_str("the lint config must be an object")),
+    }
+}
+
+fn lint_config_json(config: &str) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    if config.trim().is_empty() {
+        return Ok(serde_json::Map::new());
+    }
+    match serde_json::from_str(config).map_err(|error| error.to_string())? {
+        serde_json::Value::Object(config) => Ok(config),
+        _ => Err("the lint config must be an object".to_string()),
+    }
+}
+
+#[wasm_bindgen]
+pub fn sanitize_html(html: &str) -> String {
+    sanitize::clean(html)
+}
@@ -0,0 +1,2011 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use pulldown_cmark::{CodeBlockKind, Event, LinkType, Tag, TagEnd};
+use regex_lite::Regex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::ast::Position;
+use crate::front_matter;
+use crate::headings;
+use crate::options::MarkdownOptions;
+use crate::render::{self, Spanned};
+use crate::result::Severity;
+use crate::source_map::SourceIndex;
+
+#[wasm_bindgen(typescript_custom_section)]
+const LINT_SCHEMA: &'static str = r#"
+/** A rule violation found by `lint_markdown`. */
+export interface LintDiagnostic {
+  /** The markdownlint rule id, e.g. "MD009". */
+  ruleId: string;
+  /** The markdownlint rule name, e.g. "no-trailing-spaces". */
+  ruleName: string;
+  severity: "error" | "warning";
+  message: string;
+  position: MarkdownPosition;
+  /** Edits that fix the problem, to be applied together. */
+  fix?: TextEdit[];
+}
+
+/** Replaces the UTF-16 range `start..end` of the input with `text`. */
+export interface TextEdit {
+  start: number;
+  end: number;
+  text: string;
+}
+
+/**
+ * Rule settings for `lint_markdown`, keyed by rule id ("MD013") or name
+ * ("line-length") like a `.markdownlint.json` file. `false` turns a rule
+ * off; `true`, "warning" or "error" turn it on; an object turns it on with
+ * the rule's parameters and an optional `severity`. Rules not listed follow
+ * `default`, which is `true` unless set.
+ */
+export type LintConfig = {
+  default?: boolean;
+  [rule: string]:
+    | boolean
+    | "error"
+    | "warning"
+    | ({ severity?: "error" | "warning" } & Record<string, unknown>)
+    | undefined;
+};
+"#;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LintDiagnostic {
+    pub rule_id: &'static str,
+    pub rule_name: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub position: Position,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fix: Vec<TextEdit>,
+    #[serde(skip)]
+    pub range: Range<usize>,
+    #[serde(skip)]
+    pub edits: Vec<Edit>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// Replaces the byte range `range` of the input with `text`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Edit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// Lint and fix rounds `fix` runs at most; fixes can uncover new problems.
+const MAX_FIX_PASSES: usize = 8;
+
+/// Checks `input` against every rule enabled in `config`.
+pub(crate) fn lint(
+    input: &str,
+    options: &MarkdownOptions,
+    config: &Map<String, Value>,
+) -> Result<Vec<LintDiagnostic>, String> {
+    let context = Context::new(input, options);
+    let default = config
+        .get("default")
+        .and_then(Value::as_bool)
+        .unwrap_or(true);
+    let mut diagnostics = Vec::new();
+    for rule in RULES {
+        let setting = config
+            .iter()
+            .find(|(key, _)| {
+                key.eq_ignore_ascii_case(rule.id) || key.eq_ignore_ascii_case(rule.name)
+            })
+            .map(|(_, setting)| setting);
+        let (severity, params) = match setting {
+            None if default => (Severity::Warning, None),
+            None | Some(Value::Bool(false)) | Some(Value::Null) => continue,
+            Some(Value::Bool(true)) => (Severity::Warning, None),
+            Some(Value::String(severity)) => (parse_severity(severity)?, None),
+            Some(Value::Object(params)) => {
+                let severity = match params.get("severity").and_then(Value::as_str) {
+                    Some(severity) => parse_severity(severity)?,
+                    None => Severity::Warning,
+                };
+                (severity, Some(params))
+            }
+            Some(setting) => return Err(format!("invalid setting for {}: {}", rule.id, setting)),
+        };
+        for finding in (rule.check)(&context, &Params(params)) {
+            diagnostics.push(LintDiagnostic {
+                rule_id: rule.id,
+                rule_name: rule.name,
+                severity,
+                message: match finding.detail {
+                    Some(detail) => format!("{} [{}]", rule.description, detail),
+                    None => rule.description.to_string(),
+                },
+                position: Position::of(&context.index, finding.range.clone()),
+                fix: finding
+                    .edits
+                    .iter()
+                    .map(|edit| TextEdit {
+                        start: context.index.utf16(edit.range.start),
+                        end: context.index.utf16(edit.range.end),
+                        text: edit.text.clone(),
+                    })
+                    .collect(),
+                range: finding.range,
+                edits: finding.edits,
+            });
+        }
+    }
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.range.start, diagnostic.rule_id));
+    Ok(diagnostics)
+}
+
+/// Applies the fixes of every fixable problem `config` reports, repeating
+/// until none are left.
+pub(crate) fn fix(
+    input: &str,
+    options: &MarkdownOptions,
+    config: &Map<String, Value>,
+) -> Result<String, String> {
+    let mut text = input.to_string();
+    for _ in 0..MAX_FIX_PASSES {
+        let diagnostics = lint(&text, options, config)?;
+        // A diagnostic's edits go in together or not at all, and only if
+        // they keep clear of the edits already taken this round.
+        let mut taken: Vec<&Edit> = Vec::new();
+        for diagnostic in &diagnostics {
+            let clashes = diagnostic.edits.iter().any(|edit| {
+                taken.iter().any(|other| {
+                    edit.range.start <= other.range.end && other.range.start <= edit.range.end
+                })
+            });
+            if !clashes {
+                taken.extend(&diagnostic.edits);
+            }
+        }
+        if taken.is_empty() {
+            break;
+        }
+        taken.sort_by_key(|edit| edit.range.start);
+        for edit in taken.into_iter().rev() {
+            text.replace_range(edit.range.clone(), &edit.text);
+        }
+    }
+    Ok(text)
+}
+
+fn parse_severity(name: &str) -> Result<Severity, String> {
+    match name {
+        "error" => Ok(Severity::Error),
+        "warning" => Ok(Severity::Warning),
+        _ => Err(format!("unknown severity \"{}\"", name)),
+    }
+}
+
+/// A rule's parameters from the lint config.
+struct Params<'a>(Option<&'a Map<String, Value>>);
+
+impl<'a> Params<'a> {
+    fn get(&self, name: &str) -> Option<&'a Value> {
+        self.0.and_then(|params| params.get(name))
+    }
+
+    fn number(&self, name: &str, default: usize) -> usize {
+        self.get(name)
+            .and_then(Value::as_u64)
+            .map_or(default, |number| number as usize)
+    }
+
+    fn flag(&self, name: &str, default: bool) -> bool {
+        self.get(name).and_then(Value::as_bool).unwrap_or(default)
+    }
+
+    fn string(&self, name: &str, default: &'a str) -> &'a str {
+        self.get(name).and_then(Value::as_str).unwrap_or(default)
+    }
+
+    fn strings(&self, name: &str) -> Vec<&'a str> {
+        match self.get(name) {
+            Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A violation, with optional detail appended to the rule's description and
+/// the edits that fix it.
+struct Finding {
+    range: Range<usize>,
+    detail: Option<String>,
+    edits: Vec<Edit>,
+}
+
+impl Finding {
+    fn at(range: Range<usize>) -> Self {
+        Finding {
+            range,
+            detail: None,
+            edits: Vec::new(),
+        }
+    }
+
+    fn with(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    fn replace(self, range: Range<usize>, text: impl Into<String>) -> Self {
+        self.edit(Edit {
+            range,
+            text: text.into(),
+        })
+    }
+
+    fn edit(mut self, edit: Edit) -> Self {
+        self.edits.push(edit);
+        self
+    }
+}
+
+struct Rule {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    check: fn(&Context, &Params) -> Vec<Finding>,
+}
+
+const RULES: &[Rule] = &[
+    Rule {
+        id: "MD001",
+        name: "heading-increment",
+        description: "Heading levels should only increment by one level at a time",
+        check: heading_increment,
+    },
+    Rule {
+        id: "MD003",
+        name: "heading-style",
+        description: "Heading style",
+        check: heading_style,
+    },
+    Rule {
+        id: "MD004",
+        name: "ul-style",
+        description: "Unordered list style",
+        check: ul_style,
+    },
+    Rule {
+        id: "MD005",
+        name: "list-indent",
+        description: "Inconsistent indentation for list items at the same level",
+        check: list_indent,
+    },
+    Rule {
+        id: "MD007",
+        name: "ul-indent",
+        description: "Unordered list indentation",
+        check: ul_indent,
+    },
+    Rule {
+        id: "MD009",
+        name: "no-trailing-spaces",
+        description: "Trailing spaces",
+        check: no_trailing_spaces,
+    },
+    Rule {
+        id: "MD010",
+        name: "no-hard-tabs",
+        description: "Hard tabs",
+        check: no_hard_tabs,
+    },
+    Rule {
+        id: "MD011",
+        name: "no-reversed-links",
+        description: "Reversed link syntax",
+        check: no_reversed_links,
+    },
+    Rule {
+        id: "MD012",
+        name: "no-multiple-blanks",
+        description: "Multiple consecutive blank lines",
+        check: no_multiple_blanks,
+    },
+    Rule {
+        id: "MD013",
+        name: "line-length",
+        description: "Line length",
+        check: line_length,
+    },
+    Rule {
+        id: "MD018",
+        name: "no-missing-space-atx",
+        description: "No space after hash on atx style heading",
+        check: no_missing_space_atx,
+    },
+    Rule {
+        id: "MD019",
+        name: "no-multiple-space-atx",
+        description: "Multiple spaces after hash on atx style heading",
+        check: no_multiple_space_atx,
+    },
+    Rule {
+        id: "MD022",
+        name: "blanks-around-headings",
+        description: "Headings should be surrounded by blank lines",
+        check: blanks_around_headings,
+    },
+    Rule {
+        id: "MD023",
+        name: "heading-start-left",
+        description: "Headings must start at the beginning of the line",
+        check: heading_start_left,
+    },
+    Rule {
+        id: "MD024",
+        name: "no-duplicate-heading",
+        description: "Multiple headings with the same content",
+        check: no_duplicate_heading,
+    },
+    Rule {
+        id: "MD025",
+        name: "single-h1",
+        description: "Multiple top-level headings in the same document",
+        check: single_h1,
+    },
+    Rule {
+        id: "MD026",
+        name: "no-trailing-punctuation",
+        description: "Trailing punctuation in heading",
+        check: no_trailing_punctuation,
+    },
+    Rule {
+        id: "MD027",
+        name: "no-multiple-space-blockquote",
+        description: "Multiple spaces after blockquote symbol",
+        check: no_multiple_space_blockquote,
+    },
+    Rule {
+        id: "MD029",
+        name: "ol-prefix",
+        description: "Ordered list item prefix",
+        check: ol_prefix,
+    },
+    Rule {
+        id: "MD031",
+        name: "blanks-around-fences",
+        description: "Fenced code blocks should be surrounded by blank lines",
+        check: blanks_around_fences,
+    },
+    Rule {
+        id: "MD032",
+        name: "blanks-around-lists",
+        description: "Lists should be surrounded by blank lines",
+        check: blanks_around_lists,
+    },
+    Rule {
+        id: "MD033",
+        name: "no-inline-html",
+        description: "Inline HTML",
+        check: no_inline_html,
+    },
+    Rule {
+        id: "MD034",
+        name: "no-bare-urls",
+        description: "Bare URL used",
+        check: no_bare_urls,
+    },
+    Rule {
+        id: "MD035",
+        name: "hr-style",
+        description: "Horizontal rule style",
+        check: hr_style,
+    },
+    Rule {
+        id: "MD036",
+        name: "no-emphasis-as-heading",
+        description: "Emphasis used instead of a heading",
+        check: no_emphasis_as_heading,
+    },
+    Rule {
+        id: "MD038",
+        name: "no-space-in-code",
+        description: "Spaces inside code span elements",
+        check: no_space_in_code,
+    },
+    Rule {
+        id: "MD039",
+        name: "no-space-in-links",
+        description: "Spaces inside link text",
+        check: no_space_in_links,
+    },
+    Rule {
+        id: "MD040",
+        name: "fenced-code-language",
+        description: "Fenced code blocks should have a language specified",
+        check: fenced_code_language,
+    },
+    Rule {
+        id: "MD041",
+        name: "first-line-heading",
+        description: "First line in a file should be a top-level heading",
+        check: first_line_heading,
+    },
+    Rule {
+        id: "MD042",
+        name: "no-empty-links",
+        description: "No empty links",
+        check: no_empty_links,
+    },
+    Rule {
+        id: "MD045",
+        name: "no-alt-text",
+        description: "Images should have alternate text (alt text)",
+        check: no_alt_text,
+    },
+    Rule {
+        id: "MD046",
+        name: "code-block-style",
+        description: "Code block style",
+        check: code_block_style,
+    },
+    Rule {
+        id: "MD047",
+        name: "single-trailing-newline",
+        description: "Files should end with a single newline character",
+        check: single_trailing_newline,
+    },
+    Rule {
+        id: "MD048",
+        name: "code-fence-style",
+        description: "Code fence style",
+        check: code_fence_style,
+    },
+    Rule {
+        id: "MD049",
+        name: "emphasis-style",
+        description: "Emphasis style",
+        check: emphasis_style,
+    },
+    Rule {
+        id: "MD050",
+        name: "strong-style",
+        description: "Strong style",
+        check: strong_style,
+    },
+    Rule {
+        id: "MD051",
+        name: "link-fragments",
+        description: "Link fragments should be valid",
+        check: link_fragments,
+    },
+    Rule {
+        id: "MD052",
+        name: "reference-links-images",
+        description: "Reference links and images should use a label that is defined",
+        check: reference_links_images,
+    },
+    Rule {
+        id: "MD058",
+        name: "blanks-around-tables",
+        description: "Tables should be surrounded by blank lines",
+        check: blanks_around_tables,
+    },
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LineKind {
+    Text,
+    Code,
+    Html,
+    FrontMatter,
+}
+
+struct Heading {
+    level: u8,
+    range: Range<usize>,
+    text: String,
+    /// Whether the heading is in a list item.
+    in_item: bool,
+}
+
+/// The parsed document, shared by all rules.
+struct Context<'a> {
+    input: &'a str,
+    index: SourceIndex<'a>,
+    /// Byte range of every line, without its line ending.
+    lines: Vec<Range<usize>>,
+    kinds: Vec<LineKind>,
+    /// Events with heading ids assigned.
+    events: Vec<Spanned<'a>>,
+    /// How many list items and block quotes enclose each event.
+    nesting: Vec<(usize, usize)>,
+    headings: Vec<Heading>,
+    code_spans: Vec<Range<usize>>,
+    broken_links: Vec<(Range<usize>, String)>,
+    /// The front matter or metadata block, delimiters included.
+    front_matter: Option<Range<usize>>,
+    /// The last line of each fenced code block that runs to the end of its
+    /// container.
+    open_fences: HashSet<usize>,
+    /// The line ending used for inserted lines.
+    newline: &'static str,
+}
+
+impl<'a> Context<'a> {
+    fn new(input: &'a str, options: &MarkdownOptions) -> Self {
+        let (events, broken_links) = render::parse_checked(input, options);
+        let events = headings::assign_ids(events);
+
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for line in input.split_inclusive('\n') {
+            lines.push(start..start + line.trim_end_matches(['\n', '\r']).len());
+            start += line.len();
+        }
+        let mut context = Context {
+            input,
+            index: SourceIndex::new(input),
+            kinds: vec![LineKind::Text; lines.len()],
+            lines,
+            events: Vec::new(),
+            nesting: Vec::with_capacity(events.len()),
+            headings: Vec::new(),
+            code_spans: Vec::new(),
+            broken_links,
+            front_matter: None,
+            open_fences: HashSet::new(),
+            newline: match input.contains("\r\n") {
+                true => "\r\n",
+                false => "\n",
+            },
+        };
+        let front_matter_len = match options.front_matter {
+            true => front_matter::block_len(input),
+            false => 0,
+        };
+        if front_matter_len > 0 {
+            context.front_matter = Some(0..front_matter_len);
+            context.mark(0..front_matter_len, LineKind::FrontMatter);
+        }
+
+        let (mut items, mut quotes) = (0, 0);
+        for (position, (event, range)) in events.iter().enumerate() {
+            if let Event::End(TagEnd::Item) = event {
+                items -= 1;
+            }
+            if let Event::End(TagEnd::BlockQuote(_)) = event {
+                quotes -= 1;
+            }
+            context.nesting.push((items, quotes));
+            match event {
+                Event::Start(Tag::Item) => items += 1,
+                Event::Start(Tag::BlockQuote(_)) => quotes += 1,
+                Event::Start(Tag::CodeBlock(_)) => context.mark(range.clone(), LineKind::Code),
+                Event::Start(Tag::HtmlBlock) => context.mark(range.clone(), LineKind::Html),
+                Event::Start(Tag::MetadataBlock(_)) => {
+                    context.front_matter = Some(range.clone());
+                    context.mark(range.clone(), LineKind::FrontMatter);
+                }
+                Event::Start(Tag::Heading { level, .. }) => context.headings.push(Heading {
+                    level: *level as u8,
+                    range: range.clone(),
+                    text: headings::heading_text(&events, position).trim().to_string(),
+                    in_item: items > 0,
+                }),
+                Event::Code(_) => context.code_spans.push(range.clone()),
+                _ => {}
+            }
+        }
+        context.events = events;
+        let open_fences = context
+            .events
+            .iter()
+            .filter(|(event, range)| {
+                matches!(
+                    event,
+                    Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(_)))
+                ) && context.closing_fence(range).is_none()
+            })
+            .map(|(_, range)| context.last_line(range))
+            .collect();
+        context.open_fences = open_fences;
+        context
+    }
+
+    fn mark(&mut self, range: Range<usize>, kind: LineKind) {
+        let first = self.line_of(range.start);
+        let last = self.line_of(range.end.saturating_sub(1).max(range.start));
+        for line in first..=last.min(self.lines.len().saturating_sub(1)) {
+            self.kinds[line] = kind;
+        }
+    }
+
+    /// The 0-based line containing byte `offset`.
+    fn line_of(&self, offset: usize) -> usize {
+        self.index.line(offset) - 1
+    }
+
+    fn text(&self, line: usize) -> &'a str {
+        &self.input[self.lines[line].clone()]
+    }
+
+    /// Whether `line` is empty apart from whitespace and block quote markers.
+    fn is_blank(&self, line: usize) -> bool {
+        self.text(line)
+            .trim_matches(|c: char| c.is_whitespace() || c == '>')
+            .is_empty()
+    }
+
+    /// The last line of a block, not counting trailing blank lines or the
+    /// indentation of the next line, which list ranges can end with.
+    fn last_line(&self, range: &Range<usize>) -> usize {
+        let first = self.line_of(range.start);
+        let mut last = self.line_of(range.end.saturating_sub(1).max(range.start));
+        while last > first {
+            let line = &self.lines[last];
+            let covered = &self.input[line.start..range.end.min(line.end)];
+            if !self.is_blank(last) && !covered.trim_start_matches([' ', '\t', '>']).is_empty() {
+                break;
+            }
+            last -= 1;
+        }
+        last
+    }
+
+    /// `range` cut off at the end of its first line.
+    fn first_line(&self, range: &Range<usize>) -> Range<usize> {
+        let line_end = self
+            .lines
+            .get(self.line_of(range.start))
+            .map_or(range.end, |line| line.end);
+        range.start..range.end.min(line_end).max(range.start)
+    }
+
+    /// The number of blank lines right above `line`, up to `limit`, or
+    /// `limit` when the line starts the document or follows front matter.
+    fn blanks_above(&self, line: usize, limit: usize) -> usize {
+        let mut count = 0;
+        while count < limit {
+            if count >= line || self.kinds[line - count - 1] == LineKind::FrontMatter {
+                return limit;
+            }
+            if !self.is_blank(line - count - 1) {
+                break;
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// The number of blank lines right below `line`, up to `limit`, or
+    /// `limit` when the line ends the document.
+    fn blanks_below(&self, line: usize, limit: usize) -> usize {
+        let mut count = 0;
+        while count < limit {
+            if line + count + 1 >= self.lines.len() {
+                return limit;
+            }
+            if !self.is_blank(line + count + 1) {
+                break;
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Findings for a block not separated from its neighbours by a blank line.
+    /// Blocks in list items get no fix: a blank line would make the list
+    /// loose. The last line of a block can be a lazy continuation line, so
+    /// inserted lines take the first line's quotes.
+    fn blanks_around(&self, range: &Range<usize>, in_item: bool, findings: &mut Vec<Finding>) {
+        let first = self.line_of(range.start);
+        let last = self.last_line(range);
+        if self.blanks_above(first, 1) == 0 {
+            let mut finding = Finding::at(self.lines[first].clone());
+            if !in_item {
+                finding
+                    .edits
+                    .extend(self.insert_blank_lines(first - 1, 1, first));
+            }
+            findings.push(finding);
+        }
+        if self.blanks_below(last, 1) == 0 {
+            let mut finding = Finding::at(self.lines[last].clone());
+            if !in_item {
+                finding
+                    .edits
+                    .extend(self.insert_blank_lines(last, 1, first));
+            }
+            findings.push(finding);
+        }
+    }
+
+    /// The closing fence of the fenced code block at `range`, unless the
+    /// block runs to the end of its container.
+    fn closing_fence(&self, range: &Range<usize>) -> Option<Range<usize>> {
+        let source = &self.input[range.clone()];
+        let length = source.len() - source.trim_start_matches(['`', '~']).len();
+        let last = self.last_line(range);
+        let closing = self.text(last).trim_start_matches(['>', ' ', '\t']);
+        let fence_length = closing.len() - closing.trim_start_matches(['`', '~']).len();
+        let closed = fence_length >= length && closing[fence_length..].trim().is_empty();
+        // A line quoted more or less deeply than the opening fence is code.
+        let first = self.line_of(range.start);
+        let depth = |line| self.quote_prefix(line).matches('>').count();
+        let start = self.lines[last].end - closing.len();
+        (last > first && closed && depth(last) == depth(first)).then(|| start..start + fence_length)
+    }
+
+    /// The block quote markers at the start of `line`.
+    fn quote_prefix(&self, line: usize) -> &'a str {
+        let text = self.text(line);
+        let mut end = 0;
+        for (index, c) in text.char_indices() {
+            match c {
+                '>' => end = index + 1,
+                ' ' | '\t' => {}
+                _ => break,
+            }
+        }
+        &text[..end]
+    }
+
+    /// An edit inserting `count` blank lines after `line`, inside the block
+    /// quotes that `quoted` is in; none if `line` ends a fence that runs to
+    /// the end of its container, whose code the lines would join.
+    fn insert_blank_lines(&self, line: usize, count: usize, quoted: usize) -> Option<Edit> {
+        let end = self.lines[line].end;
+        (!self.open_fences.contains(&line)).then(|| Edit {
+            range: end..end,
+            text: format!("{}{}", self.newline, self.quote_prefix(quoted)).repeat(count),
+        })
+    }
+
+    /// The byte range of a heading's inline content: an ATX heading's text
+    /// without the hashes, or the text line of a one-line setext heading.
+    fn heading_content(&self, heading: &Heading) -> Option<Range<usize>> {
+        let line = self.first_line(&heading.range);
+        let text = &self.input[line.clone()];
+        if self.is_setext(heading) {
+            let single_line = self.last_line(&heading.range) == self.line_of(line.start) + 1;
+            return single_line.then(|| line.start..line.start + text.trim_end().len());
+        }
+        let content = text.trim_start_matches('#').trim_start_matches([' ', '\t']);
+        let start = line.end - content.len();
+        let mut content = content.trim_end();
+        let unclosed = content.trim_end_matches('#');
+        if unclosed.len() < content.len()
+            && (unclosed.is_empty() || unclosed.ends_with([' ', '\t']))
+        {
+            content = unclosed.trim_end();
+        }
+        Some(start..start + content.len())
+    }
+
+    /// Whether `heading` is underlined. Its text can start with a `#`.
+    fn is_setext(&self, heading: &Heading) -> bool {
+        self.last_line(&heading.range) > self.line_of(heading.range.start)
+    }
+
+    /// The offset of a list item's marker. Item ranges can start with the
+    /// indentation before it, or the line ending before that.
+    fn marker(&self, item: &Range<usize>) -> usize {
+        let text = &self.input[item.start..];
+        item.start + text.len() - text.trim_start().len()
+    }
+
+    /// The column of byte `offset` within its line, in chars.
+    fn column(&self, offset: usize) -> usize {
+        let line = &self.lines[self.line_of(offset)];
+        self.input[line.start..offset].chars().count()
+    }
+
+    fn in_code_span(&self, offset: usize) -> bool {
+        self.code_spans.iter().any(|span| span.contains(&offset))
+    }
+
+    /// Whether the front matter has a title, per the `front_matter_title`
+    /// parameter.
+    fn front_matter_title(&self, params: &Params) -> bool {
+        let pattern = params.string("front_matter_title", r#"^\s*"?title"?\s*[:=]"#);
+        let Some(range) = self.front_matter.clone().filter(|_| !pattern.is_empty()) else {
+            return false;
+        };
+        Regex::new(&format!("(?m){}", pattern))
+            .is_ok_and(|regex| regex.is_match(&self.input[range]))
+    }
+}
+
+/// The style of a heading's source: `atx`, `atx_closed` or `setext`.
+fn atx_style(context: &Context, heading: &Heading) -> &'static str {
+    if context.is_setext(heading) {
+        return "setext";
+    }
+    let line = context.input[context.first_line(&heading.range)].trim_end();
+    let text = line.trim_start_matches('#');
+    let content = text.trim_end_matches('#');
+    match content.len() < text.len()
+        && content.trim().len() < content.len()
+        && !content.trim().is_empty()
+    {
+        true => "atx_closed",
+        false => "atx",
+    }
+}
+
+fn heading_increment(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut previous = 0;
+    for heading in &context.headings {
+        if previous != 0 && heading.level > previous + 1 {
+            findings.push(
+                Finding::at(context.first_line(&heading.range)).with(format!(
+                    "Expected: h{}; Actual: h{}",
+                    previous + 1,
+                    heading.level
+                )),
+            );
+        }
+        previous = heading.level;
+    }
+    findings
+}
+
+fn heading_style(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut style = match params.string("style", "consistent") {
+        "consistent" => None,
+        style => Some(style),
+    };
+    for heading in &context.headings {
+        let actual = atx_style(context, heading);
+        let expected = match *style.get_or_insert(actual) {
+            "setext_with_atx" if heading.level > 2 => "atx",
+            "setext_with_atx_closed" if heading.level > 2 => "atx_closed",
+            "setext_with_atx" | "setext_with_atx_closed" => "setext",
+            expected => expected,
+        };
+        if expected != actual {
+            let mut finding = Finding::at(context.first_line(&heading.range))
+                .with(format!("Expected: {}; Actual: {}", expected, actual));
+            if let Some(edit) = restyle_heading(context, heading, expected) {
+                finding = finding.edit(edit);
+            }
+            findings.push(finding);
+        }
+    }
+    findings
+}
+
+/// The edit rewriting `heading` in `style`, if it has a single line of
+/// content and `style` can express its level.
+fn restyle_heading(context: &Context, heading: &Heading, style: &str) -> Option<Edit> {
+    let content = &context.input[context.heading_content(heading)?];
+    let hashes = "#".repeat(heading.level as usize);
+    let text = match style {
+        "atx" => format!("{} {}", hashes, content),
+        "atx_closed" => format!("{} {} {}", hashes, content, hashes),
+        // Underlined text right after a paragraph would join it, and the
+        // underline of a list item's heading would need its indentation.
+        "setext"
+            if heading.level <= 2
+                && !content.is_empty()
+                && !heading.in_item
+                && context.blanks_above(context.line_of(heading.range.start), 1) == 1 =>
+        {
+            let prefix = match context.quote_prefix(context.line_of(heading.range.start)) {
+                "" => String::new(),
+                prefix => format!("{} ", prefix),
+            };
+            let underline = if heading.level == 1 { "=" } else { "-" };
+            let underline = underline.repeat(content.chars().count().max(3));
+            format!("{}{}{}{}", content, context.newline, prefix, underline)
+        }
+        _ => return None,
+    };
+    let end = context.lines[context.last_line(&heading.range)].end;
+    Some(Edit {
+        range: heading.range.start..end,
+        text,
+    })
+}
+
+fn marker_name(marker: char) -> &'static str {
+    match marker {
+        '*' => "asterisk",
+        '+' => "plus",
+        _ => "dash",
+    }
+}
+
+fn marker_char(name: &str) -> &'static str {
+    match name {
+        "asterisk" => "*",
+        "plus" => "+",
+        _ => "-",
+    }
+}
+
+fn ul_style(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let style = params.string("style", "consistent");
+    // With "sublist", each nesting level keeps its own marker.
+    let mut expected: HashMap<usize, &str> = HashMap::new();
+    // Bullet lists right next to another get no fix: with the same marker
+    // they would merge into one list.
+    let mut adjacent = HashSet::new();
+    let mut starts = Vec::new();
+    for (position, (event, _)) in context.events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::List(_)) => starts.push(position),
+            Event::End(TagEnd::List(ordered)) => {
+                let start = starts.pop();
+                let next = context.events.get(position + 1);
+                if !ordered && matches!(next, Some((Event::Start(Tag::List(None)), _))) {
+                    adjacent.extend(start);
+                    adjacent.insert(position + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    // Whether each open list is a bullet list, whether it can be fixed, and
+    // its item markers in the wrong style.
+    type Markers<'a> = Vec<(usize, &'a str, &'a str)>;
+    let mut lists: Vec<(bool, bool, Markers)> = Vec::new();
+    for (position, (event, range)) in context.events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::List(start)) => {
+                lists.push((start.is_none(), !adjacent.contains(&position), Vec::new()))
+            }
+            Event::End(TagEnd::List(_)) => {
+                let Some((_, fixable, markers)) = lists.pop() else {
+                    continue;
+                };
+                // The items of a list share a marker; changing only some of
+                // them would split the list.
+                let edits: Vec<Edit> = markers
+                    .iter()
+                    .map(|&(start, expected, _)| Edit {
+                        range: start..start + 1,
+                        text: marker_char(expected).to_string(),
+                    })
+                    .collect();
+                for (start, expected, actual) in markers {
+                    let mut finding = Finding::at(start..start + 1)
+                        .with(format!("Expected: {}; Actual: {}", expected, actual));
+                    if fixable {
+                        for edit in &edits {
+                            finding = finding.edit(edit.clone());
+                        }
+                    }
+                    findings.push(finding);
+                }
+            }
+            Event::Start(Tag::Item) if lists.last().is_some_and(|(bullet, ..)| *bullet) => {
+                let start = context.marker(range);
+                let Some(marker) = context.input[start..].chars().next() else {
+                    continue;
+                };
+                let actual = marker_name(marker);
+                let level = match style {
+                    "sublist" => lists.len(),
+                    _ => 0,
+                };
+                let expected = match style {
+                    "asterisk" | "plus" | "dash" => style,
+                    _ => *expected.entry(level).or_insert(actual),
+                };
+                if let Some((.., markers)) = lists.last_mut().filter(|_| expected != actual) {
+                    markers.push((start, expected, actual));
+                }
+            }
+            _ => {}
+        }
+    }
+    findings
+}
+
+fn list_indent(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    // The start and marker end column of each open list's first item.
+    let mut lists: Vec<Option<(usize, usize)>> = Vec::new();
+    for (event, range) in &context.events {
+        match event {
+            Event::Start(Tag::List(_)) => lists.push(None),
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                let Some(first) = lists.last_mut() else {
+                    continue;
+                };
+                let start = context.marker(range);
+                let column = context.column(start);
+                let marker = context.input[start..]
+                    .find(|c: char| !c.is_ascii_digit())
+                    .map_or(1, |digits| digits + 1);
+                match *first {
+                    None => *first = Some((column, column + marker)),
+                    // Right-aligned ordered list markers line up at the end.
+                    Some((first_column, end))
+                        if column != first_column && column + marker != end =>
+                    {
+                        findings.push(
+                            Finding::at(start..start + marker)
+                                .with(format!("Expected: {}; Actual: {}", first_column, column)),
+                        );
+                    }
+                    Some(_) => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    findings
+}
+
+fn ul_indent(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let indent = params.number("indent", 2);
+    let start = match params.flag("start_indented", false) {
+        true => params.number("start_indent", indent),
+        false => 0,
+    };
+    let mut lists: Vec<bool> = Vec::new();
+    for (position, (event, range)) in context.events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::List(first)) => lists.push(first.is_some()),
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::Start(Tag::Item)
+                if context.nesting[position].1 == 0 && lists.iter().all(|ordered| !ordered) =>
+            {
+                let expected = start + (lists.len() - 1) * indent;
+                let marker = context.marker(range);
+                let actual = context.column(marker);
+                if actual != expected {
+                    findings.push(
+                        Finding::at(marker..marker + 1)
+                            .with(format!("Expected: {}; Actual: {}", expected, actual)),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    findings
+}
+
+fn no_trailing_spaces(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let br_spaces = params.number("br_spaces", 2);
+    let strict = params.flag("strict", false);
+    for (line, range) in context.lines.iter().enumerate() {
+        if context.kinds[line] == LineKind::Code {
+            continue;
+        }
+        let text = context.text(line);
+        let trailing = text.len() - text.trim_end_matches(' ').len();
+        if trailing == 0 {
+            continue;
+        }
+        let continued = line + 1 < context.lines.len()
+            && context.kinds[line + 1] == LineKind::Text
+            && !context.is_blank(line + 1);
+        // A hard line break, continued on the next line when `strict`.
+        let line_break = br_spaces >= 2
+            && trailing == br_spaces
+            && !text.trim().is_empty()
+            && (!strict || continued);
+        if !line_break {
+            let expected = match br_spaces >= 2 {
+                true => format!("0 or {}", br_spaces),
+                false => "0".to_string(),
+            };
+            // Keep hard line breaks written with too many spaces.
+            let replacement =
+                match br_spaces >= 2 && trailing >= 2 && continued && !text.trim().is_empty() {
+                    true => " ".repeat(br_spaces),
+                    false => String::new(),
+                };
+            let mut finding = Finding::at(range.end - trailing..range.end)
+                .with(format!("Expected: {}; Actual: {}", expected, trailing));
+            // Raw HTML keeps its spaces in the output.
+            if context.kinds[line] != LineKind::Html {
+                finding = finding.replace(range.end - trailing..range.end, replacement);
+            }
+            findings.push(finding);
+        }
+    }
+    findings
+}
+
+fn no_hard_tabs(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let code_blocks = params.flag("code_blocks", true);
+    for (line, range) in context.lines.iter().enumerate() {
+        match context.kinds[line] {
+            LineKind::FrontMatter => continue,
+            LineKind::Code if !code_blocks => continue,
+            _ => {}
+        }
+        let text = context.text(line);
+        if let Some(tab) = text.find('\t') {
+            let tabs = text[tab..].len() - text[tab..].trim_start_matches('\t').len();
+            let start = range.start + tab;
+            let column = text[..tab].chars().count();
+            let mut finding =
+                Finding::at(start..start + tabs).with(format!("Column: {}", column + 1));
+            // Indentation keeps its width, so blocks keep their structure.
+            // Other tabs are content, which spaces would change.
+            let indentation = text[..tab].trim_start_matches([' ', '>']).is_empty();
+            if indentation && context.kinds[line] == LineKind::Text {
+                finding = finding.replace(start..start + tabs, " ".repeat(4 * tabs - column % 4));
+            }
+            findings.push(finding);
+        }
+    }
+    findings
+}
+
+fn no_reversed_links(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let reversed = Regex::new(r"\(([^()]+)\)\[([^\]^][^\]]*)\]").unwrap();
+    for (line, range) in context.lines.iter().enumerate() {
+        if context.kinds[line] != LineKind::Text {
+            continue;
+        }
+        let text = context.text(line);
+        for found in reversed.find_iter(text) {
+            let start = range.start + found.start();
+            if text[..found.start()].ends_with('\\')
+                || text[found.end()..].starts_with('(')
+                || context.in_code_span(start)
+            {
+                continue;
+            }
+            findings.push(
+                Finding::at(start..range.start + found.end()).with(found.as_str().to_string()),
+            );
+        }
+    }
+    findings
+}
+
+fn no_multiple_blanks(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let maximum = params.number("maximum", 1);
+    let mut blanks = 0;
+    for (line, range) in context.lines.iter().enumerate() {
+        let blank = matches!(context.kinds[line], LineKind::Text | LineKind::Html)
+            && context.text(line).trim().is_empty();
+        blanks = match blank {
+            true => blanks + 1,
+            false => 0,
+        };
+        if blanks > maximum {
+            findings.push(
+                Finding::at(range.clone())
+                    .with(format!("Expected: {}; Actual: {}", maximum, blanks))
+                    .replace(context.lines[line - 1].end..range.end, ""),
+            );
+        }
+    }
+    findings
+}
+
+fn line_length(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let limit = params.number("line_length", 80);
+    let heading_limit = params.number("heading_line_length", limit);
+    let code_limit = params.number("code_block_line_length", limit);
+    let strict = params.flag("strict", false);
+    let mut heading_lines = HashSet::new();
+    let mut table_lines = HashSet::new();
+    for (event, range) in &context.events {
+        let lines = context.line_of(range.start)..=context.last_line(range);
+        match event {
+            Event::Start(Tag::Heading { .. }) => heading_lines.extend(lines),
+            Event::Start(Tag::Table(_)) => table_lines.extend(lines),
+            _ => {}
+        }
+    }
+    for (line, range) in context.lines.iter().enumerate() {
+        let (limit, enabled) = match context.kinds[line] {
+            LineKind::FrontMatter => continue,
+            LineKind::Code => (code_limit, params.flag("code_blocks", true)),
+            _ if heading_lines.contains(&line) => (heading_limit, params.flag("headings", true)),
+            _ if table_lines.contains(&line) => (limit, params.flag("tables", true)),
+            _ => (limit, true),
+        };
+        let text = context.text(line);
+        let length = text.chars().count();
+        if !enabled || length <= limit {
+            continue;
+        }
+        // Unless strict, long words such as URLs may run past the limit.
+        if !strict && !text.chars().skip(limit).any(char::is_whitespace) {
+            continue;
+        }
+        let start = text
+            .char_indices()
+            .nth(limit)
+            .map_or(text.len(), |(index, _)| index);
+        findings.push(
+            Finding::at(range.start + start..range.end)
+                .with(format!("Expected: {}; Actual: {}", limit, length)),
+        );
+    }
+    findings
+}
+
+fn no_missing_space_atx(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let heading_lines: HashSet<usize> = context
+        .headings
+        .iter()
+        .map(|heading| context.line_of(heading.range.start))
+        .collect();
+    for (line, range) in context.lines.iter().enumerate() {
+        if context.kinds[line] != LineKind::Text || heading_lines.contains(&line) {
+            continue;
+        }
+        let text = context.text(line);
+        let content = text.trim_start_matches(' ');
+        if text.len() - content.len() > 3 {
+            continue;
+        }
+        let hashes = content.len() - content.trim_start_matches('#').len();
+        let next = content[hashes..].chars().next();
+        if (1..=6).contains(&hashes) && next.is_some_and(|c| !c.is_whitespace()) {
+            let start = range.start + text.len() - content.len();
+            findings.push(
+                Finding::at(start..range.end)
+                    .with(content.to_string())
+                    .replace(start + hashes..start + hashes, " "),
+            );
+        }
+    }
+    findings
+}
+
+fn no_multiple_space_atx(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for heading in &context.headings {
+        let range = context.first_line(&heading.range);
+        let text = &context.input[range.clone()];
+        let Some(rest) = text
+            .strip_prefix('#')
+            .map(|rest| rest.trim_start_matches('#'))
+        else {
+            continue;
+        };
+        let content = rest.trim_start_matches([' ', '\t']);
+        if rest.len() - content.len() > 1 && !content.is_empty() {
+            let start = range.end - rest.len();
+            findings.push(
+                Finding::at(range)
+                    .with(text.to_string())
+                    .replace(start..start + rest.len() - content.len(), " "),
+            );
+        }
+    }
+    findings
+}
+
+fn blanks_around_headings(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let required = |name: &str, level: u8| match params.get(name) {
+        Some(Value::Array(levels)) => levels
+            .get(level as usize - 1)
+            .and_then(Value::as_u64)
+            .unwrap_or(1),
+        Some(value) => value.as_u64().unwrap_or(1),
+        None => 1,
+    } as usize;
+    for heading in &context.headings {
+        let first = context.line_of(heading.range.start);
+        let last = context.last_line(&heading.range);
+        let above = required("lines_above", heading.level);
+        let below = required("lines_below", heading.level);
+        let actual = context.blanks_above(first, above);
+        // A blank line in a list item would make the list loose.
+        if actual < above {
+            let mut finding = Finding::at(context.lines[first].clone())
+                .with(format!("Expected: {}; Actual: {}; Above", above, actual));
+            if !heading.in_item {
+                finding.edits.extend(context.insert_blank_lines(
+                    first - actual - 1,
+                    above - actual,
+                    first,
+                ));
+            }
+            findings.push(finding);
+        }
+        let actual = context.blanks_below(last, below);
+        if actual < below {
+            let mut finding = Finding::at(context.lines[first].clone())
+                .with(format!("Expected: {}; Actual: {}; Below", below, actual));
+            if !heading.in_item {
+                finding.edits.extend(context.insert_blank_lines(
+                    last + actual,
+                    below - actual,
+                    first,
+                ));
+            }
+            findings.push(finding);
+        }
+    }
+    findings
+}
+
+fn heading_start_left(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (position, (event, range)) in context.events.iter().enumerate() {
+        if !matches!(event, Event::Start(Tag::Heading { .. }))
+            || context.nesting[position] != (0, 0)
+        {
+            continue;
+        }
+        let line = &context.lines[context.line_of(range.start)];
+        if range.start > line.start {
+            findings.push(
+                Finding::at(line.start..context.first_line(range).end)
+                    .replace(line.start..range.start, ""),
+            );
+        }
+    }
+    findings
+}
+
+fn no_duplicate_heading(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let siblings_only = params.flag("siblings_only", false);
+    // The headings seen under the current parent, per level.
+    let mut seen: Vec<HashSet<&str>> = vec![HashSet::new(); 7];
+    for heading in &context.headings {
+        let level = match siblings_only {
+            true => {
+                for deeper in &mut seen[heading.level as usize + 1..] {
+                    deeper.clear();
+                }
+                heading.level as usize
+            }
+            false => 0,
+        };
+        if !seen[level].insert(&heading.text) {
+            findings
+                .push(Finding::at(context.first_line(&heading.range)).with(heading.text.clone()));
+        }
+    }
+    findings
+}
+
+fn single_h1(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let level = params.number("level", 1);
+    let mut seen = context.front_matter_title(params);
+    for heading in &context.headings {
+        if heading.level as usize == level {
+            if seen {
+                findings.push(
+                    Finding::at(context.first_line(&heading.range)).with(heading.text.clone()),
+                );
+            }
+            seen = true;
+        }
+    }
+    findings
+}
+
+fn no_trailing_punctuation(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let punctuation = params.string("punctuation", ".,;:!。，；：！");
+    for heading in &context.headings {
+        if let Some(last) = heading
+            .text
+            .chars()
+            .last()
+            .filter(|c| punctuation.contains(*c))
+        {
+            findings.push(
+                Finding::at(context.first_line(&heading.range))
+                    .with(format!("Punctuation: '{}'", last)),
+            );
+        }
+    }
+    findings
+}
+
+fn no_multiple_space_blockquote(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (line, range) in context.lines.iter().enumerate() {
+        if context.kinds[line] != LineKind::Text {
+            continue;
+        }
+        let text = context.text(line);
+        let mut rest = text.trim_start_matches(' ');
+        while let Some(after) = rest.strip_prefix('>') {
+            let content = after.trim_start_matches(' ');
+            if after.len() - content.len() > 1 && !content.is_empty() {
+                let start = range.start + text.len() - after.len();
+                let spaces = start..start + after.len() - content.len();
+                findings.push(Finding::at(spaces.clone()).replace(spaces, " "));
+                break;
+            }
+            rest = content;
+        }
+    }
+    findings
+}
+
+fn ol_prefix(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let style = params.string("style", "one_or_ordered");
+    // The number and marker range of each item of the open lists; `None`
+    // for bullet lists.
+    type Items = Vec<(u64, Range<usize>)>;
+    let mut lists: Vec<Option<Items>> = Vec::new();
+    for (event, range) in &context.events {
+        match event {
+            Event::Start(Tag::List(start)) => lists.push(start.map(|_| Vec::new())),
+            Event::Start(Tag::Item) => {
+                if let Some(Some(items)) = lists.last_mut() {
+                    let start = context.marker(range);
+                    let text = &context.input[start..];
+                    let digits = text
+                        .find(|c: char| !c.is_ascii_digit())
+                        .unwrap_or(text.len());
+                    let number = text[..digits].parse().unwrap_or(0);
+                    items.push((number, start..start + digits + 1));
+                }
+            }
+            Event::End(TagEnd::List(_)) => {
+                let Some(Some(items)) = lists.pop() else {
+                    continue;
+                };
+                let first = items[0].0;
+                let same = match style {
+                    "one" | "zero" => true,
+                    "ordered" => false,
+                    _ => items.len() > 1 && items[1].0 == first && first <= 1,
+                };
+                let start = match style {
+                    "one" => 1,
+                    "zero" => 0,
+                    _ => first,
+                };
+                let pattern = match same {
+                    true => format!("{0}/{0}/{0}", start),
+                    false => format!("{}/{}/{}", start, start + 1, start + 2),
+                };
+                for (offset, (number, range)) in items.into_iter().enumerate() {
+                    let expected = match same {
+                        true => start,
+                        false => start + offset as u64,
+                    };
+                    if number != expected {
+                        findings.push(Finding::at(range).with(format!(
+                            "Expected: {}; Actual: {}; Style: {}",
+                            expected, number, pattern
+                        )));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    findings
+}
+
+fn blanks_around_fences(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let list_items = params.flag("list_items", true);
+    for (position, (event, range)) in context.events.iter().enumerate() {
+        if let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(_))) = event {
+            let in_item = context.nesting[position].0 > 0;
+            if list_items || !in_item {
+                context.blanks_around(range, in_item, &mut findings);
+            }
+        }
+    }
+    findings
+}
+
+fn blanks_around_lists(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (position, (event, range)) in context.events.iter().enumerate() {
+        if let Event::Start(Tag::List(_)) = event {
+            if context.nesting[position].0 == 0 {
+                context.blanks_around(range, false, &mut findings);
+            }
+        }
+    }
+    findings
+}
+
+fn blanks_around_tables(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (position, (event, range)) in context.events.iter().enumerate() {
+        if let Event::Start(Tag::Table(_)) = event {
+            context.blanks_around(range, context.nesting[position].0 > 0, &mut findings);
+        }
+    }
+    findings
+}
+
+fn no_inline_html(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let allowed: Vec<String> = params
+        .strings("allowed_elements")
+        .iter()
+        .map(|name| name.to_ascii_lowercase())
+        .collect();
+    let element = Regex::new(r"<([A-Za-z][A-Za-z0-9-]*)").unwrap();
+    let comment = Regex::new(r"(?s)<!--.*?(?:-->|$)").unwrap();
+    for (event, range) in &context.events {
+        if !matches!(event, Event::Html(_) | Event::InlineHtml(_)) {
+            continue;
+        }
+        let source = &context.input[range.clone()];
+        let comments: Vec<Range<usize>> = comment
+            .find_iter(source)
+            .map(|found| found.range())
+            .collect();
+        for found in element.captures_iter(source) {
+            let (whole, name) = (found.get(0).unwrap(), &found[1]);
+            if comments
+                .iter()
+                .any(|comment| comment.contains(&whole.start()))
+                || allowed.contains(&name.to_ascii_lowercase())
+            {
+                continue;
+            }
+            findings.push(
+                Finding::at(range.start + whole.start()..range.start + whole.end())
+                    .with(format!("Element: {}", name)),
+            );
+        }
+    }
+    findings
+}
+
+fn no_bare_urls(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let url = Regex::new(
+        r#"(?:https?|ftp)://[^\s<>\[\]`]*[^\s<>\[\]`.,;:!?'")]|[\w.+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)+"#,
+    )
+    .unwrap();
+    let mut skip = 0;
+    for (event, range) in &context.events {
+        match event {
+            Event::Start(Tag::Link { .. } | Tag::Image { .. } | Tag::CodeBlock(_)) => skip += 1,
+            Event::End(TagEnd::Link | TagEnd::Image | TagEnd::CodeBlock) => skip -= 1,
+            Event::Text(_) if skip == 0 => {
+                let source = &context.input[range.clone()];
+                for found in url.find_iter(source) {
+                    let (start, end) = (range.start + found.start(), range.start + found.end());
+                    findings.push(
+                        Finding::at(start..end)
+                            .with(format!("Context: \"{}\"", found.as_str()))
+                            .replace(start..start, "<")
+                            .replace(end..end, ">"),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    findings
+}
+
+fn hr_style(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut expected = match params.string("style", "consistent") {
+        "consistent" => None,
+        style => Some(style),
+    };
+    for (event, range) in &context.events {
+        if let Event::Rule = event {
+            let source = context.input[range.clone()].trim();
+            let expected = *expected.get_or_insert(source);
+            if source != expected {
+                findings.push(
+                    Finding::at(context.first_line(range))
+                        .with(format!("Expected: {}; Actual: {}", expected, source)),
+                );
+            }
+        }
+    }
+    findings
+}
+
+fn no_emphasis_as_heading(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let punctuation = params.string("punctuation", ".,;:!?。，；：！？");
+    let events = &context.events;
+    for (position, (event, range)) in events.iter().enumerate() {
+        if !matches!(event, Event::Start(Tag::Paragraph)) || context.nesting[position] != (0, 0) {
+            continue;
+        }
+        if !matches!(
+            events.get(position + 1),
+            Some((Event::Start(Tag::Emphasis | Tag::Strong), _))
+        ) {
+            continue;
+        }
+        let mut text = String::new();
+        let mut end = position + 2;
+        while let Some((Event::Text(value), _)) = events.get(end) {
+            text.push_str(value);
+            end += 1;
+        }
+        let closed = matches!(
+            events.get(end),
+            Some((Event::End(TagEnd::Emphasis | TagEnd::Strong), _))
+        ) && matches!(
+            events.get(end + 1),
+            Some((Event::End(TagEnd::Paragraph), _))
+        );
+        let text = text.trim();
+        if closed && !text.is_empty() && !text.ends_with(|c| punctuation.contains(c)) {
+            findings.push(Finding::at(context.first_line(range)).with(text.to_string()));
+        }
+    }
+    findings
+}
+
+fn no_space_in_code(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for span in &context.code_spans {
+        let source = &context.input[span.clone()];
+        let ticks = source.len() - source.trim_start_matches('`').len();
+        let Some(content) = source.get(ticks..source.len().saturating_sub(ticks)) else {
+            continue;
+        };
+        let trimmed = content.trim();
+        if trimmed.is_empty() || trimmed.len() == content.len() {
+            continue;
+        }
+        // One space of padding on each side lets a span start or end with a
+        // backtick.
+        let padded = content.len() == trimmed.len() + 2
+            && content.starts_with(' ')
+            && content.ends_with(' ');
+        if padded && (trimmed.starts_with('`') || trimmed.ends_with('`')) {
+            continue;
+        }
+        findings.push(Finding::at(span.clone()).with(format!("Context: \"{}\"", source)));
+    }
+    findings
+}
+
+fn no_space_in_links(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let events = &context.events;
+    for (position, (event, range)) in events.iter().enumerate() {
+        let Event::Start(Tag::Link { link_type, .. }) = event else {
+            continue;
+        };
+        if matches!(link_type, LinkType::Autolink | LinkType::Email) {
+            continue;
+        }
+        let Some(end) = events[position..]
+            .iter()
+            .position(|(event, _)| matches!(event, Event::End(TagEnd::Link)))
+            .map(|offset| position + offset)
+        else {
+            continue;
+        };
+        if end == position + 1 || !context.input[range.start..].starts_with('[') {
+            continue;
+        }
+        let mut close = events[end - 1].1.end;
+        while context.input[close..].starts_with([' ', '\t']) {
+            close += 1;
+        }
+        let text = &context.input[range.start + 1..close];
+        if text.trim().len() < text.len() && !text.trim().is_empty() {
+            findings.push(
+                Finding::at(range.clone())
+                    .with(format!("Context: \"{}\"", &context.input[range.clone()])),
+            );
+        }
+    }
+    findings
+}
+
+fn fenced_code_language(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let allowed = params.strings("allowed_languages");
+    for (event, range) in &context.events {
+        let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) = event else {
+            continue;
+        };
+        let language = info.split_whitespace().next().unwrap_or_default();
+        if language.is_empty() {
+            findings.push(Finding::at(context.first_line(range)));
+        } else if !allowed.is_empty() && !allowed.contains(&language) {
+            findings.push(
+                Finding::at(context.first_line(range))
+                    .with(format!("\"{}\" is not allowed", language)),
+            );
+        }
+    }
+    findings
+}
+
+fn first_line_heading(context: &Context, params: &Params) -> Vec<Finding> {
+    let level = params.number("level", 1);
+    if context.front_matter_title(params) {
+        return Vec::new();
+    }
+    let mut events = context.events.iter();
+    while let Some((event, range)) = events.next() {
+        let Event::Start(tag) = event else {
+            continue;
+        };
+        let valid = match tag {
+            Tag::MetadataBlock(_) => {
+                events.find(|(event, _)| matches!(event, Event::End(TagEnd::MetadataBlock(_))));
+                continue;
+            }
+            Tag::HtmlBlock => {
+                let html = context.input[range.clone()].trim_start();
+                if html.starts_with("<!--") {
+                    events.find(|(event, _)| matches!(event, Event::End(TagEnd::HtmlBlock)));
+                    continue;
+                }
+                html.to_ascii_lowercase()
+                    .starts_with(&format!("<h{}", level))
+            }
+            Tag::Heading { level: actual, .. } => *actual as usize == level,
+            _ => false,
+        };
+        return match valid {
+            true => Vec::new(),
+            false => vec![Finding::at(context.first_line(range))],
+        };
+    }
+    Vec::new()
+}
+
+fn no_empty_links(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (event, range) in &context.events {
+        if let Event::Start(Tag::Link { dest_url, .. }) = event {
+            if dest_url.is_empty() || &**dest_url == "#" {
+                findings.push(
+                    Finding::at(range.clone())
+                        .with(format!("Context: \"{}\"", &context.input[range.clone()])),
+                );
+            }
+        }
+    }
+    findings
+}
+
+fn no_alt_text(context: &Context, _: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut images: Vec<(Range<usize>, String)> = Vec::new();
+    for (event, range) in &context.events {
+        match event {
+            Event::Start(Tag::Image { .. }) => images.push((range.clone(), String::new())),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, alt)) = images.last_mut() {
+                    alt.push_str(text);
+                }
+            }
+            Event::End(TagEnd::Image) => {
+                if let Some((range, alt)) = images.pop() {
+                    if alt.trim().is_empty() {
+                        findings.push(Finding::at(range));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    findings
+}
+
+fn code_block_style(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut expected = match params.string("style", "consistent") {
+        "consistent" => None,
+        style => Some(style),
+    };
+    for (event, range) in &context.events {
+        let Event::Start(Tag::CodeBlock(kind)) = event else {
+            continue;
+        };
+        let actual = match kind {
+            CodeBlockKind::Fenced(_) => "fenced",
+            CodeBlockKind::Indented => "indented",
+        };
+        let expected = *expected.get_or_insert(actual);
+        if expected != actual {
+            findings.push(
+                Finding::at(context.first_line(range))
+                    .with(format!("Expected: {}; Actual: {}", expected, actual)),
+            );
+        }
+    }
+    findings
+}
+
+fn single_trailing_newline(context: &Context, _: &Params) -> Vec<Finding> {
+    let input = context.input;
+    match input.is_empty() || input.ends_with('\n') {
+        true => Vec::new(),
+        false => {
+            let end = input.len();
+            vec![Finding::at(end..end).replace(end..end, context.newline)]
+        }
+    }
+}
+
+fn code_fence_style(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut expected = match params.string("style", "consistent") {
+        "consistent" => None,
+        style => Some(style),
+    };
+    for (event, range) in &context.events {
+        if let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(_))) = event {
+            let actual = match context.input[range.start..].starts_with('~') {
+                true => "tilde",
+                false => "backtick",
+            };
+            let expected = *expected.get_or_insert(actual);
+            if expected != actual {
+                let mut finding = Finding::at(context.first_line(range))
+                    .with(format!("Expected: {}; Actual: {}", expected, actual));
+                for edit in refence(context, range, if expected == "tilde" { '~' } else { '`' }) {
+                    finding = finding.edit(edit);
+                }
+                findings.push(finding);
+            }
+        }
+    }
+    findings
+}
+
+/// The edits swapping the fence characters of the fenced code block at
+/// `range` for `fence`; none if the block's content or info string uses it.
+fn refence(context: &Context, range: &Range<usize>, fence: char) -> Vec<Edit> {
+    let source = &context.input[range.clone()];
+    let length = source.len() - source.trim_start_matches(['`', '~']).len();
+    let rest = &source[length..];
+    let info = rest.lines().next().unwrap_or_default();
+    if rest.contains(&fence.to_string().repeat(3)) || info.contains(fence) {
+        return Vec::new();
+    }
+    let mut edits = vec![Edit {
+        range: range.start..range.start + length,
+        text: fence.to_string().repeat(length),
+    }];
+    if let Some(closing) = context.closing_fence(range) {
+        let text = fence.to_string().repeat(closing.len());
+        edits.push(Edit {
+            range: closing,
+            text,
+        });
+    }
+    edits
+}
+
+/// Findings for emphasis or strong spans not using the configured marker.
+fn marker_style(context: &Context, params: &Params, strong: bool) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut expected = match params.string("style", "consistent") {
+        "consistent" => None,
+        style => Some(style),
+    };
+    for (event, range) in &context.events {
+        let matched = match event {
+            Event::Start(Tag::Strong) => strong,
+            Event::Start(Tag::Emphasis) => !strong,
+            _ => false,
+        };
+        if !matched {
+            continue;
+        }
+        let actual = match context.input[range.start..].starts_with('_') {
+            true => "underscore",
+            false => "asterisk",
+        };
+        let expected = *expected.get_or_insert(actual);
+        if expected != actual {
+            let marker = if strong { 2 } else { 1 };
+            let mut finding = Finding::at(range.start..range.start + marker)
+                .with(format!("Expected: {}; Actual: {}", expected, actual));
+            // Underscores do not emphasize within a word.
+            let input = context.input;
+            let intraword = input[..range.start].ends_with(char::is_alphanumeric)
+                || input[range.end..].starts_with(char::is_alphanumeric);
+            if expected == "asterisk" || !intraword {
+                let text = if expected == "asterisk" { "*" } else { "_" }.repeat(marker);
+                finding = finding
+                    .replace(range.start..range.start + marker, text.clone())
+                    .replace(range.end - marker..range.end, text);
+            }
+            findings.push(finding);
+        }
+    }
+    findings
+}
+
+fn emphasis_style(context: &Context, params: &Params) -> Vec<Finding> {
+    marker_style(context, params, false)
+}
+
+fn strong_style(context: &Context, params: &Params) -> Vec<Finding> {
+    marker_style(context, params, true)
+}
+
+fn link_fragments(context: &Context, params: &Params) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let ignore_case = params.flag("ignore_case", false);
+    let normalize = |fragment: &str| match ignore_case {
+        true => fragment.to_lowercase(),
+        false => fragment.to_string(),
+    };
+    let anchor = Regex::new(r#"\b(?:id|name)\s*=\s*["']?([^"'\s>]+)"#).unwrap();
+    let line_reference = Regex::new(r"^L\d+(?:C\d+)?(?:-L\d+(?:C\d+)?)?$").unwrap();
+    let mut fragments: HashSet<String> = HashSet::from([normalize("top")]);
+    for (event, range) in &context.events {
+        match event {
+            Event::Start(Tag::Heading { id: Some(id), .. }) => {
+                fragments.insert(normalize(id));
+            }
+            Event::Html(_) | Event::InlineHtml(_) => {
+                for found in anchor.captures_iter(&context.input[range.clone()]) {
+                    fragments.insert(normalize(&found[1]));
+                }
+            }
+            _ => {}
+        }
+    }
+    for (event, range) in &context.events {
+        let Event::Start(Tag::Link { dest_url, .. }) = event else {
+            continue;
+        };
+        let Some(fragment) = dest_url
+            .strip_prefix('#')
+            .filter(|fragment| !fragment.is_empty())
+        else {
+            continue;
+        };
+        if !fragments.contains(&normalize(fragment)) && !line_reference.is_match(fragment) {
+            findings.push(
+                Finding::at(range.clone())
+                    .with(format!("Context: \"{}\"", &context.input[range.clone()])),
+            );
+        }
+    }
+    findings
+}
+
+fn reference_links_images(context: &Context, _: &Params) -> Vec<Finding> {
+    context
+        .broken_links
+        .iter()
+        .map(|(range, label)| {
+            Finding::at(range.clone()).with(format!(
+                "Missing link or image reference definition: \"{}\"",
+                label
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,491 @@
+use pulldown_cmark::{Alignment, CowStr, Event, HeadingLevel, Tag, TagEnd};
+use serde_json::Value;
+
+use crate::front_matter;
+use crate::gfm;
+use crate::options::{HtmlPolicy, MarkdownOptions};
+use crate::plain_text::needs_url;
+use crate::raw_html;
+use crate::render::{self, Spanned};
+
+/// Renders `input` as a `man(7)` page. The `.TH` line comes from the front
+/// matter `name`, `section` (default 1), `date`, `source` and `manual`
+/// fields; with a `description` and no NAME section of its own, the page
+/// gets one. A level 1 heading that starts the document and is the only one
+/// of its level is taken as the title: it names the page when the front
+/// matter does not, and is otherwise left out. The remaining shallowest
+/// headings become `.SH` sections, the next level `.SS` subsections.
+pub(crate) fn convert(input: &str, options: &MarkdownOptions) -> String {
+    let options = MarkdownOptions {
+        front_matter: true,
+        ..options.clone()
+    };
+    let html = match options.html {
+        HtmlPolicy::Escape => HtmlPolicy::Escape,
+        _ => HtmlPolicy::Strip,
+    };
+    let mut events = raw_html::apply(render::parse(input, &options), html);
+    if options.autolinks {
+        events = gfm::autolink(events);
+    }
+    let fields = front_matter::fields(input);
+    let field = |key: &str| match fields.get(key) {
+        Some(Value::String(text)) => Some(text.clone()),
+        Some(Value::Number(number)) => Some(number.to_string()),
+        _ => None,
+    };
+
+    let title = title(&events);
+    let levels: Vec<HeadingLevel> = events
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (event, _))| match event {
+            Event::Start(Tag::Heading { level, .. }) if Some(index) != title => Some(*level),
+            _ => None,
+        })
+        .collect();
+    let top = levels.iter().min().copied().unwrap_or(HeadingLevel::H1);
+    let name = field("name")
+        .or_else(|| title.map(|title| heading_text(&events, title)))
+        .unwrap_or_else(|| "untitled".to_string());
+
+    let mut writer = Writer {
+        out: String::new(),
+        fonts: Vec::new(),
+        lists: Vec::new(),
+        items: 0,
+        fresh: true,
+        upper: false,
+        code: false,
+        hidden: 0,
+        links: Vec::new(),
+        table: None,
+    };
+    let arguments: Vec<String> = [
+        Some(name.to_uppercase()),
+        Some(field("section").unwrap_or_else(|| "1".to_string())),
+        field("date"),
+        field("source"),
+        field("manual"),
+    ]
+    .into_iter()
+    .map(|argument| quote(&argument.unwrap_or_default()))
+    .collect();
+    let arguments = arguments.join(" ");
+    writer.request(&format!(".TH {}", arguments.trim_end_matches(" \"\"")));
+    let has_name = events.iter().enumerate().any(|(index, (event, _))| {
+        matches!(event, Event::Start(Tag::Heading { .. }))
+            && heading_text(&events, index).eq_ignore_ascii_case("name")
+    });
+    if let (Some(description), false) = (field("description"), has_name) {
+        writer.request(".SH NAME");
+        writer.text(&format!("{} - {}", name, description));
+    }
+
+    let mut skip = false;
+    for (index, (event, _)) in events.into_iter().enumerate() {
+        if Some(index) == title {
+            skip = true;
+        }
+        if skip {
+            skip = !matches!(event, Event::End(TagEnd::Heading(_)));
+            continue;
+        }
+        writer.event(event, top);
+    }
+    writer.line();
+    writer.out
+}
+
+/// The index of the level 1 heading that starts the document, if it is the
+/// only one and there are other headings after it.
+fn title(events: &[Spanned]) -> Option<usize> {
+    let mut metadata = false;
+    let first = events.iter().position(|(event, _)| match event {
+        Event::Start(Tag::MetadataBlock(_)) => {
+            metadata = true;
+            false
+        }
+        Event::End(TagEnd::MetadataBlock(_)) => {
+            metadata = false;
+            false
+        }
+        _ => !metadata,
+    })?;
+    let levels: Vec<HeadingLevel> = events
+        .iter()
+        .filter_map(|(event, _)| match event {
+            Event::Start(Tag::Heading { level, .. }) => Some(*level),
+            _ => None,
+        })
+        .collect();
+    let is_title = matches!(
+        events[first].0,
+        Event::Start(Tag::Heading {
+            level: HeadingLevel::H1,
+            ..
+        })
+    ) && levels.len() > 1
+        && levels
+            .iter()
+            .filter(|&&level| level == HeadingLevel::H1)
+            .count()
+            == 1;
+    is_title.then_some(first)
+}
+
+fn heading_text(events: &[Spanned], start: usize) -> String {
+    let mut text = String::new();
+    for (event, _) in &events[start + 1..] {
+        match event {
+            Event::Text(part) | Event::Code(part) => text.push_str(part),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            Event::End(TagEnd::Heading(_)) => break,
+            _ => {}
+        }
+    }
+    text.trim().to_string()
+}
+
+struct Table {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<String>>,
+    /// Where the current cell's text starts in the output, from which it
+    /// is moved into `rows`.
+    cell: usize,
+}
+
+struct Writer<'a> {
+    out: String,
+    /// The open fonts: `B`, `I` or `BI`.
+    fonts: Vec<&'static str>,
+    lists: Vec<Option<u64>>,
+    /// Open list items and definitions, whose later paragraphs keep their
+    /// indentation.
+    items: usize,
+    /// Right after a section heading or an item's tag, where a paragraph
+    /// needs no request of its own.
+    fresh: bool,
+    /// Inside a `.SH` heading, whose text is upper case.
+    upper: bool,
+    code: bool,
+    hidden: usize,
+    /// The text of every open link so far, and its URL.
+    links: Vec<(String, CowStr<'a>)>,
+    table: Option<Table>,
+}
+
+impl<'a> Writer<'a> {
+    fn event(&mut self, event: Event<'a>, top: HeadingLevel) {
+        if self.hidden > 0 {
+            match event {
+                Event::Start(Tag::MetadataBlock(_)) => self.hidden += 1,
+                Event::End(TagEnd::MetadataBlock(_)) => self.hidden -= 1,
+                _ => {}
+            }
+            return;
+        }
+        match event {
+            Event::Start(tag) => self.start(tag, top),
+            Event::End(tag) => self.end(tag, top),
+            Event::Text(text) if self.code => {
+                for line in text.split_inclusive('\n') {
+                    self.out.push_str("\\&");
+                    self.out.push_str(&escape(line));
+                }
+            }
+            Event::Text(text) | Event::InlineMath(text) | Event::DisplayMath(text) => {
+                self.text(&text)
+            }
+            Event::Code(code) => {
+                self.font("B");
+                self.text(&code);
+                self.end_font();
+            }
+            Event::Html(_) | Event::InlineHtml(_) => {}
+            Event::SoftBreak => self.out.push('\n'),
+            Event::HardBreak => self.request(".br"),
+            Event::Rule => {
+                self.request(".PP");
+                self.fresh = false;
+            }
+            Event::FootnoteReference(name) => self.text(&format!("[{}]", name)),
+            Event::TaskListMarker(checked) => self.text(if checked { "[x] " } else { "[ ] " }),
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'a>, top: HeadingLevel) {
+        match tag {
+            Tag::Paragraph => self.paragraph(),
+            Tag::Heading { level, .. } => match level as usize - top as usize {
+                0 => {
+                    self.request(".SH");
+                    self.upper = true;
+                }
+                1 => self.request(".SS"),
+                _ => {
+                    self.paragraph();
+                    self.font("B");
+                }
+            },
+            Tag::BlockQuote(_) => {
+                self.paragraph();
+                self.request(".RS 4");
+                self.fresh = true;
+            }
+            Tag::CodeBlock(_) => {
+                self.paragraph();
+                self.request(".RS 4");
+                self.request(".nf");
+                self.code = true;
+            }
+            Tag::List(start) => {
+                if self.items > 0 {
+                    self.request(".RS");
+                }
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                let request = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        let tag = format!("{}.", *number - 1);
+                        format!(".IP {} {}", tag, (tag.len() + 1).max(4))
+                    }
+                    _ => ".IP \\(bu 2".to_string(),
+                };
+                self.request(&request);
+                self.items += 1;
+                self.fresh = true;
+            }
+            Tag::DefinitionListTitle => {
+                self.request(".TP");
+                self.font("B");
+            }
+            Tag::DefinitionListDefinition => {
+                // Later definitions of a term get paragraphs of their own.
+                self.items += 1;
+                self.paragraph();
+                self.fresh = true;
+            }
+            Tag::FootnoteDefinition(name) => {
+                self.paragraph();
+                self.text(&format!("[{}] ", name));
+                self.fresh = true;
+            }
+            Tag::Table(alignments) => {
+                self.paragraph();
+                self.table = Some(Table {
+                    alignments,
+                    rows: Vec::new(),
+                    cell: 0,
+                });
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = self.table.as_mut() {
+                    table.rows.push(Vec::new());
+                }
+            }
+            Tag::TableCell => {
+                let start = self.out.len();
+                if let Some(table) = self.table.as_mut() {
+                    table.cell = start;
+                }
+            }
+            Tag::Emphasis => self.font("I"),
+            Tag::Strong => self.font("B"),
+            Tag::Link { dest_url, .. } => self.links.push((String::new(), dest_url)),
+            Tag::MetadataBlock(_) => self.hidden = 1,
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd, top: HeadingLevel) {
+        match tag {
+            TagEnd::Heading(level) => {
+                if level as usize - top as usize > 1 {
+                    self.end_font();
+                }
+                self.line();
+                self.upper = false;
+                self.fresh = true;
+            }
+            TagEnd::Paragraph => self.fresh = false,
+            TagEnd::BlockQuote(_) => {
+                self.request(".RE");
+                self.fresh = false;
+            }
+            TagEnd::CodeBlock => {
+                self.code = false;
+                self.request(".fi");
+                self.request(".RE");
+                self.fresh = false;
+            }
+            TagEnd::List(_) => {
+                self.lists.pop();
+                if self.items > 0 {
+                    self.request(".RE");
+                }
+            }
+            TagEnd::Item | TagEnd::DefinitionListDefinition => {
+                self.items -= 1;
+                self.fresh = false;
+            }
+            TagEnd::DefinitionListTitle => {
+                self.end_font();
+                self.line();
+                self.fresh = true;
+            }
+            TagEnd::TableCell => {
+                if let Some(table) = self.table.as_mut() {
+                    let cell = self.out.split_off(table.cell).replace(['\n', '\t'], " ");
+                    table
+                        .rows
+                        .last_mut()
+                        .expect("cells are inside rows")
+                        .push(cell);
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.tbl(table);
+                }
+                self.fresh = false;
+            }
+            TagEnd::Emphasis | TagEnd::Strong => self.end_font(),
+            TagEnd::Link => {
+                let Some((text, url)) = self.links.pop() else {
+                    return;
+                };
+                if needs_url(text.trim(), &url) {
+                    if !text.trim().is_empty() {
+                        self.out.push(' ');
+                    }
+                    self.out.push_str("\\(la");
+                    self.text(&url);
+                    self.out.push_str("\\(ra");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Starts a paragraph, unless one was just started by a heading or item.
+    fn paragraph(&mut self) {
+        match (self.fresh, self.items > 0) {
+            (true, _) => {}
+            (false, true) => self.request(".IP"),
+            (false, false) => self.request(".PP"),
+        }
+        self.fresh = false;
+    }
+
+    /// Ends the current line, if anything is on it.
+    fn line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    /// Writes `line` as a request on a line of its own.
+    fn request(&mut self, line: &str) {
+        self.line();
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn text(&mut self, text: &str) {
+        for (link, _) in &mut self.links {
+            link.push_str(text);
+        }
+        let text = if self.upper {
+            text.to_uppercase()
+        } else {
+            text.to_string()
+        };
+        let at_line_start = self.out.is_empty() || self.out.ends_with('\n');
+        let text = match at_line_start {
+            true => text.trim_start(),
+            false => &text,
+        };
+        if at_line_start && (text.starts_with('.') || text.starts_with('\'')) {
+            self.out.push_str("\\&");
+        }
+        self.out.push_str(&escape(text));
+    }
+
+    fn font(&mut self, font: &'static str) {
+        let current = self.fonts.last().copied().unwrap_or("R");
+        let font = match (current, font) {
+            ("R", font) => font,
+            ("BI", _) | ("B", "I") | ("I", "B") => "BI",
+            (current, _) => current,
+        };
+        self.fonts.push(font);
+        self.out.push_str(&font_escape(font));
+    }
+
+    fn end_font(&mut self) {
+        self.fonts.pop();
+        let font = self.fonts.last().copied().unwrap_or("R");
+        self.out.push_str(&font_escape(font));
+    }
+
+    /// Writes a table for the `tbl` preprocessor, which `man` runs.
+    fn tbl(&mut self, table: Table) {
+        self.request(".TS");
+        self.request("allbox tab(\t);");
+        let format = |bold: bool| {
+            let columns: Vec<String> = table
+                .alignments
+                .iter()
+                .map(|alignment| {
+                    let key = match alignment {
+                        Alignment::Center => "c",
+                        Alignment::Right => "r",
+                        _ => "l",
+                    };
+                    if bold {
+                        format!("{}b", key)
+                    } else {
+                        key.to_string()
+                    }
+                })
+                .collect();
+            columns.join(" ")
+        };
+        self.request(&format(true));
+        self.request(&format!("{}.", format(false)));
+        for row in &table.rows {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|cell| match cell.trim() {
+                    cell if cell.starts_with('.') || cell.starts_with('\'') => {
+                        format!("\\&{}", cell)
+                    }
+                    cell => cell.to_string(),
+                })
+                .collect();
+            self.request(&cells.join("\t"));
+        }
+        self.request(".TE");
+    }
+}
+
+fn font_escape(font: &str) -> String {
+    match font.len() {
+        1 => format!("\\f{}", font),
+        _ => format!("\\f({}", font),
+    }
+}
+
+/// Escapes backslashes and hyphens, which would otherwise be typeset as
+/// hyphens rather than the minus signs of command-line options.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e").replace('-', "\\-")
+}
+
+/// A `.TH` argument.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", escape(text).replace('"', "\\(dq"))
+}
@@ -0,0 +1,957 @@
+use std::ops::Range;
+
+use pulldown_cmark::Event;
+use pulldown_cmark_escape::{escape_html, escape_html_body_text};
+
+use crate::render::Spanned;
+
+/// Replaces inline and display math with MathML.
+pub(crate) fn apply(events: Vec<Spanned<'_>>) -> Vec<Spanned<'_>> {
+    events
+        .into_iter()
+        .map(|(event, range)| match event {
+            Event::InlineMath(tex) => (Event::InlineHtml(to_mathml(&tex, false).into()), range),
+            Event::DisplayMath(tex) => (Event::InlineHtml(to_mathml(&tex, true).into()), range),
+            event => (event, range),
+        })
+        .collect()
+}
+
+/// Translates a LaTeX formula into a `<math>` element, keeping the source as
+/// an annotation. Formulas that cannot be translated come out as a
+/// `<span class="math-error">` with the offending part in a `<mark>`.
+pub(crate) fn to_mathml(tex: &str, display: bool) -> String {
+    let mut out = String::new();
+    match Translator::new(tex, display).formula() {
+        Ok(body) => {
+            out.push_str(match display {
+                true => "<math display=\"block\">",
+                false => "<math>",
+            });
+            out.push_str("<semantics><mrow>");
+            out.push_str(&body);
+            out.push_str("</mrow><annotation encoding=\"application/x-tex\">");
+            escape_html_body_text(&mut out, tex).unwrap();
+            out.push_str("</annotation></semantics></math>");
+        }
+        Err(error) => {
+            out.push_str("<span class=\"math-error\" title=\"");
+            escape_html(&mut out, &error.message).unwrap();
+            out.push_str("\">");
+            escape_html_body_text(&mut out, &tex[..error.range.start]).unwrap();
+            out.push_str("<mark>");
+            escape_html_body_text(&mut out, &tex[error.range.clone()]).unwrap();
+            out.push_str("</mark>");
+            escape_html_body_text(&mut out, &tex[error.range.end..]).unwrap();
+            out.push_str("</span>");
+        }
+    }
+    out
+}
+
+/// Why `tex` cannot be translated, if it cannot.
+pub(crate) fn check(tex: &str, display: bool) -> Option<String> {
+    Translator::new(tex, display)
+        .formula()
+        .err()
+        .map(|error| error.message)
+}
+
+#[derive(Debug)]
+struct MathError {
+    message: String,
+    /// Byte range of the offending input in the formula.
+    range: Range<usize>,
+}
+
+type Result<T> = std::result::Result<T, MathError>;
+
+fn error<T>(message: impl Into<String>, range: Range<usize>) -> Result<T> {
+    Err(MathError {
+        message: message.into(),
+        range,
+    })
+}
+
+/// A recursive descent translator over the formula source.
+struct Translator<'a> {
+    tex: &'a str,
+    pos: usize,
+    display: bool,
+    /// The `mathvariant` set by an enclosing `\mathbf` and friends.
+    variant: Option<&'static str>,
+}
+
+impl<'a> Translator<'a> {
+    fn new(tex: &'a str, display: bool) -> Self {
+        Translator {
+            tex,
+            pos: 0,
+            display,
+            variant: None,
+        }
+    }
+
+    fn formula(mut self) -> Result<String> {
+        let body = self.sequence(false)?;
+        match self.rest().is_empty() {
+            true => Ok(body),
+            false => self.unexpected(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.tex[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Skips whitespace and `%` comments.
+    fn skip_space(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if !trimmed.starts_with('%') {
+                return;
+            }
+            self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+        }
+    }
+
+    /// Whether the input continues with the command `\name`.
+    fn at_command(&self, name: &str) -> bool {
+        self.rest()
+            .strip_prefix('\\')
+            .and_then(|rest| rest.strip_prefix(name))
+            .is_some_and(|after| !after.starts_with(|c: char| c.is_ascii_alphabetic()))
+    }
+
+    /// Whether the input continues with something that ends the current
+    /// sequence, which the caller deals with.
+    fn at_stop(&self, bracket: bool) -> bool {
+        let rest = self.rest();
+        rest.is_empty()
+            || rest.starts_with(['}', '&'])
+            || rest.starts_with("\\\\")
+            || (bracket && rest.starts_with(']'))
+            || self.at_command("right")
+            || self.at_command("end")
+    }
+
+    /// Reports whatever ended a sequence where it did not belong.
+    fn unexpected<T>(&self) -> Result<T> {
+        let rest = self.rest();
+        let at = |len: usize| self.pos..self.pos + len;
+        if rest.starts_with('}') {
+            error("unmatched `}`", at(1))
+        } else if rest.starts_with('&') {
+            error("`&` outside of an environment", at(1))
+        } else if rest.starts_with("\\\\") {
+            error("`\\\\` outside of an environment", at(2))
+        } else if self.at_command("right") {
+            error("`\\right` without a matching `\\left`", at(6))
+        } else if self.at_command("end") {
+            error("`\\end` without a matching `\\begin`", at(4))
+        } else if rest.starts_with(']') {
+            error("unmatched `]`", at(1))
+        } else {
+            error("unexpected end of formula", at(0))
+        }
+    }
+
+    /// Atoms with their scripts up to the next stop. With `bracket`, a `]`
+    /// stops the sequence too.
+    fn sequence(&mut self, bracket: bool) -> Result<String> {
+        let mut out = String::new();
+        loop {
+            self.skip_space();
+            if self.at_stop(bracket) {
+                return Ok(out);
+            }
+            let (base, limits) = self.atom(false)?;
+            out.push_str(&self.scripts(base, limits)?);
+        }
+    }
+
+    /// Attaches any `^`, `_` and `'` following an atom. Operators with
+    /// `limits` put their scripts under and over instead.
+    fn scripts(&mut self, base: String, limits: bool) -> Result<String> {
+        let mut sub = None;
+        let mut sup = None;
+        let mut primes = 0;
+        loop {
+            self.skip_space();
+            let start = self.pos;
+            match self.peek() {
+                Some('^') => {
+                    self.pos += 1;
+                    if sup.is_some() {
+                        return error("double superscript", start..self.pos);
+                    }
+                    sup = Some(self.argument()?);
+                }
+                Some('_') => {
+                    self.pos += 1;
+                    if sub.is_some() {
+                        return error("double subscript", start..self.pos);
+                    }
+                    sub = Some(self.argument()?);
+                }
+                Some('\'') => {
+                    self.pos += 1;
+                    primes += 1;
+                }
+                _ => break,
+            }
+        }
+        if primes > 0 {
+            let primes = format!("<mo>{}</mo>", "\u{2032}".repeat(primes));
+            sup = Some(match sup {
+                Some(sup) => format!("<mrow>{}{}</mrow>", primes, sup),
+                None => primes,
+            });
+        }
+        let (under, over, both) = match limits {
+            true => ("munder", "mover", "munderover"),
+            false => ("msub", "msup", "msubsup"),
+        };
+        Ok(match (sub, sup) {
+            (None, None) => base,
+            (Some(sub), None) => format!("<{under}>{base}{sub}</{under}>"),
+            (None, Some(sup)) => format!("<{over}>{base}{sup}</{over}>"),
+            (Some(sub), Some(sup)) => format!("<{both}>{base}{sub}{sup}</{both}>"),
+        })
+    }
+
+    /// A single atom used as the argument of a script or command: a group,
+    /// a command, or one character.
+    fn argument(&mut self) -> Result<String> {
+        self.skip_space();
+        if self.at_stop(false) || self.rest().starts_with(['^', '_']) {
+            return error("missing argument", self.pos..self.pos);
+        }
+        Ok(self.atom(true)?.0)
+    }
+
+    /// A braced group as an `mrow`.
+    fn group(&mut self) -> Result<String> {
+        let open = self.pos;
+        self.pos += 1;
+        let body = self.sequence(false)?;
+        if self.rest().is_empty() {
+            return error("missing `}`", open..open + 1);
+        }
+        if !self.rest().starts_with('}') {
+            return self.unexpected();
+        }
+        self.pos += 1;
+        Ok(format!("<mrow>{}</mrow>", body))
+    }
+
+    /// The raw source of a braced argument, for text and names.
+    fn raw_group(&mut self) -> Result<&'a str> {
+        self.skip_space();
+        let open = self.pos;
+        if !self.rest().starts_with('{') {
+            return error("expected `{`", open..open);
+        }
+        let mut depth = 0;
+        for (index, c) in self.rest().char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 1 => {
+                    self.pos = open + index + 1;
+                    return Ok(&self.tex[open + 1..open + index]);
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        error("missing `}`", open..open + 1)
+    }
+
+    /// One atom, and whether its scripts go under and over it. With
+    /// `single`, a run of digits only contributes its first digit, as in
+    /// `x^23`.
+    fn atom(&mut self, single: bool) -> Result<(String, bool)> {
+        let start = self.pos;
+        let Some(c) = self.peek() else {
+            return error("missing argument", start..start);
+        };
+        let atom = match c {
+            '{' => self.group()?,
+            '\\' => return self.command(),
+            '0'..='9' => {
+                let digits = match single {
+                    true => 1,
+                    false => number_len(self.rest()),
+                };
+                self.pos += digits;
+                self.token("mn", &self.tex[start..start + digits], false)
+            }
+            '.' if self.rest()[1..].starts_with(|c: char| c.is_ascii_digit()) && !single => {
+                let len = number_len(self.rest());
+                self.pos += len;
+                self.token("mn", &self.tex[start..start + len], false)
+            }
+            // Scripts and primes without a base attach to an empty one.
+            '^' | '_' | '\'' => String::from("<mrow></mrow>"),
+            '~' => {
+                self.pos += 1;
+                String::from("<mtext>\u{a0}</mtext>")
+            }
+            '#' | '$' => return error(format!("unexpected `{}`", c), start..start + 1),
+            c if c.is_alphabetic() => {
+                self.pos += c.len_utf8();
+                self.token("mi", &self.tex[start..self.pos], false)
+            }
+            c => {
+                self.pos += c.len_utf8();
+                operator(&self.tex[start..self.pos])
+            }
+        };
+        Ok((atom, false))
+    }
+
+    /// An `mi`, `mn` or `mtext` element in the current font.
+    fn token(&self, element: &str, text: &str, upright: bool) -> String {
+        let variant = self.variant.or(upright.then_some("normal"));
+        let mut out = format!("<{}", element);
+        if let Some(variant) = variant {
+            out.push_str(&format!(" mathvariant=\"{}\"", variant));
+        }
+        out.push('>');
+        escape_html_body_text(&mut out, text).unwrap();
+        out.push_str(&format!("</{}>", element));
+        out
+    }
+
+    fn command(&mut self) -> Result<(String, bool)> {
+        let start = self.pos;
+        self.pos += 1;
+        let name_len = match self.rest().find(|c: char| !c.is_ascii_alphabetic()) {
+            Some(0) => self.peek().map_or(0, char::len_utf8),
+            Some(len) => len,
+            None => self.rest().len(),
+        };
+        let name = &self.tex[self.pos..self.pos + name_len];
+        self.pos += name_len;
+        let range = start..self.pos;
+        if name.is_empty() {
+            return error("`\\` at the end of the formula", range);
+        }
+
+        if let Some((symbol, upright)) = identifier(name) {
+            return Ok((self.token("mi", symbol, upright), false));
+        }
+        if let Some(symbol) = symbol_operator(name) {
+            return Ok((operator(symbol), false));
+        }
+        if let Some((symbol, movable)) = large_operator(name) {
+            let limits = self.limits(movable && self.display);
+            return Ok((format!("<mo>{}</mo>", symbol), limits));
+        }
+        if let Some(movable) = function(name) {
+            let limits = self.limits(movable && self.display);
+            return Ok((format!("<mi>{}</mi>", name), limits));
+        }
+        if let Some(width) = space(name) {
+            return Ok((format!("<mspace width=\"{}\"></mspace>", width), false));
+        }
+        if let Some(variant) = font(name) {
+            let outer = self.variant.replace(variant);
+            let argument = self.argument();
+            self.variant = outer;
+            return Ok((argument?, false));
+        }
+        if let Some((accent, over, stretchy)) = accent(name) {
+            let base = self.argument()?;
+            let mo = match stretchy {
+                true => format!("<mo stretchy=\"true\">{}</mo>", accent),
+                false => format!("<mo stretchy=\"false\">{}</mo>", accent),
+            };
+            return Ok((
+                match over {
+                    true => format!("<mover accent=\"true\">{}{}</mover>", base, mo),
+                    false => format!("<munder accentunder=\"true\">{}{}</munder>", base, mo),
+                },
+                false,
+            ));
+        }
+        if let Some(size) = delimiter_size(name) {
+            let delimiter = self.delimiter(range)?;
+            return Ok((
+                format!(
+                    "<mo fence=\"true\" stretchy=\"true\" minsize=\"{size}\" maxsize=\"{size}\">{}</mo>",
+                    delimiter
+                ),
+                false,
+            ));
+        }
+
+        let atom = match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let numerator = self.argument()?;
+                let denominator = self.argument()?;
+                let fraction = format!("<mfrac>{}{}</mfrac>", numerator, denominator);
+                match name {
+                    "frac" => fraction,
+                    "tfrac" => format!("<mstyle displaystyle=\"false\">{}</mstyle>", fraction),
+                    _ => format!("<mstyle displaystyle=\"true\">{}</mstyle>", fraction),
+                }
+            }
+            "binom" | "dbinom" | "tbinom" => {
+                let top = self.argument()?;
+                let bottom = self.argument()?;
+                format!(
+                    "<mrow><mo>(</mo><mfrac linethickness=\"0\">{}{}</mfrac><mo>)</mo></mrow>",
+                    top, bottom
+                )
+            }
+            "sqrt" => {
+                self.skip_space();
+                if self.rest().starts_with('[') {
+                    let open = self.pos;
+                    self.pos += 1;
+                    let index = self.sequence(true)?;
+                    if !self.rest().starts_with(']') {
+                        return match self.rest().is_empty() {
+                            true => error("missing `]`", open..open + 1),
+                            false => self.unexpected(),
+                        };
+                    }
+                    self.pos += 1;
+                    let radicand = self.argument()?;
+                    format!("<mroot>{}<mrow>{}</mrow></mroot>", radicand, index)
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.argument()?)
+                }
+            }
+            "left" => {
+                let open = self.delimiter(range.clone())?;
+                let body = self.sequence(false)?;
+                if !self.at_command("right") {
+                    return match self.rest().is_empty() {
+                        true => error("missing `\\right` for this `\\left`", range),
+                        false => self.unexpected(),
+                    };
+                }
+                let right = self.pos..self.pos + 6;
+                self.pos += 6;
+                let close = self.delimiter(right)?;
+                format!("<mrow>{}{}{}</mrow>", fence(&open), body, fence(&close))
+            }
+            "middle" => fence(&self.delimiter(range)?),
+            "begin" => self.environment(range)?,
+            "text" | "textrm" | "textnormal" | "textup" | "mbox" | "textit" | "textbf"
+            | "texttt" | "textsf" => {
+                let text = unescape_text(self.raw_group()?);
+                let variant = match name {
+                    "textit" => Some("italic"),
+                    "textbf" => Some("bold"),
+                    "texttt" => Some("monospace"),
+                    "textsf" => Some("sans-serif"),
+                    _ => None,
+                };
+                let mut out = String::from("<mtext");
+                if let Some(variant) = variant {
+                    out.push_str(&format!(" mathvariant=\"{}\"", variant));
+                }
+                out.push('>');
+                escape_html_body_text(&mut out, &text).unwrap();
+                out.push_str("</mtext>");
+                out
+            }
+            "operatorname" => {
+                let name = self.raw_group()?.trim().to_string();
+                let limits = self.limits(false);
+                return Ok((self.token("mi", &name, true), limits));
+            }
+            "not" => {
+                self.skip_space();
+                let (negated, _) = self.atom(true)?;
+                match negated.strip_suffix("</mo>") {
+                    Some(operator) => format!("{}\u{338}</mo>", operator),
+                    None => format!("<mrow><mo>\u{338}</mo>{}</mrow>", negated),
+                }
+            }
+            "bmod" => String::from("<mo lspace=\"0.2222em\" rspace=\"0.2222em\">mod</mo>"),
+            "pmod" => format!(
+                "<mrow><mspace width=\"1em\"></mspace><mo>(</mo><mi>mod</mi><mspace width=\"0.3333em\"></mspace>{}<mo>)</mo></mrow>",
+                self.argument()?
+            ),
+            "hline" => String::new(),
+            "displaystyle" | "textstyle" => {
+                let display = name == "displaystyle";
+                let body = self.sequence(false)?;
+                format!("<mstyle displaystyle=\"{}\">{}</mstyle>", display, body)
+            }
+            "limits" | "nolimits" => {
+                return error(format!("`\\{}` must follow an operator", name), range);
+            }
+            _ => return error(format!("unsupported command `\\{}`", name), range),
+        };
+        Ok((atom, false))
+    }
+
+    /// Consumes a `\limits` or `\nolimits` after an operator, which override
+    /// the `default` placement of its scripts.
+    fn limits(&mut self, default: bool) -> bool {
+        let saved = self.pos;
+        self.skip_space();
+        if self.at_command("limits") {
+            self.pos += 7;
+            true
+        } else if self.at_command("nolimits") {
+            self.pos += 9;
+            false
+        } else {
+            self.pos = saved;
+            default
+        }
+    }
+
+    /// The delimiter after `\left`, `\right`, `\middle` or `\big`; empty for
+    /// the invisible `.`.
+    fn delimiter(&mut self, command: Range<usize>) -> Result<String> {
+        self.skip_space();
+        let start = self.pos;
+        let Some(c) = self.peek() else {
+            return error("missing delimiter", command);
+        };
+        if c == '\\' {
+            let (atom, _) = self.command()?;
+            let symbol = atom
+                .strip_prefix("<mo>")
+                .or_else(|| atom.strip_prefix("<mo stretchy=\"false\">"))
+                .and_then(|atom| atom.strip_suffix("</mo>"));
+            return match symbol {
+                Some(symbol) if is_delimiter(symbol) => Ok(symbol.to_string()),
+                _ => error("expected a delimiter", start..self.pos),
+            };
+        }
+        self.pos += c.len_utf8();
+        match c {
+            '.' => Ok(String::new()),
+            '<' => Ok(String::from("\u{27e8}")),
+            '>' => Ok(String::from("\u{27e9}")),
+            '(' | ')' | '[' | ']' | '|' | '/' => Ok(c.to_string()),
+            _ => error("expected a delimiter", start..self.pos),
+        }
+    }
+
+    fn environment(&mut self, begin: Range<usize>) -> Result<String> {
+        let name = self.raw_group()?.trim();
+        let columns = match name {
+            "array" => self.raw_group()?,
+            _ => "",
+        };
+        let begin = begin.start..self.pos;
+        let (open, close, align) = match name {
+            "matrix" | "smallmatrix" => ("", "", String::new()),
+            "pmatrix" => ("(", ")", String::new()),
+            "bmatrix" => ("[", "]", String::new()),
+            "Bmatrix" => ("{", "}", String::new()),
+            "vmatrix" => ("|", "|", String::new()),
+            "Vmatrix" => ("\u{2016}", "\u{2016}", String::new()),
+            "cases" => ("{", "", String::from("left left")),
+            "aligned" | "align" | "align*" | "split" => ("", "", String::from("right left")),
+            "gathered" | "gather" | "gather*" => ("", "", String::new()),
+            "array" => {
+                let align: Vec<&str> = columns
+                    .chars()
+                    .filter_map(|c| match c {
+                        'l' => Some("left"),
+                        'c' => Some("center"),
+                        'r' => Some("right"),
+                        _ => None,
+                    })
+                    .collect();
+                ("", "", align.join(" "))
+            }
+            _ => return error(format!("unsupported environment `{}`", name), begin),
+        };
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut row = Vec::new();
+        loop {
+            row.push(self.sequence(false)?);
+            let rest = self.rest();
+            if rest.starts_with('&') {
+                self.pos += 1;
+            } else if rest.starts_with("\\\\") {
+                self.pos += 2;
+                rows.push(std::mem::take(&mut row));
+            } else if self.at_command("end") {
+                let end = self.pos;
+                self.pos += 4;
+                let end_name = self.raw_group()?.trim();
+                if end_name != name {
+                    return error(format!("expected `\\end{{{}}}`", name), end..self.pos);
+                }
+                if !(row.len() == 1 && row[0].is_empty()) {
+                    rows.push(row);
+                }
+                break;
+            } else if rest.is_empty() {
+                return error(format!("missing `\\end{{{}}}`", name), begin);
+            } else {
+                return self.unexpected();
+            }
+        }
+
+        let mut table = String::from("<mtable");
+        if !align.is_empty() {
+            table.push_str(&format!(" columnalign=\"{}\"", align));
+        }
+        if name.starts_with("align") || name == "split" {
+            table.push_str(" columnspacing=\"0em\"");
+        }
+        if name == "smallmatrix" {
+            table.push_str(" displaystyle=\"false\"");
+        }
+        table.push('>');
+        for row in rows {
+            table.push_str("<mtr>");
+            for cell in row {
+                table.push_str(&format!("<mtd>{}</mtd>", cell));
+            }
+            table.push_str("</mtr>");
+        }
+        table.push_str("</mtable>");
+        Ok(match (open, close) {
+            ("", "") => table,
+            (open, close) => format!("<mrow>{}{}{}</mrow>", fence(open), table, fence(close)),
+        })
+    }
+}
+
+/// The length of the number at the start of `rest`.
+fn number_len(rest: &str) -> usize {
+    let mut seen_point = false;
+    let mut len = 0;
+    for (index, c) in rest.char_indices() {
+        let digit_follows = rest[index + c.len_utf8()..].starts_with(|c: char| c.is_ascii_digit());
+        if c == '.' && !seen_point && digit_follows {
+            seen_point = true;
+        } else if !c.is_ascii_digit() {
+            break;
+        }
+        len = index + c.len_utf8();
+    }
+    len
+}
+
+/// An `mo` for an operator or punctuation character. Brackets keep their
+/// natural size outside of `\left ... \right`.
+fn operator(symbol: &str) -> String {
+    let symbol = match symbol {
+        "-" => "\u{2212}",
+        "*" => "\u{2217}",
+        symbol => symbol,
+    };
+    let mut out = String::from(match is_delimiter(symbol) {
+        true => "<mo stretchy=\"false\">",
+        false => "<mo>",
+    });
+    escape_html_body_text(&mut out, symbol).unwrap();
+    out.push_str("</mo>");
+    out
+}
+
+fn fence(symbol: &str) -> String {
+    if symbol.is_empty() {
+        return String::new();
+    }
+    let mut out = String::from("<mo fence=\"true\" stretchy=\"true\">");
+    escape_html_body_text(&mut out, symbol).unwrap();
+    out.push_str("</mo>");
+    out
+}
+
+fn is_delimiter(symbol: &str) -> bool {
+    matches!(
+        symbol,
+        "(" | ")"
+            | "["
+            | "]"
+            | "{"
+            | "}"
+            | "|"
+            | "/"
+            | "\\"
+            | "\u{2016}"
+            | "\u{27e8}"
+            | "\u{27e9}"
+            | "\u{230a}"
+            | "\u{230b}"
+            | "\u{2308}"
+            | "\u{2309}"
+            | "\u{2191}"
+            | "\u{2193}"
+    )
+}
+
+/// `\text` content with TeX's character escapes resolved.
+fn unescape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek().is_some_and(|c| "{}%$&#_ ".contains(*c)) => {
+                out.extend(chars.next());
+            }
+            '~' => out.push('\u{a0}'),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Letter-like symbols, rendered as `mi`, and whether they are upright.
+fn identifier(name: &str) -> Option<(&'static str, bool)> {
+    let lower = match name {
+        "alpha" => "\u{3b1}",
+        "beta" => "\u{3b2}",
+        "gamma" => "\u{3b3}",
+        "delta" => "\u{3b4}",
+        "epsilon" => "\u{3f5}",
+        "varepsilon" => "\u{3b5}",
+        "zeta" => "\u{3b6}",
+        "eta" => "\u{3b7}",
+        "theta" => "\u{3b8}",
+        "vartheta" => "\u{3d1}",
+        "iota" => "\u{3b9}",
+        "kappa" => "\u{3ba}",
+        "lambda" => "\u{3bb}",
+        "mu" => "\u{3bc}",
+        "nu" => "\u{3bd}",
+        "xi" => "\u{3be}",
+        "pi" => "\u{3c0}",
+        "varpi" => "\u{3d6}",
+        "rho" => "\u{3c1}",
+        "varrho" => "\u{3f1}",
+        "sigma" => "\u{3c3}",
+        "varsigma" => "\u{3c2}",
+        "tau" => "\u{3c4}",
+        "upsilon" => "\u{3c5}",
+        "phi" => "\u{3d5}",
+        "varphi" => "\u{3c6}",
+        "chi" => "\u{3c7}",
+        "psi" => "\u{3c8}",
+        "omega" => "\u{3c9}",
+        "ell" => "\u{2113}",
+        "hbar" => "\u{210f}",
+        "imath" => "\u{131}",
+        "jmath" => "\u{237}",
+        "wp" => "\u{2118}",
+        _ => "",
+    };
+    if !lower.is_empty() {
+        return Some((lower, false));
+    }
+    let upright = match name {
+        "Gamma" => "\u{393}",
+        "Delta" => "\u{394}",
+        "Theta" => "\u{398}",
+        "Lambda" => "\u{39b}",
+        "Xi" => "\u{39e}",
+        "Pi" => "\u{3a0}",
+        "Sigma" => "\u{3a3}",
+        "Upsilon" => "\u{3a5}",
+        "Phi" => "\u{3a6}",
+        "Psi" => "\u{3a8}",
+        "Omega" => "\u{3a9}",
+        "infty" => "\u{221e}",
+        "partial" => "\u{2202}",
+        "nabla" => "\u{2207}",
+        "emptyset" | "varnothing" => "\u{2205}",
+        "aleph" => "\u{2135}",
+        "Re" => "\u{211c}",
+        "Im" => "\u{2111}",
+        "$" => "$",
+        "_" => "_",
+        _ => return None,
+    };
+    Some((upright, true))
+}
+
+/// Symbols rendered as `mo`.
+fn symbol_operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "times" => "\u{d7}",
+        "cdot" => "\u{22c5}",
+        "div" => "\u{f7}",
+        "pm" => "\u{b1}",
+        "mp" => "\u{2213}",
+        "ast" => "\u{2217}",
+        "star" => "\u{22c6}",
+        "circ" => "\u{2218}",
+        "bullet" => "\u{2219}",
+        "oplus" => "\u{2295}",
+        "ominus" => "\u{2296}",
+        "otimes" => "\u{2297}",
+        "odot" => "\u{2299}",
+        "wedge" | "land" => "\u{2227}",
+        "vee" | "lor" => "\u{2228}",
+        "neg" | "lnot" => "\u{ac}",
+        "cup" => "\u{222a}",
+        "cap" => "\u{2229}",
+        "setminus" => "\u{2216}",
+        "le" | "leq" => "\u{2264}",
+        "ge" | "geq" => "\u{2265}",
+        "ne" | "neq" => "\u{2260}",
+        "ll" => "\u{226a}",
+        "gg" => "\u{226b}",
+        "approx" => "\u{2248}",
+        "equiv" => "\u{2261}",
+        "sim" => "\u{223c}",
+        "simeq" => "\u{2243}",
+        "cong" => "\u{2245}",
+        "propto" => "\u{221d}",
+        "in" => "\u{2208}",
+        "notin" => "\u{2209}",
+        "ni" => "\u{220b}",
+        "subset" => "\u{2282}",
+        "subseteq" => "\u{2286}",
+        "supset" => "\u{2283}",
+        "supseteq" => "\u{2287}",
+        "forall" => "\u{2200}",
+        "exists" => "\u{2203}",
+        "nexists" => "\u{2204}",
+        "perp" => "\u{22a5}",
+        "parallel" => "\u{2225}",
+        "mid" => "\u{2223}",
+        "angle" => "\u{2220}",
+        "to" | "rightarrow" => "\u{2192}",
+        "gets" | "leftarrow" => "\u{2190}",
+        "leftrightarrow" => "\u{2194}",
+        "Rightarrow" | "implies" => "\u{21d2}",
+        "Leftarrow" => "\u{21d0}",
+        "Leftrightarrow" | "iff" => "\u{21d4}",
+        "mapsto" => "\u{21a6}",
+        "uparrow" => "\u{2191}",
+        "downarrow" => "\u{2193}",
+        "ldots" | "dots" => "\u{2026}",
+        "cdots" => "\u{22ef}",
+        "vdots" => "\u{22ee}",
+        "ddots" => "\u{22f1}",
+        "prime" => "\u{2032}",
+        "langle" => "\u{27e8}",
+        "rangle" => "\u{27e9}",
+        "lfloor" => "\u{230a}",
+        "rfloor" => "\u{230b}",
+        "lceil" => "\u{2308}",
+        "rceil" => "\u{2309}",
+        "vert" | "lvert" | "rvert" => "|",
+        "Vert" | "lVert" | "rVert" | "|" => "\u{2016}",
+        "backslash" => "\\",
+        "{" | "lbrace" => "{",
+        "}" | "rbrace" => "}",
+        "%" => "%",
+        "#" => "#",
+        "&" => "&",
+        _ => return None,
+    })
+}
+
+/// Big operators, and whether their limits go under and over in display
+/// style.
+fn large_operator(name: &str) -> Option<(&'static str, bool)> {
+    Some(match name {
+        "sum" => ("\u{2211}", true),
+        "prod" => ("\u{220f}", true),
+        "coprod" => ("\u{2210}", true),
+        "bigcup" => ("\u{22c3}", true),
+        "bigcap" => ("\u{22c2}", true),
+        "bigoplus" => ("\u{2a01}", true),
+        "bigotimes" => ("\u{2a02}", true),
+        "bigvee" => ("\u{22c1}", true),
+        "bigwedge" => ("\u{22c0}", true),
+        "int" => ("\u{222b}", false),
+        "iint" => ("\u{222c}", false),
+        "iiint" => ("\u{222d}", false),
+        "oint" => ("\u{222e}", false),
+        _ => return None,
+    })
+}
+
+/// Named functions, rendered upright, and whether their limits go under
+/// them in display style.
+fn function(name: &str) -> Option<bool> {
+    match name {
+        "lim" | "liminf" | "limsup" | "max" | "min" | "sup" | "inf" | "det" | "gcd" | "Pr"
+        | "argmax" | "argmin" => Some(true),
+        "sin" | "cos" | "tan" | "cot" | "sec" | "csc" | "arcsin" | "arccos" | "arctan" | "sinh"
+        | "cosh" | "tanh" | "coth" | "log" | "ln" | "lg" | "exp" | "deg" | "dim" | "ker"
+        | "arg" | "hom" => Some(false),
+        _ => None,
+    }
+}
+
+fn space(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "," | "thinspace" => "0.1667em",
+        ":" | ">" | "medspace" => "0.2222em",
+        ";" | "thickspace" => "0.2778em",
+        "!" | "negthinspace" => "-0.1667em",
+        " " => "0.3333em",
+        "quad" => "1em",
+        "qquad" => "2em",
+        _ => return None,
+    })
+}
+
+fn font(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "mathrm" => "normal",
+        "mathit" => "italic",
+        "mathbf" => "bold",
+        "boldsymbol" | "bm" => "bold-italic",
+        "mathbb" => "double-struck",
+        "mathcal" => "script",
+        "mathfrak" => "fraktur",
+        "mathsf" => "sans-serif",
+        "mathtt" => "monospace",
+        _ => return None,
+    })
+}
+
+/// Accents: the mark, whether it goes over the base, and whether it
+/// stretches to the base's width.
+fn accent(name: &str) -> Option<(&'static str, bool, bool)> {
+    Some(match name {
+        "hat" => ("^", true, false),
+        "widehat" => ("^", true, true),
+        "tilde" => ("~", true, false),
+        "widetilde" => ("~", true, true),
+        "bar" => ("\u{af}", true, false),
+        "overline" => ("\u{203e}", true, true),
+        "underline" => ("_", false, true),
+        "vec" => ("\u{2192}", true, false),
+        "overrightarrow" => ("\u{2192}", true, true),
+        "overleftarrow" => ("\u{2190}", true, true),
+        "dot" => ("\u{2d9}", true, false),
+        "ddot" => ("\u{a8}", true, false),
+        "acute" => ("\u{b4}", true, false),
+        "grave" => ("`", true, false),
+        "breve" => ("\u{2d8}", true, false),
+        "check" => ("\u{2c7}", true, false),
+        "overbrace" => ("\u{23de}", true, true),
+        "underbrace" => ("\u{23df}", false, true),
+        _ => return None,
+    })
+}
+
+fn delimiter_size(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "big" | "bigl" | "bigr" | "bigm" => "1.2em",
+        "Big" | "Bigl" | "Bigr" | "Bigm" => "1.623em",
+        "bigg" | "biggl" | "biggr" | "biggm" => "2.047em",
+        "Bigg" | "Biggl" | "Biggr" | "Biggm" => "2.470em",
+        _ => return None,
+    })
+}
@@ -0,0 +1,204 @@
+use pulldown_cmark::Options;
+use wasm_bindgen::prelude::*;
+
+use crate::sanitize::SanitizerPolicy;
+
+/// How raw HTML written in the Markdown source is rendered.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HtmlPolicy {
+    /// Emit raw HTML verbatim.
+    #[default]
+    Pass,
+    /// Escape raw HTML so it shows up as literal text.
+    Escape,
+    /// Drop raw HTML from the output.
+    Strip,
+}
+
+/// How highlighted code tokens are styled.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HighlightStyle {
+    /// `hl-keyword`, `hl-string`, ... classes, styled by the page (see
+    /// `highlight_stylesheet`).
+    #[default]
+    Classes,
+    /// `style` attributes with the built-in palette. The sanitizer drops
+    /// these unless its policy allows `style` on `span`.
+    Inline,
+}
+
+/// Parser extensions and render settings for a single
+/// `parse_markdown_with_options` call.
+///
+/// The extension fields map to pulldown-cmark's `Options` flags, the rest
+/// are applied by the renderer. A freshly constructed value is strict
+/// CommonMark, the presets below turn on the common combinations.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MarkdownOptions {
+    pub tables: bool,
+    pub footnotes: bool,
+    /// Older footnote syntax; implies `footnotes`.
+    pub old_footnotes: bool,
+    pub strikethrough: bool,
+    pub tasklists: bool,
+    pub smart_punctuation: bool,
+    pub heading_attributes: bool,
+    pub yaml_metadata_blocks: bool,
+    pub pluses_metadata_blocks: bool,
+    pub math: bool,
+    /// GitHub blockquote alerts (`> [!NOTE]` and friends).
+    pub gfm: bool,
+    pub definition_list: bool,
+    pub superscript: bool,
+    pub subscript: bool,
+    pub wikilinks: bool,
+    /// GFM extended autolinks: bare `www.`, `http(s)://` and email addresses.
+    pub autolinks: bool,
+    /// GFM tagfilter: escape `<script>`, `<iframe>` and the other disallowed
+    /// tags in raw HTML.
+    pub tagfilter: bool,
+    /// Run the rendered HTML through a `SanitizerPolicy`, the default one
+    /// unless `sanitizer_policy` is set, making the output safe to assign to
+    /// `innerHTML` for untrusted input. Heading and footnote ids, and the
+    /// `#` links to them, get the policy's id prefix.
+    pub sanitize: bool,
+    /// The policy `sanitize` applies; see `set_sanitizer_policy`.
+    #[wasm_bindgen(skip)]
+    pub sanitizer_policy: Option<SanitizerPolicy>,
+    /// What to do with raw HTML blocks and inline tags in the source.
+    pub html: HtmlPolicy,
+    /// Tag block elements with `data-source-start`, `data-source-end` (UTF-16
+    /// offsets into the input) and `data-source-line` attributes.
+    pub source_positions: bool,
+    /// Give headings GitHub-style slug ids. Explicit `{#id}` attributes
+    /// (see `heading_attributes`) win over generated ones.
+    pub heading_ids: bool,
+    /// Prepend a `<a class="heading-anchor">` link to every heading; implies
+    /// `heading_ids`.
+    pub heading_anchors: bool,
+    /// Replace a `[TOC]` paragraph or `<!-- toc -->` comment with a nested
+    /// list of links to the document's headings; implies `heading_ids`.
+    pub toc: bool,
+    /// Shallowest heading level listed in the table of contents, 0 for 1.
+    pub toc_min_level: u8,
+    /// Deepest heading level listed in the table of contents, 0 for 6.
+    pub toc_max_level: u8,
+    /// Highlight fenced code blocks in the bundled languages (TypeScript and
+    /// JavaScript, Rust, JSON, YAML, TOML, shell, Python and SQL).
+    pub highlight: bool,
+    pub highlight_style: HighlightStyle,
+    /// Translate `$...$` and `$$...$$` into MathML instead of leaving the
+    /// TeX in `<span class="math">` wrappers; implies `math`.
+    pub mathml: bool,
+    /// Treat a `---` YAML or `+++` TOML block at the very start of the
+    /// document as front matter and leave it out of the output. Unlike the
+    /// metadata block extensions, this does not hide `---` blocks further
+    /// down.
+    pub front_matter: bool,
+    /// Write well-formed XHTML, for XML pipelines: raw HTML is re-serialized
+    /// as XML (or escaped or dropped, per `html`), void elements are closed
+    /// and named character references become numeric.
+    pub xhtml: bool,
+}
+
+#[wasm_bindgen]
+impl MarkdownOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> MarkdownOptions {
+        MarkdownOptions::default()
+    }
+
+    /// Sanitizes with `policy` instead of the default policy; implies
+    /// `sanitize`.
+    pub fn set_sanitizer_policy(&mut self, policy: &SanitizerPolicy) {
+        self.sanitize = true;
+        self.sanitizer_policy = Some(policy.clone());
+    }
+
+    /// Strict CommonMark, no extensions.
+    pub fn commonmark() -> MarkdownOptions {
+        MarkdownOptions::default()
+    }
+
+    /// The extensions most documents expect: tables, footnotes,
+    /// strikethrough, task lists and heading attributes.
+    pub fn extended() -> MarkdownOptions {
+        MarkdownOptions {
+            tables: true,
+            footnotes: true,
+            strikethrough: true,
+            tasklists: true,
+            heading_attributes: true,
+            ..MarkdownOptions::default()
+        }
+    }
+
+    /// GitHub-Flavored Markdown, as rendered on github.com.
+    pub fn gfm() -> MarkdownOptions {
+        MarkdownOptions {
+            tables: true,
+            footnotes: true,
+            strikethrough: true,
+            tasklists: true,
+            gfm: true,
+            autolinks: true,
+            tagfilter: true,
+            heading_ids: true,
+            ..MarkdownOptions::default()
+        }
+    }
+
+    /// Every extension pulldown-cmark supports, except the old footnote
+    /// syntax and subscript (which would shadow single-tilde strikethrough).
+    pub fn all() -> MarkdownOptions {
+        MarkdownOptions {
+            smart_punctuation: true,
+            yaml_metadata_blocks: true,
+            pluses_metadata_blocks: true,
+            math: true,
+            gfm: true,
+            definition_list: true,
+            superscript: true,
+            wikilinks: true,
+            ..MarkdownOptions::extended()
+        }
+    }
+}
+
+impl From<&MarkdownOptions> for Options {
+    fn from(options: &MarkdownOptions) -> Self {
+        let flags = [
+            (options.tables, Options::ENABLE_TABLES),
+            (options.footnotes, Options::ENABLE_FOOTNOTES),
+            (options.old_footnotes, Options::ENABLE_OLD_FOOTNOTES),
+            (options.strikethrough, Options::ENABLE_STRIKETHROUGH),
+            (options.tasklists, Options::ENABLE_TASKLISTS),
+            (options.smart_punctuation, Options::ENABLE_SMART_PUNCTUATION),
+            (
+                options.heading_attributes,
+                Options::ENABLE_HEADING_ATTRIBUTES,
+            ),
+            (
+                options.yaml_metadata_blocks,
+                Options::ENABLE_YAML_STYLE_METADATA_BLOCKS,
+            ),
+            (
+                options.pluses_metadata_blocks,
+                Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS,
+            ),
+            (options.math || options.mathml, Options::ENABLE_MATH),
+            (options.gfm, Options::ENABLE_GFM),
+            (options.definition_list, Options::ENABLE_DEFINITION_LIST),
+            (options.superscript, Options::ENABLE_SUPERSCRIPT),
+            (options.subscript, Options::ENABLE_SUBSCRIPT),
+            (options.wikilinks, Options::ENABLE_WIKILINKS),
+        ];
+        flags
+            .into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .fold(Options::empty(), |acc, (_, flag)| acc | flag)
+    }
+}
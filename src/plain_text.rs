@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+use wasm_bindgen::prelude::*;
+
+use crate::gfm;
+use crate::headings;
+use crate::options::MarkdownOptions;
+use crate::raw_html;
+use crate::render;
+use crate::toc;
+
+/// Settings for `render_plain_text`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlainTextOptions {
+    /// Longest output in characters, 0 for no limit. Longer text is cut at
+    /// a word boundary and ends with `…`.
+    pub max_length: usize,
+    /// Follow link text with the URL in parentheses, unless the text is the
+    /// URL or the link points into the same document.
+    pub link_urls: bool,
+}
+
+#[wasm_bindgen]
+impl PlainTextOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> PlainTextOptions {
+        PlainTextOptions::default()
+    }
+}
+
+/// Renders `input` as plain text: blocks separated by blank lines, list
+/// items on their own lines behind `•` or their number, table cells
+/// separated by tabs. Raw HTML is left out unless `options.html` escapes it.
+pub(crate) fn convert(input: &str, options: &MarkdownOptions, plain: &PlainTextOptions) -> String {
+    let mut events = render::parse(input, options);
+    if options.toc {
+        events = toc::insert(headings::assign_ids(events), options);
+    }
+    events = raw_html::apply(events, options.html);
+    if options.autolinks {
+        events = gfm::autolink(events);
+    }
+
+    let mut writer = Writer {
+        out: String::new(),
+        options: plain,
+        pending: 0,
+        indent: String::new(),
+        indents: Vec::new(),
+        lists: Vec::new(),
+        item_start: false,
+        first_cell: false,
+        links: Vec::new(),
+        code: None,
+        hidden: 0,
+        numbers: HashMap::new(),
+    };
+    for (event, _) in events {
+        writer.event(event);
+    }
+    truncate(writer.out.trim_end(), plain.max_length)
+}
+
+struct Writer<'a, 'o> {
+    out: String,
+    options: &'o PlainTextOptions,
+    /// Line breaks owed before the next text.
+    pending: usize,
+    /// Written after every line break, to line up with a list item's text.
+    indent: String,
+    indents: Vec<usize>,
+    /// The next number of every open list, `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+    /// Just after a list item's bullet, where its first paragraph goes.
+    item_start: bool,
+    first_cell: bool,
+    /// Where the text of every open link starts, and its URL.
+    links: Vec<(usize, CowStr<'a>)>,
+    code: Option<String>,
+    /// Depth of metadata blocks, whose text is not written.
+    hidden: usize,
+    numbers: HashMap<CowStr<'a>, usize>,
+}
+
+impl<'a> Writer<'a, '_> {
+    fn event(&mut self, event: Event<'a>) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text)
+            | Event::Code(text)
+            | Event::InlineMath(text)
+            | Event::DisplayMath(text) => match self.code.as_mut() {
+                Some(code) => code.push_str(&text),
+                None if self.hidden > 0 => {}
+                None => self.write(&text),
+            },
+            Event::SoftBreak => self.write(" "),
+            Event::HardBreak => self.pending = self.pending.max(1),
+            Event::FootnoteReference(name) => {
+                let number = self.number(name);
+                self.write(&format!("[{}]", number));
+            }
+            Event::TaskListMarker(true) => self.write("[x] "),
+            Event::TaskListMarker(false) => self.write("[ ] "),
+            Event::Html(_) | Event::InlineHtml(_) | Event::Rule => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'a>) {
+        match tag {
+            Tag::Paragraph if self.item_start => {}
+            Tag::Paragraph
+            | Tag::Heading { .. }
+            | Tag::BlockQuote(_)
+            | Tag::HtmlBlock
+            | Tag::Table(_)
+            | Tag::DefinitionList => self.block(),
+            Tag::CodeBlock(_) => {
+                self.block();
+                self.code = Some(String::new());
+            }
+            Tag::List(start) => {
+                self.block();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.line();
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.write(&bullet);
+                self.nest(bullet.chars().count());
+                self.item_start = true;
+            }
+            Tag::FootnoteDefinition(name) => {
+                self.block();
+                let label = format!("[{}] ", self.number(name));
+                self.write(&label);
+                self.nest(label.chars().count());
+                self.item_start = true;
+            }
+            Tag::DefinitionListTitle => self.line(),
+            Tag::DefinitionListDefinition => {
+                self.line();
+                self.nest(2);
+            }
+            Tag::TableHead | Tag::TableRow => {
+                self.line();
+                self.first_cell = true;
+            }
+            Tag::TableCell => {
+                if !self.first_cell {
+                    self.write("\t");
+                }
+                self.first_cell = false;
+            }
+            Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => {
+                self.links.push((self.out.len(), dest_url));
+            }
+            Tag::MetadataBlock(_) => self.hidden += 1,
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::CodeBlock => {
+                let code = self.code.take().unwrap_or_default();
+                for (index, line) in code.trim_end_matches('\n').split('\n').enumerate() {
+                    if index > 0 {
+                        self.line();
+                    }
+                    self.write(line);
+                }
+            }
+            TagEnd::List(_) => {
+                self.lists.pop();
+            }
+            TagEnd::Item | TagEnd::FootnoteDefinition | TagEnd::DefinitionListDefinition => {
+                let indent = self.indents.pop().unwrap_or(0);
+                self.indent.truncate(indent);
+                self.item_start = false;
+            }
+            TagEnd::Link | TagEnd::Image => {
+                let Some((start, url)) = self.links.pop() else {
+                    return;
+                };
+                let text = self.out[start..].trim();
+                if self.options.link_urls && needs_url(text, &url) {
+                    match text.is_empty() {
+                        true => self.write(&url),
+                        false => self.write(&format!(" ({})", url)),
+                    }
+                }
+            }
+            TagEnd::MetadataBlock(_) => self.hidden -= 1,
+            _ => {}
+        }
+    }
+
+    /// Starts a block: a blank line before it at the top level, a line break
+    /// inside lists, where blocks are kept together.
+    fn block(&mut self) {
+        let breaks = if self.indents.is_empty() { 2 } else { 1 };
+        if !self.out.is_empty() {
+            self.pending = self.pending.max(breaks);
+        }
+        self.item_start = false;
+    }
+
+    fn line(&mut self) {
+        if !self.out.is_empty() {
+            self.pending = self.pending.max(1);
+        }
+        self.item_start = false;
+    }
+
+    /// Indents the lines after the current one by `width` more columns.
+    fn nest(&mut self, width: usize) {
+        self.indents.push(self.indent.len());
+        self.indent.push_str(&" ".repeat(width));
+    }
+
+    fn write(&mut self, text: &str) {
+        if self.pending > 0 {
+            self.out.push_str(&"\n".repeat(self.pending));
+            self.out.push_str(&self.indent);
+            self.pending = 0;
+        }
+        self.out.push_str(text);
+        self.item_start = false;
+    }
+
+    /// The number of a footnote, counting from 1 in order of first mention
+    /// like the HTML renderer does.
+    fn number(&mut self, name: CowStr<'a>) -> usize {
+        let len = self.numbers.len() + 1;
+        *self.numbers.entry(name).or_insert(len)
+    }
+}
+
+/// Whether a link to `url` with `text` should be followed by its URL: not
+/// for links within the document, or autolinks that already show it,
+/// maybe without the scheme.
+pub(crate) fn needs_url(text: &str, url: &str) -> bool {
+    let schemes = ["mailto:", "http://", "https://"];
+    let shown = text == url
+        || schemes
+            .iter()
+            .any(|scheme| url.strip_prefix(scheme) == Some(text));
+    !url.is_empty() && !url.starts_with('#') && !shown
+}
+
+/// Cuts `text` to at most `max` characters, ellipsis included, at the last
+/// word boundary that fits. A single word longer than that is cut inside.
+fn truncate(text: &str, max: usize) -> String {
+    if max == 0 || text.chars().count() <= max {
+        return text.to_string();
+    }
+    let end = text
+        .char_indices()
+        .nth(max - 1)
+        .map_or(text.len(), |(index, _)| index);
+    let at_boundary = text[end..].starts_with(char::is_whitespace);
+    let cut = match at_boundary {
+        true => end,
+        false => text[..end].rfind(char::is_whitespace).unwrap_or(end),
+    };
+    let kept = match text[..cut].trim_end() {
+        "" => &text[..end],
+        kept => kept,
+    };
+    format!("{}…", kept)
+}
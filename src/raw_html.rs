@@ -0,0 +1,49 @@
+use pulldown_cmark::{Event, Tag, TagEnd};
+
+use crate::options::HtmlPolicy;
+use crate::render::Spanned;
+
+/// Applies `policy` to the raw HTML in an event stream. Escaped HTML blocks
+/// become paragraphs of literal text, inline HTML becomes text in place.
+pub(crate) fn apply(events: Vec<Spanned<'_>>, policy: HtmlPolicy) -> Vec<Spanned<'_>> {
+    match policy {
+        HtmlPolicy::Pass => events,
+        HtmlPolicy::Strip => events
+            .into_iter()
+            .filter(|(event, _)| {
+                !matches!(
+                    event,
+                    Event::Html(_)
+                        | Event::InlineHtml(_)
+                        | Event::Start(Tag::HtmlBlock)
+                        | Event::End(TagEnd::HtmlBlock)
+                )
+            })
+            .collect(),
+        HtmlPolicy::Escape => escape(events),
+    }
+}
+
+fn escape(events: Vec<Spanned<'_>>) -> Vec<Spanned<'_>> {
+    let mut out = Vec::with_capacity(events.len());
+    let mut block: Option<String> = None;
+    for (event, range) in events {
+        match event {
+            Event::Start(Tag::HtmlBlock) => block = Some(String::new()),
+            Event::End(TagEnd::HtmlBlock) => {
+                let text = block.take().unwrap_or_default();
+                out.push((Event::Start(Tag::Paragraph), range.clone()));
+                let text = text.trim_end_matches('\n').to_string();
+                out.push((Event::Text(text.into()), range.clone()));
+                out.push((Event::End(TagEnd::Paragraph), range));
+            }
+            Event::Html(html) => match block.as_mut() {
+                Some(text) => text.push_str(&html),
+                None => out.push((Event::Text(html), range)),
+            },
+            Event::InlineHtml(html) => out.push((Event::Text(html), range)),
+            event => out.push((event, range)),
+        }
+    }
+    out
+}
@@ -0,0 +1,108 @@
+use std::ops::Range;
+
+use pulldown_cmark::{BrokenLink, Event, LinkType, Parser};
+
+use crate::front_matter;
+use crate::gfm;
+use crate::headings;
+use crate::highlight;
+use crate::html_writer;
+use crate::math;
+use crate::options::MarkdownOptions;
+use crate::raw_html;
+use crate::sanitize;
+use crate::source_map::SourceIndex;
+use crate::toc;
+use crate::xml;
+
+/// An event and the byte range of the source it was parsed from.
+pub(crate) type Spanned<'a> = (Event<'a>, Range<usize>);
+
+/// Parses `input` with the extensions selected in `options`. With
+/// `front_matter`, a leading front matter block is skipped; ranges still
+/// refer to `input`.
+pub(crate) fn parse<'a>(input: &'a str, options: &MarkdownOptions) -> Vec<Spanned<'a>> {
+    parse_checked(input, options).0
+}
+
+/// Like `parse`, also returning the range and label of every full or
+/// collapsed reference link (`[text][label]`, `[label][]`) whose label has no
+/// definition. Those render as plain text.
+pub(crate) fn parse_checked<'a>(
+    input: &'a str,
+    options: &MarkdownOptions,
+) -> (Vec<Spanned<'a>>, Vec<(Range<usize>, String)>) {
+    let offset = match options.front_matter {
+        true => front_matter::block_len(input),
+        false => 0,
+    };
+    let mut broken = Vec::new();
+    let callback = |link: BrokenLink| {
+        if matches!(link.link_type, LinkType::Reference | LinkType::Collapsed) {
+            let range = link.span.start + offset..link.span.end + offset;
+            broken.push((range, link.reference.to_string()));
+        }
+        None
+    };
+    let events =
+        Parser::new_with_broken_link_callback(&input[offset..], options.into(), Some(callback))
+            .into_offset_iter()
+            .map(|(event, range)| (event, range.start + offset..range.end + offset))
+            .collect();
+    (events, broken)
+}
+
+/// Parses `input` with the extensions selected in `options` and renders it
+/// to an HTML fragment, applying the render-time settings on the way.
+pub(crate) fn render_html(input: &str, options: &MarkdownOptions) -> String {
+    let source = options.source_positions.then(|| SourceIndex::new(input));
+    render_events(parse(input, options), options, source.as_ref())
+}
+
+/// Renders an already parsed event stream with the render-time settings in
+/// `options`. Source positions are only written when `source` is given.
+pub(crate) fn render_events(
+    events: Vec<Spanned<'_>>,
+    options: &MarkdownOptions,
+    source: Option<&SourceIndex>,
+) -> String {
+    let mut events = events;
+    if options.heading_ids || options.heading_anchors || options.toc {
+        events = headings::assign_ids(events);
+    }
+    if options.toc {
+        events = toc::insert(events, options);
+    }
+    events = raw_html::apply(events, options.html);
+    if options.autolinks {
+        events = gfm::autolink(events);
+    }
+    if options.tagfilter {
+        gfm::tagfilter(&mut events);
+    }
+    if options.mathml {
+        events = math::apply(events);
+    }
+    if options.highlight {
+        events = highlight::apply(events, options.highlight_style);
+    }
+    if options.heading_anchors {
+        events = headings::insert_anchors(events);
+    }
+    if options.xhtml {
+        events = xml::apply(events);
+    }
+
+    let mut html_output = String::new();
+    html_writer::push_html(&mut html_output, events.into_iter(), source);
+    if options.sanitize {
+        html_output = match &options.sanitizer_policy {
+            Some(policy) => policy.clean(&html_output),
+            None => sanitize::clean(&html_output),
+        };
+    }
+    if options.xhtml {
+        html_output = xml::xhtml(&html_output);
+    }
+    html_output
+}
@@ -0,0 +1,334 @@
+use pulldown_cmark::{Event, Tag, TagEnd};
+use serde::Serialize;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::ast::{link_type_name, Position};
+use crate::front_matter::{self, FrontMatter};
+use crate::gfm;
+use crate::headings;
+use crate::math;
+use crate::options::MarkdownOptions;
+use crate::render::{self, Spanned};
+use crate::source_map::SourceIndex;
+use crate::toc::{self, TocEntry};
+
+#[wasm_bindgen(typescript_custom_section)]
+const RESULT_SCHEMA: &'static str = r#"
+/** A link or image in the document, as listed by `RenderResult.links`. */
+export interface RenderLink {
+  url: string;
+  title?: string;
+  /** The link text, or an image's alt text. */
+  text: string;
+  image: boolean;
+  linkType: "inline" | "reference" | "collapsed" | "shortcut" | "autolink" | "email" | "wikilink";
+  position: MarkdownPosition;
+}
+
+/** A problem found while rendering, listed by `RenderResult.diagnostics`. */
+export interface RenderDiagnostic {
+  severity: "error" | "warning";
+  message: string;
+  position: MarkdownPosition;
+}
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub position: Position,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Link {
+    pub url: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    pub text: String,
+    pub image: bool,
+    pub link_type: &'static str,
+    pub position: Position,
+}
+
+/// How long each stage of `render` took, in milliseconds.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderTiming {
+    /// Front matter extraction and Markdown parsing.
+    pub parse_ms: f64,
+    /// Collecting headings, links and diagnostics.
+    pub analyze_ms: f64,
+    /// Writing (and sanitizing) the HTML.
+    pub render_ms: f64,
+    pub total_ms: f64,
+}
+
+/// Everything `render` learns about a document besides its HTML.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct RenderResult {
+    #[wasm_bindgen(getter_with_clone)]
+    pub html: String,
+    /// The front matter `title`, else the text of the first level 1 heading.
+    #[wasm_bindgen(getter_with_clone)]
+    pub title: Option<String>,
+    pub timing: RenderTiming,
+    headings: Vec<TocEntry>,
+    links: Vec<Link>,
+    diagnostics: Vec<Diagnostic>,
+    front_matter: Option<FrontMatter>,
+}
+
+#[wasm_bindgen]
+impl RenderResult {
+    /// The nested heading outline, as returned by `heading_outline`. Slugs
+    /// are empty unless the options give headings ids.
+    #[wasm_bindgen(getter, unchecked_return_type = "TocEntry[]")]
+    pub fn headings(&self) -> Result<JsValue, JsValue> {
+        serialize(&self.headings)
+    }
+
+    /// Links and images in document order.
+    #[wasm_bindgen(getter, unchecked_return_type = "RenderLink[]")]
+    pub fn links(&self) -> Result<JsValue, JsValue> {
+        serialize(&self.links)
+    }
+
+    /// Invalid front matter, undefined references and untranslatable math.
+    #[wasm_bindgen(getter, unchecked_return_type = "RenderDiagnostic[]")]
+    pub fn diagnostics(&self) -> Result<JsValue, JsValue> {
+        serialize(&self.diagnostics)
+    }
+
+    /// The parsed front matter; undefined when `front_matter` is off, there
+    /// is none, or it is invalid.
+    #[wasm_bindgen(getter, unchecked_return_type = "unknown")]
+    pub fn front_matter(&self) -> Result<JsValue, JsValue> {
+        match self
+            .front_matter
+            .as_ref()
+            .map(|front_matter| &front_matter.value)
+        {
+            Some(Ok(value)) => serialize(value),
+            _ => Ok(JsValue::UNDEFINED),
+        }
+    }
+
+    #[wasm_bindgen(getter, unchecked_return_type = "\"yaml\" | \"toml\" | undefined")]
+    pub fn front_matter_format(&self) -> JsValue {
+        match self
+            .front_matter
+            .as_ref()
+            .map(|front_matter| front_matter.format)
+        {
+            Some(front_matter::Format::Yaml) => "yaml".into(),
+            Some(front_matter::Format::Toml) => "toml".into(),
+            None => JsValue::UNDEFINED,
+        }
+    }
+
+    /// Everything but the timing as a JSON string with camelCase keys, for
+    /// callers that cannot use the getters.
+    pub fn json(&self) -> String {
+        let front_matter = self.front_matter.as_ref();
+        let json = Json {
+            html: &self.html,
+            title: self.title.as_deref(),
+            headings: &self.headings,
+            links: &self.links,
+            diagnostics: &self.diagnostics,
+            front_matter: front_matter.and_then(|front_matter| front_matter.value.as_ref().ok()),
+            front_matter_format: front_matter.map(|front_matter| front_matter.format),
+        };
+        serde_json::to_string(&json).expect("the result serializes to JSON")
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Json<'a> {
+    html: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+    headings: &'a [TocEntry],
+    links: &'a [Link],
+    diagnostics: &'a [Diagnostic],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    front_matter: Option<&'a Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    front_matter_format: Option<front_matter::Format>,
+}
+
+fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, JsValue> {
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(value.serialize(&serializer)?)
+}
+
+/// Renders `input` like `render::render_html`, collecting the document's
+/// metadata on the way.
+pub(crate) fn render(input: &str, options: &MarkdownOptions) -> RenderResult {
+    let start = now();
+    let index = SourceIndex::new(input);
+    let front_matter = options
+        .front_matter
+        .then(|| front_matter::extract(input))
+        .flatten();
+    let (events, broken_links) = render::parse_checked(input, options);
+    let parsed = now();
+
+    // Analyze a copy with the ids and autolinks the renderer will add, so
+    // the outline and link list match the HTML.
+    let mut analyzed = events.clone();
+    if options.heading_ids || options.heading_anchors || options.toc {
+        analyzed = headings::assign_ids(analyzed);
+    }
+    if options.autolinks {
+        analyzed = gfm::autolink(analyzed);
+    }
+    let headings = toc::outline_of(&analyzed, &index);
+    let links = links(&analyzed, &index);
+
+    let mut diagnostics = Vec::new();
+    if let Some(Err(error)) = front_matter
+        .as_ref()
+        .map(|front_matter| &front_matter.value)
+    {
+        let offset = index.offset(error.line, error.column);
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            message: format!("invalid front matter: {}", error.message),
+            position: Position::of(&index, offset..offset),
+        });
+    }
+    for (range, label) in broken_links {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            message: format!("undefined link reference [{}]", label),
+            position: Position::of(&index, range),
+        });
+    }
+    if options.mathml {
+        for (event, range) in &analyzed {
+            let error = match event {
+                Event::InlineMath(tex) => math::check(tex, false),
+                Event::DisplayMath(tex) => math::check(tex, true),
+                _ => None,
+            };
+            if let Some(message) = error {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    message: format!("cannot render math: {}", message),
+                    position: Position::of(&index, range.clone()),
+                });
+            }
+        }
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.position.start);
+
+    let title = front_matter
+        .as_ref()
+        .and_then(|front_matter| front_matter.value.as_ref().ok())
+        .and_then(|value| value.get("title"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .or_else(|| {
+            let heading = headings.iter().find(|entry| entry.level == 1)?;
+            Some(heading.text.clone())
+        });
+    let analyzed_at = now();
+
+    let source = options.source_positions.then_some(&index);
+    let html = render::render_events(events, options, source);
+    let end = now();
+
+    RenderResult {
+        html,
+        title,
+        timing: RenderTiming {
+            parse_ms: parsed - start,
+            analyze_ms: analyzed_at - parsed,
+            render_ms: end - analyzed_at,
+            total_ms: end - start,
+        },
+        headings,
+        links,
+        diagnostics,
+        front_matter,
+    }
+}
+
+/// Every link and image, with its plain text content.
+fn links(events: &[Spanned], index: &SourceIndex) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+    for (event, range) in events {
+        match event {
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                ..
+            })
+            | Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                ..
+            }) => {
+                open.push(links.len());
+                links.push(Link {
+                    url: dest_url.to_string(),
+                    title: title.to_string(),
+                    text: String::new(),
+                    image: matches!(event, Event::Start(Tag::Image { .. })),
+                    link_type: link_type_name(*link_type),
+                    position: Position::of(index, range.clone()),
+                });
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                open.pop();
+            }
+            Event::Text(text) | Event::Code(text) | Event::InlineMath(text) => {
+                for &link in &open {
+                    links[link].text.push_str(text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                for &link in &open {
+                    links[link].text.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    links
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now() -> f64 {
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = performance, js_name = now)]
+        fn performance_now() -> f64;
+    }
+    performance_now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static ORIGIN: OnceLock<Instant> = OnceLock::new();
+    ORIGIN.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
@@ -0,0 +1,235 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use wasm_bindgen::prelude::*;
+
+/// The MathML elements and attributes `math::to_mathml` produces.
+const MATHML: &[(&str, &[&str])] = &[
+    ("math", &["display"]),
+    ("semantics", &[]),
+    ("annotation", &["encoding"]),
+    ("mrow", &[]),
+    ("mi", &["mathvariant"]),
+    ("mn", &["mathvariant"]),
+    (
+        "mo",
+        &[
+            "fence", "stretchy", "minsize", "maxsize", "lspace", "rspace",
+        ],
+    ),
+    ("mtext", &["mathvariant"]),
+    ("mspace", &["width"]),
+    ("msub", &[]),
+    ("msup", &[]),
+    ("msubsup", &[]),
+    ("munder", &["accentunder"]),
+    ("mover", &["accent"]),
+    ("munderover", &[]),
+    ("mfrac", &["linethickness"]),
+    ("msqrt", &[]),
+    ("mroot", &[]),
+    ("mstyle", &["displaystyle"]),
+    ("mtable", &["columnalign", "columnspacing", "displaystyle"]),
+    ("mtr", &[]),
+    ("mtd", &[]),
+    ("mark", &[]),
+];
+
+/// Tags, attributes and URL schemes allowed to survive sanitization.
+///
+/// The default policy is ammonia's, widened just enough for everything the
+/// Markdown renderer itself produces: task list checkboxes, code language
+/// classes, alert and footnote classes, heading ids, table alignment,
+/// source position attributes and the MathML written for `mathml`. Ids
+/// are prefixed with `user-content-`, as on GitHub, so a document cannot
+/// clobber the page's globals with `id="location"` and the like.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SanitizerPolicy {
+    tags: BTreeSet<String>,
+    tag_attributes: BTreeMap<String, BTreeSet<String>>,
+    generic_attributes: BTreeSet<String>,
+    url_schemes: BTreeSet<String>,
+    strip_comments: bool,
+    id_prefix: String,
+}
+
+impl Default for SanitizerPolicy {
+    fn default() -> Self {
+        let defaults = ammonia::Builder::default();
+        let mut policy = SanitizerPolicy {
+            tags: defaults
+                .clone_tags()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            tag_attributes: defaults
+                .clone_tag_attributes()
+                .into_iter()
+                .map(|(tag, attributes)| {
+                    (
+                        tag.to_string(),
+                        attributes.into_iter().map(String::from).collect(),
+                    )
+                })
+                .collect(),
+            generic_attributes: defaults
+                .clone_generic_attributes()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            url_schemes: defaults
+                .clone_url_schemes()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            strip_comments: true,
+            id_prefix: "user-content-".to_string(),
+        };
+        policy.allow_tag("input");
+        policy.allow_attribute("a", "aria-hidden");
+        for attribute in ["checked", "disabled"] {
+            policy.allow_attribute("input", attribute);
+        }
+        for tag in ["th", "td"] {
+            policy.allow_attribute(tag, "style");
+        }
+        for tag in ["h1", "h2", "h3", "h4", "h5", "h6", "div"] {
+            policy.allow_attribute(tag, "id");
+        }
+        for (tag, attributes) in MATHML {
+            policy.allow_tag(tag);
+            for attribute in *attributes {
+                policy.allow_attribute(tag, attribute);
+            }
+        }
+        for attribute in [
+            "class",
+            "data-source-start",
+            "data-source-end",
+            "data-source-line",
+        ] {
+            policy.allow_generic_attribute(attribute);
+        }
+        policy
+    }
+}
+
+#[wasm_bindgen]
+impl SanitizerPolicy {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SanitizerPolicy {
+        SanitizerPolicy::default()
+    }
+
+    pub fn allow_tag(&mut self, tag: &str) {
+        self.tags.insert(tag.to_ascii_lowercase());
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.tags.remove(&tag.to_ascii_lowercase());
+    }
+
+    pub fn allow_attribute(&mut self, tag: &str, attribute: &str) {
+        self.tag_attributes
+            .entry(tag.to_ascii_lowercase())
+            .or_default()
+            .insert(attribute.to_ascii_lowercase());
+    }
+
+    pub fn remove_attribute(&mut self, tag: &str, attribute: &str) {
+        if let Some(attributes) = self.tag_attributes.get_mut(&tag.to_ascii_lowercase()) {
+            attributes.remove(&attribute.to_ascii_lowercase());
+        }
+    }
+
+    /// Allows `attribute` on every allowed tag.
+    pub fn allow_generic_attribute(&mut self, attribute: &str) {
+        self.generic_attributes
+            .insert(attribute.to_ascii_lowercase());
+    }
+
+    pub fn remove_generic_attribute(&mut self, attribute: &str) {
+        self.generic_attributes
+            .remove(&attribute.to_ascii_lowercase());
+    }
+
+    pub fn allow_url_scheme(&mut self, scheme: &str) {
+        self.url_schemes.insert(scheme.to_ascii_lowercase());
+    }
+
+    pub fn remove_url_scheme(&mut self, scheme: &str) {
+        self.url_schemes.remove(&scheme.to_ascii_lowercase());
+    }
+
+    pub fn set_strip_comments(&mut self, strip: bool) {
+        self.strip_comments = strip;
+    }
+
+    /// Sets the prefix added to every `id`, and to the `#` links pointing at
+    /// them so they keep working. An empty prefix keeps ids as written.
+    pub fn set_id_prefix(&mut self, prefix: &str) {
+        self.id_prefix = prefix.to_string();
+    }
+
+    /// Sanitizes an HTML fragment according to this policy.
+    pub fn clean(&self, html: &str) -> String {
+        let tags: HashSet<&str> = self.tags.iter().map(String::as_str).collect();
+        let tag_attributes: HashMap<&str, HashSet<&str>> = self
+            .tag_attributes
+            .iter()
+            .map(|(tag, attributes)| {
+                (
+                    tag.as_str(),
+                    attributes.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        let clean_content_tags: HashSet<&str> = ["script", "style"]
+            .into_iter()
+            .filter(|tag| !tags.contains(tag) && !tag_attributes.contains_key(tag))
+            .collect();
+        // ammonia refuses a caller-controlled `rel` while it sets its own.
+        let rel_allowed = self.generic_attributes.contains("rel")
+            || tag_attributes
+                .get("a")
+                .is_some_and(|attributes| attributes.contains("rel"));
+        let link_rel = (!rel_allowed).then_some("noopener noreferrer");
+        let id_prefix = self.id_prefix.clone();
+
+        ammonia::Builder::default()
+            .tags(tags)
+            .clean_content_tags(clean_content_tags)
+            .tag_attributes(tag_attributes)
+            .add_tag_attribute_values("input", "type", &["checkbox"])
+            .generic_attributes(self.generic_attributes.iter().map(String::as_str).collect())
+            .url_schemes(self.url_schemes.iter().map(String::as_str).collect())
+            // Table alignment and the inline highlighting palette.
+            .filter_style_properties(HashSet::from([
+                "text-align",
+                "color",
+                "font-style",
+                "font-weight",
+            ]))
+            .link_rel(link_rel)
+            .strip_comments(self.strip_comments)
+            .id_prefix(Some(self.id_prefix.as_str()).filter(|prefix| !prefix.is_empty()))
+            .attribute_filter(move |_, attribute, value| match value.strip_prefix('#') {
+                Some(id)
+                    if attribute == "href"
+                        && !id_prefix.is_empty()
+                        && !id.starts_with(&id_prefix) =>
+                {
+                    Some(Cow::Owned(format!("#{}{}", id_prefix, id)))
+                }
+                _ => Some(Cow::Borrowed(value)),
+            })
+            .clean(html)
+            .to_string()
+    }
+}
+
+/// Sanitizes `html` with the default policy.
+pub(crate) fn clean(html: &str) -> String {
+    SanitizerPolicy::default().clean(html)
+}
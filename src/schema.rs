@@ -0,0 +1,749 @@
+use std::collections::HashMap;
+
+use regex_lite::Regex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::front_matter::{self, pointer_child};
+
+#[wasm_bindgen(typescript_custom_section)]
+const SCHEMA_SCHEMA: &'static str = r#"
+/** A front matter problem found by `FrontMatterSchema.validate`. */
+export interface FrontMatterDiagnostic {
+  message: string;
+  /** The schema keyword that failed, or "parse" for unparseable front matter. */
+  keyword: string;
+  /** JSON pointer to the offending front matter value. */
+  instancePath: string;
+  /** JSON pointer to the failing keyword, e.g. `#/properties/date/format`. */
+  schemaPath: string;
+  /** The 1-based document line of the offending value. */
+  line: number;
+}
+"#;
+
+/// Nesting limit for subschemas and `$ref` chains.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Diagnostic {
+    pub message: String,
+    pub keyword: String,
+    pub instance_path: String,
+    pub schema_path: String,
+    pub line: usize,
+}
+
+/// A JSON Schema for front matter.
+///
+/// Supports the draft 2020-12 validation vocabulary (types, `enum`,
+/// `const`, numeric and string bounds, `pattern`, array and object
+/// keywords), the applicators `allOf`, `anyOf`, `oneOf`, `not` and
+/// `if`/`then`/`else`, `$ref` to `#`-pointers such as `#/$defs/tag`, and
+/// the `date`, `date-time`, `time`, `email`, `uri`, `uri-reference` and
+/// `uuid` formats. Other formats and unknown keywords are ignored.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct FrontMatterSchema {
+    schema: Value,
+}
+
+#[wasm_bindgen]
+impl FrontMatterSchema {
+    #[wasm_bindgen(constructor)]
+    pub fn new(schema: JsValue) -> Result<FrontMatterSchema, JsValue> {
+        let schema: Value = serde_wasm_bindgen::from_value(schema)?;
+        FrontMatterSchema::from_value(schema).map_err(|message| JsValue::from_str(&message))
+    }
+
+    /// Validates the front matter of `input`. A document without front
+    /// matter is validated as an empty object.
+    #[wasm_bindgen(unchecked_return_type = "FrontMatterDiagnostic[]")]
+    pub fn validate(&self, input: &str) -> Result<JsValue, JsValue> {
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        Ok(self.diagnostics(input).serialize(&serializer)?)
+    }
+
+    /// Same as the constructor, with the schema given as a JSON string.
+    pub fn from_json(schema: &str) -> Result<FrontMatterSchema, String> {
+        let schema: Value = serde_json::from_str(schema).map_err(|error| error.to_string())?;
+        FrontMatterSchema::from_value(schema)
+    }
+
+    /// Same diagnostics as `validate`, serialized as a JSON string.
+    pub fn validate_json(&self, input: &str) -> String {
+        serde_json::to_string(&self.diagnostics(input)).expect("diagnostics serialize to JSON")
+    }
+}
+
+impl FrontMatterSchema {
+    pub(crate) fn from_value(schema: Value) -> Result<FrontMatterSchema, String> {
+        match schema {
+            Value::Object(_) | Value::Bool(_) => Ok(FrontMatterSchema { schema }),
+            _ => Err("a JSON Schema must be an object or a boolean".to_string()),
+        }
+    }
+
+    pub(crate) fn diagnostics(&self, input: &str) -> Vec<Diagnostic> {
+        let (value, lines) = match front_matter::extract(input) {
+            Some(front_matter) => match front_matter.value {
+                Ok(value) => (value, front_matter.lines),
+                Err(error) => {
+                    return vec![Diagnostic {
+                        message: error.message,
+                        keyword: "parse".to_string(),
+                        instance_path: String::new(),
+                        schema_path: String::new(),
+                        line: error.line,
+                    }];
+                }
+            },
+            None => (Value::Object(Map::new()), HashMap::new()),
+        };
+        let mut validator = Validator {
+            root: &self.schema,
+            lines: &lines,
+            patterns: HashMap::new(),
+            diagnostics: Vec::new(),
+        };
+        validator.validate(&self.schema, "#", &value, "", 0);
+        let mut diagnostics = validator.diagnostics;
+        diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+        diagnostics
+    }
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    lines: &'a HashMap<String, usize>,
+    patterns: HashMap<String, Option<Regex>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+    /// Records a diagnostic for every keyword of `schema` that `instance`
+    /// fails and returns whether there were none.
+    fn validate(
+        &mut self,
+        schema: &'a Value,
+        path: &str,
+        instance: &Value,
+        pointer: &str,
+        depth: usize,
+    ) -> bool {
+        let start = self.diagnostics.len();
+        let schema = match schema {
+            Value::Bool(true) => return true,
+            Value::Bool(false) => {
+                self.error(
+                    "false schema",
+                    path,
+                    pointer,
+                    "boolean schema is false".to_string(),
+                );
+                return false;
+            }
+            Value::Object(schema) => schema,
+            _ => return true,
+        };
+        if depth > MAX_DEPTH {
+            self.error(
+                "$ref",
+                path,
+                pointer,
+                "schema is nested too deeply".to_string(),
+            );
+            return false;
+        }
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let path = format!("{}/$ref", path);
+            match reference
+                .strip_prefix('#')
+                .and_then(|target| self.root.pointer(target))
+            {
+                Some(target) => {
+                    self.validate(target, reference, instance, pointer, depth + 1);
+                }
+                None => {
+                    let message = format!("can't resolve reference {}", reference);
+                    self.error("$ref", &path, pointer, message);
+                }
+            }
+        }
+        self.validate_any(schema, path, instance, pointer);
+        self.validate_applicators(schema, path, instance, pointer, depth);
+        match instance {
+            Value::Number(_) => self.validate_number(schema, path, instance, pointer),
+            Value::String(string) => self.validate_string(schema, path, string, pointer),
+            Value::Array(items) => self.validate_array(schema, path, items, pointer, depth),
+            Value::Object(members) => self.validate_object(schema, path, members, pointer, depth),
+            _ => {}
+        }
+        self.diagnostics.len() == start
+    }
+
+    /// Whether `instance` satisfies `schema`, without recording diagnostics.
+    fn check(&mut self, schema: &'a Value, path: &str, instance: &Value, depth: usize) -> bool {
+        let start = self.diagnostics.len();
+        let valid = self.validate(schema, path, instance, "", depth);
+        self.diagnostics.truncate(start);
+        valid
+    }
+
+    fn validate_any(
+        &mut self,
+        schema: &Map<String, Value>,
+        path: &str,
+        instance: &Value,
+        pointer: &str,
+    ) {
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(name) => vec![name],
+                Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.is_empty() && !types.iter().any(|name| has_type(instance, name)) {
+                let message = format!("must be {}", types.join(","));
+                self.error("type", &format!("{}/type", path), pointer, message);
+            }
+        }
+        if let Some(Value::Array(allowed)) = schema.get("enum") {
+            if !allowed.iter().any(|value| equal(value, instance)) {
+                let message = "must be equal to one of the allowed values".to_string();
+                self.error("enum", &format!("{}/enum", path), pointer, message);
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if !equal(constant, instance) {
+                let message = "must be equal to constant".to_string();
+                self.error("const", &format!("{}/const", path), pointer, message);
+            }
+        }
+    }
+
+    fn validate_applicators(
+        &mut self,
+        schema: &'a Map<String, Value>,
+        path: &str,
+        instance: &Value,
+        pointer: &str,
+        depth: usize,
+    ) {
+        if let Some(Value::Array(schemas)) = schema.get("allOf") {
+            for (index, subschema) in schemas.iter().enumerate() {
+                let path = format!("{}/allOf/{}", path, index);
+                self.validate(subschema, &path, instance, pointer, depth + 1);
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+            let matched = schemas.iter().enumerate().any(|(index, subschema)| {
+                self.check(
+                    subschema,
+                    &format!("{}/anyOf/{}", path, index),
+                    instance,
+                    depth + 1,
+                )
+            });
+            if !matched {
+                let message = "must match a schema in anyOf".to_string();
+                self.error("anyOf", &format!("{}/anyOf", path), pointer, message);
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("oneOf") {
+            let matched = schemas
+                .iter()
+                .enumerate()
+                .filter(|(index, subschema)| {
+                    self.check(
+                        subschema,
+                        &format!("{}/oneOf/{}", path, index),
+                        instance,
+                        depth + 1,
+                    )
+                })
+                .count();
+            if matched != 1 {
+                let message = "must match exactly one schema in oneOf".to_string();
+                self.error("oneOf", &format!("{}/oneOf", path), pointer, message);
+            }
+        }
+        if let Some(subschema) = schema.get("not") {
+            let path = format!("{}/not", path);
+            if self.check(subschema, &path, instance, depth + 1) {
+                self.error("not", &path, pointer, "must NOT be valid".to_string());
+            }
+        }
+        if let Some(condition) = schema.get("if") {
+            let branch = match self.check(condition, &format!("{}/if", path), instance, depth + 1) {
+                true => "then",
+                false => "else",
+            };
+            if let Some(subschema) = schema.get(branch) {
+                let path = format!("{}/{}", path, branch);
+                self.validate(subschema, &path, instance, pointer, depth + 1);
+            }
+        }
+    }
+
+    fn validate_number(
+        &mut self,
+        schema: &Map<String, Value>,
+        path: &str,
+        instance: &Value,
+        pointer: &str,
+    ) {
+        let number = instance.as_f64().unwrap_or_default();
+        for (keyword, comparison) in [
+            ("minimum", ">="),
+            ("maximum", "<="),
+            ("exclusiveMinimum", ">"),
+            ("exclusiveMaximum", "<"),
+        ] {
+            let Some(limit) = schema.get(keyword).filter(|limit| limit.is_number()) else {
+                continue;
+            };
+            let bound = limit.as_f64().unwrap_or_default();
+            let holds = match comparison {
+                ">=" => number >= bound,
+                "<=" => number <= bound,
+                ">" => number > bound,
+                _ => number < bound,
+            };
+            if !holds {
+                let message = format!("must be {} {}", comparison, limit);
+                self.error(keyword, &format!("{}/{}", path, keyword), pointer, message);
+            }
+        }
+        if let Some(divisor) = schema
+            .get("multipleOf")
+            .filter(|divisor| divisor.is_number())
+        {
+            let quotient = number / divisor.as_f64().unwrap_or_default();
+            if !quotient.is_finite() || (quotient - quotient.round()).abs() > 1e-9 {
+                let message = format!("must be multiple of {}", divisor);
+                self.error(
+                    "multipleOf",
+                    &format!("{}/multipleOf", path),
+                    pointer,
+                    message,
+                );
+            }
+        }
+    }
+
+    fn validate_string(
+        &mut self,
+        schema: &Map<String, Value>,
+        path: &str,
+        string: &str,
+        pointer: &str,
+    ) {
+        let length = string.chars().count() as u64;
+        if let Some(limit) = schema.get("minLength").and_then(Value::as_u64) {
+            if length < limit {
+                let message = format!("must NOT have fewer than {} characters", limit);
+                self.error(
+                    "minLength",
+                    &format!("{}/minLength", path),
+                    pointer,
+                    message,
+                );
+            }
+        }
+        if let Some(limit) = schema.get("maxLength").and_then(Value::as_u64) {
+            if length > limit {
+                let message = format!("must NOT have more than {} characters", limit);
+                self.error(
+                    "maxLength",
+                    &format!("{}/maxLength", path),
+                    pointer,
+                    message,
+                );
+            }
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            let path = format!("{}/pattern", path);
+            match self.pattern(pattern) {
+                Some(regex) if regex.is_match(string) => {}
+                Some(_) => {
+                    let message = format!("must match pattern \"{}\"", pattern);
+                    self.error("pattern", &path, pointer, message);
+                }
+                None => {
+                    let message = format!("invalid pattern \"{}\"", pattern);
+                    self.error("pattern", &path, pointer, message);
+                }
+            }
+        }
+        if let Some(format) = schema.get("format").and_then(Value::as_str) {
+            if !matches_format(format, string) {
+                let message = format!("must match format \"{}\"", format);
+                self.error("format", &format!("{}/format", path), pointer, message);
+            }
+        }
+    }
+
+    fn validate_array(
+        &mut self,
+        schema: &'a Map<String, Value>,
+        path: &str,
+        items: &[Value],
+        pointer: &str,
+        depth: usize,
+    ) {
+        let prefix = match schema.get("prefixItems") {
+            Some(Value::Array(schemas)) => schemas.as_slice(),
+            _ => &[],
+        };
+        for (index, item) in items.iter().enumerate() {
+            let item_pointer = pointer_child(pointer, &index.to_string());
+            if let Some(subschema) = prefix.get(index) {
+                let path = format!("{}/prefixItems/{}", path, index);
+                self.validate(subschema, &path, item, &item_pointer, depth + 1);
+            } else if let Some(subschema) = schema.get("items") {
+                let path = format!("{}/items", path);
+                self.validate(subschema, &path, item, &item_pointer, depth + 1);
+            }
+        }
+        if let Some(limit) = schema.get("minItems").and_then(Value::as_u64) {
+            if (items.len() as u64) < limit {
+                let message = format!("must NOT have fewer than {} items", limit);
+                self.error("minItems", &format!("{}/minItems", path), pointer, message);
+            }
+        }
+        if let Some(limit) = schema.get("maxItems").and_then(Value::as_u64) {
+            if items.len() as u64 > limit {
+                let message = format!("must NOT have more than {} items", limit);
+                self.error("maxItems", &format!("{}/maxItems", path), pointer, message);
+            }
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            let duplicate = (0..items.len()).find_map(|second| {
+                (0..second)
+                    .find(|&first| equal(&items[first], &items[second]))
+                    .map(|first| (first, second))
+            });
+            if let Some((first, second)) = duplicate {
+                let message = format!(
+                    "must NOT have duplicate items (items {} and {} are identical)",
+                    first, second
+                );
+                let item_pointer = pointer_child(pointer, &second.to_string());
+                self.error(
+                    "uniqueItems",
+                    &format!("{}/uniqueItems", path),
+                    &item_pointer,
+                    message,
+                );
+            }
+        }
+        if let Some(subschema) = schema.get("contains") {
+            let contains_path = format!("{}/contains", path);
+            let count = items
+                .iter()
+                .filter(|item| self.check(subschema, &contains_path, item, depth + 1))
+                .count() as u64;
+            let min = schema
+                .get("minContains")
+                .and_then(Value::as_u64)
+                .unwrap_or(1);
+            let max = schema.get("maxContains").and_then(Value::as_u64);
+            if count < min {
+                let message = format!("must contain at least {} valid item(s)", min);
+                self.error("contains", &contains_path, pointer, message);
+            }
+            if let Some(max) = max.filter(|&max| count > max) {
+                let message = format!("must contain at most {} valid item(s)", max);
+                self.error(
+                    "maxContains",
+                    &format!("{}/maxContains", path),
+                    pointer,
+                    message,
+                );
+            }
+        }
+    }
+
+    fn validate_object(
+        &mut self,
+        schema: &'a Map<String, Value>,
+        path: &str,
+        members: &Map<String, Value>,
+        pointer: &str,
+        depth: usize,
+    ) {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !members.contains_key(name) {
+                    let message = format!("must have required property '{}'", name);
+                    self.error("required", &format!("{}/required", path), pointer, message);
+                }
+            }
+        }
+        if let Some(Value::Object(dependencies)) = schema.get("dependentRequired") {
+            for (name, required) in dependencies {
+                if !members.contains_key(name) {
+                    continue;
+                }
+                for dependency in required
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                {
+                    if !members.contains_key(dependency) {
+                        let message = format!(
+                            "must have property '{}' when property '{}' is present",
+                            dependency, name
+                        );
+                        let path = format!("{}/dependentRequired", path);
+                        self.error("dependentRequired", &path, pointer, message);
+                    }
+                }
+            }
+        }
+        let count = members.len() as u64;
+        if let Some(limit) = schema.get("minProperties").and_then(Value::as_u64) {
+            if count < limit {
+                let message = format!("must NOT have fewer than {} properties", limit);
+                self.error(
+                    "minProperties",
+                    &format!("{}/minProperties", path),
+                    pointer,
+                    message,
+                );
+            }
+        }
+        if let Some(limit) = schema.get("maxProperties").and_then(Value::as_u64) {
+            if count > limit {
+                let message = format!("must NOT have more than {} properties", limit);
+                self.error(
+                    "maxProperties",
+                    &format!("{}/maxProperties", path),
+                    pointer,
+                    message,
+                );
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let pattern_properties = schema.get("patternProperties").and_then(Value::as_object);
+        for (name, value) in members {
+            let member = pointer_child(pointer, name);
+            let mut evaluated = false;
+            if let Some(subschema) = properties.and_then(|properties| properties.get(name)) {
+                evaluated = true;
+                let path = pointer_child(&format!("{}/properties", path), name);
+                self.validate(subschema, &path, value, &member, depth + 1);
+            }
+            for (pattern, subschema) in pattern_properties.into_iter().flatten() {
+                if self
+                    .pattern(pattern)
+                    .is_some_and(|regex| regex.is_match(name))
+                {
+                    evaluated = true;
+                    let path = pointer_child(&format!("{}/patternProperties", path), pattern);
+                    self.validate(subschema, &path, value, &member, depth + 1);
+                }
+            }
+            if let Some(subschema) = schema.get("propertyNames") {
+                let path = format!("{}/propertyNames", path);
+                self.validate(
+                    subschema,
+                    &path,
+                    &Value::String(name.clone()),
+                    &member,
+                    depth + 1,
+                );
+            }
+            if evaluated {
+                continue;
+            }
+            match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    let message = format!("must NOT have additional property '{}'", name);
+                    let path = format!("{}/additionalProperties", path);
+                    self.error("additionalProperties", &path, &member, message);
+                }
+                Some(subschema) => {
+                    let path = format!("{}/additionalProperties", path);
+                    self.validate(subschema, &path, value, &member, depth + 1);
+                }
+                None => {}
+            }
+        }
+    }
+
+    fn pattern(&mut self, pattern: &str) -> Option<&Regex> {
+        self.patterns
+            .entry(pattern.to_string())
+            .or_insert_with(|| Regex::new(pattern).ok())
+            .as_ref()
+    }
+
+    fn error(&mut self, keyword: &str, path: &str, pointer: &str, message: String) {
+        // Locate values without a line of their own, such as the target of a
+        // missing required property, at their closest located ancestor.
+        let mut located = pointer;
+        let line = loop {
+            if let Some(line) = self.lines.get(located) {
+                break *line;
+            }
+            match located.rfind('/') {
+                Some(index) => located = &located[..index],
+                None => break 1,
+            }
+        };
+        self.diagnostics.push(Diagnostic {
+            message,
+            keyword: keyword.to_string(),
+            instance_path: pointer.to_string(),
+            schema_path: path.to_string(),
+            line,
+        });
+    }
+}
+
+fn has_type(instance: &Value, name: &str) -> bool {
+    match name {
+        "null" => instance.is_null(),
+        "boolean" => instance.is_boolean(),
+        "number" => instance.is_number(),
+        "integer" => instance
+            .as_f64()
+            .is_some_and(|number| number.fract() == 0.0),
+        "string" => instance.is_string(),
+        "array" => instance.is_array(),
+        "object" => instance.is_object(),
+        _ => false,
+    }
+}
+
+/// JSON equality, with `1` and `1.0` equal.
+fn equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64() == right.as_f64(),
+        (Value::Array(left), Value::Array(right)) => {
+            left.len() == right.len()
+                && left
+                    .iter()
+                    .zip(right)
+                    .all(|(left, right)| equal(left, right))
+        }
+        (Value::Object(left), Value::Object(right)) => {
+            left.len() == right.len()
+                && left
+                    .iter()
+                    .all(|(key, value)| right.get(key).is_some_and(|other| equal(value, other)))
+        }
+        _ => left == right,
+    }
+}
+
+fn matches_format(format: &str, value: &str) -> bool {
+    match format {
+        "date" => is_date(value),
+        "time" => is_time(value),
+        "date-time" => value.split_at_checked(10).is_some_and(|(date, rest)| {
+            is_date(date) && rest.starts_with(['T', 't', ' ']) && is_time(&rest[1..])
+        }),
+        "email" => value.rsplit_once('@').is_some_and(|(local, domain)| {
+            !local.is_empty()
+                && !local.contains(char::is_whitespace)
+                && domain.split('.').count() > 1
+                && domain.split('.').all(|label| {
+                    !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+                })
+        }),
+        "uri" => value.split_once(':').is_some_and(|(scheme, _)| {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+                && is_uri_reference(value)
+        }),
+        "uri-reference" => is_uri_reference(value),
+        "uuid" => {
+            let groups: Vec<&str> = value.split('-').collect();
+            groups.iter().map(|group| group.len()).eq([8, 4, 4, 4, 12])
+                && groups
+                    .iter()
+                    .all(|group| group.chars().all(|c| c.is_ascii_hexdigit()))
+        }
+        _ => true,
+    }
+}
+
+/// An RFC 3339 `full-date`.
+fn is_date(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return false;
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return false;
+    }
+    let (Some(year), Some(month), Some(day)) = (digits(year), digits(month), digits(day)) else {
+        return false;
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+/// An RFC 3339 `full-time`: a `partial-time` and a `time-offset`.
+fn is_time(value: &str) -> bool {
+    let Some((time, zone)) = value.get(..8).zip(value.get(8..)) else {
+        return false;
+    };
+    let fields: Vec<Option<u32>> = time.split(':').map(digits).collect();
+    let [Some(hour), Some(minute), Some(second)] = fields[..] else {
+        return false;
+    };
+    if time.len() != 8 || hour > 23 || minute > 59 || second > 60 {
+        return false;
+    }
+    let zone = match zone.strip_prefix('.') {
+        Some(rest) => {
+            let fraction = rest.chars().take_while(char::is_ascii_digit).count();
+            if fraction == 0 {
+                return false;
+            }
+            &rest[fraction..]
+        }
+        None => zone,
+    };
+    match zone {
+        "Z" | "z" => true,
+        _ => {
+            zone.starts_with(['+', '-'])
+                && zone.len() == 6
+                && zone.as_bytes()[3] == b':'
+                && digits(&zone[1..3]).is_some_and(|hours| hours <= 23)
+                && digits(&zone[4..]).is_some_and(|minutes| minutes <= 59)
+        }
+    }
+}
+
+fn is_uri_reference(value: &str) -> bool {
+    !value
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control() || "<>\"{}|\\^`".contains(c))
+}
+
+fn digits(value: &str) -> Option<u32> {
+    match !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) {
+        true => value.parse().ok(),
+        false => None,
+    }
+}
@@ -0,0 +1,177 @@
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+use wasm_bindgen::prelude::*;
+
+use crate::options::MarkdownOptions;
+use crate::render;
+
+/// Converts byte offsets into the source text to the UTF-16 offsets and
+/// 1-based line numbers the editor works with.
+pub(crate) struct SourceIndex<'s> {
+    text: &'s str,
+    /// Byte offset and UTF-16 offset of the start of every line.
+    lines: Vec<(usize, usize)>,
+}
+
+impl<'s> SourceIndex<'s> {
+    pub(crate) fn new(text: &'s str) -> Self {
+        let mut lines = vec![(0, 0)];
+        let mut utf16 = 0;
+        for (index, c) in text.char_indices() {
+            utf16 += c.len_utf16();
+            if c == '\n' {
+                lines.push((index + 1, utf16));
+            }
+        }
+        SourceIndex { text, lines }
+    }
+
+    fn line_index(&self, offset: usize) -> usize {
+        self.lines.partition_point(|&(start, _)| start <= offset) - 1
+    }
+
+    /// The 1-based line containing byte `offset`.
+    pub(crate) fn line(&self, offset: usize) -> usize {
+        self.line_index(offset) + 1
+    }
+
+    /// The UTF-16 offset of byte `offset`.
+    pub(crate) fn utf16(&self, offset: usize) -> usize {
+        let offset = offset.min(self.text.len());
+        let (line_start, line_utf16) = self.lines[self.line_index(offset)];
+        line_utf16 + self.text[line_start..offset].encode_utf16().count()
+    }
+
+    /// The byte offset of 1-based `line` and `column`, counted in chars and
+    /// clamped to the line.
+    pub(crate) fn offset(&self, line: usize, column: usize) -> usize {
+        let Some(&(start, _)) = self.lines.get(line.saturating_sub(1)) else {
+            return self.text.len();
+        };
+        let text = &self.text[start..];
+        let line = &text[..text.find('\n').unwrap_or(text.len())];
+        start
+            + line
+                .char_indices()
+                .nth(column.saturating_sub(1))
+                .map_or(line.len(), |(index, _)| index)
+    }
+}
+
+/// The HTML element a block-level tag renders to, for the tags that get
+/// source position attributes.
+pub(crate) fn block_element(tag: &Tag) -> Option<&'static str> {
+    Some(match tag {
+        Tag::Paragraph => "p",
+        Tag::Heading { level, .. } => match *level as usize {
+            1 => "h1",
+            2 => "h2",
+            3 => "h3",
+            4 => "h4",
+            5 => "h5",
+            _ => "h6",
+        },
+        Tag::BlockQuote(_) => "blockquote",
+        Tag::CodeBlock(_) => "pre",
+        Tag::List(Some(_)) => "ol",
+        Tag::List(None) => "ul",
+        Tag::Item => "li",
+        Tag::FootnoteDefinition(_) => "div",
+        Tag::DefinitionList => "dl",
+        Tag::DefinitionListTitle => "dt",
+        Tag::DefinitionListDefinition => "dd",
+        Tag::Table(_) => "table",
+        Tag::TableHead | Tag::TableRow => "tr",
+        _ => return None,
+    })
+}
+
+/// A rendered block element and the Markdown it came from. Offsets are
+/// UTF-16 code units, lines are 1-based.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct SourceBlock {
+    #[wasm_bindgen(getter_with_clone)]
+    pub element: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub end_line: usize,
+    /// Nesting depth, 0 for top-level blocks.
+    pub depth: usize,
+}
+
+/// Maps positions in a Markdown source to the block elements rendered from
+/// it, matching the `data-source-*` attributes written when
+/// `source_positions` is enabled.
+#[wasm_bindgen]
+pub struct SourceMap {
+    /// Blocks in document order, so parents precede their children.
+    blocks: Vec<SourceBlock>,
+}
+
+#[wasm_bindgen]
+impl SourceMap {
+    #[wasm_bindgen(constructor)]
+    pub fn new(input: &str, options: &MarkdownOptions) -> SourceMap {
+        let index = SourceIndex::new(input);
+        let mut blocks = Vec::new();
+        let mut depth = 0;
+        for (event, range) in render::parse(input, options) {
+            match event {
+                Event::Start(tag) => {
+                    if let Some(element) = block_element(&tag) {
+                        blocks.push(source_block(&index, element, range, depth));
+                    }
+                    depth += 1;
+                }
+                Event::End(_) => depth -= 1,
+                Event::Rule => blocks.push(source_block(&index, "hr", range, depth)),
+                _ => {}
+            }
+        }
+        SourceMap { blocks }
+    }
+
+    pub fn blocks(&self) -> Vec<SourceBlock> {
+        self.blocks.clone()
+    }
+
+    /// The innermost block containing UTF-16 offset `offset`.
+    pub fn block_at(&self, offset: usize) -> Option<SourceBlock> {
+        self.blocks
+            .iter()
+            .rev()
+            .filter(|block| block.start <= offset && offset < block.end)
+            .max_by_key(|block| block.depth)
+            .cloned()
+    }
+
+    /// The innermost block starting on or enclosing 1-based line `line`.
+    pub fn block_at_line(&self, line: usize) -> Option<SourceBlock> {
+        self.blocks
+            .iter()
+            .rev()
+            .filter(|block| block.line <= line && line <= block.end_line)
+            .max_by_key(|block| block.depth)
+            .cloned()
+    }
+}
+
+fn source_block(
+    index: &SourceIndex,
+    element: &str,
+    range: Range<usize>,
+    depth: usize,
+) -> SourceBlock {
+    let last = range.end.saturating_sub(1).max(range.start);
+    SourceBlock {
+        element: element.to_string(),
+        start: index.utf16(range.start),
+        end: index.utf16(range.end),
+        line: index.line(range.start),
+        end_line: index.line(last),
+        depth,
+    }
+}
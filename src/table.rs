@@ -0,0 +1,345 @@
+use std::ops::Range;
+
+use pulldown_cmark::{Alignment, Event, Tag, TagEnd};
+use unicode_width::UnicodeWidthStr;
+use wasm_bindgen::prelude::*;
+
+use crate::incremental::utf16_to_byte;
+use crate::options::MarkdownOptions;
+use crate::render;
+use crate::source_map::SourceIndex;
+
+/// A change to the table under the cursor, made by `edit_table`.
+///
+/// The header counts as the first row, so inserting a row above it makes
+/// the new, empty row the header, and deleting it promotes the first body
+/// row. Removing the last row or column removes the table.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableOperation {
+    /// Only realign the table.
+    Format,
+    InsertColumnLeft,
+    InsertColumnRight,
+    DeleteColumn,
+    MoveColumnLeft,
+    MoveColumnRight,
+    InsertRowAbove,
+    InsertRowBelow,
+    DeleteRow,
+    MoveRowUp,
+    MoveRowDown,
+}
+
+/// The document after `edit_table`.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct TableEdit {
+    #[wasm_bindgen(getter_with_clone)]
+    pub text: String,
+    /// UTF-16 offset of the start of the edited cell, where the cursor
+    /// should go next.
+    pub cursor: usize,
+}
+
+/// A pipe table as found in the source.
+struct Table {
+    /// From the start of the header line to the end of the last row,
+    /// without its line break.
+    range: Range<usize>,
+    /// The container markers and indentation before the header row.
+    prefix: String,
+    alignments: Vec<Alignment>,
+    /// The line with the alignment markers.
+    delimiter: Range<usize>,
+    /// The header row, then the body rows.
+    rows: Vec<Row>,
+}
+
+struct Row {
+    range: Range<usize>,
+    /// The trimmed source of every cell, including the empty ones the
+    /// parser fills in for short rows.
+    cells: Vec<String>,
+    ranges: Vec<Range<usize>>,
+    /// Cells past the header's column count, which do not render but are
+    /// kept as written.
+    rest: String,
+}
+
+impl Row {
+    fn empty(columns: usize) -> Self {
+        Row {
+            range: 0..0,
+            cells: vec![String::new(); columns],
+            ranges: Vec::new(),
+            rest: String::new(),
+        }
+    }
+}
+
+/// Rewrites every pipe table in `input` with padded, aligned columns.
+pub(crate) fn format(input: &str, options: &MarkdownOptions) -> String {
+    let mut output = String::new();
+    let mut copied = 0;
+    for table in tables(input, options) {
+        output.push_str(&input[copied..table.range.start]);
+        output.push_str(&table.write().0);
+        copied = table.range.end;
+    }
+    output.push_str(&input[copied..]);
+    output
+}
+
+/// Applies `operation` to the table containing UTF-16 offset `cursor`, or
+/// returns `None` when the cursor is outside every table.
+pub(crate) fn edit(
+    input: &str,
+    options: &MarkdownOptions,
+    cursor: usize,
+    operation: TableOperation,
+) -> Option<TableEdit> {
+    let offset = utf16_to_byte(input, 0, cursor);
+    let mut table = tables(input, options).into_iter().find(|table| {
+        table.range.start <= offset
+            && (offset < table.range.end
+                || (offset == table.range.end && !input[offset..].starts_with('\n')))
+    })?;
+    let (mut row, mut column) = table.position(input, offset);
+    let columns = table.alignments.len();
+    match operation {
+        TableOperation::Format => {}
+        TableOperation::InsertColumnLeft | TableOperation::InsertColumnRight => {
+            if operation == TableOperation::InsertColumnRight {
+                column += 1;
+            }
+            table.alignments.insert(column, Alignment::None);
+            for row in &mut table.rows {
+                row.cells.insert(column, String::new());
+            }
+        }
+        TableOperation::DeleteColumn => {
+            table.alignments.remove(column);
+            for row in &mut table.rows {
+                row.cells.remove(column);
+            }
+            column = column.min(columns.saturating_sub(2));
+        }
+        TableOperation::MoveColumnLeft | TableOperation::MoveColumnRight => {
+            let other = match operation {
+                TableOperation::MoveColumnLeft => column.checked_sub(1),
+                _ => Some(column + 1).filter(|&other| other < columns),
+            };
+            if let Some(other) = other {
+                table.alignments.swap(column, other);
+                for row in &mut table.rows {
+                    row.cells.swap(column, other);
+                }
+                column = other;
+            }
+        }
+        TableOperation::InsertRowAbove | TableOperation::InsertRowBelow => {
+            if operation == TableOperation::InsertRowBelow {
+                row += 1;
+            }
+            table.rows.insert(row, Row::empty(columns));
+        }
+        TableOperation::DeleteRow => {
+            table.rows.remove(row);
+            row = row.min(table.rows.len().saturating_sub(1));
+        }
+        TableOperation::MoveRowUp | TableOperation::MoveRowDown => {
+            let other = match operation {
+                TableOperation::MoveRowUp => row.checked_sub(1),
+                _ => Some(row + 1).filter(|&other| other < table.rows.len()),
+            };
+            if let Some(other) = other {
+                table.rows.swap(row, other);
+                row = other;
+            }
+        }
+    }
+
+    let mut text = input[..table.range.start].to_string();
+    let cursor = match table.alignments.is_empty() || table.rows.is_empty() {
+        true => {
+            // Take the line break after the table along with it.
+            let end = table.range.end + usize::from(input[table.range.end..].starts_with('\n'));
+            text.push_str(&input[end..]);
+            table.range.start
+        }
+        false => {
+            let (lines, cells) = table.write();
+            text.push_str(&lines);
+            text.push_str(&input[table.range.end..]);
+            table.range.start + cells[row][column]
+        }
+    };
+    let cursor = SourceIndex::new(&text).utf16(cursor);
+    Some(TableEdit { text, cursor })
+}
+
+impl Table {
+    /// The row and column at byte `offset`, with the delimiter line counting
+    /// as part of the header.
+    fn position(&self, input: &str, offset: usize) -> (usize, usize) {
+        let last = self.alignments.len() - 1;
+        if self.delimiter.start <= offset && offset <= self.delimiter.end {
+            let line = &input[self.delimiter.clone()];
+            let before = &line[..offset - self.delimiter.start];
+            let pipes = before.matches('|').count();
+            let leading = line
+                .trim_start_matches(|c| c != '|' && c != '-' && c != ':')
+                .starts_with('|');
+            return (0, (pipes - usize::from(leading && pipes > 0)).min(last));
+        }
+        let row = self
+            .rows
+            .iter()
+            .rposition(|row| row.range.start <= offset)
+            .unwrap_or(0);
+        let column = self.rows[row]
+            .ranges
+            .iter()
+            .filter(|range| range.start <= offset)
+            .count();
+        (row, column.saturating_sub(1).min(last))
+    }
+
+    /// The realigned table and, for every row, the offset of the start of
+    /// each cell's content in it.
+    fn write(&self) -> (String, Vec<Vec<usize>>) {
+        let mut widths = vec![3; self.alignments.len()];
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(&row.cells) {
+                *width = (*width).max(cell.width());
+            }
+        }
+        // Later lines keep any `>` of the header line's prefix, with list
+        // markers turned into indentation.
+        let continuation: String = self
+            .prefix
+            .chars()
+            .map(|c| {
+                if c == '>' || c.is_whitespace() {
+                    c
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+
+        let markers: Vec<String> = self
+            .alignments
+            .iter()
+            .zip(&widths)
+            .map(|(alignment, &width)| match alignment {
+                Alignment::None => "-".repeat(width),
+                Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+                Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+                Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+            })
+            .collect();
+
+        let mut output = String::new();
+        let mut starts = Vec::new();
+        for (index, row) in self.rows.iter().enumerate() {
+            output.push_str(if index == 0 {
+                &self.prefix
+            } else {
+                &continuation
+            });
+            output.push('|');
+            let mut cells = Vec::new();
+            for ((cell, &width), alignment) in row.cells.iter().zip(&widths).zip(&self.alignments) {
+                let padding = width - cell.width();
+                let (left, right) = match alignment {
+                    Alignment::Right => (padding, 0),
+                    Alignment::Center => (padding / 2, padding - padding / 2),
+                    _ => (0, padding),
+                };
+                output.push(' ');
+                output.push_str(&" ".repeat(left));
+                cells.push(output.len());
+                output.push_str(cell);
+                output.push_str(&" ".repeat(right));
+                output.push_str(" |");
+            }
+            if !row.rest.is_empty() {
+                output.push(' ');
+                output.push_str(&row.rest);
+            }
+            starts.push(cells);
+            if index == 0 {
+                output.push('\n');
+                output.push_str(&continuation);
+                output.push_str(&format!("| {} |", markers.join(" | ")));
+            }
+            if index + 1 < self.rows.len() {
+                output.push('\n');
+            }
+        }
+        (output, starts)
+    }
+}
+
+/// Every pipe table in `input`, parsed with `tables` enabled whatever
+/// `options` says.
+fn tables(input: &str, options: &MarkdownOptions) -> Vec<Table> {
+    let options = MarkdownOptions {
+        tables: true,
+        ..options.clone()
+    };
+    let mut tables: Vec<Table> = Vec::new();
+    for (event, range) in render::parse(input, &options) {
+        match event {
+            Event::Start(Tag::Table(alignments)) => {
+                let start = input[..range.start]
+                    .rfind('\n')
+                    .map_or(0, |index| index + 1);
+                let end = match input[..range.end].ends_with('\n') {
+                    true => range.end - 1,
+                    false => range.end,
+                };
+                tables.push(Table {
+                    range: start..end,
+                    prefix: input[start..range.start].to_string(),
+                    alignments,
+                    delimiter: 0..0,
+                    rows: Vec::new(),
+                });
+            }
+            Event::Start(Tag::TableHead | Tag::TableRow) => {
+                let table = tables.last_mut().expect("rows are inside tables");
+                table.rows.push(Row {
+                    range,
+                    cells: Vec::new(),
+                    ranges: Vec::new(),
+                    rest: String::new(),
+                });
+            }
+            Event::Start(Tag::TableCell) => {
+                let table = tables.last_mut().expect("rows are inside tables");
+                let row = table.rows.last_mut().expect("cells are inside rows");
+                row.cells.push(input[range.clone()].trim().to_string());
+                row.ranges.push(range);
+            }
+            Event::End(TagEnd::TableHead | TagEnd::TableRow) => {
+                let table = tables.last_mut().expect("rows are inside tables");
+                let row = table.rows.last_mut().expect("rows are open");
+                let last = row.ranges.last().map_or(range.start, |cell| cell.end);
+                let tail = input[last.min(range.end)..range.end].trim();
+                row.rest = tail.strip_prefix('|').unwrap_or(tail).trim().to_string();
+                if matches!(event, Event::End(TagEnd::TableHead)) {
+                    let start = range.end;
+                    let end = input[start..]
+                        .find('\n')
+                        .map_or(input.len(), |index| start + index);
+                    table.delimiter = start..end;
+                }
+            }
+            _ => {}
+        }
+    }
+    tables
+}
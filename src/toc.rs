@@ -0,0 +1,195 @@
+use std::ops::Range;
+
+use pulldown_cmark::{Event, LinkType, Tag, TagEnd};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::ast::Position;
+use crate::headings;
+use crate::options::MarkdownOptions;
+use crate::render::{self, Spanned};
+use crate::source_map::SourceIndex;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TOC_SCHEMA: &'static str = r#"
+/** A heading in the outline returned by `heading_outline`. */
+export interface TocEntry {
+  level: number;
+  text: string;
+  slug: string;
+  position: MarkdownPosition;
+  children?: TocEntry[];
+}
+"#;
+
+/// A heading and the headings nested under it.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct TocEntry {
+    pub level: u8,
+    pub text: String,
+    pub slug: String,
+    #[serde(skip)]
+    pub range: Range<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<Position>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TocEntry>,
+}
+
+/// The nested heading outline of `input`, with the same slugs the renderer
+/// assigns as heading ids.
+pub(crate) fn outline(input: &str, options: &MarkdownOptions) -> Vec<TocEntry> {
+    let events = headings::assign_ids(render::parse(input, options));
+    outline_of(&events, &SourceIndex::new(input))
+}
+
+/// The nested outline of already parsed events. Headings must already carry
+/// their ids.
+pub(crate) fn outline_of(events: &[Spanned], index: &SourceIndex) -> Vec<TocEntry> {
+    let mut entries = collect(events, 1, 6);
+    for entry in &mut entries {
+        entry.position = Some(Position::of(index, entry.range.clone()));
+    }
+    nest(entries)
+}
+
+/// The headings with levels in `min..=max`, flat and in document order.
+/// Headings must already carry their ids.
+fn collect(events: &[Spanned], min: u8, max: u8) -> Vec<TocEntry> {
+    let mut entries = Vec::new();
+    for (index, (event, range)) in events.iter().enumerate() {
+        if let Event::Start(Tag::Heading { level, id, .. }) = event {
+            let level = *level as u8;
+            if (min..=max).contains(&level) {
+                entries.push(TocEntry {
+                    level,
+                    text: headings::heading_text(events, index).trim().to_string(),
+                    slug: id.as_deref().unwrap_or_default().to_string(),
+                    range: range.clone(),
+                    position: None,
+                    children: Vec::new(),
+                });
+            }
+        }
+    }
+    entries
+}
+
+/// Nests each heading under the closest preceding heading of a lower level.
+fn nest(entries: Vec<TocEntry>) -> Vec<TocEntry> {
+    let mut roots: Vec<TocEntry> = Vec::new();
+    let mut stack: Vec<TocEntry> = Vec::new();
+    for entry in entries {
+        while stack.last().is_some_and(|open| open.level >= entry.level) {
+            close(&mut stack, &mut roots);
+        }
+        stack.push(entry);
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    roots
+}
+
+fn close(stack: &mut Vec<TocEntry>, roots: &mut Vec<TocEntry>) {
+    let entry = stack.pop().expect("only called with open entries");
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(entry),
+        None => roots.push(entry),
+    }
+}
+
+/// Replaces every `[TOC]` paragraph and `<!-- toc -->` HTML block with a
+/// nested list linking to the headings between the configured levels.
+/// Headings must already carry their ids.
+pub(crate) fn insert<'a>(events: Vec<Spanned<'a>>, options: &MarkdownOptions) -> Vec<Spanned<'a>> {
+    let min = match options.toc_min_level {
+        0 => 1,
+        level => level,
+    };
+    let max = match options.toc_max_level {
+        0 => 6,
+        level => level,
+    };
+    let mut placeholders = Vec::new();
+    let mut index = 0;
+    while index < events.len() {
+        match placeholder_end(&events, index) {
+            Some(end) => {
+                placeholders.push(index..end + 1);
+                index = end + 1;
+            }
+            None => index += 1,
+        }
+    }
+    if placeholders.is_empty() {
+        return events;
+    }
+
+    let entries = nest(collect(&events, min, max));
+    let mut out = Vec::with_capacity(events.len());
+    let mut placeholders = placeholders.into_iter().peekable();
+    for (index, (event, range)) in events.into_iter().enumerate() {
+        match placeholders.peek() {
+            Some(placeholder) if placeholder.contains(&index) => {
+                if index + 1 == placeholder.end {
+                    placeholders.next();
+                    if !entries.is_empty() {
+                        push_list(&mut out, &entries, &range);
+                    }
+                }
+            }
+            _ => out.push((event, range)),
+        }
+    }
+    out
+}
+
+/// If a placeholder block starts at `events[start]`, the index of its end.
+fn placeholder_end(events: &[Spanned], start: usize) -> Option<usize> {
+    let end_tag = match events[start].0 {
+        Event::Start(Tag::Paragraph) => TagEnd::Paragraph,
+        Event::Start(Tag::HtmlBlock) => TagEnd::HtmlBlock,
+        _ => return None,
+    };
+    let mut content = String::new();
+    for (offset, (event, _)) in events[start + 1..].iter().enumerate() {
+        match event {
+            Event::End(tag) if *tag == end_tag => {
+                let content: String = content.split_whitespace().collect();
+                let is_placeholder = match end_tag {
+                    TagEnd::Paragraph => content.eq_ignore_ascii_case("[toc]"),
+                    _ => content.eq_ignore_ascii_case("<!--toc-->"),
+                };
+                return is_placeholder.then_some(start + 1 + offset);
+            }
+            Event::Text(text) | Event::Html(text) => content.push_str(text),
+            _ => return None,
+        }
+    }
+    None
+}
+
+fn push_list<'a>(out: &mut Vec<Spanned<'a>>, entries: &[TocEntry], range: &Range<usize>) {
+    let push = |out: &mut Vec<Spanned<'a>>, event| out.push((event, range.clone()));
+    push(out, Event::Start(Tag::List(None)));
+    for entry in entries {
+        push(out, Event::Start(Tag::Item));
+        push(
+            out,
+            Event::Start(Tag::Link {
+                link_type: LinkType::Inline,
+                dest_url: format!("#{}", entry.slug).into(),
+                title: "".into(),
+                id: "".into(),
+            }),
+        );
+        push(out, Event::Text(entry.text.clone().into()));
+        push(out, Event::End(TagEnd::Link));
+        if !entry.children.is_empty() {
+            push_list(out, &entry.children, range);
+        }
+        push(out, Event::End(TagEnd::Item));
+    }
+    push(out, Event::End(TagEnd::List(false)));
+}
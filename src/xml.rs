@@ -0,0 +1,430 @@
+use html5ever::data::NAMED_ENTITIES;
+use pulldown_cmark::{Event, TagEnd};
+
+use crate::render::Spanned;
+
+/// Escapes `text` for XML content and attribute values, dropping the
+/// control characters XML 1.0 does not allow at all.
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c if is_char(c) => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Rewrites an HTML fragment as well-formed XML. Tags are re-serialized with
+/// quoted, deduplicated attributes and void elements closed; end tags
+/// without a start tag are dropped and elements left open are closed where
+/// their parent ends, as browsers do. Named character references other
+/// than XML's five become numeric, a `<` or `&` that starts nothing is
+/// escaped, and comments, doctypes and processing instructions are made
+/// safe or dropped. Well-formed input, such as the renderer's own markup,
+/// keeps its structure.
+pub(crate) fn xhtml(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut open = Vec::new();
+    write(&mut out, html, &mut open, false, true);
+    close(&mut out, open);
+    out
+}
+
+/// Balances the raw HTML in an event stream before `xhtml` runs over the
+/// whole document, each HTML block and each inline tag on its own. Elements
+/// are closed where the Markdown element they were opened in ends, so an
+/// unclosed tag cannot take in the Markdown after it, and end tags only
+/// close elements opened in the same Markdown element. Script and style
+/// content is left for `xhtml` to escape, and raw HTML in image
+/// descriptions for the writer.
+pub(crate) fn apply(events: Vec<Spanned<'_>>) -> Vec<Spanned<'_>> {
+    let mut out = Vec::with_capacity(events.len());
+    // The raw elements left open in each enclosing Markdown element.
+    let mut open: Vec<Vec<String>> = vec![Vec::new()];
+    let mut block: Option<String> = None;
+    let mut images = 0;
+    for (event, range) in events {
+        match event {
+            Event::Start(tag) => {
+                match tag {
+                    pulldown_cmark::Tag::HtmlBlock => block = Some(String::new()),
+                    pulldown_cmark::Tag::Image { .. } => images += 1,
+                    _ => {}
+                }
+                open.push(Vec::new());
+                out.push((Event::Start(tag), range));
+            }
+            Event::End(tag) => {
+                let mut level = open.pop().unwrap_or_default();
+                if open.is_empty() {
+                    open.push(Vec::new());
+                }
+                let mut html = String::new();
+                if let Some(text) = block.take() {
+                    write(&mut html, &text, &mut level, foreign(&open), false);
+                }
+                close(&mut html, level);
+                match tag {
+                    TagEnd::HtmlBlock => out.push((Event::Html(html.into()), range.clone())),
+                    _ if !html.is_empty() => {
+                        out.push((Event::InlineHtml(html.into()), range.clone()))
+                    }
+                    _ => {}
+                }
+                if tag == TagEnd::Image {
+                    images -= 1;
+                }
+                out.push((Event::End(tag), range));
+            }
+            Event::Html(html) if block.is_some() => block.get_or_insert_default().push_str(&html),
+            Event::Html(ref html) | Event::InlineHtml(ref html) if images == 0 => {
+                let foreign = foreign(&open);
+                let mut text = String::with_capacity(html.len());
+                write(&mut text, html, open.last_mut().unwrap(), foreign, false);
+                let event = match event {
+                    Event::Html(_) => Event::Html(text.into()),
+                    _ => Event::InlineHtml(text.into()),
+                };
+                out.push((event, range));
+            }
+            event => out.push((event, range)),
+        }
+    }
+    let end = out.last().map_or(0, |(_, range): &Spanned| range.end);
+    let mut html = String::new();
+    for level in open.into_iter().rev() {
+        close(&mut html, level);
+    }
+    if !html.is_empty() {
+        out.push((Event::Html(html.into()), end..end));
+    }
+    out
+}
+
+/// Whether the raw elements open around a piece include `svg` or `math`.
+fn foreign(open: &[Vec<String>]) -> bool {
+    open.iter()
+        .flatten()
+        .any(|name| name == "svg" || name == "math")
+}
+
+/// Writes `html` as XML. `open` holds the elements the writer may close and
+/// gets those left open; `foreign` says whether the enclosing content is
+/// `svg` or `math`, where names keep their case. Script and style content is
+/// only escaped with `escape_raw_text`.
+fn write(
+    out: &mut String,
+    html: &str,
+    open: &mut Vec<String>,
+    foreign: bool,
+    escape_raw_text: bool,
+) {
+    let raw_text = |text: &str| match escape_raw_text {
+        true => escape(text),
+        false => text.to_string(),
+    };
+    let mut rest = html;
+    while let Some(index) = rest.find('<') {
+        text(out, &rest[..index]);
+        rest = &rest[index..];
+        if let Some(after) = rest.strip_prefix("<!--") {
+            let (body, next) = split(after, "-->");
+            // `--` may not appear in XML comments, nor may they end in `-`.
+            let mut body = body.to_string();
+            while body.contains("--") {
+                body = body.replace("--", "- -");
+            }
+            if body.ends_with('-') {
+                body.push(' ');
+            }
+            body.retain(is_char);
+            out.push_str(&format!("<!--{}-->", body));
+            rest = next;
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let (body, next) = split(after, "]]>");
+            out.push_str(&escape(body));
+            rest = next;
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = split(rest, ">").1;
+        } else if let Some((tag, length)) = Tag::parse(rest) {
+            rest = &rest[length..];
+            let foreign = foreign || open.iter().any(|name| name == "svg" || name == "math");
+            if tag.end {
+                if let Some(index) = open
+                    .iter()
+                    .rposition(|name| name.eq_ignore_ascii_case(&tag.name))
+                {
+                    for name in open.drain(index..).rev() {
+                        out.push_str(&format!("</{}>", name));
+                    }
+                } else if out.is_empty() || out.ends_with('\n') {
+                    // A dropped end tag at the start of a line takes its line
+                    // break with it.
+                    rest = rest
+                        .strip_prefix("\r\n")
+                        .or(rest.strip_prefix('\n'))
+                        .unwrap_or(rest);
+                }
+                continue;
+            }
+            let name = match foreign {
+                true => tag.name.clone(),
+                false => tag.name.to_ascii_lowercase(),
+            };
+            out.push('<');
+            out.push_str(&name);
+            tag.write_attributes(out, &name, foreign);
+            if tag.self_closing || VOID.contains(&name.as_str()) {
+                out.push_str(" />");
+            } else if name == "script" || name == "style" {
+                // Raw text: everything up to the end tag is content. Inline,
+                // the end tag comes in a later piece.
+                out.push('>');
+                match find_ignore_case(rest, &format!("</{}", name)) {
+                    Some(end) => {
+                        out.push_str(&raw_text(&rest[..end]));
+                        out.push_str(&format!("</{}>", name));
+                        rest = split(&rest[end..], ">").1;
+                    }
+                    None if rest.is_empty() => open.push(name),
+                    None => {
+                        out.push_str(&raw_text(rest));
+                        out.push_str(&format!("</{}>", name));
+                        rest = "";
+                    }
+                }
+            } else {
+                out.push('>');
+                open.push(name);
+            }
+        } else {
+            out.push_str("&lt;");
+            rest = &rest[1..];
+        }
+    }
+    text(out, rest);
+}
+
+/// Closes the elements in `open`, innermost first.
+fn close(out: &mut String, open: Vec<String>) {
+    for name in open.into_iter().rev() {
+        out.push_str(&format!("</{}>", name));
+    }
+}
+
+struct Tag {
+    name: String,
+    end: bool,
+    self_closing: bool,
+    attributes: Vec<(String, Option<String>)>,
+}
+
+impl Tag {
+    /// The tag at the start of `input` and its length, or `None` when the
+    /// `<` does not start one.
+    fn parse(input: &str) -> Option<(Tag, usize)> {
+        let (end, mut rest) = match input.strip_prefix("</") {
+            Some(rest) => (true, rest),
+            None => (false, &input[1..]),
+        };
+        if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let length = rest
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .unwrap_or(rest.len());
+        let name = rest[..length].to_string();
+        if !is_name(&name) || name.contains(':') {
+            return None;
+        }
+        rest = &rest[length..];
+        let mut tag = Tag {
+            name,
+            end,
+            self_closing: false,
+            attributes: Vec::new(),
+        };
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix('>') {
+                return Some((tag, input.len() - after.len()));
+            }
+            if let Some(after) = rest.strip_prefix("/>") {
+                tag.self_closing = true;
+                return Some((tag, input.len() - after.len()));
+            }
+            if let Some(after) = rest.strip_prefix('/') {
+                rest = after;
+                continue;
+            }
+            let length = rest
+                .find(|c: char| c.is_whitespace() || "/>=".contains(c))
+                .unwrap_or(rest.len());
+            if length == 0 && !rest.starts_with('=') {
+                return None;
+            }
+            let name = rest[..length].to_string();
+            rest = rest[length..].trim_start();
+            let value = match rest.strip_prefix('=') {
+                Some(after) => {
+                    let after = after.trim_start();
+                    let (value, next) = match after.chars().next() {
+                        Some(quote @ ('"' | '\'')) => {
+                            let close = after[1..].find(quote)? + 1;
+                            (&after[1..close], &after[close + 1..])
+                        }
+                        _ => {
+                            let end = after
+                                .find(|c: char| c.is_whitespace() || c == '>')
+                                .unwrap_or(after.len());
+                            (&after[..end], &after[end..])
+                        }
+                    };
+                    rest = next;
+                    Some(value.to_string())
+                }
+                None => None,
+            };
+            tag.attributes.push((name, value));
+        }
+    }
+
+    /// Writes the attributes that make valid XML, each once. Attributes
+    /// without a value repeat their name, and `svg` and `math` elements get
+    /// their namespace.
+    fn write_attributes(&self, out: &mut String, name: &str, foreign: bool) {
+        let mut written: Vec<String> = Vec::new();
+        for (attribute, value) in &self.attributes {
+            let attribute = match foreign || name == "svg" || name == "math" {
+                true => attribute.clone(),
+                false => attribute.to_ascii_lowercase(),
+            };
+            let prefix = attribute.split_once(':').map(|(prefix, _)| prefix);
+            if !is_name(&attribute)
+                || !matches!(prefix, None | Some("xml" | "xmlns" | "xlink"))
+                || written.contains(&attribute)
+            {
+                continue;
+            }
+            let declared =
+                self.declares("xmlns:xlink") || written.iter().any(|name| name == "xmlns:xlink");
+            if prefix == Some("xlink") && !declared {
+                out.push_str(" xmlns:xlink=\"http://www.w3.org/1999/xlink\"");
+                written.push("xmlns:xlink".to_string());
+            }
+            out.push_str(&format!(" {}=\"", attribute));
+            text(out, value.as_deref().unwrap_or(&attribute));
+            out.push('"');
+            written.push(attribute);
+        }
+        let namespace = match name {
+            "svg" => Some("http://www.w3.org/2000/svg"),
+            "math" => Some("http://www.w3.org/1998/Math/MathML"),
+            _ => None,
+        };
+        if let Some(namespace) = namespace.filter(|_| !written.iter().any(|name| name == "xmlns")) {
+            out.push_str(&format!(" xmlns=\"{}\"", namespace));
+        }
+    }
+
+    fn declares(&self, attribute: &str) -> bool {
+        self.attributes.iter().any(|(name, _)| name == attribute)
+    }
+}
+
+/// Writes text or an attribute value, keeping character references XML
+/// knows and escaping everything else that is not plain text.
+fn text(out: &mut String, text: &str) {
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '&' => match reference(rest) {
+                Some((reference, length)) => {
+                    out.push_str(&reference);
+                    rest = &rest[length..];
+                }
+                None => out.push_str("&amp;"),
+            },
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c if is_char(c) => out.push(c),
+            _ => {}
+        }
+    }
+}
+
+/// The XML form of the character reference after a `&`, and its length up
+/// to and including the `;`.
+fn reference(input: &str) -> Option<(String, usize)> {
+    let end = input.find(';').filter(|&end| end <= 32)?;
+    let name = &input[..end];
+    let code = match name.strip_prefix('#') {
+        Some(number) => {
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            // Invalid references turn into the replacement character, as
+            // HTML parsers read them.
+            let code = Some(code)
+                .filter(|&code| char::from_u32(code).is_some_and(is_char))
+                .unwrap_or(0xfffd);
+            return Some((format!("&#{};", code), end + 1));
+        }
+        None if matches!(name, "amp" | "lt" | "gt" | "quot" | "apos") => {
+            return Some((format!("&{};", name), end + 1));
+        }
+        None => NAMED_ENTITIES.get(&input[..=end])?,
+    };
+    let reference = match code {
+        (first, 0) => format!("&#{};", first),
+        (first, second) => format!("&#{};&#{};", first, second),
+    };
+    Some((reference, end + 1))
+}
+
+/// Whether `c` may appear in an XML 1.0 document.
+fn is_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | ' '..='\u{d7ff}' | '\u{e000}'..='\u{fffd}' | '\u{10000}'..)
+}
+
+/// Whether `name` is an XML name, with at most one colon not at either end.
+fn is_name(name: &str) -> bool {
+    let start = |c: char| c.is_alphabetic() || c == '_';
+    let mut parts = name.split(':');
+    let valid = |part: &str| {
+        part.starts_with(start)
+            && part
+                .chars()
+                .all(|c| c.is_alphanumeric() || "_-.".contains(c))
+    };
+    parts.all(valid) && name.matches(':').count() <= 1
+}
+
+/// The text before `delimiter` and the text after it, or all of `text` and
+/// nothing when it does not occur.
+fn split<'a>(text: &'a str, delimiter: &str) -> (&'a str, &'a str) {
+    match text.find(delimiter) {
+        Some(index) => (&text[..index], &text[index + delimiter.len()..]),
+        None => (text, ""),
+    }
+}
+
+fn find_ignore_case(text: &str, needle: &str) -> Option<usize> {
+    let lower = text.to_ascii_lowercase();
+    lower.find(needle)
+}
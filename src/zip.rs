@@ -0,0 +1,110 @@
+/// A ZIP archive written in memory. Entries are stored uncompressed, which
+/// every reader accepts and EPUB requires of its `mimetype`, and carry a
+/// fixed timestamp so the same input gives the same bytes.
+pub(crate) struct Zip {
+    out: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// 1980-01-01 00:00 in MS-DOS format, the earliest date ZIP can hold.
+const DATE: u16 = (1 << 5) | 1;
+
+impl Zip {
+    pub(crate) fn new() -> Self {
+        Zip {
+            out: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub(crate) fn add(&mut self, name: &str, data: &[u8]) {
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc32(data),
+            size: data.len() as u32,
+            offset: self.out.len() as u32,
+        };
+        self.out.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        self.header(&entry);
+        self.out.extend_from_slice(name.as_bytes());
+        self.out.extend_from_slice(data);
+        self.entries.push(entry);
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        let start = self.out.len() as u32;
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            self.out.extend_from_slice(&0x02014b50u32.to_le_bytes());
+            // Made by version 2.0, on MS-DOS.
+            self.out.extend_from_slice(&20u16.to_le_bytes());
+            self.header(entry);
+            // Comment length, disk number, internal and external attributes.
+            self.out.extend_from_slice(&[0; 10]);
+            self.out.extend_from_slice(&entry.offset.to_le_bytes());
+            self.out.extend_from_slice(entry.name.as_bytes());
+        }
+        let size = self.out.len() as u32 - start;
+        self.out.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        self.out.extend_from_slice(&[0; 4]);
+        self.out
+            .extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        self.out
+            .extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        self.out.extend_from_slice(&size.to_le_bytes());
+        self.out.extend_from_slice(&start.to_le_bytes());
+        self.out.extend_from_slice(&0u16.to_le_bytes());
+        self.out
+    }
+
+    /// The fields the local and central headers share, from the version
+    /// needed to extract through the extra field length.
+    fn header(&mut self, entry: &Entry) {
+        self.out.extend_from_slice(&10u16.to_le_bytes());
+        // Bit 11: the name is UTF-8.
+        self.out.extend_from_slice(&(1u16 << 11).to_le_bytes());
+        // Stored.
+        self.out.extend_from_slice(&0u16.to_le_bytes());
+        self.out.extend_from_slice(&0u16.to_le_bytes());
+        self.out.extend_from_slice(&DATE.to_le_bytes());
+        self.out.extend_from_slice(&entry.crc.to_le_bytes());
+        self.out.extend_from_slice(&entry.size.to_le_bytes());
+        self.out.extend_from_slice(&entry.size.to_le_bytes());
+        self.out
+            .extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+        self.out.extend_from_slice(&0u16.to_le_bytes());
+    }
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xedb88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
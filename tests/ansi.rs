@@ -0,0 +1,98 @@
+use markdown_wasm::{render_ansi, MarkdownOptions, TerminalOptions};
+use unicode_width::UnicodeWidthStr;
+
+const DOCUMENTS: &[&str] = &[
+    "# Title\n\nSome *emphasis*, **strong** and `code` in a paragraph long enough to wrap.\n",
+    "```javascript\nlet x = 1;\n中文\n\tindented\n```\n\n```a-very-long-language-name\nx\n```\n",
+    "    indented code\n\n```\n```\n",
+    "> - quote item text\n>   > nested quote\n\n> [!WARNING]\n> An alert.\n",
+    "1. one\n2. two\n   - [x] done\n   - [ ] todo\n",
+    "| a | b |\n|:-:|-:|\n| 中文 | long cell |\n",
+    "Note[^1] and [link](http://example.com).\n\n[^1]: A footnote.\n\n---\n",
+    "Term\n: Definition text.\n",
+];
+
+fn plain(input: &str, width: usize) -> String {
+    let terminal = TerminalOptions {
+        width,
+        color: false,
+        hyperlinks: false,
+    };
+    render_ansi(input, &MarkdownOptions::all(), &terminal)
+}
+
+#[test]
+fn styles() {
+    let terminal = TerminalOptions::default();
+    assert_eq!(
+        render_ansi(
+            "# H\n\n*a* **b** `c` [l](http://u)\n",
+            &MarkdownOptions::gfm(),
+            &terminal
+        ),
+        "\x1b[0;1;35;4mH\x1b[0m\n\n\x1b[0;3ma\x1b[0m \x1b[0;1mb\x1b[0m \x1b[0;33mc\x1b[0m \
+         \x1b]8;;http://u\x1b\\\x1b[0;4;34ml\x1b[0m\x1b]8;;\x1b\\\n"
+    );
+    for document in DOCUMENTS {
+        let output = render_ansi(document, &MarkdownOptions::all(), &terminal);
+        assert!(!output.contains("\x1b[0m\x1b[0m"), "{:?}", output);
+    }
+    assert_eq!(
+        plain("[l](http://u) and <http://u>", 80),
+        "l (http://u) and http://u\n"
+    );
+}
+
+#[test]
+fn wraps_to_the_width() {
+    assert_eq!(plain("one two three four", 9), "one two\nthree\nfour\n");
+    assert_eq!(plain("- one two three", 9), "• one two\n  three\n");
+    assert_eq!(plain("> one two three", 9), "│ one two\n│ three\n");
+    // Words longer than the width stay whole.
+    assert_eq!(plain("a verylongword b", 5), "a\nverylongword\nb\n");
+    assert_eq!(plain("one two three four", 0), "one two three four\n");
+}
+
+#[test]
+fn code_blocks() {
+    assert_eq!(
+        plain("```rust\nfn main() {}\n```\n", 80),
+        "┌─ rust ───────┐\n│ fn main() {} │\n└──────────────┘\n"
+    );
+    assert_eq!(
+        plain("```\nlong line\n```\n", 8),
+        "┌──────┐\n│ long │\n│  lin │\n│ e    │\n└──────┘\n"
+    );
+    // The language label is cut to fit in the box.
+    assert_eq!(
+        plain("```javascript\nx\n```\n", 9),
+        "┌─ java ┐\n│ x     │\n└───────┘\n"
+    );
+    assert_eq!(
+        plain("```javascript\nx\n```\n", 6),
+        "┌─ j ┐\n│ x  │\n└────┘\n"
+    );
+    assert_eq!(plain("```javascript\nx\n```\n", 5), "┌───┐\n│ x │\n└───┘\n");
+}
+
+#[test]
+fn narrow_widths() {
+    for width in 0..24 {
+        for document in DOCUMENTS {
+            plain(document, width);
+        }
+        // Every line of a code block's box is as wide as its top, except
+        // for wide characters that don't fit in a one-column box.
+        let output = plain(DOCUMENTS[1], width);
+        let mut top = 0;
+        for line in output
+            .lines()
+            .filter(|line| !line.is_empty() && !line.contains(['中', '文']))
+        {
+            if line.starts_with('┌') {
+                top = line.width();
+            }
+            assert_eq!(line.width(), top, "at {width}:\n{output}");
+        }
+    }
+}
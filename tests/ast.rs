@@ -0,0 +1,130 @@
+use markdown_wasm::{parse_markdown_ast_json, MarkdownOptions};
+use serde_json::{json, Value};
+
+fn ast(input: &str, options: &MarkdownOptions) -> Value {
+    serde_json::from_str(&parse_markdown_ast_json(input, options)).unwrap()
+}
+
+/// Every `type` in the tree, depth first.
+fn types(node: &Value, out: &mut Vec<String>) {
+    out.push(node["type"].as_str().unwrap().to_string());
+    for child in node["children"].as_array().into_iter().flatten() {
+        types(child, out);
+    }
+}
+
+#[test]
+fn text_is_merged_and_positioned() {
+    assert_eq!(
+        ast("Hi *a* b&amp;c\n", &MarkdownOptions::new()),
+        json!({
+            "type": "document",
+            "position": {"start": 0, "end": 15, "line": 1, "endLine": 1},
+            "children": [{
+                "type": "paragraph",
+                "position": {"start": 0, "end": 15, "line": 1, "endLine": 1},
+                "children": [
+                    {"type": "text", "value": "Hi ", "position": {"start": 0, "end": 3, "line": 1, "endLine": 1}},
+                    {
+                        "type": "emphasis",
+                        "position": {"start": 3, "end": 6, "line": 1, "endLine": 1},
+                        "children": [
+                            {"type": "text", "value": "a", "position": {"start": 4, "end": 5, "line": 1, "endLine": 1}}
+                        ]
+                    },
+                    {"type": "text", "value": " b&c", "position": {"start": 6, "end": 14, "line": 1, "endLine": 1}}
+                ]
+            }]
+        })
+    );
+}
+
+#[test]
+fn positions_are_utf16_offsets() {
+    let tree = ast("😀 a\n\n*b*\n", &MarkdownOptions::new());
+    assert_eq!(
+        tree["children"][1]["position"],
+        json!({"start": 6, "end": 10, "line": 3, "endLine": 3})
+    );
+    assert_eq!(tree["position"]["end"], 10);
+}
+
+#[test]
+fn headings_lists_and_code_carry_their_attributes() {
+    let tree = ast(
+        "# T {#x .c k=v}\n\n- [x] a\n\n```rs meta\nfn\n```\n",
+        &MarkdownOptions::all(),
+    );
+    let children = &tree["children"];
+    assert_eq!(children[0]["type"], "heading");
+    assert_eq!(children[0]["level"], 1);
+    assert_eq!(children[0]["id"], "x");
+    assert_eq!(children[0]["classes"], json!(["c"]));
+    assert_eq!(children[0]["attributes"], json!({"k": "v"}));
+    assert_eq!(children[1]["ordered"], false);
+    assert_eq!(children[1]["children"][0]["checked"], true);
+    assert_eq!(children[2]["lang"], "rs");
+    assert_eq!(children[2]["meta"], "meta");
+    assert_eq!(children[2]["value"], "fn\n");
+    assert!(children[2].get("children").is_none());
+}
+
+#[test]
+fn tables_images_and_links() {
+    let tree = ast(
+        "| a | b |\n|:-|-:|\n| 1 | 2 |\n\n![alt *x*](/i \"t\") [r] www.x.com\n\n[r]: /u\n",
+        &MarkdownOptions::gfm(),
+    );
+    let table = &tree["children"][0];
+    assert_eq!(table["align"], json!(["left", "right"]));
+    assert_eq!(table["children"][0]["type"], "tableHead");
+    assert_eq!(
+        table["children"][1]["children"][1]["children"][0]["value"],
+        "2"
+    );
+
+    let paragraph = &tree["children"][1]["children"];
+    assert_eq!(paragraph[0]["type"], "image");
+    assert_eq!(paragraph[0]["alt"], "alt x");
+    assert_eq!(paragraph[0]["title"], "t");
+    assert_eq!(paragraph[2]["linkType"], "shortcut");
+    assert_eq!(paragraph[2]["identifier"], "r");
+    assert_eq!(paragraph[2]["url"], "/u");
+    assert_eq!(paragraph[4]["linkType"], "autolink");
+    assert_eq!(paragraph[4]["url"], "http://www.x.com");
+}
+
+#[test]
+fn html_blocks_are_one_node() {
+    let tree = ast("<div>\na\n</div>\n\nx <b>y</b>\n", &MarkdownOptions::new());
+    assert_eq!(
+        tree["children"][0],
+        json!({
+            "type": "html",
+            "value": "<div>\na\n</div>\n",
+            "position": {"start": 0, "end": 15, "line": 1, "endLine": 3}
+        })
+    );
+    assert_eq!(tree["children"][1]["children"][1]["type"], "inlineHtml");
+}
+
+#[test]
+fn node_types_are_in_the_typescript_schema() {
+    let source = include_str!("../src/ast.rs");
+    let input =
+        "---\na: 1\n---\n\n# h\n\n> [!TIP]\n> q\n\n1. a  \n   b\n2. c\n\n    code\n\n<p>x</p>\n\n\
+                 *e* **s** ~~d~~ ^sup^ `c` $m$ $$d$$ <i>x</i> [l](u) ![i](u) x[^1]\n\n[^1]: n\n\n\
+                 t\n: d\n\n| a |\n|-|\n| b |\n\n---\n";
+    let mut found = Vec::new();
+    types(&ast(input, &MarkdownOptions::all()), &mut found);
+    found.sort();
+    found.dedup();
+    assert_eq!(found.len(), 30, "{:?}", found);
+    for kind in found {
+        assert!(
+            source.contains(&format!("{{ type: \"{}\"", kind)),
+            "{} is missing from MarkdownNode",
+            kind
+        );
+    }
+}
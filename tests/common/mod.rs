@@ -0,0 +1,277 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+/// Checks that `xml` is a well-formed XML fragment: balanced elements with
+/// valid names, unique quoted attributes, and only character references
+/// XML defines. Doctypes, processing instructions and CDATA sections are
+/// not expected in rendered output, so they are rejected.
+pub fn check(xml: &str) -> Result<(), String> {
+    parse(xml).map(|_| ())
+}
+
+/// Checks that `xml` is a well-formed XML document: an optional XML
+/// declaration and `<!DOCTYPE html>`, then a single root element.
+pub fn check_document(xml: &str) -> Result<(), String> {
+    let body = match xml.strip_prefix("<?xml ") {
+        Some(after) => &after[after.find("?>").ok_or("unterminated XML declaration")? + 2..],
+        None => xml,
+    };
+    let body = body.trim_start();
+    let body = body.strip_prefix("<!DOCTYPE html>").unwrap_or(body);
+    match parse(body)? {
+        (1, false) => Ok(()),
+        (elements, text) => Err(format!(
+            "{} root elements, text outside them: {}",
+            elements, text
+        )),
+    }
+}
+
+/// The number of top-level elements in `xml`, and whether there is text
+/// outside them.
+fn parse(xml: &str) -> Result<(usize, bool), String> {
+    let mut open: Vec<&str> = Vec::new();
+    let (mut elements, mut text) = (0, false);
+    let mut rest = xml;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            let end = after.find("-->").ok_or("unterminated comment")?;
+            let body = &after[..end];
+            if body.contains("--") || body.ends_with('-') {
+                return Err(format!("invalid comment {:?}", body));
+            }
+            check_chars(body)?;
+            rest = &after[end + 3..];
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            return Err(format!("unexpected markup at {:?}", truncate(rest)));
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').ok_or("unterminated end tag")?;
+            let name = after[..end].trim_end();
+            match open.pop() {
+                Some(expected) if expected == name => {}
+                expected => return Err(format!("</{}> closes {:?}", name, expected)),
+            }
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('<') {
+            let end = tag_end(after)
+                .ok_or_else(|| format!("unterminated tag at {:?}", truncate(rest)))?;
+            let tag = &after[..end];
+            let (tag, empty) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+            let name = &tag[..name_end];
+            check_name(name)?;
+            check_attributes(&tag[name_end..])?;
+            if open.is_empty() {
+                elements += 1;
+            }
+            if !empty {
+                open.push(name);
+            }
+            rest = &after[end + 1..];
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            check_text(&rest[..end])?;
+            text |= open.is_empty() && !rest[..end].trim().is_empty();
+            rest = &rest[end..];
+        }
+    }
+    match open.is_empty() {
+        true => Ok((elements, text)),
+        false => Err(format!("unclosed {:?}", open)),
+    }
+}
+
+/// The index of the `>` ending a tag, skipping quoted attribute values.
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, '>') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn check_attributes(mut rest: &str) -> Result<(), String> {
+    let mut names = Vec::new();
+    loop {
+        let trimmed = rest.trim_start();
+        if trimmed.is_empty() {
+            return Ok(());
+        }
+        if trimmed.len() == rest.len() {
+            return Err(format!("no space before attribute {:?}", rest));
+        }
+        let equals = trimmed
+            .find('=')
+            .ok_or_else(|| format!("attribute without value {:?}", trimmed))?;
+        let name = &trimmed[..equals];
+        check_name(name)?;
+        if names.contains(&name) {
+            return Err(format!("duplicate attribute {}", name));
+        }
+        names.push(name);
+        let value = &trimmed[equals + 1..];
+        let quote = value
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or("unquoted value")?;
+        let end = value[1..].find(quote).ok_or("unterminated value")? + 1;
+        if value[1..end].contains('<') {
+            return Err(format!("< in attribute {}", name));
+        }
+        check_text(&value[1..end])?;
+        rest = &value[end + 1..];
+    }
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_-.:".contains(c))
+        && name.matches(':').count() <= 1
+        && !name.ends_with(':');
+    match valid {
+        true => Ok(()),
+        false => Err(format!("invalid name {:?}", name)),
+    }
+}
+
+fn check_text(text: &str) -> Result<(), String> {
+    if text.contains("]]>") {
+        return Err("]]> in text".to_string());
+    }
+    check_chars(text)?;
+    let mut rest = text;
+    while let Some(index) = rest.find('&') {
+        let after = &rest[index + 1..];
+        let end = after
+            .find(';')
+            .ok_or_else(|| format!("bare & in {:?}", truncate(text)))?;
+        let name = &after[..end];
+        let valid = match name.strip_prefix('#') {
+            Some(number) => {
+                let code = match number.strip_prefix('x') {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => number.parse().ok(),
+                };
+                code.and_then(char::from_u32).is_some_and(is_char)
+            }
+            None => matches!(name, "amp" | "lt" | "gt" | "quot" | "apos"),
+        };
+        if !valid {
+            return Err(format!("undefined reference &{};", name));
+        }
+        rest = &after[end + 1..];
+    }
+    Ok(())
+}
+
+fn check_chars(text: &str) -> Result<(), String> {
+    match text.chars().find(|c| !is_char(*c)) {
+        Some(c) => Err(format!("invalid character {:?}", c)),
+        None => Ok(()),
+    }
+}
+
+fn is_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | ' '..='\u{d7ff}' | '\u{e000}'..='\u{fffd}' | '\u{10000}'..)
+}
+
+fn truncate(text: &str) -> String {
+    text.chars().take(40).collect()
+}
+
+/// Every attribute value in `xml` that follows `before`, which ends with
+/// the opening quote: `values(xml, " r:id=\"")`.
+pub fn values<'a>(xml: &'a str, before: &str) -> Vec<&'a str> {
+    xml.match_indices(before)
+        .map(|(index, _)| {
+            let value = &xml[index + before.len()..];
+            &value[..value.find('"').expect("values are quoted")]
+        })
+        .collect()
+}
+
+/// The names and contents of the entries of a ZIP archive, in the order of
+/// its central directory. Only stored entries are supported, and their
+/// local headers and checksums must agree with the directory.
+pub fn unzip(zip: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let u16_at = |at: usize| {
+        zip.get(at..at + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+    };
+    let u32_at = |at: usize| {
+        zip.get(at..at + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+    // The archives have no comment, so the end record is the last 22 bytes.
+    let end = zip.len().checked_sub(22).ok_or("too short")?;
+    if u32_at(end) != Some(0x06054b50) {
+        return Err("no end of central directory record".to_string());
+    }
+    let count = u16_at(end + 10).ok_or("truncated")?;
+    let mut at = u32_at(end + 16).ok_or("truncated")?;
+    if u32_at(end + 12) != Some(end - at) {
+        return Err("wrong central directory size".to_string());
+    }
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        if u32_at(at) != Some(0x02014b50) {
+            return Err(format!("no central directory header at {}", at));
+        }
+        let method = u16_at(at + 10).ok_or("truncated")?;
+        let crc = u32_at(at + 16).ok_or("truncated")?;
+        let size = u32_at(at + 20).ok_or("truncated")?;
+        let uncompressed = u32_at(at + 24).ok_or("truncated")?;
+        let name_length = u16_at(at + 28).ok_or("truncated")?;
+        let extra = u16_at(at + 30).ok_or("truncated")? + u16_at(at + 32).ok_or("truncated")?;
+        let offset = u32_at(at + 42).ok_or("truncated")?;
+        let name = zip.get(at + 46..at + 46 + name_length).ok_or("truncated")?;
+        let name = String::from_utf8(name.to_vec()).map_err(|error| error.to_string())?;
+        at += 46 + name_length + extra;
+
+        if method != 0 || uncompressed != size {
+            return Err(format!("{} is not stored", name));
+        }
+        if u32_at(offset) != Some(0x04034b50)
+            || u16_at(offset + 8) != Some(method)
+            || u32_at(offset + 14) != Some(crc)
+            || u32_at(offset + 18) != Some(size)
+            || u32_at(offset + 22) != Some(size)
+            || zip.get(offset + 30..offset + 30 + name_length) != Some(name.as_bytes())
+        {
+            return Err(format!("the local header of {} differs", name));
+        }
+        let start = offset + 30 + name_length + u16_at(offset + 28).ok_or("truncated")?;
+        let data = zip.get(start..start + size).ok_or("truncated")?.to_vec();
+        if crc32(&data) != crc {
+            return Err(format!("wrong checksum for {}", name));
+        }
+        entries.push((name, data));
+    }
+    Ok(entries)
+}
+
+fn crc32(data: &[u8]) -> usize {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc as usize
+}
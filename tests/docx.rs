@@ -0,0 +1,233 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::{check_document, unzip, values};
+use markdown_wasm::{render_docx, DocxOptions, MarkdownOptions};
+
+const DOCUMENTS: &[&str] = &[
+    "---\ntitle: A & <B>\nauthor: [X, \"Y\"]\n---\n\n# Title\n\nSome *emphasis*, **strong**, `code` and ~~gone~~.\n",
+    "- one\n- two\n  1. nested\n  2. items\n\n3. three\n4. four\n\n- [x] done\n- [ ] todo\n",
+    "| a | b |\n|:-|-:|\n| 1 & 2 | <3 |\n",
+    "> quote\n\n```rust\nfn main() {\n\tprintln!(\"<>&\");\n}\n```\n\n---\n",
+    "[external](https://example.com/?a=1&b=\"2\") and [again](https://example.com/?a=1&b=\"2\"), \
+     [inside](#section), <me@example.com>\n\n## Section\n",
+    "![png](a.png \"t\") ![again](a.png) ![gif](b.gif) ![missing](c.png) ![alt & more](d.txt)\n",
+    "Footnote[^1].\n\n[^1]: The note & more.\n\nTerm\n: Definition\n",
+    "Text with \u{1} control \u{fffe} characters and a tab\there.\n",
+];
+
+/// A 2×1 PNG: the signature and the start of the header chunk, which is
+/// all the size is read from.
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x02\0\0\0\x01";
+const GIF: &[u8] = b"GIF89a\x03\0\x04\0";
+
+fn render(input: &str) -> HashMap<String, String> {
+    let mut docx = DocxOptions::new();
+    docx.add_image("a.png", PNG);
+    docx.add_image("b.gif", GIF);
+    docx.add_image("d.txt", b"not an image");
+    let options = MarkdownOptions {
+        front_matter: true,
+        ..MarkdownOptions::all()
+    };
+    let entries =
+        unzip(&render_docx(input, &options, &docx)).unwrap_or_else(|error| panic!("{}", error));
+    entries
+        .into_iter()
+        .map(|(name, data)| {
+            let text = match name.starts_with("word/media/") {
+                true => format!("{} bytes", data.len()),
+                false => String::from_utf8(data).expect("parts are UTF-8"),
+            };
+            (name, text)
+        })
+        .collect()
+}
+
+#[test]
+fn the_checks_reject_broken_packages() {
+    for xml in [
+        "<a/><b/>",
+        "<?xml version=\"1.0\"?>\ntext<a/>",
+        "<a>",
+        "<?xml version=\"1.0\"?>",
+    ] {
+        assert!(check_document(xml).is_err(), "{:?}", xml);
+    }
+    let docx = render_docx("text\n", &MarkdownOptions::new(), &DocxOptions::new());
+    assert!(unzip(&docx).is_ok());
+    let position = docx
+        .windows(4)
+        .position(|window| window == b"text")
+        .unwrap();
+    let mut corrupted = docx.clone();
+    corrupted[position] = b'T';
+    assert_eq!(
+        unzip(&corrupted).unwrap_err(),
+        "wrong checksum for word/document.xml"
+    );
+    assert!(unzip(&docx[..docx.len() - 1]).is_err());
+}
+
+#[test]
+fn parts_are_well_formed() {
+    for document in DOCUMENTS {
+        let parts = render(document);
+        for name in [
+            "[Content_Types].xml",
+            "_rels/.rels",
+            "docProps/core.xml",
+            "word/document.xml",
+            "word/_rels/document.xml.rels",
+            "word/styles.xml",
+            "word/numbering.xml",
+        ] {
+            let part = parts
+                .get(name)
+                .unwrap_or_else(|| panic!("no {} for {:?}", name, document));
+            if let Err(error) = check_document(part) {
+                panic!("{} in {} for {:?}:\n{}", error, name, document, part);
+            }
+        }
+    }
+}
+
+#[test]
+fn parts_have_content_types() {
+    for document in DOCUMENTS {
+        let parts = render(document);
+        let types = &parts["[Content_Types].xml"];
+        let defaults = values(types, "<Default Extension=\"");
+        let overrides = values(types, " PartName=\"");
+        for name in parts.keys() {
+            let extension = name.rsplit('.').next().unwrap();
+            assert!(
+                overrides.contains(&format!("/{}", name).as_str()) || defaults.contains(&extension),
+                "{} in {:?}",
+                name,
+                document
+            );
+        }
+        for part in overrides {
+            assert!(parts.contains_key(&part[1..]), "{} in {:?}", part, document);
+        }
+    }
+}
+
+#[test]
+fn relationships_resolve() {
+    for document in DOCUMENTS {
+        let parts = render(document);
+        for (rels, base) in [
+            ("_rels/.rels", ""),
+            ("word/_rels/document.xml.rels", "word/"),
+        ] {
+            let rels = &parts[rels];
+            let ids = values(rels, " Id=\"");
+            let mut unique = ids.clone();
+            unique.sort();
+            unique.dedup();
+            assert_eq!(ids.len(), unique.len(), "{:?}", document);
+            // Internal targets are parts of the package.
+            for relationship in rels.split("<Relationship ").skip(1) {
+                let target = values(relationship, "Target=\"")[0];
+                match relationship.contains("TargetMode=\"External\"") {
+                    true => assert!(relationship.contains("/hyperlink\""), "{}", relationship),
+                    false => assert!(
+                        parts.contains_key(&format!("{}{}", base, target)),
+                        "{}",
+                        relationship
+                    ),
+                }
+            }
+        }
+
+        let body = &parts["word/document.xml"];
+        let rels = &parts["word/_rels/document.xml.rels"];
+        let ids = values(rels, " Id=\"");
+        let relationship = |id: &str| {
+            rels.split("<Relationship ")
+                .find(|relationship| relationship.contains(&format!("Id=\"{}\"", id)))
+                .unwrap()
+        };
+        for id in values(body, " r:id=\"") {
+            assert!(ids.contains(&id), "{} in {:?}", id, document);
+            assert!(
+                relationship(id).contains("/hyperlink\""),
+                "{} in {:?}",
+                id,
+                document
+            );
+        }
+        for id in values(body, " r:embed=\"") {
+            assert!(ids.contains(&id), "{} in {:?}", id, document);
+            assert!(
+                relationship(id).contains("/image\""),
+                "{} in {:?}",
+                id,
+                document
+            );
+        }
+        // Anchors point at bookmarks.
+        let bookmarks = values(body, " w:name=\"");
+        for anchor in values(body, " w:anchor=\"") {
+            assert!(bookmarks.contains(&anchor), "{} in {:?}", anchor, document);
+        }
+        // List paragraphs use numbering definitions that exist.
+        let numbering = &parts["word/numbering.xml"];
+        let nums = values(numbering, "<w:num w:numId=\"");
+        for id in values(body, "<w:numId w:val=\"") {
+            assert!(nums.contains(&id), "{} in {:?}", id, document);
+        }
+        let abstracts = values(numbering, "<w:abstractNum w:abstractNumId=\"");
+        for id in values(numbering, "<w:abstractNumId w:val=\"") {
+            assert!(abstracts.contains(&id), "{} in {:?}", id, document);
+        }
+    }
+}
+
+#[test]
+fn links_and_images_share_relationships() {
+    let parts = render(DOCUMENTS[4]);
+    let rels = &parts["word/_rels/document.xml.rels"];
+    assert_eq!(values(rels, " Id=\"").len(), 4);
+    assert!(rels.contains(
+        "Target=\"https://example.com/?a=1&amp;b=&quot;2&quot;\" TargetMode=\"External\"/>"
+    ));
+    assert!(rels.contains("Target=\"mailto:me@example.com\" TargetMode=\"External\"/>"));
+    assert_eq!(
+        values(&parts["word/document.xml"], " r:id=\"rId3\"").len(),
+        2
+    );
+
+    let parts = render(DOCUMENTS[5]);
+    let mut media: Vec<(&str, &str)> = parts
+        .iter()
+        .filter(|(name, _)| name.starts_with("word/media/"))
+        .map(|(name, text)| (name.as_str(), text.as_str()))
+        .collect();
+    media.sort();
+    assert_eq!(
+        media,
+        [
+            ("word/media/image1.png", "24 bytes"),
+            ("word/media/image2.gif", "10 bytes")
+        ]
+    );
+    let body = &parts["word/document.xml"];
+    assert_eq!(values(body, " r:embed=\""), ["rId3", "rId3", "rId4"]);
+    // Images without usable bytes are written as their alt text.
+    assert!(body.contains(">missing</w:t>"));
+    assert!(body.contains(">alt &amp; more</w:t>"));
+    // 2×1 pixels at 9525 EMU each.
+    assert!(body.contains("<wp:extent cx=\"19050\" cy=\"9525\"/>"));
+}
+
+#[test]
+fn properties_come_from_front_matter() {
+    let core = &render(DOCUMENTS[0])["docProps/core.xml"];
+    assert!(core.contains("<dc:title>A &amp; &lt;B&gt;</dc:title><dc:creator>X; Y</dc:creator>"));
+    let core = &render(DOCUMENTS[1])["docProps/core.xml"];
+    assert!(!core.contains("<dc:"));
+}
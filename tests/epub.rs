@@ -0,0 +1,303 @@
+mod common;
+
+use common::{check_document, unzip, values};
+use markdown_wasm::{build_epub_json, EpubOptions, MarkdownOptions};
+use serde_json::json;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn chapters() -> Vec<(&'static str, String)> {
+    vec![
+        (
+            "intro.md",
+            "---\ntitle: The Book\nauthor: [A, B]\nlanguage: fr\ndate: 2024-01-02\n---\n\n\
+             # Introduction\n\n![Picture](images/my%20pic.png) and <img src=\"./raw.gif\">\n\n\
+             Go [on](./next.md#part) or [away](https://example.com/?a&b).\n"
+                .to_string(),
+        ),
+        (
+            "next.md",
+            "## Part {#part}\n\nMath $x^2$, ![remote](https://example.com/a.png) and \
+             ![inline](data:image/png;base64,AAAA).\n\n<div>\n<p>unclosed\n\n*text*\n"
+                .to_string(),
+        ),
+        ("empty.md", String::new()),
+    ]
+}
+
+fn epub() -> EpubOptions {
+    let mut epub = EpubOptions::new();
+    epub.modified = "2024-05-06T07:08:09Z".into();
+    epub.add_resource("images/my pic.png", "image/png", PNG);
+    epub.add_resource("raw.gif", "image/gif", b"GIF89a");
+    epub
+}
+
+fn options() -> MarkdownOptions {
+    MarkdownOptions {
+        heading_attributes: true,
+        math: true,
+        mathml: true,
+        ..MarkdownOptions::gfm()
+    }
+}
+
+fn build(chapters: &[(&str, String)], epub: &EpubOptions) -> Result<Vec<u8>, String> {
+    let chapters: Vec<_> = chapters
+        .iter()
+        .map(|(name, markdown)| json!({ "name": name, "markdown": markdown }))
+        .collect();
+    build_epub_json(&json!(chapters).to_string(), &options(), epub)
+}
+
+fn files(chapters: &[(&str, String)], epub: &EpubOptions) -> Vec<(String, Vec<u8>)> {
+    unzip(&build(chapters, epub).unwrap()).unwrap()
+}
+
+fn text(files: &[(String, Vec<u8>)], name: &str) -> String {
+    let (_, data) = files
+        .iter()
+        .find(|(file, _)| file == name)
+        .unwrap_or_else(|| panic!("no {}", name));
+    String::from_utf8(data.clone()).unwrap()
+}
+
+#[test]
+fn manifest_and_spine_are_complete() {
+    let files = files(&chapters(), &epub());
+    assert_eq!(
+        files[0],
+        ("mimetype".to_string(), b"application/epub+zip".to_vec())
+    );
+    let container = text(&files, "META-INF/container.xml");
+    check_document(&container).unwrap();
+    assert_eq!(values(&container, " full-path=\""), ["OEBPS/content.opf"]);
+
+    let package = text(&files, "OEBPS/content.opf");
+    check_document(&package).unwrap();
+    let items: Vec<&str> = package.split("<item ").skip(1).collect();
+    let ids: Vec<&str> = items.iter().map(|item| values(item, "id=\"")[0]).collect();
+    let hrefs: Vec<&str> = items
+        .iter()
+        .map(|item| values(item, " href=\"")[0])
+        .collect();
+    assert_eq!(
+        hrefs,
+        [
+            "nav.xhtml",
+            "style.css",
+            "chapter-1.xhtml",
+            "chapter-2.xhtml",
+            "chapter-3.xhtml",
+            "images/my%20pic.png",
+            "raw.gif"
+        ]
+    );
+    assert!(items
+        .iter()
+        .all(|item| !values(item, " media-type=\"")[0].is_empty()));
+    assert!(items[0].contains("properties=\"nav\""));
+    assert!(items[3].contains("properties=\"mathml remote-resources\""));
+    // Every file but the package's own is in the manifest, and the
+    // other way around.
+    let mut manifested: Vec<String> = hrefs
+        .iter()
+        .map(|href| format!("OEBPS/{}", href.replace("%20", " ")))
+        .collect();
+    manifested.sort();
+    let mut packed: Vec<String> = files.iter().map(|(name, _)| name.clone()).collect();
+    packed.retain(|name| {
+        !["mimetype", "META-INF/container.xml", "OEBPS/content.opf"].contains(&name.as_str())
+    });
+    packed.sort();
+    assert_eq!(manifested, packed);
+    assert!(files.contains(&("OEBPS/images/my pic.png".to_string(), PNG.to_vec())));
+
+    let spine = values(&package, "<itemref idref=\"");
+    assert_eq!(spine, ["chapter-1", "chapter-2", "chapter-3"]);
+    assert!(spine.iter().all(|id| ids.contains(id)));
+    assert_eq!(
+        values(&package, " unique-identifier=\""),
+        values(&package, "<dc:identifier id=\"")
+    );
+    assert!(package.contains(
+        "<dc:title>The Book</dc:title>\n<dc:language>fr</dc:language>\n<dc:creator>A</dc:creator>\n\
+         <dc:creator>B</dc:creator>\n<dc:date>2024-01-02</dc:date>\n\
+         <meta property=\"dcterms:modified\">2024-05-06T07:08:09Z</meta>\n"
+    ));
+}
+
+#[test]
+fn chapters_are_well_formed() {
+    let files = files(&chapters(), &epub());
+    let nav = text(&files, "OEBPS/nav.xhtml");
+    check_document(&nav).unwrap();
+    assert_eq!(
+        values(&nav, "<a href=\""),
+        ["chapter-1.xhtml", "chapter-2.xhtml", "chapter-3.xhtml"]
+    );
+    assert!(
+        nav.contains(">Introduction</a>")
+            && nav.contains(">next.md</a>")
+            && nav.contains(">empty.md</a>")
+    );
+
+    for index in 1..=3 {
+        let chapter = text(&files, &format!("OEBPS/chapter-{}.xhtml", index));
+        if let Err(error) = check_document(&chapter) {
+            panic!("{} in chapter {}:\n{}", error, index, chapter);
+        }
+        assert!(chapter.contains("<html xmlns=\"http://www.w3.org/1999/xhtml\""));
+        assert!(chapter.contains(" xml:lang=\"fr\" lang=\"fr\">"));
+        // Local references are files of the book.
+        for reference in values(&chapter, " src=\"")
+            .into_iter()
+            .chain(values(&chapter, " href=\""))
+        {
+            let path = reference
+                .split('#')
+                .next()
+                .unwrap()
+                .replace("./", "")
+                .replace("%20", " ");
+            assert!(
+                reference.contains(':')
+                    || files
+                        .iter()
+                        .any(|(name, _)| *name == format!("OEBPS/{}", path)),
+                "{} in chapter {}",
+                reference,
+                index
+            );
+        }
+    }
+    let first = text(&files, "OEBPS/chapter-1.xhtml");
+    assert!(first.contains("<title>Introduction</title>"));
+    assert!(first.contains("<a href=\"chapter-2.xhtml#part\">on</a>"));
+    assert!(first.contains("<a href=\"https://example.com/?a&amp;b\">away</a>"));
+    let second = text(&files, "OEBPS/chapter-2.xhtml");
+    assert!(second.contains("<h2 id=\"part\">Part</h2>"));
+    assert!(second.contains("<math"));
+}
+
+#[test]
+fn missing_resources_are_errors() {
+    let mut epub = EpubOptions::new();
+    epub.modified = "2024-05-06T07:08:09Z".into();
+    assert_eq!(
+        build(&chapters(), &epub).unwrap_err(),
+        "intro.md refers to \"images/my%20pic.png\", which is not one of the resources"
+    );
+    epub.add_resource("images/my pic.png", "image/png", PNG);
+    assert_eq!(
+        build(&chapters(), &epub).unwrap_err(),
+        "intro.md refers to \"./raw.gif\", which is not one of the resources"
+    );
+    // Text that looks like a reference is not one.
+    let chapters = [(
+        "a.md",
+        "`<img src=\"x.png\">` and <!-- <img src=\"y.png\"> -->\n".to_string(),
+    )];
+    assert!(build(&chapters, &epub).is_ok());
+}
+
+#[test]
+fn references_are_unescaped_and_decoded() {
+    let chapters = [(
+        "a.md",
+        "<img src=\"a&amp;b.png\" alt='x src=\"no\"'> src=\"text\"\n\n\
+         <video controls src=\"&#x76;.mp4\"></video>\n\n<!-- <img src=\"c.png\"> -->\n\n\
+         ![x](my%20pic%C3%A9.png)\n"
+            .to_string(),
+    )];
+    let mut epub = epub();
+    for (href, media_type) in [
+        ("a&b.png", "image/png"),
+        ("v.mp4", "video/mp4"),
+        ("my picé.png", "image/png"),
+    ] {
+        assert!(build(&chapters, &epub).is_err(), "{:?}", href);
+        epub.add_resource(href, media_type, PNG);
+    }
+    assert!(build(&chapters, &epub).is_ok());
+}
+
+#[test]
+fn resources_must_be_inside_the_book() {
+    for href in [
+        "",
+        "/abs.png",
+        "../up.png",
+        "a/../../up.png",
+        "http://x/y.png",
+        "nav.xhtml",
+        "chapter-2.xhtml",
+    ] {
+        let mut epub = epub();
+        epub.add_resource(href, "image/png", PNG);
+        assert!(build(&chapters(), &epub).is_err(), "{:?}", href);
+    }
+    let mut epub = epub();
+    epub.add_resource("chapter-4.xhtml", "application/xhtml+xml", b"");
+    assert!(build(&chapters(), &epub).is_ok());
+    epub.add_resource("raw.gif", "", b"GIF89a");
+    assert_eq!(
+        build(&chapters(), &epub).unwrap_err(),
+        "resource \"raw.gif\" has no media type"
+    );
+}
+
+#[test]
+fn modified_time() {
+    let modified = |time: &str, date: Option<&str>| {
+        let mut chapters = chapters();
+        chapters[0].1 = chapters[0].1.replace("date: 2024-01-02\n", "");
+        if let Some(date) = date {
+            chapters[0].1 = chapters[0]
+                .1
+                .replace("---\n\n", &format!("date: {}\n---\n\n", date));
+        }
+        let mut epub = epub();
+        epub.modified = time.to_string();
+        let files = unzip(&build(&chapters, &epub)?).unwrap();
+        let package = text(&files, "OEBPS/content.opf");
+        let (_, after) = package.split_once("\"dcterms:modified\">").unwrap();
+        Ok::<_, String>(after[..after.find('<').unwrap()].to_string())
+    };
+    assert_eq!(
+        modified("2024-05-06T07:08:09Z", Some("2020-01-01")).as_deref(),
+        Ok("2024-05-06T07:08:09Z")
+    );
+    assert_eq!(
+        modified("", Some("2020-01-01")).as_deref(),
+        Ok("2020-01-01T00:00:00Z")
+    );
+    assert_eq!(
+        modified("", Some("2020-01-01T10:00:00+02:00")).as_deref(),
+        Ok("2020-01-01T00:00:00Z")
+    );
+    for (time, date) in [
+        ("", Some("January 2020")),
+        ("2024-05-06", None),
+        ("2024-05-06T07:08:09+01:00", None),
+        ("2024-05-06 07:08:09Z", None),
+    ] {
+        assert!(modified(time, date).is_err(), "{:?} {:?}", time, date);
+    }
+    assert_eq!(
+        modified("", None).unwrap_err(),
+        "the book needs a modification time or a front matter date"
+    );
+}
+
+#[test]
+fn builds_are_reproducible() {
+    let build = || build(&chapters(), &epub()).unwrap();
+    assert_eq!(build(), build());
+}
+
+#[test]
+fn chapters_must_be_a_json_list() {
+    assert!(build_epub_json("{}", &options(), &epub()).is_err());
+    assert!(build_epub_json("[]", &options(), &epub()).is_ok());
+}
@@ -0,0 +1,303 @@
+use markdown_wasm::{
+    format_markdown, parse_markdown_with_options, BulletMarker, EmphasisMarker, FenceStyle,
+    FormatOptions, MarkdownOptions, ProseWrap,
+};
+
+const DOCUMENTS: &[&str] = &[
+    "Title\n=====\n\nSubtitle\n--------\n\n### Closed ###\n",
+    "Some *emphasis*, _more_, __strong__ and ***both***.\n\
+     A second line with `code`, ``a ` tick`` and \\*escaped\\* text.\n",
+    "snake_case and intra*word*emphasis stay as they are: foo__bar__baz.\n",
+    "* one\n* two\n    * nested\n    * items\n\n\
+     + a second list\n\n1) first\n2) second\n\n7. seven\n8. eight\n",
+    "- loose\n\n- items\n\n  with two paragraphs\n",
+    "- [x] done\n- [ ] todo\n",
+    "> quote\n> with *two* lines\n>\n> > nested\n\n> [!NOTE]\n> An alert.\n",
+    "    indented code\n\n~~~ js\nlet x = 1;\n~~~\n\n````\n```\nfence in a fence\n```\n````\n",
+    "| a | b | c |\n|:-|:-:|-:|\n| long cell | x | `a \\| b` |\n| y |\n",
+    "See [ref], [ref][] and [text][ref], or [inline](/url \"title\") and ![image](/img.png).\n\n\
+     [ref]: <http://example.com/a b> 'title'\n",
+    "<https://example.com> and <me@example.com>.\n\n\
+     <div>\n*raw* html\n</div>\n\nInline <span>html</span>.\n",
+    "Footnote[^1] here.\n\n[^1]: The note,\n    over two lines.\n",
+    "Text that looks like markup once rewrapped: the year was\n\
+     1984. Then + and - and # and > signs at the\nstart of words.\n",
+    "Hard  \nbreak and another\\\nbreak.\n\n---\n\nafter a rule\n",
+    "1\\. not a list\n\\# not a heading\n\\- not an item\n&lt;b&gt; not a tag &amp;copy; not an entity\n",
+];
+
+fn options() -> MarkdownOptions {
+    MarkdownOptions::gfm()
+}
+
+fn styles() -> Vec<FormatOptions> {
+    vec![
+        FormatOptions::new(),
+        FormatOptions {
+            prose_wrap: ProseWrap::Always,
+            line_width: 24,
+            ..FormatOptions::new()
+        },
+        FormatOptions {
+            prose_wrap: ProseWrap::Never,
+            bullet: BulletMarker::Asterisk,
+            emphasis: EmphasisMarker::Underscore,
+            strong: EmphasisMarker::Underscore,
+            fence: FenceStyle::Tilde,
+            ..FormatOptions::new()
+        },
+    ]
+}
+
+/// Rewrapping turns spaces into line breaks and back, which HTML does not
+/// tell apart.
+fn normalize(html: &str) -> String {
+    html.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[test]
+fn formatting_preserves_the_rendered_html() {
+    for document in DOCUMENTS {
+        for style in styles() {
+            let formatted = format_markdown(document, &options(), &style);
+            assert_eq!(
+                normalize(&parse_markdown_with_options(document, &options())),
+                normalize(&parse_markdown_with_options(&formatted, &options())),
+                "{:?} formatted as {:?} with {:?}",
+                document,
+                formatted,
+                style,
+            );
+        }
+    }
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    for document in DOCUMENTS {
+        for style in styles() {
+            let formatted = format_markdown(document, &options(), &style);
+            assert_eq!(
+                formatted,
+                format_markdown(&formatted, &options(), &style),
+                "{:?}",
+                style
+            );
+        }
+    }
+}
+
+#[test]
+fn headings_become_atx() {
+    let formatted = format_markdown(DOCUMENTS[0], &options(), &FormatOptions::new());
+    assert_eq!(formatted, "# Title\n\n## Subtitle\n\n### Closed\n");
+}
+
+#[test]
+fn list_markers_are_consistent() {
+    let formatted = format_markdown(DOCUMENTS[3], &options(), &FormatOptions::new());
+    assert_eq!(
+        formatted,
+        "- one\n- two\n  - nested\n  - items\n\n\
+         * a second list\n\n1. first\n2. second\n\n7) seven\n8) eight\n",
+    );
+}
+
+#[test]
+fn tables_are_aligned() {
+    let formatted = format_markdown(DOCUMENTS[8], &options(), &FormatOptions::new());
+    assert_eq!(
+        formatted,
+        "| a         |  b  |        c |\n\
+         | :-------- | :-: | -------: |\n\
+         | long cell |  x  | `a \\| b` |\n\
+         | y         |     |          |\n",
+    );
+}
+
+#[test]
+fn paragraphs_wrap_and_unwrap() {
+    let document =
+        "A paragraph that is long enough to wrap, with a [link text](/url) in it.\nAnd a second line.\n";
+    let wrap = |prose_wrap, line_width| {
+        let format = FormatOptions {
+            prose_wrap,
+            line_width,
+            ..FormatOptions::new()
+        };
+        format_markdown(document, &options(), &format)
+    };
+    assert_eq!(wrap(ProseWrap::Preserve, 20), document);
+    assert_eq!(
+        wrap(ProseWrap::Always, 30),
+        "A paragraph that is long\nenough to wrap, with a [link\ntext](/url) in it. And a\nsecond line.\n",
+    );
+    assert_eq!(
+        wrap(ProseWrap::Never, 30),
+        "A paragraph that is long enough to wrap, with a [link text](/url) in it. And a second line.\n",
+    );
+}
+
+#[test]
+fn escapes_markup_at_the_start_of_wrapped_lines() {
+    let style = FormatOptions {
+        prose_wrap: ProseWrap::Always,
+        line_width: 8,
+        ..FormatOptions::new()
+    };
+    let formatted = format_markdown("aaaaaaaa 1. bbbbbbbb - cccccccc # d\n", &options(), &style);
+    assert_eq!(
+        formatted,
+        "aaaaaaaa\n1\\.\nbbbbbbbb\n\\-\ncccccccc\n\\# d\n"
+    );
+}
+
+#[test]
+fn emphasis_and_fences_follow_the_style() {
+    let style = FormatOptions {
+        emphasis: EmphasisMarker::Underscore,
+        strong: EmphasisMarker::Asterisk,
+        fence: FenceStyle::Tilde,
+        ..FormatOptions::new()
+    };
+    let formatted = format_markdown("*a* __b__ c*d*e\n\n```rust\nx\n```\n", &options(), &style);
+    assert_eq!(formatted, "_a_ **b** c*d*e\n\n~~~rust\nx\n~~~\n");
+}
+
+#[test]
+fn nested_emphasis_follows_the_style() {
+    let formatted = |emphasis, strong| {
+        let style = FormatOptions {
+            emphasis,
+            strong,
+            ..FormatOptions::new()
+        };
+        format_markdown("***bold italic***\n", &options(), &style)
+    };
+    let (asterisk, underscore) = (EmphasisMarker::Asterisk, EmphasisMarker::Underscore);
+    assert_eq!(formatted(asterisk, asterisk), "***bold italic***\n");
+    assert_eq!(formatted(underscore, asterisk), "_**bold italic**_\n");
+    assert_eq!(formatted(asterisk, underscore), "*__bold italic__*\n");
+    assert_eq!(formatted(underscore, underscore), "___bold italic___\n");
+}
+
+#[test]
+fn edge_cases_keep_their_meaning() {
+    let cases = [
+        ("\0\n", "\0\n"),
+        ("a\0", "a\0\n"),
+        ("a\0/b\n", "a\0/b\n"),
+        ("# Title&nbsp;\n", "# Title\u{a0}\n"),
+        ("- <div>\n\n\t***\n", "- <div>\n\n  ___\n"),
+        ("> *| 1 |\n|-|:-:|\n", "> \\*| 1 |\n> \\|-|:-:|\n"),
+        ("*$x^2$    * \n", "\\*$x^2$    \\*\n"),
+    ];
+    for options in [options(), MarkdownOptions::all()] {
+        for (input, expected) in cases {
+            let formatted = format_markdown(input, &options, &FormatOptions::new());
+            assert_eq!(formatted, expected, "{:?}", input);
+            assert_eq!(
+                normalize(&parse_markdown_with_options(input, &options)),
+                normalize(&parse_markdown_with_options(&formatted, &options)),
+                "{:?}",
+                input
+            );
+        }
+    }
+}
+
+#[test]
+fn random_markup_formats_stably() {
+    let pieces = [
+        "*",
+        "**",
+        "***",
+        "_",
+        "__",
+        "`",
+        "~",
+        "~~",
+        "$",
+        "^",
+        "# ",
+        "- ",
+        "* ",
+        "1. ",
+        "2) ",
+        "> ",
+        "|",
+        "|-|",
+        ":-:",
+        "\n",
+        "\n\n",
+        " ",
+        "    ",
+        "\t",
+        "a",
+        "b c",
+        "x^2",
+        "&nbsp;",
+        "&amp;",
+        "&",
+        "<div>",
+        "</div>",
+        "<span>",
+        "<",
+        ">",
+        "[",
+        "]",
+        "](/u)",
+        "[^1]",
+        "[^1]: ",
+        "\\",
+        "***\n",
+        "---",
+        "===",
+        "```",
+        "\0",
+        "<http://a.b>",
+        "www.x.y",
+        ": ",
+        "[x] ",
+        "'",
+        "\u{a0}",
+        "{#id}",
+        "[[w]]",
+        "<!--",
+        "-->",
+    ];
+    let mut seed: u64 = 0x2545f4914f6cdd1d;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    let presets = [MarkdownOptions::gfm(), MarkdownOptions::all()];
+    let styles = styles();
+    for _ in 0..2000 {
+        let length = next() % 16;
+        let mut input: String = (0..length)
+            .map(|_| pieces[(next() % pieces.len() as u64) as usize])
+            .collect();
+        input.push('\n');
+        let options = &presets[(next() % presets.len() as u64) as usize];
+        let style = &styles[(next() % styles.len() as u64) as usize];
+        let formatted = format_markdown(&input, options, style);
+        assert_eq!(
+            normalize(&parse_markdown_with_options(&input, options)),
+            normalize(&parse_markdown_with_options(&formatted, options)),
+            "{:?} formatted as {:?} with {:?}",
+            input,
+            formatted,
+            style,
+        );
+        assert_eq!(
+            formatted,
+            format_markdown(&formatted, options, style),
+            "{:?} with {:?}",
+            input,
+            style
+        );
+    }
+}
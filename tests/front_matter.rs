@@ -0,0 +1,150 @@
+use markdown_wasm::{
+    parse_markdown_with_front_matter_json, render_man, FrontMatterSchema, MarkdownOptions,
+};
+use serde_json::{json, Value};
+
+fn parse(input: &str) -> Value {
+    serde_json::from_str(&parse_markdown_with_front_matter_json(
+        input,
+        &MarkdownOptions::new(),
+    ))
+    .unwrap()
+}
+
+fn value(input: &str) -> Value {
+    let result = parse(input);
+    assert_eq!(result.get("frontMatterError"), None, "{:?}", input);
+    result["frontMatter"].clone()
+}
+
+fn error(input: &str) -> (String, u64, u64) {
+    let error = &parse(input)["frontMatterError"];
+    (
+        error["message"]
+            .as_str()
+            .expect("invalid front matter")
+            .to_string(),
+        error["line"].as_u64().unwrap(),
+        error["column"].as_u64().unwrap(),
+    )
+}
+
+/// The document line of the value at `pointer`, as schema diagnostics
+/// report it: the schema rejects only that value.
+fn line(input: &str, pointer: &str) -> u64 {
+    let mut schema = json!(false);
+    for key in pointer
+        .split('/')
+        .skip(1)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+    {
+        schema = match key.parse::<usize>() {
+            Ok(index) => {
+                let mut items = vec![json!(true); index];
+                items.push(schema);
+                json!({ "prefixItems": items })
+            }
+            Err(_) => json!({ "properties": { key: schema } }),
+        };
+    }
+    let schema = FrontMatterSchema::from_json(&schema.to_string()).unwrap();
+    let diagnostics: Value = serde_json::from_str(&schema.validate_json(input)).unwrap();
+    assert_eq!(diagnostics[0]["instancePath"], pointer);
+    diagnostics[0]["line"].as_u64().unwrap()
+}
+
+#[test]
+fn yaml_scalars_follow_the_core_schema() {
+    let input =
+        "---\nk: &a [1, 2]\nl: *a\nm: !!str 12\nn: '3'\no: 1.5\np: ~\nq: yes\nr: true\n---\nbody\n";
+    assert_eq!(
+        value(input),
+        json!({"k": [1, 2], "l": [1, 2], "m": "12", "n": "3", "o": 1.5, "p": null, "q": "yes", "r": true})
+    );
+    assert_eq!(parse(input)["html"], "<p>body</p>\n");
+    assert_eq!(parse(input)["frontMatterFormat"], "yaml");
+}
+
+#[test]
+fn yaml_lines_are_document_lines() {
+    let input = "---\ntitle: a\ntags:\n  - x\n  - y\n---\n";
+    assert_eq!(line(input, ""), 1);
+    assert_eq!(line(input, "/title"), 2);
+    assert_eq!(line(input, "/tags"), 3);
+    assert_eq!(line(input, "/tags/1"), 5);
+}
+
+#[test]
+fn yaml_errors_point_into_the_document() {
+    assert_eq!(
+        error("---\na: 1\n  b: 2\n---\n"),
+        (
+            "mapping values are not allowed in this context".to_string(),
+            3,
+            4
+        )
+    );
+    assert_eq!(
+        error("---\na: *x\n---\n"),
+        ("while parsing node, found unknown anchor".to_string(), 2, 4)
+    );
+    assert_eq!(error("---\ntitle: a\nbad: [1, 2\n---\n").1, 4);
+}
+
+#[test]
+fn yaml_aliases_are_limited() {
+    let mut input =
+        String::from("---\na0: &a0 [lol, lol, lol, lol, lol, lol, lol, lol, lol, lol]\n");
+    for level in 1..9 {
+        let aliases = vec![format!("*a{}", level - 1); 10].join(", ");
+        input.push_str(&format!("a{0}: &a{0} [{1}]\n", level, aliases));
+    }
+    input.push_str("---\n");
+    let (message, line, _) = error(&input);
+    assert_eq!(message, "front matter expands to too many values");
+    assert_eq!(line, 5);
+    // A few aliases of a small value are fine.
+    assert_eq!(
+        value("---\na: &a {x: 1}\nb: [*a, *a]\n---\n"),
+        json!({"a": {"x": 1}, "b": [{"x": 1}, {"x": 1}]})
+    );
+}
+
+#[test]
+fn toml_values_and_lines() {
+    let input = "+++\ntitle = \"a\"\ndate = 2024-01-02\nn = 0x10\n[t]\nx = [1.5, true]\n+++\n";
+    assert_eq!(
+        value(input),
+        json!({"title": "a", "date": "2024-01-02", "n": 16, "t": {"x": [1.5, true]}})
+    );
+    assert_eq!(parse(input)["frontMatterFormat"], "toml");
+    assert_eq!(line(input, "/date"), 3);
+    assert_eq!(line(input, "/t/x/1"), 6);
+}
+
+#[test]
+fn toml_errors_count_columns_in_characters() {
+    assert_eq!(
+        error("+++\na = 1\n[t]\n  x = \"é\" y\n+++\n"),
+        (
+            "unexpected key or value, expected newline, `#`".to_string(),
+            4,
+            11
+        )
+    );
+    assert_eq!(error("+++\ntitle = \"a\"\nbad = \n+++\n").1, 3);
+}
+
+#[test]
+fn blocks_need_content_and_a_closing_fence() {
+    for input in ["---\n\na: 1\n---\n", "---\na: 1\n", "--- x\na: 1\n---\n"] {
+        assert_eq!(parse(input).get("frontMatterFormat"), None, "{:?}", input);
+    }
+    assert_eq!(value("---\r\na: 1\r\n...\r\n"), json!({"a": 1}));
+    // Only a mapping has fields.
+    let options = MarkdownOptions::new();
+    assert!(render_man("---\n- a\n---\n", &options).starts_with(".TH \"UNTITLED\""));
+    assert!(render_man("---\nname: a\n---\n", &options).starts_with(".TH \"A\""));
+}
@@ -0,0 +1,90 @@
+use markdown_wasm::{
+    parse_markdown, parse_markdown_gfm, parse_markdown_with_options, MarkdownOptions,
+};
+
+#[test]
+fn www_links_get_an_http_scheme() {
+    assert_eq!(
+        parse_markdown_gfm("Visit www.example.com/path?x=1.\n"),
+        "<p>Visit <a href=\"http://www.example.com/path?x=1\">www.example.com/path?x=1</a>.</p>\n"
+    );
+}
+
+#[test]
+fn trailing_punctuation_and_unbalanced_parentheses_are_left_out() {
+    assert_eq!(
+        parse_markdown_gfm("See https://example.com/a_(b)) and http://x.org, now.\n"),
+        "<p>See <a href=\"https://example.com/a_(b)\">https://example.com/a_(b)</a>) and \
+         <a href=\"http://x.org\">http://x.org</a>, now.</p>\n"
+    );
+}
+
+#[test]
+fn entity_like_suffixes_and_angle_brackets_end_a_link() {
+    assert_eq!(
+        parse_markdown_gfm("www.commonmark.org/he&lt;lp and www.x.com/a&amp;b&hl;\n"),
+        "<p><a href=\"http://www.commonmark.org/he\">www.commonmark.org/he</a>&lt;lp and \
+         <a href=\"http://www.x.com/a&amp;b\">www.x.com/a&amp;b</a>&amp;hl;</p>\n"
+    );
+}
+
+#[test]
+fn email_addresses_become_mailto_links() {
+    assert_eq!(
+        parse_markdown_gfm("Mail me at foo.bar+baz@example.co.uk.\n"),
+        "<p>Mail me at <a href=\"mailto:foo.bar+baz@example.co.uk\">foo.bar+baz@example.co.uk</a>.</p>\n"
+    );
+    assert_eq!(
+        parse_markdown_gfm("a@b and x@y.\n"),
+        "<p>a@b and x@y.</p>\n"
+    );
+}
+
+#[test]
+fn domains_need_a_period_and_no_underscore_at_the_end() {
+    assert_eq!(
+        parse_markdown_gfm("www.a_b.c and http://localhost\n"),
+        "<p>www.a_b.c and http://localhost</p>\n"
+    );
+    assert_eq!(
+        parse_markdown_gfm("(www.example.com)\n"),
+        "<p>(<a href=\"http://www.example.com\">www.example.com</a>)</p>\n"
+    );
+}
+
+#[test]
+fn code_and_links_are_not_linked() {
+    assert_eq!(
+        parse_markdown_gfm("`www.example.com` and [www.example.com](/x)\n\n    www.example.com\n"),
+        "<p><code>www.example.com</code> and <a href=\"/x\">www.example.com</a></p>\n\
+         <pre><code>www.example.com\n</code></pre>\n"
+    );
+}
+
+#[test]
+fn tagfilter_escapes_disallowed_tags_only() {
+    assert_eq!(
+        parse_markdown_gfm("<script>alert(1)</script>\n\n<div>ok <iframe src=x></iframe> <Title> <textareax></div>\n"),
+        "&lt;script>alert(1)&lt;/script>\n<div>ok &lt;iframe src=x>&lt;/iframe> &lt;Title> <textareax></div>\n"
+    );
+    assert_eq!(
+        parse_markdown_gfm("inline <style>x</style> and <scriptx>\n"),
+        "<p>inline &lt;style>x&lt;/style> and <scriptx></p>\n"
+    );
+}
+
+#[test]
+fn commonmark_has_neither_extension() {
+    assert_eq!(
+        parse_markdown("www.example.com <script>\n"),
+        "<p>www.example.com <script></p>\n"
+    );
+    let options = MarkdownOptions {
+        autolinks: true,
+        ..MarkdownOptions::commonmark()
+    };
+    assert_eq!(
+        parse_markdown_with_options("www.example.com <script>\n", &options),
+        "<p><a href=\"http://www.example.com\">www.example.com</a> <script></p>\n"
+    );
+}
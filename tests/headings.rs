@@ -0,0 +1,62 @@
+use markdown_wasm::{parse_markdown_with_options, MarkdownOptions};
+
+fn render(input: &str, options: MarkdownOptions) -> String {
+    parse_markdown_with_options(input, &options)
+}
+
+fn ids() -> MarkdownOptions {
+    MarkdownOptions {
+        heading_ids: true,
+        ..MarkdownOptions::new()
+    }
+}
+
+#[test]
+fn slugs_follow_github() {
+    assert_eq!(
+        render(
+            "# Hello, *World*!\n\n## `render()` -- the_API\n\n### Ünïcödé 日本語\n",
+            ids()
+        ),
+        "<h1 id=\"hello-world\">Hello, <em>World</em>!</h1>\n\
+         <h2 id=\"render----the_api\"><code>render()</code> -- the_API</h2>\n\
+         <h3 id=\"ünïcödé-日本語\">Ünïcödé 日本語</h3>\n"
+    );
+}
+
+#[test]
+fn repeated_headings_get_numbered() {
+    assert_eq!(
+        render("# A\n\n# A\n\n# A-1\n\n# A\n", ids()),
+        "<h1 id=\"a\">A</h1>\n<h1 id=\"a-1\">A</h1>\n<h1 id=\"a-1-1\">A-1</h1>\n<h1 id=\"a-2\">A</h1>\n"
+    );
+}
+
+#[test]
+fn explicit_ids_win_and_are_avoided() {
+    let options = MarkdownOptions {
+        heading_attributes: true,
+        ..ids()
+    };
+    assert_eq!(
+        render("# Intro\n\n# Other {#intro}\n", options),
+        "<h1 id=\"intro-1\">Intro</h1>\n<h1 id=\"intro\">Other</h1>\n"
+    );
+}
+
+#[test]
+fn anchors_link_to_the_heading() {
+    let options = MarkdownOptions {
+        heading_anchors: true,
+        ..MarkdownOptions::new()
+    };
+    assert_eq!(
+        render("## Set \"up\"\n", options),
+        "<h2 id=\"set-up\"><a class=\"heading-anchor\" href=\"#set-up\" aria-hidden=\"true\">#</a>Set \"up\"</h2>\n"
+    );
+}
+
+#[test]
+fn headings_have_no_ids_by_default() {
+    assert_eq!(render("# A\n", MarkdownOptions::new()), "<h1>A</h1>\n");
+}
@@ -0,0 +1,126 @@
+use markdown_wasm::{highlight_code, parse_markdown_with_options, HighlightStyle, MarkdownOptions};
+
+fn classes(code: &str, language: &str) -> String {
+    highlight_code(code, language, HighlightStyle::Classes).unwrap()
+}
+
+#[test]
+fn rust_raw_strings_end_at_their_own_delimiter() {
+    assert_eq!(
+        classes("let s = r##\"a \"# b\"##; // c\nfn x() {}", "rust"),
+        "<span class=\"hl-keyword\">let</span> s = <span class=\"hl-string\">r##\"a \"# b\"##</span>; \
+         <span class=\"hl-comment\">// c</span>\n<span class=\"hl-keyword\">fn</span> \
+         <span class=\"hl-function\">x</span>() {}"
+    );
+    assert_eq!(
+        classes("br#\"x\"# r\"C:\\\" y", "rs"),
+        "<span class=\"hl-string\">br#\"x\"#</span> <span class=\"hl-string\">r\"C:\\\"</span> y"
+    );
+    assert_eq!(
+        classes("r#\"open\nstill", "rust"),
+        "<span class=\"hl-string\">r#\"open\nstill</span>"
+    );
+}
+
+#[test]
+fn rust_identifiers_named_r_are_not_strings() {
+    assert_eq!(classes("r # x", "rust"), "r # x");
+    assert_eq!(
+        classes("r#type", "rust"),
+        "r#<span class=\"hl-keyword\">type</span>"
+    );
+}
+
+#[test]
+fn rust_lifetimes_chars_attributes_and_macros() {
+    assert_eq!(
+        classes("#[derive(Debug)]\nfn f<'a>(c: char) { println!(\"{}\", 'x'); }", "rust"),
+        "<span class=\"hl-attribute\">#[derive(Debug)]</span>\n<span class=\"hl-keyword\">fn</span> f&lt;<span class=\"hl-variable\">'a</span>&gt;(c: \
+         <span class=\"hl-type\">char</span>) { <span class=\"hl-function\">println</span>!(\
+         <span class=\"hl-string\">\"{}\"</span>, <span class=\"hl-string\">'x'</span>); }"
+    );
+}
+
+#[test]
+fn strings_comments_and_numbers() {
+    assert_eq!(
+        classes("const a = `t${x}`; // c <b>", "ts"),
+        "<span class=\"hl-keyword\">const</span> a = <span class=\"hl-string\">`t${x}`</span>; \
+         <span class=\"hl-comment\">// c &lt;b&gt;</span>"
+    );
+    assert_eq!(
+        classes("x = 0x1F + 1.5e-3 /* a\nb */", "js"),
+        "x = <span class=\"hl-number\">0x1F</span> + <span class=\"hl-number\">1.5e-3</span> \
+         <span class=\"hl-comment\">/* a\nb */</span>"
+    );
+}
+
+#[test]
+fn data_languages_mark_keys() {
+    assert_eq!(
+        classes("{\"a\": [1, true]}", "json"),
+        "{<span class=\"hl-property\">\"a\"</span>: [<span class=\"hl-number\">1</span>, \
+         <span class=\"hl-literal\">true</span>]}"
+    );
+    assert_eq!(
+        classes("x = 1 # c\n[t]", "toml"),
+        "<span class=\"hl-property\">x</span> = <span class=\"hl-number\">1</span> \
+         <span class=\"hl-comment\"># c</span>\n<span class=\"hl-type\">[t]</span>"
+    );
+    assert_eq!(
+        classes("k: 'v' # c\nn: 1", "yaml"),
+        "<span class=\"hl-property\">k</span>: <span class=\"hl-string\">'v'</span> \
+         <span class=\"hl-comment\"># c</span>\n<span class=\"hl-property\">n</span>: \
+         <span class=\"hl-number\">1</span>"
+    );
+}
+
+#[test]
+fn shell_python_and_sql() {
+    assert_eq!(
+        classes("echo $HOME 'a' a#b # c", "sh"),
+        "<span class=\"hl-function\">echo</span> <span class=\"hl-variable\">$HOME</span> \
+         <span class=\"hl-string\">'a'</span> a#b <span class=\"hl-comment\"># c</span>"
+    );
+    assert_eq!(
+        classes("@d\ndef f(): return None", "py"),
+        "<span class=\"hl-attribute\">@d</span>\n<span class=\"hl-keyword\">def</span> \
+         <span class=\"hl-function\">f</span>(): <span class=\"hl-keyword\">return</span> \
+         <span class=\"hl-literal\">None</span>"
+    );
+    assert_eq!(
+        classes("SELECT 1 FROM t -- c", "SQL"),
+        "<span class=\"hl-keyword\">SELECT</span> <span class=\"hl-number\">1</span> \
+         <span class=\"hl-keyword\">FROM</span> t <span class=\"hl-comment\">-- c</span>"
+    );
+}
+
+#[test]
+fn unknown_languages_are_not_highlighted() {
+    assert_eq!(highlight_code("x", "cobol", HighlightStyle::Classes), None);
+    let options = MarkdownOptions {
+        highlight: true,
+        ..MarkdownOptions::new()
+    };
+    assert_eq!(
+        parse_markdown_with_options("```cobol\n<x>\n```\n", &options),
+        "<pre><code class=\"language-cobol\">&lt;x&gt;\n</code></pre>\n"
+    );
+}
+
+#[test]
+fn inline_style_and_code_blocks() {
+    assert_eq!(
+        highlight_code("let x = 1;", "rs", HighlightStyle::Inline).unwrap(),
+        "<span style=\"color:#d73a49\">let</span> x = <span style=\"color:#005cc5\">1</span>;"
+    );
+    let options = MarkdownOptions {
+        highlight: true,
+        ..MarkdownOptions::new()
+    };
+    assert_eq!(
+        parse_markdown_with_options("```rust\nlet x = r#\"\"\"#;\n```\n", &options),
+        "<pre><code class=\"language-rust\"><span class=\"hl-keyword\">let</span> x = \
+         <span class=\"hl-string\">r#\"\"\"#</span>;\n</code></pre>\n"
+    );
+}
@@ -0,0 +1,138 @@
+use markdown_wasm::{parse_markdown_with_options, MarkdownOptions, SourceMap};
+use pulldown_cmark::{html, Options, Parser};
+
+/// Documents touching every event the writer handles.
+const DOCUMENTS: &[&str] = &[
+    "# Heading\n\nSetext\n======\n\n## Closed ##\n\n###### Six\n",
+    "Some *emphasis*, __strong__, ~~struck~~, `code` and a  \nhard break\\\nand a soft\nbreak.\n",
+    "* one\n* two\n    * nested\n\n1. first\n\n   para\n2. second\n\n7) seven\n",
+    "- [x] done\n- [ ] todo\n\n---\n\n***\n",
+    "> quote\n>\n> > nested\n\n> [!NOTE]\n> An alert.\n\n> [!WARNING]\n> Careful.\n",
+    "    indented <code>\n\n~~~ rust,ignore\nlet x = \"<&>\";\n~~~\n\n```\nplain\n```\n",
+    "| a | b | c | d |\n|:-|:-:|-:|-|\n| 1 | `x \\| y` | **b** |\n| 2 |\n",
+    "[link](/url \"title\") [ref] ![image *alt*](/img.png \"t\") ![ref]\n\n[ref]: /r&amp;s?a=1&b=\"2\"\n",
+    "<https://example.com> <me@example.com> [empty]() [spaces](<a b>)\n",
+    "<div>\n*raw* html\n</div>\n\nInline <span class=\"x\">html</span> &amp; &copy; &#35; & <!-- c -->\n",
+    "Footnote[^1] and another[^note] and again[^1].\n\n[^1]: The note.\n\n[^note]: Second\n\n    with code\n",
+    "[^unused]: Defined first.\n\nText[^unused].\n",
+    "Math $x^2$ and $$\\int_0^1 f$$ inline.\n\n$$\na + b\n$$\n",
+    "Term\n: Definition\n: Another\n\nSecond term\n\n: Loose definition\n",
+    "^super^ and ~sub~ and ~~strike~~.\n",
+    "[[Wiki Link]] and [[target|label]].\n",
+    "---\ntitle: front\n---\n\nBody.\n\n+++\nx = 1\n+++\n",
+    "Smart \"quotes\" -- and 'apostrophes' --- ... done.\n",
+    "# Heading {#custom .class key=value}\n\nText\n",
+    "\n\n  \n",
+    "<p>block</p>\n<script>\nlet a = 1 < 2;\n</script>\n\nafter\n",
+    "Emoji 😀 and é and <T> text.\n\n|x|\n|-|\n|😀|\n",
+];
+
+fn presets() -> Vec<MarkdownOptions> {
+    vec![
+        MarkdownOptions::commonmark(),
+        MarkdownOptions::extended(),
+        MarkdownOptions::all(),
+        MarkdownOptions {
+            subscript: true,
+            old_footnotes: true,
+            ..MarkdownOptions::all()
+        },
+        MarkdownOptions {
+            autolinks: false,
+            tagfilter: false,
+            heading_ids: false,
+            ..MarkdownOptions::gfm()
+        },
+    ]
+}
+
+#[test]
+fn output_matches_pulldown_cmark() {
+    for options in presets() {
+        for document in DOCUMENTS {
+            let mut expected = String::new();
+            html::push_html(
+                &mut expected,
+                Parser::new_ext(document, Options::from(&options)),
+            );
+            assert_eq!(
+                parse_markdown_with_options(document, &options),
+                expected,
+                "\n{:?}\n{:?}",
+                document,
+                options
+            );
+        }
+    }
+}
+
+#[test]
+fn source_positions_tag_block_elements() {
+    let options = MarkdownOptions {
+        source_positions: true,
+        ..MarkdownOptions::extended()
+    };
+    assert_eq!(
+        parse_markdown_with_options("# é\n\n- a\n- b\n\n---\n", &options),
+        "<h1 data-source-start=\"0\" data-source-end=\"4\" data-source-line=\"1\">é</h1>\n\
+         <ul data-source-start=\"5\" data-source-end=\"14\" data-source-line=\"3\">\n\
+         <li data-source-start=\"5\" data-source-end=\"9\" data-source-line=\"3\">a</li>\n\
+         <li data-source-start=\"9\" data-source-end=\"14\" data-source-line=\"4\">b</li>\n\
+         </ul>\n<hr data-source-start=\"14\" data-source-end=\"18\" data-source-line=\"6\" />\n"
+    );
+}
+
+#[test]
+fn source_positions_are_the_only_difference() {
+    let options = MarkdownOptions {
+        source_positions: true,
+        ..MarkdownOptions::all()
+    };
+    for document in DOCUMENTS {
+        let html = parse_markdown_with_options(document, &options);
+        let stripped = strip_source_attributes(&html);
+        let plain = parse_markdown_with_options(document, &MarkdownOptions::all());
+        assert_eq!(stripped, plain, "\n{:?}", document);
+    }
+}
+
+fn strip_source_attributes(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+    while let Some(index) = rest.find(" data-source-") {
+        out.push_str(&rest[..index]);
+        let after = &rest[index + 1..];
+        let close = after.find('"').unwrap();
+        let end = after[close + 1..].find('"').unwrap();
+        rest = &after[close + end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[test]
+fn source_map_finds_the_innermost_block() {
+    let input = "> quote 😀\n> - item\n\npara\n";
+    let map = SourceMap::new(input, &MarkdownOptions::new());
+    let elements: Vec<String> = map
+        .blocks()
+        .iter()
+        .map(|block| block.element.clone())
+        .collect();
+    assert_eq!(elements, ["blockquote", "p", "ul", "li", "p"]);
+
+    let item = map.block_at(15).unwrap();
+    assert_eq!(
+        (item.element.as_str(), item.depth, item.line, item.end_line),
+        ("li", 2, 2, 2)
+    );
+    // The emoji is two UTF-16 code units.
+    let quote = map.block_at(12).unwrap();
+    assert_eq!(
+        (quote.element.as_str(), quote.start, quote.end),
+        ("blockquote", 0, 20)
+    );
+    assert_eq!(map.block_at_line(4).unwrap().element, "p");
+    assert_eq!(map.block_at_line(4).unwrap().start, 21);
+    assert!(map.block_at(20).is_none());
+}
@@ -0,0 +1,212 @@
+use markdown_wasm::{parse_markdown_with_options, MarkdownDocument, MarkdownOptions};
+
+/// Pieces of Markdown that interact across block boundaries: lazy
+/// continuation lines, fences, indented code, link reference definitions,
+/// setext underlines and list items.
+const PIECES: &[&str] = &[
+    "\n",
+    "\n\n",
+    "text",
+    " more",
+    "# ",
+    "- ",
+    "1. ",
+    "> ",
+    "    code",
+    "```",
+    "~~~",
+    "[x]",
+    "[x]: /u",
+    "[y]: /v \"t\"",
+    "   -",
+    "===",
+    "---",
+    "*",
+    "| a | b |",
+    "|-|-|",
+    "<div>",
+    "</div>",
+    "    ",
+    "  ",
+    "é",
+    "[^1]",
+];
+
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+
+    fn text(&mut self, pieces: usize) -> String {
+        (0..pieces)
+            .map(|_| PIECES[self.next(PIECES.len())])
+            .collect()
+    }
+}
+
+fn options() -> MarkdownOptions {
+    MarkdownOptions {
+        tables: true,
+        strikethrough: true,
+        ..MarkdownOptions::new()
+    }
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// Applies an edit to `text` at UTF-16 offsets, like `MarkdownDocument::edit`.
+fn splice(text: &str, offset: usize, deleted: usize, inserted: &str) -> String {
+    let units: Vec<u16> = text.encode_utf16().collect();
+    let mut out: Vec<u16> = units[..offset].to_vec();
+    out.extend(inserted.encode_utf16());
+    out.extend(&units[offset + deleted..]);
+    String::from_utf16(&out).expect("edits fall on character boundaries")
+}
+
+/// A UTF-16 offset into `text` that is not inside a surrogate pair.
+fn boundary(text: &str, random: &mut Random) -> usize {
+    let chars = text.chars().count();
+    let index = random.next(chars + 1);
+    text.chars().take(index).map(char::len_utf16).sum()
+}
+
+fn assert_edit(document: &mut MarkdownDocument, offset: usize, deleted: usize, inserted: &str) {
+    let before = document.text();
+    document.edit(offset, deleted, inserted);
+    let text = document.text();
+    assert_eq!(text, splice(&before, offset, deleted, inserted));
+    assert_eq!(
+        document.html(),
+        parse_markdown_with_options(&text, &options()),
+        "\nbefore: {:?}\nedit: {} -{} +{:?}\nafter: {:?}",
+        before,
+        offset,
+        deleted,
+        inserted,
+        text
+    );
+}
+
+#[test]
+fn a_new_document_renders_like_render_html() {
+    let text = "# Title\n\nSome [x] text\n\n- a\n- b\n\n[x]: /u\n\n```\ncode\n```\n";
+    let document = MarkdownDocument::new(text, &options());
+    assert_eq!(
+        document.html(),
+        parse_markdown_with_options(text, &options())
+    );
+    assert_eq!(document.blocks().len(), 4);
+}
+
+#[test]
+fn typing_keeps_untouched_blocks() {
+    let mut document = MarkdownDocument::new("one\n\ntwo\n\nthree\n", &options());
+    let ids: Vec<u32> = document.blocks().iter().map(|block| block.id).collect();
+    let patch = document.edit(5, 0, "and ");
+    assert!(patch.removed.is_empty());
+    assert_eq!(patch.blocks.len(), 1);
+    assert_eq!(patch.blocks[0].id, ids[1]);
+    assert_eq!(patch.blocks[0].html, "<p>and two</p>\n");
+    assert_eq!(document.block_at(6), Some(ids[1]));
+    assert_eq!(document.block_at(15), Some(ids[2]));
+}
+
+#[test]
+fn splitting_a_block_inserts_before_the_next() {
+    let mut document = MarkdownDocument::new("one two\n\nthree\n", &options());
+    let ids: Vec<u32> = document.blocks().iter().map(|block| block.id).collect();
+    let patch = document.edit(3, 1, "\n\n");
+    assert_eq!(patch.blocks.len(), 2);
+    assert_eq!(patch.next, Some(ids[1]));
+    assert_eq!(document.html(), "<p>one</p>\n<p>two</p>\n<p>three</p>\n");
+}
+
+#[test]
+fn definitions_rerender_the_blocks_using_them() {
+    let mut document = MarkdownDocument::new("see [x]\n\nend\n", &options());
+    assert_eq!(document.html(), "<p>see [x]</p>\n<p>end</p>\n");
+    let end = utf16_len(&document.text());
+    assert_edit(&mut document, end, 0, "\n[x]: /u\n");
+    assert_eq!(
+        document.html(),
+        "<p>see <a href=\"/u\">x</a></p>\n<p>end</p>\n"
+    );
+}
+
+#[test]
+fn indented_lines_after_a_definition() {
+    let text = "    code\n[x]: /u\n    code\n";
+    for offset in 0..=utf16_len(text) {
+        for (deleted, inserted) in [(0, "v"), (0, "\n"), (0, "["), (1, ""), (1, " ")] {
+            if offset + deleted <= utf16_len(text) {
+                let mut document = MarkdownDocument::new(text, &options());
+                assert_edit(&mut document, offset, deleted, inserted);
+            }
+        }
+    }
+}
+
+#[test]
+fn blocks_continued_across_a_blank_line() {
+    let mut document = MarkdownDocument::new("   ]: /u\n\n      -    code [", &options());
+    assert_edit(&mut document, 2, 0, " ");
+    let mut document = MarkdownDocument::new("a\n\n  b\n", &options());
+    assert_edit(&mut document, 0, 0, "- ");
+}
+
+#[test]
+fn lines_after_a_definition_in_a_later_block() {
+    let text = " -   cod> xx[[\n[x]: /u]/u[\n \n\n[x]: /u\n   -\n\n";
+    let mut document = MarkdownDocument::new(text, &options());
+    assert_edit(&mut document, utf16_len(text), 0, "     code");
+}
+
+#[test]
+fn multibyte_text_is_edited_at_utf16_offsets() {
+    let mut document = MarkdownDocument::new("é 😀 x\n\ny\n", &options());
+    assert_edit(&mut document, 5, 1, "z");
+    assert_eq!(document.text(), "é 😀 z\n\ny\n");
+}
+
+/// Makes random edits to random documents, checking each against a full
+/// render, with `ending` ending the lines.
+fn random_edits(ending: &str) {
+    let mut random = Random(0x9e3779b97f4a7c15);
+    for _ in 0..300 {
+        let pieces = random.next(12);
+        let text = random.text(pieces).replace('\n', ending);
+        let mut document = MarkdownDocument::new(&text, &options());
+        for _ in 0..8 {
+            let text = document.text();
+            let offset = boundary(&text, &mut random);
+            let rest =
+                String::from_utf16(&text.encode_utf16().skip(offset).collect::<Vec<_>>()).unwrap();
+            let deleted: usize = rest.chars().take(random.next(6)).map(char::len_utf16).sum();
+            let pieces = random.next(3);
+            let inserted = random.text(pieces).replace('\n', ending);
+            assert_edit(&mut document, offset, deleted, &inserted);
+        }
+    }
+}
+
+#[test]
+fn random_edits_match_a_full_render() {
+    random_edits("\n");
+}
+
+#[test]
+fn carriage_returns_end_lines_too() {
+    random_edits("\r");
+    random_edits("\r\n");
+    let mut document = MarkdownDocument::new("a\r\rb\r\n\r\nc\r", &options());
+    assert_eq!(document.blocks().len(), 3);
+    assert_edit(&mut document, 1, 0, "\r- x");
+    assert_edit(&mut document, 7, 0, "```");
+}
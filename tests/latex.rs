@@ -0,0 +1,142 @@
+use markdown_wasm::{render_latex, CodeEnvironment, LatexOptions, MarkdownOptions};
+
+fn body(input: &str) -> String {
+    body_with(input, CodeEnvironment::Listings)
+}
+
+fn body_with(input: &str, code: CodeEnvironment) -> String {
+    let latex = LatexOptions {
+        standalone: false,
+        code,
+        ..LatexOptions::new()
+    };
+    render_latex(input, &MarkdownOptions::gfm(), &latex)
+}
+
+#[test]
+fn inlines_and_blocks() {
+    assert_eq!(
+        body("# Title\n\n*a* **b** `c` ~~d~~ 50% & $5_x^2 {}\n"),
+        "\\section{Title}\\label{title}\n\n\
+         \\emph{a} \\textbf{b} \\texttt{c} \\sout{d} 50\\% \\& \\$5\\_x\\textasciicircum{}2 \\{\\}\n"
+    );
+    assert_eq!(
+        body("- [x] one\n\n3. three\n\n> [!NOTE]\n> hi\n"),
+        "\\begin{itemize}\n\\item[$\\boxtimes$] one\n\\end{itemize}\n\n\
+         \\begin{enumerate}\n\\setcounter{enumi}{2}\n\\item three\n\\end{enumerate}\n\n\
+         \\begin{quote}\n\\textbf{Note.} hi\n\n\\end{quote}\n"
+    );
+}
+
+#[test]
+fn code_blocks() {
+    assert_eq!(
+        body("```python\nx = 1\n```\n"),
+        "\\begin{lstlisting}[language=Python]\nx = 1\n\\end{lstlisting}\n"
+    );
+    assert_eq!(
+        body("```unknown\nx\n```\n"),
+        "\\begin{lstlisting}\nx\n\\end{lstlisting}\n"
+    );
+    assert_eq!(
+        body_with("```c++\nx\n```\n", CodeEnvironment::Minted),
+        "\\begin{minted}{c++}\nx\n\\end{minted}\n"
+    );
+    assert_eq!(
+        body_with("    x\n", CodeEnvironment::Minted),
+        "\\begin{minted}{text}\nx\n\\end{minted}\n"
+    );
+    assert_eq!(
+        body_with("    x\n", CodeEnvironment::Verbatim),
+        "\\begin{verbatim}\nx\n\\end{verbatim}\n"
+    );
+}
+
+#[test]
+fn code_that_would_end_its_environment() {
+    // Such code is written as escaped lines instead.
+    assert_eq!(
+        body("```\na\n\\end{lstlisting}\n  b\t{c}\n```\n"),
+        "\\noindent\\texttt{a}\\\\\n\
+         \\texttt{\\textbackslash{}end\\{lstlisting\\}}\\\\\n\
+         \\texttt{~~b~~~~\\{c\\}}\n"
+    );
+    assert_eq!(
+        body_with("```\nx \\end{verbatim}\n```\n", CodeEnvironment::Verbatim),
+        "\\noindent\\texttt{x~\\textbackslash{}end\\{verbatim\\}}\n"
+    );
+    assert_eq!(
+        body_with("```\n\\end{minted}\n```\n", CodeEnvironment::Minted),
+        "\\noindent\\texttt{\\textbackslash{}end\\{minted\\}}\n"
+    );
+    // Only the environment's own name ends it.
+    assert_eq!(
+        body_with("```\n\\end{lstlisting}\n```\n", CodeEnvironment::Verbatim),
+        "\\begin{verbatim}\n\\end{lstlisting}\n\\end{verbatim}\n"
+    );
+}
+
+#[test]
+fn code_in_footnotes() {
+    assert_eq!(
+        body("Text[^1].\n\n[^1]: Note:\n\n    ```python\n    x = 1\n      y\n    ```\n"),
+        "Text\\footnote{Note:\n\n\\noindent\\texttt{x~=~1}\\\\\n\\texttt{~~y}\\label{fn:1}}.\n"
+    );
+    for code in [
+        CodeEnvironment::Listings,
+        CodeEnvironment::Minted,
+        CodeEnvironment::Verbatim,
+    ] {
+        let output = body_with("A[^n]\n\n[^n]: ```\n    x\n    ```\n", code);
+        assert_eq!(
+            output, "A\\footnote{\\noindent\\texttt{x}\\label{fn:1}}\n",
+            "{code:?}"
+        );
+    }
+}
+
+#[test]
+fn urls() {
+    assert_eq!(
+        body("[x](http://e.com/?a=1&b=2#f%20) <http://e.com/a&b> <me@e.com>\n"),
+        "\\href{http://e.com/?a=1\\&b=2\\#f\\%20}{x} \\url{http://e.com/a\\&b} \\href{mailto:me@e.com}{me@e.com}\n"
+    );
+    assert_eq!(body("[x](</a{b}\\c>)\n"), "\\href{/a%7Bb%7D%5Cc}{x}\n");
+    // An unescaped `&` would end the table cell.
+    assert_eq!(
+        body("| a |\n|-|\n| [x](http://e.com/?a=1&b=2) |\n"),
+        "\\begin{longtable}[]{@{}l@{}}\n\\toprule\na \\\\\n\\midrule\n\\endhead\n\
+         \\href{http://e.com/?a=1\\&b=2}{x} \\\\\n\\bottomrule\n\\end{longtable}\n"
+    );
+    assert_eq!(body("[x](#title)\n"), "\\hyperref[title]{x}\n");
+    assert_eq!(
+        body("![a](pic.png) ![b](https://e.com/p.png?a&b)\n"),
+        "\\includegraphics[max width=\\linewidth]{pic.png} \\url{https://e.com/p.png?a\\&b}\n"
+    );
+}
+
+#[test]
+fn standalone() {
+    let options = MarkdownOptions {
+        front_matter: true,
+        ..MarkdownOptions::gfm()
+    };
+    let output = render_latex(
+        "---\ntitle: A & B\nauthor: [X, Y]\n---\n\nText\n",
+        &options,
+        &LatexOptions::new(),
+    );
+    assert!(output.starts_with("\\documentclass{article}\n"));
+    assert!(output.contains("\\usepackage{listings}\n"));
+    assert!(output.contains("\\title{A \\& B}\n\\author{X \\and Y}\n"));
+    assert!(output.ends_with("\\begin{document}\n\\maketitle\n\nText\n\\end{document}\n"));
+
+    let latex = LatexOptions {
+        template: Some("$title$: $body$ $5 $other$".into()),
+        ..LatexOptions::new()
+    };
+    assert_eq!(
+        render_latex("---\ntitle: $body$\n---\n\nx\n", &options, &latex),
+        "\\$body\\$: x $5 $other$"
+    );
+}
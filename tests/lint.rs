@@ -0,0 +1,583 @@
+use markdown_wasm::{
+    fix_markdown_json, lint_markdown_json, parse_markdown_with_options, MarkdownOptions,
+};
+use serde_json::{json, Value};
+
+/// The diagnostics of `input` under `config`.
+fn lint(input: &str, options: &MarkdownOptions, config: &Value) -> Result<Vec<Value>, String> {
+    let diagnostics = lint_markdown_json(input, options, &config.to_string())?;
+    Ok(serde_json::from_str(&diagnostics).unwrap())
+}
+
+/// The byte offset of `units` UTF-16 code units into `text`.
+fn byte_offset(text: &str, units: &Value) -> usize {
+    let mut remaining = units.as_u64().unwrap() as usize;
+    for (index, c) in text.char_indices() {
+        if remaining == 0 {
+            return index;
+        }
+        remaining -= c.len_utf16();
+    }
+    text.len()
+}
+
+/// The start and source text of each problem `rule` finds in `input`,
+/// with every other rule off.
+fn problems_with<'a>(
+    input: &'a str,
+    rule: &str,
+    setting: Value,
+    options: &MarkdownOptions,
+) -> Vec<(usize, &'a str)> {
+    let config = json!({"default": false, rule: setting});
+    lint(input, options, &config)
+        .unwrap()
+        .iter()
+        .map(|diagnostic| {
+            let start = byte_offset(input, &diagnostic["position"]["start"]);
+            let end = byte_offset(input, &diagnostic["position"]["end"]);
+            (start, &input[start..end])
+        })
+        .collect()
+}
+
+fn problems<'a>(input: &'a str, rule: &str) -> Vec<(usize, &'a str)> {
+    problems_with(input, rule, json!(true), &MarkdownOptions::default())
+}
+
+#[test]
+fn every_rule_is_covered() {
+    let rules = include_str!("../src/lint.rs");
+    let tests = include_str!("lint.rs");
+    let ids: Vec<&str> = rules
+        .split("id: \"")
+        .skip(1)
+        .map(|rest| &rest[..rest.find('"').unwrap()])
+        .collect();
+    assert!(!ids.is_empty());
+    for id in ids {
+        assert!(tests.contains(&format!("\"{}\"", id)), "{} has no test", id);
+    }
+}
+
+#[test]
+fn heading_increment() {
+    assert_eq!(problems("# A\n\n### B\n", "MD001"), [(5, "### B")]);
+    assert!(problems("# A\n\n## B\n\n### C\n\n# D\n\n## E\n", "MD001").is_empty());
+}
+
+#[test]
+fn heading_style() {
+    assert_eq!(
+        problems("# A\n\nB\n---\n\n# C #\n", "MD003"),
+        [(5, "B"), (12, "# C #")]
+    );
+    assert!(problems("# A\n\n## B\n", "MD003").is_empty());
+    let setting = json!({"style": "setext_with_atx"});
+    let input = "A\n=\n\n### B\n";
+    assert!(problems_with(input, "MD003", setting, &MarkdownOptions::default()).is_empty());
+}
+
+#[test]
+fn ul_style() {
+    assert_eq!(problems("- a\n\n* b\n", "MD004"), [(5, "*")]);
+    assert!(problems("- a\n- b\n", "MD004").is_empty());
+    let input = "- a\n  * b\n";
+    assert_eq!(problems(input, "MD004"), [(6, "*")]);
+    let setting = json!({"style": "sublist"});
+    assert!(problems_with(input, "MD004", setting, &MarkdownOptions::default()).is_empty());
+}
+
+#[test]
+fn list_indent() {
+    assert_eq!(problems("- a\n - b\n", "MD005"), [(5, "-")]);
+    assert!(problems("- a\n- b\n", "MD005").is_empty());
+    assert!(problems(" 9. a\n10. b\n", "MD005").is_empty());
+}
+
+#[test]
+fn ul_indent() {
+    assert_eq!(problems("- a\n   - b\n", "MD007"), [(7, "-")]);
+    assert!(problems("- a\n  - b\n", "MD007").is_empty());
+    assert!(problems("1. a\n   - b\n", "MD007").is_empty());
+}
+
+#[test]
+fn no_trailing_spaces() {
+    assert_eq!(problems("a \nb  \nc   \n", "MD009"), [(1, " "), (8, "   ")]);
+    assert!(problems("a\nb  \nc\n", "MD009").is_empty());
+    assert!(problems("```\na \n```\n", "MD009").is_empty());
+}
+
+#[test]
+fn no_hard_tabs() {
+    assert_eq!(
+        problems("a\tb\n\n```\n\t\tx\n```\n", "MD010"),
+        [(1, "\t"), (9, "\t\t")]
+    );
+    assert!(problems("a b\n", "MD010").is_empty());
+    let setting = json!({"code_blocks": false});
+    let input = "```\n\tx\n```\n";
+    assert!(problems_with(input, "MD010", setting, &MarkdownOptions::default()).is_empty());
+}
+
+#[test]
+fn no_reversed_links() {
+    assert_eq!(
+        problems("see (text)[https://a.example]\n", "MD011"),
+        [(4, "(text)[https://a.example]")]
+    );
+    assert!(problems("[text](https://a.example)\n", "MD011").is_empty());
+    assert!(problems("`(a)[b]` and (a)[^1]\n", "MD011").is_empty());
+}
+
+#[test]
+fn no_multiple_blanks() {
+    assert_eq!(problems("a\n\n\nb\n", "MD012"), [(3, "")]);
+    assert!(problems("a\n\nb\n", "MD012").is_empty());
+    assert!(problems("```\n\n\n```\n", "MD012").is_empty());
+}
+
+#[test]
+fn line_length() {
+    let setting = json!({"line_length": 10});
+    let options = MarkdownOptions::default();
+    assert_eq!(
+        problems_with(
+            "short\naaaa bbbb cc dd\n",
+            "MD013",
+            setting.clone(),
+            &options
+        ),
+        [(16, "cc dd")]
+    );
+    let input = "see https://a.example/long\n";
+    assert!(problems_with(input, "MD013", setting, &options).is_empty());
+    let setting = json!({"line_length": 10, "strict": true});
+    assert_eq!(
+        problems_with(input, "MD013", setting, &options),
+        [(10, "//a.example/long")]
+    );
+}
+
+#[test]
+fn no_missing_space_atx() {
+    assert_eq!(
+        problems("#Heading\n\n  ##Two\n", "MD018"),
+        [(0, "#Heading"), (12, "##Two")]
+    );
+    assert!(problems("# Heading\n\n#\n", "MD018").is_empty());
+}
+
+#[test]
+fn no_multiple_space_atx() {
+    assert_eq!(problems("#  Heading\n", "MD019"), [(0, "#  Heading")]);
+    assert!(problems("# Heading\n", "MD019").is_empty());
+}
+
+#[test]
+fn blanks_around_headings() {
+    assert_eq!(problems("# A\ntext\n", "MD022"), [(0, "# A")]);
+    assert_eq!(
+        problems("text\n\n# A\n\ntext\n## B\n", "MD022"),
+        [(16, "## B")]
+    );
+    assert!(problems("# A\n\ntext\n", "MD022").is_empty());
+}
+
+#[test]
+fn heading_start_left() {
+    assert_eq!(problems(" # A\n", "MD023"), [(0, " # A")]);
+    assert!(problems("# A\n", "MD023").is_empty());
+    assert!(problems(">  # A\n", "MD023").is_empty());
+}
+
+#[test]
+fn no_duplicate_heading() {
+    assert_eq!(problems("# A\n\n## A\n", "MD024"), [(5, "## A")]);
+    let input = "# A\n\n## B\n\n# C\n\n## B\n";
+    assert_eq!(problems(input, "MD024"), [(16, "## B")]);
+    let setting = json!({"siblings_only": true});
+    assert!(problems_with(input, "MD024", setting, &MarkdownOptions::default()).is_empty());
+}
+
+#[test]
+fn single_h1() {
+    assert_eq!(problems("# A\n\n# B\n", "MD025"), [(5, "# B")]);
+    assert!(problems("# A\n\n## B\n", "MD025").is_empty());
+    let options = MarkdownOptions {
+        front_matter: true,
+        ..MarkdownOptions::default()
+    };
+    assert_eq!(
+        problems_with("---\ntitle: T\n---\n# A\n", "MD025", json!(true), &options),
+        [(17, "# A")]
+    );
+}
+
+#[test]
+fn no_trailing_punctuation() {
+    assert_eq!(
+        problems("# Hello.\n\n## Bye：\n", "MD026"),
+        [(0, "# Hello."), (10, "## Bye：")]
+    );
+    assert!(problems("# Hello?\n", "MD026").is_empty());
+}
+
+#[test]
+fn no_multiple_space_blockquote() {
+    assert_eq!(problems(">  a\n", "MD027"), [(1, "  ")]);
+    assert_eq!(problems("> >   a\n", "MD027"), [(3, "   ")]);
+    assert!(problems("> a\n>\n", "MD027").is_empty());
+}
+
+#[test]
+fn ol_prefix() {
+    assert_eq!(problems("1. a\n1. b\n3. c\n", "MD029"), [(10, "3.")]);
+    assert_eq!(problems("1. a\n2. b\n4. c\n", "MD029"), [(10, "4.")]);
+    assert!(problems("1. a\n2. b\n3. c\n", "MD029").is_empty());
+    assert!(problems("1. a\n1. b\n", "MD029").is_empty());
+}
+
+#[test]
+fn blanks_around_fences() {
+    assert_eq!(
+        problems("text\n```\ncode\n```\nmore\n", "MD031"),
+        [(5, "```"), (14, "```")]
+    );
+    assert!(problems("text\n\n```\ncode\n```\n\nmore\n", "MD031").is_empty());
+    let input = "- a\n  ```\n  x\n  ```\n";
+    assert_eq!(problems(input, "MD031"), [(4, "  ```")]);
+    let setting = json!({"list_items": false});
+    assert!(problems_with(input, "MD031", setting, &MarkdownOptions::default()).is_empty());
+}
+
+#[test]
+fn blanks_around_lists() {
+    assert_eq!(problems("text\n- a\n", "MD032"), [(5, "- a")]);
+    assert!(problems("text\n\n- a\n  - b\n", "MD032").is_empty());
+}
+
+#[test]
+fn no_inline_html() {
+    assert_eq!(problems("a <b>bold</b>\n", "MD033"), [(2, "<b")]);
+    assert!(problems("a <!-- <b> -->\n", "MD033").is_empty());
+    let setting = json!({"allowed_elements": ["B"]});
+    let input = "a <b>bold</b>\n";
+    assert!(problems_with(input, "MD033", setting, &MarkdownOptions::default()).is_empty());
+}
+
+#[test]
+fn no_bare_urls() {
+    assert_eq!(
+        problems("see https://a.example, or me@a.example.\n", "MD034"),
+        [(4, "https://a.example"), (26, "me@a.example")]
+    );
+    assert!(problems(
+        "<https://a.example> [x](https://a.example) `https://a.example`\n",
+        "MD034"
+    )
+    .is_empty());
+}
+
+#[test]
+fn hr_style() {
+    assert_eq!(problems("---\n\n***\n", "MD035"), [(5, "***")]);
+    assert!(problems("---\n\n---\n", "MD035").is_empty());
+}
+
+#[test]
+fn no_emphasis_as_heading() {
+    assert_eq!(
+        problems("**Section**\n\ntext\n", "MD036"),
+        [(0, "**Section**")]
+    );
+    assert!(problems("**Section.**\n\n**a** b\n", "MD036").is_empty());
+}
+
+#[test]
+fn no_space_in_code() {
+    assert_eq!(
+        problems("x ` a ` y `b `\n", "MD038"),
+        [(2, "` a `"), (10, "`b `")]
+    );
+    assert!(problems("`a` and `` ` ``\n", "MD038").is_empty());
+}
+
+#[test]
+fn no_space_in_links() {
+    assert_eq!(problems("x [ a ](u)\n", "MD039"), [(2, "[ a ](u)")]);
+    assert!(problems("[a](u) <https://a.example>\n", "MD039").is_empty());
+}
+
+#[test]
+fn fenced_code_language() {
+    assert_eq!(problems("```\nx\n```\n", "MD040"), [(0, "```")]);
+    let input = "```rust\nx\n```\n";
+    assert!(problems(input, "MD040").is_empty());
+    let setting = json!({"allowed_languages": ["js"]});
+    assert_eq!(
+        problems_with(input, "MD040", setting, &MarkdownOptions::default()),
+        [(0, "```rust")]
+    );
+}
+
+#[test]
+fn first_line_heading() {
+    assert_eq!(problems("text\n\n# A\n", "MD041"), [(0, "text")]);
+    assert!(problems("<!-- c -->\n# A\n", "MD041").is_empty());
+    assert_eq!(problems("## A\n", "MD041"), [(0, "## A")]);
+}
+
+#[test]
+fn no_empty_links() {
+    assert_eq!(
+        problems("[a]() and [b](#)\n", "MD042"),
+        [(0, "[a]()"), (10, "[b](#)")]
+    );
+    assert!(problems("[a](u)\n", "MD042").is_empty());
+}
+
+#[test]
+fn no_alt_text() {
+    assert_eq!(problems("x ![](a.png)\n", "MD045"), [(2, "![](a.png)")]);
+    assert!(problems("![alt](a.png) ![`code`](b.png)\n", "MD045").is_empty());
+}
+
+#[test]
+fn code_block_style() {
+    assert_eq!(problems("```\nx\n```\n\n    y\n", "MD046"), [(15, "y")]);
+    assert!(problems("```\nx\n```\n\n~~~\ny\n~~~\n", "MD046").is_empty());
+}
+
+#[test]
+fn single_trailing_newline() {
+    assert_eq!(problems("a", "MD047"), [(1, "")]);
+    assert!(problems("a\n", "MD047").is_empty());
+    assert!(problems("", "MD047").is_empty());
+}
+
+#[test]
+fn code_fence_style() {
+    assert_eq!(
+        problems("```\nx\n```\n\n~~~\ny\n~~~\n", "MD048"),
+        [(11, "~~~")]
+    );
+    assert!(problems("```\nx\n```\n\n```\ny\n```\n", "MD048").is_empty());
+}
+
+#[test]
+fn emphasis_style() {
+    assert_eq!(problems("*a* _b_\n", "MD049"), [(4, "_")]);
+    assert!(problems("*a* *b*\n", "MD049").is_empty());
+    let setting = json!({"style": "underscore"});
+    assert_eq!(
+        problems_with("*a* _b_\n", "MD049", setting, &MarkdownOptions::default()),
+        [(0, "*")]
+    );
+}
+
+#[test]
+fn strong_style() {
+    assert_eq!(problems("**a** __b__\n", "MD050"), [(6, "__")]);
+    assert!(problems("**a** **b** _c_\n", "MD050").is_empty());
+}
+
+#[test]
+fn link_fragments() {
+    assert_eq!(
+        problems("[x](#nope)\n\n# Yes\n", "MD051"),
+        [(0, "[x](#nope)")]
+    );
+    assert!(problems(
+        "[x](#yes) [y](#top) [z](#L10) [w](#a)\n\n# Yes\n\n<a id=\"a\"></a>\n",
+        "MD051"
+    )
+    .is_empty());
+    let setting = json!({"ignore_case": true});
+    let input = "[x](#Yes)\n\n# Yes\n";
+    assert_eq!(problems(input, "MD051"), [(0, "[x](#Yes)")]);
+    assert!(problems_with(input, "MD051", setting, &MarkdownOptions::default()).is_empty());
+}
+
+#[test]
+fn reference_links_images() {
+    assert_eq!(problems("[a][missing]\n", "MD052"), [(0, "[a][missing]")]);
+    assert!(problems("[a][x]\n\n[x]: u\n", "MD052").is_empty());
+}
+
+#[test]
+fn blanks_around_tables() {
+    let options = MarkdownOptions {
+        tables: true,
+        ..MarkdownOptions::default()
+    };
+    assert_eq!(
+        problems_with("# H\n| a |\n| - |\n", "MD058", json!(true), &options),
+        [(4, "| a |")]
+    );
+    assert!(problems_with(
+        "# H\n\n| a |\n| - |\n\n# I\n",
+        "MD058",
+        json!(true),
+        &options
+    )
+    .is_empty());
+}
+
+#[test]
+fn severity_and_names() {
+    let config = json!({"default": false, "no-trailing-spaces": "error"});
+    let diagnostics = lint("a \n", &MarkdownOptions::default(), &config).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["ruleId"], "MD009");
+    assert_eq!(diagnostics[0]["ruleName"], "no-trailing-spaces");
+    assert_eq!(diagnostics[0]["severity"], "error");
+    assert_eq!(
+        diagnostics[0]["message"],
+        "Trailing spaces [Expected: 0 or 2; Actual: 1]"
+    );
+    let config = json!({"MD009": "fatal"});
+    assert!(lint("a \n", &MarkdownOptions::default(), &config).is_err());
+    assert!(lint("a \n", &MarkdownOptions::default(), &json!([])).is_err());
+}
+
+/// Fixes under `config`, which leaves out the rules whose fixes are meant
+/// to change the output: MD018 turns text into a heading and MD034 links
+/// bare URLs.
+fn check_fix(input: &str, options: &MarkdownOptions) {
+    let config = json!({"MD018": false, "MD034": false}).to_string();
+    let fixed = fix_markdown_json(input, options, &config).unwrap();
+    assert_eq!(
+        fix_markdown_json(&fixed, options, &config).unwrap(),
+        fixed,
+        "{:?}",
+        input
+    );
+    assert_eq!(
+        parse_markdown_with_options(&fixed, options),
+        parse_markdown_with_options(input, options),
+        "{:?} fixed as {:?}",
+        input,
+        fixed
+    );
+}
+
+#[test]
+fn fixes_are_idempotent_and_keep_the_html() {
+    let documents = [
+        "# A\ntext  \nmore   \nend \n\n\n\n## B ##\nC\n-\n",
+        "- a\n\n* b\n+ c\n",
+        "text\n- a\n- b\n\ntext\n",
+        "a\tb\n\n\tcode\n\n-\ta\n",
+        "##  B\n   # C\n",
+        ">  quoted\n>   more\n> # H\n> text\n",
+        "text\n```\ncode\n```\n~~~\nx\n~~~\nmore",
+        "*a* _b_ **c** __d__ x_y_z foo_bar_\n",
+        "| a |\n| - |\n# H\n| b |\n| - |\n",
+        "- a\n  ```\n  x\n  ```\n- b\n",
+        "> - a\n> ```\n> x\n> ```\n",
+        "Setext\n===\n# ATX #\ntext\n# After text\n",
+        "```\n\n\n```\n<div>\n\n\n</div>\n",
+        "<div>\nraw   \n</div>\n",
+        "#B\n---\n\n## C\n",
+        "- a\n* b\n",
+        "+ c\n\t- t\n  - n\n",
+        "> ```\n`` c ``\n",
+        "> > q\n\t- t\n> - a\n",
+        "1. o\n   * s\n\n  E\n  ---\n",
+        "* b\n  ```\n> > q\n",
+        "- a\n  ```\n# F\n",
+        "```\n> ```\n\n~~~\nx\n~~~\n",
+        "- # L\n- # L\n",
+        "text\r\n# A\r\nmore\r\n",
+    ];
+    for options in [MarkdownOptions::default(), MarkdownOptions::gfm()] {
+        for input in documents {
+            check_fix(input, &options);
+        }
+    }
+}
+
+#[test]
+fn fixes_keep_the_html_of_generated_documents() {
+    const PIECES: &[&str] = &[
+        "# A",
+        "#B",
+        "##  C ##",
+        " # I",
+        "D\n=",
+        "  E\n  ---",
+        "> # h",
+        "text",
+        "x   ",
+        "more  ",
+        "x\ty",
+        "\tz",
+        "\t\tw",
+        "*e* _f_ **g** __h__",
+        "a_b_c",
+        "`` c ``",
+        "https://a.example",
+        "- a",
+        "* b",
+        "+ c",
+        "  - n",
+        "\t- t",
+        "1. o\n   * s",
+        "3. p",
+        "- a\n\n  b",
+        "- # L",
+        "```",
+        "```js",
+        "~~~",
+        "  ```",
+        "> ```",
+        "    code",
+        "> q",
+        ">  r",
+        "> > q",
+        "> - a",
+        ">",
+        "---",
+        "| a |\n| - |",
+        "  | a |\n  | - |",
+        "<div>",
+        "</div>",
+        "<!-- c -->",
+        "",
+        "",
+    ];
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as usize
+    };
+    for _ in 0..300 {
+        let mut input = String::new();
+        for _ in 0..next() % 8 + 1 {
+            input.push_str(PIECES[next() % PIECES.len()]);
+            input.push('\n');
+        }
+        check_fix(&input, &MarkdownOptions::default());
+        check_fix(&input, &MarkdownOptions::gfm());
+    }
+}
+
+#[test]
+fn fixes() {
+    let options = MarkdownOptions::default();
+    let fixed = |input| fix_markdown_json(input, &options, "").unwrap();
+    assert_eq!(
+        fixed("#A\n\nsee https://a.example"),
+        "# A\n\nsee <https://a.example>\n"
+    );
+    assert_eq!(
+        fixed("# A\ntext\n- a\n\n* b\n"),
+        "# A\n\ntext\n\n- a\n\n* b\n"
+    );
+    assert_eq!(fixed("a\tb\n\n\t\tcode\n"), "a\tb\n\n\t\tcode\n");
+    assert_eq!(fixed("- a\n  # B\n  text\n"), "- a\n  # B\n  text\n");
+}
@@ -0,0 +1,110 @@
+use markdown_wasm::{render_man, MarkdownOptions};
+
+fn man(input: &str) -> String {
+    let options = MarkdownOptions {
+        definition_list: true,
+        ..MarkdownOptions::gfm()
+    };
+    render_man(input, &options)
+}
+
+/// The page without its `.TH` line.
+fn body(input: &str) -> String {
+    let output = man(input);
+    output
+        .split_once('\n')
+        .expect("the page has a .TH line")
+        .1
+        .to_string()
+}
+
+#[test]
+fn title_from_front_matter() {
+    let input = "---\nname: tool\nsection: 8\ndate: 2024-01-02\nsource: Tool 1.0\n\
+                 manual: Tool \"Manual\"\ndescription: does things\n---\n\n## Synopsis\n\ntext\n";
+    assert_eq!(
+        man(input),
+        ".TH \"TOOL\" \"8\" \"2024\\-01\\-02\" \"Tool 1.0\" \"Tool \\(dqManual\\(dq\"\n\
+         .SH NAME\ntool \\- does things\n.SH\nSYNOPSIS\ntext\n"
+    );
+    // Missing trailing fields are left off, and the section defaults to 1.
+    assert_eq!(
+        man("---\nname: tool\ndate: 2024\n---\n\nx\n"),
+        ".TH \"TOOL\" \"1\" \"2024\"\nx\n"
+    );
+    assert_eq!(man("x\n"), ".TH \"UNTITLED\" \"1\"\nx\n");
+    // A NAME section of the page's own takes the description's place.
+    assert_eq!(
+        man("---\nname: tool\ndescription: d\n---\n\n# Name\n\ntool - does it\n"),
+        ".TH \"TOOL\" \"1\"\n.SH\nNAME\ntool \\- does it\n"
+    );
+}
+
+#[test]
+fn sections() {
+    // A lone level 1 heading at the start names the page and is left out.
+    assert_eq!(
+        man("# tool\n\n## Synopsis\n\n`tool -v`\n\n### Options\n\ntext\n\n#### Detail\n\nmore\n"),
+        ".TH \"TOOL\" \"1\"\n.SH\nSYNOPSIS\n\\fBtool \\-v\\fR\n.SS\nOptions\ntext\n.PP\n\\fBDetail\\fR\nmore\n"
+    );
+    // With a name in the front matter, the title is still left out.
+    assert_eq!(
+        man("---\nname: x\n---\n\n# tool\n\n## A\n"),
+        ".TH \"X\" \"1\"\n.SH\nA\n"
+    );
+    // Several level 1 headings are all sections.
+    assert_eq!(body("# One\n\n# Two\n"), ".SH\nONE\n.SH\nTWO\n");
+}
+
+#[test]
+fn leading_dots_and_quotes() {
+    assert_eq!(
+        body("Line\n.start and\n'quote\n\n.dot paragraph\n\n'quote paragraph\n"),
+        "Line\n\\&.start and\n\\&'quote\n.PP\n\\&.dot paragraph\n.PP\n\\&'quote paragraph\n"
+    );
+    assert_eq!(
+        body("    .code\n    'x\n    y.\n"),
+        ".RS 4\n.nf\n\\&.code\n\\&'x\n\\&y.\n.fi\n.RE\n"
+    );
+    assert_eq!(body("- .item\n"), ".IP \\(bu 2\n\\&.item\n");
+    assert_eq!(
+        body("| a | .b |\n|-|:-:|\n| 'c | d |\n")
+            .lines()
+            .skip(4)
+            .take(2)
+            .collect::<Vec<_>>(),
+        ["a\t\\&.b", "\\&'c\td"]
+    );
+    // Only at the start of a line.
+    assert_eq!(body("a .b 'c\n"), "a .b 'c\n");
+    assert_eq!(body("*.x*\n"), "\\fI.x\\fR\n");
+}
+
+#[test]
+fn escapes() {
+    assert_eq!(body("a-b \\\\ c\n"), "a\\-b \\e c\n");
+    assert_eq!(
+        body("[site](https://example.com)\n"),
+        "site \\(lahttps://example.com\\(ra\n"
+    );
+}
+
+#[test]
+fn definition_lists() {
+    assert_eq!(
+        body("Term\n: Definition one\n: Definition two\n\n`-v`\n: Verbose\n"),
+        ".TP\n\\fBTerm\\fR\nDefinition one\n.IP\nDefinition two\n.TP\n\\fB\\fB\\-v\\fB\\fR\nVerbose\n"
+    );
+    assert_eq!(
+        body("Term\n\n: Loose one\n\n  more\n\n: Loose two\n\nAfter\n"),
+        ".TP\n\\fBTerm\\fR\nLoose one\n.IP\nmore\n.IP\nLoose two\n.PP\nAfter\n"
+    );
+}
+
+#[test]
+fn lists() {
+    assert_eq!(
+        body("- a\n\n  b\n- c\n\n  3. d\n  4. e\n"),
+        ".IP \\(bu 2\na\n.IP\nb\n.IP \\(bu 2\nc\n.RS\n.IP 3. 4\nd\n.IP 4. 4\ne\n.RE\n"
+    );
+}
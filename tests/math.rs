@@ -0,0 +1,145 @@
+use markdown_wasm::{latex_to_mathml, parse_markdown_with_options, MarkdownOptions};
+
+/// The `<mrow>` body of the `<math>` element for inline `tex`.
+fn body(tex: &str) -> String {
+    let mathml = latex_to_mathml(tex, false);
+    let start = "<math><semantics><mrow>";
+    let end = format!(
+        "</mrow><annotation encoding=\"application/x-tex\">{}</annotation></semantics></math>",
+        tex
+    );
+    assert!(
+        mathml.starts_with(start) && mathml.ends_with(&end),
+        "{}",
+        mathml
+    );
+    mathml[start.len()..mathml.len() - end.len()].to_string()
+}
+
+#[test]
+fn numbers_next_to_non_ascii_letters() {
+    assert_eq!(body("2π r"), "<mn>2</mn><mi>π</mi><mi>r</mi>");
+    assert_eq!(body("12日本"), "<mn>12</mn><mi>日</mi><mi>本</mi>");
+    assert_eq!(body("3.14é"), "<mn>3.14</mn><mi>é</mi>");
+    assert_eq!(body("1.π"), "<mn>1</mn><mo>.</mo><mi>π</mi>");
+}
+
+#[test]
+fn greek_letters_and_operators() {
+    assert_eq!(
+        body("\\alpha + \\beta - 1"),
+        "<mi>α</mi><mo>+</mo><mi>β</mi><mo>−</mo><mn>1</mn>"
+    );
+}
+
+#[test]
+fn fractions_and_roots() {
+    assert_eq!(
+        body("\\frac{a}{b}"),
+        "<mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac>"
+    );
+    assert_eq!(
+        body("\\sqrt[3]{x}"),
+        "<mroot><mrow><mi>x</mi></mrow><mrow><mn>3</mn></mrow></mroot>"
+    );
+}
+
+#[test]
+fn scripts() {
+    assert_eq!(
+        body("x^2_i"),
+        "<msubsup><mi>x</mi><mi>i</mi><mn>2</mn></msubsup>"
+    );
+    assert_eq!(
+        body("x_{ij}^{n+1}"),
+        "<msubsup><mi>x</mi><mrow><mi>i</mi><mi>j</mi></mrow><mrow><mi>n</mi><mo>+</mo><mn>1</mn></mrow></msubsup>"
+    );
+}
+
+#[test]
+fn display_math_is_a_block() {
+    assert!(latex_to_mathml("x", true).starts_with("<math display=\"block\">"));
+}
+
+#[test]
+fn invalid_input_marks_the_error() {
+    assert_eq!(
+        latex_to_mathml("\\frac{1}", false),
+        "<span class=\"math-error\" title=\"missing argument\">\\frac{1}<mark></mark></span>"
+    );
+    assert_eq!(
+        latex_to_mathml("\\unknown x", false),
+        "<span class=\"math-error\" title=\"unsupported command `\\unknown`\"><mark>\\unknown</mark> x</span>"
+    );
+    assert_eq!(
+        latex_to_mathml("{a", false),
+        "<span class=\"math-error\" title=\"missing `}`\"><mark>{</mark>a</span>"
+    );
+    assert_eq!(
+        latex_to_mathml("a}", false),
+        "<span class=\"math-error\" title=\"unmatched `}`\">a<mark>}</mark></span>"
+    );
+    assert_eq!(
+        latex_to_mathml("a < b & c", false),
+        "<span class=\"math-error\" title=\"`&amp;` outside of an environment\">a &lt; b <mark>&amp;</mark> c</span>"
+    );
+}
+
+#[test]
+fn markdown_math_with_non_ascii() {
+    let options = MarkdownOptions {
+        mathml: true,
+        ..MarkdownOptions::new()
+    };
+    assert_eq!(
+        parse_markdown_with_options("Area is $2πr$.", &options),
+        "<p>Area is <math><semantics><mrow><mn>2</mn><mi>π</mi><mi>r</mi></mrow>\
+         <annotation encoding=\"application/x-tex\">2πr</annotation></semantics></math>.</p>\n"
+    );
+}
+
+#[test]
+fn random_input_does_not_panic() {
+    const PIECES: &[&str] = &[
+        "1",
+        "2.",
+        ".5",
+        "π",
+        "日",
+        "é",
+        "😀",
+        "x",
+        "^",
+        "_",
+        "{",
+        "}",
+        "\\frac",
+        "\\sqrt",
+        "[",
+        "]",
+        "\\left(",
+        "\\right)",
+        "&",
+        "\\\\",
+        "\\begin{matrix}",
+        "\\end{matrix}",
+        "\\alpha",
+        "\\",
+        " ",
+        "'",
+        "\\text{",
+        "\\mathbf",
+    ];
+    let mut state: u64 = 0x2545f4914f6cdd1d;
+    for _ in 0..2000 {
+        let mut tex = String::new();
+        for _ in 0..8 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            tex.push_str(PIECES[(state % PIECES.len() as u64) as usize]);
+        }
+        latex_to_mathml(&tex, false);
+        latex_to_mathml(&tex, true);
+    }
+}
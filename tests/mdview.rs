@@ -0,0 +1,74 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn mdview(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mdview"))
+        .args(args)
+        .env_remove("COLUMNS")
+        .env_remove("NO_COLOR")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("mdview runs");
+    // mdview doesn't read standard input when given a file, which may then
+    // be closed before the write.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let _ = stdin.write_all(input.as_bytes());
+    drop(stdin);
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn renders_standard_input() {
+    // Output that isn't a terminal is not colored.
+    let output = mdview(&[], "# Title\n\n*one* two\n");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Title\n\none two\n");
+    let output = mdview(&["-"], "| a |\n|-|\n");
+    assert_eq!(stdout(&output), "┌───┐\n│ a │\n└───┘\n");
+}
+
+#[test]
+fn renders_a_file() {
+    let path = std::env::temp_dir().join(format!("mdview-{}.md", std::process::id()));
+    std::fs::write(&path, "- item\n").unwrap();
+    let output = mdview(&[path.to_str().unwrap()], "ignored");
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "• item\n");
+}
+
+#[test]
+fn options() {
+    let output = mdview(&["--width", "9"], "one two three four\n");
+    assert_eq!(stdout(&output), "one two\nthree\nfour\n");
+    let output = mdview(&["-w", "0"], "one two three four\n");
+    assert_eq!(stdout(&output), "one two three four\n");
+    let output = mdview(&["--color", "--no-links"], "*a*\n");
+    assert_eq!(stdout(&output), "\x1b[0;3ma\x1b[0m\n");
+    let output = mdview(&["--color", "--no-color"], "*a*\n");
+    assert_eq!(stdout(&output), "a\n");
+    // Tables are GitHub Flavored Markdown.
+    let output = mdview(&["--commonmark"], "| a |\n|-|\n");
+    assert_eq!(stdout(&output), "| a | |-|\n");
+    let output = mdview(&["--help"], "");
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("usage: mdview"));
+}
+
+#[test]
+fn errors() {
+    for args in [&["--bogus"][..], &["--width"], &["--width", "wide"]] {
+        let output = mdview(args, "");
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("usage: mdview"));
+    }
+    let output = mdview(&["/nonexistent/file.md"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("mdview: /nonexistent/file.md: "));
+}
@@ -0,0 +1,140 @@
+use markdown_wasm::{
+    parse_markdown, parse_markdown_gfm, parse_markdown_with_options, MarkdownOptions,
+};
+
+/// Turns one option on, with an input it changes and a fragment of the
+/// change.
+type Case = (fn(&mut MarkdownOptions), &'static str, &'static str);
+
+/// Renders `input` with only the options `enable` turns on.
+fn with(enable: impl FnOnce(&mut MarkdownOptions), input: &str) -> String {
+    let mut options = MarkdownOptions::new();
+    enable(&mut options);
+    parse_markdown_with_options(input, &options)
+}
+
+#[test]
+fn each_extension_is_off_until_enabled() {
+    let cases: [Case; 9] = [
+        (|options| options.tables = true, "| a |\n| - |\n", "<table>"),
+        (
+            |options| options.footnotes = true,
+            "x[^1]\n\n[^1]: note\n",
+            "class=\"footnote-definition\"",
+        ),
+        (
+            |options| options.old_footnotes = true,
+            "x[^1]\n\n[^1]: note\n",
+            "class=\"footnote-definition\"",
+        ),
+        (
+            |options| options.strikethrough = true,
+            "~~x~~\n",
+            "<del>x</del>",
+        ),
+        (
+            |options| options.tasklists = true,
+            "- [x] done\n",
+            "type=\"checkbox\"",
+        ),
+        (
+            |options| options.smart_punctuation = true,
+            "\"x\" -- y...\n",
+            "“x” – y…",
+        ),
+        (
+            |options| options.heading_attributes = true,
+            "# A {#b}\n",
+            "<h1 id=\"b\">",
+        ),
+        (
+            |options| options.math = true,
+            "$x$\n",
+            "class=\"math math-inline\"",
+        ),
+        (|options| options.definition_list = true, "a\n: b\n", "<dl>"),
+    ];
+    for (enable, input, expected) in cases {
+        assert!(!parse_markdown(input).contains(expected), "{:?}", input);
+        let html = with(enable, input);
+        assert!(
+            html.contains(expected),
+            "{:?} rendered as {:?}",
+            input,
+            html
+        );
+    }
+}
+
+#[test]
+fn more_extensions() {
+    assert_eq!(
+        with(|options| options.superscript = true, "a ^b^ c\n"),
+        "<p>a <sup>b</sup> c</p>\n"
+    );
+    assert_eq!(
+        with(|options| options.subscript = true, "a ~b~ c\n"),
+        "<p>a <sub>b</sub> c</p>\n"
+    );
+    assert_eq!(
+        with(|options| options.wikilinks = true, "[[Page]]\n"),
+        "<p><a href=\"Page\">Page</a></p>\n"
+    );
+    assert!(with(|options| options.gfm = true, "> [!NOTE]\n> x\n").contains("markdown-alert-note"));
+    assert_eq!(
+        with(
+            |options| options.yaml_metadata_blocks = true,
+            "---\na: 1\n---\nx\n"
+        ),
+        "<p>x</p>\n"
+    );
+    assert_eq!(
+        with(
+            |options| options.pluses_metadata_blocks = true,
+            "+++\na = 1\n+++\nx\n"
+        ),
+        "<p>x</p>\n"
+    );
+}
+
+#[test]
+fn parse_markdown_is_strict_commonmark() {
+    let input = "| a |\n| - |\n\n~~x~~ www.example.com\n";
+    assert_eq!(
+        parse_markdown(input),
+        parse_markdown_with_options(input, &MarkdownOptions::new())
+    );
+    assert_eq!(MarkdownOptions::commonmark(), MarkdownOptions::new());
+    assert_eq!(
+        parse_markdown_gfm(input),
+        parse_markdown_with_options(input, &MarkdownOptions::gfm())
+    );
+}
+
+#[test]
+fn presets() {
+    let extended = MarkdownOptions::extended();
+    assert!(
+        extended.tables
+            && extended.footnotes
+            && extended.strikethrough
+            && extended.tasklists
+            && extended.heading_attributes
+    );
+    assert!(!extended.gfm && !extended.math && !extended.smart_punctuation);
+
+    let gfm = MarkdownOptions::gfm();
+    assert!(gfm.tables && gfm.strikethrough && gfm.tasklists && gfm.footnotes);
+    assert!(gfm.gfm && gfm.autolinks && gfm.tagfilter && gfm.heading_ids);
+    assert!(!gfm.math && !gfm.smart_punctuation && !gfm.heading_attributes);
+
+    // Everything but the old footnote syntax and subscript, which would
+    // take single tildes from strikethrough.
+    let all = MarkdownOptions::all();
+    assert!(all.footnotes && !all.old_footnotes && !all.subscript);
+    assert!(all.math && all.definition_list && all.superscript && all.wikilinks);
+    assert_eq!(
+        parse_markdown_with_options("~x~\n", &all),
+        "<p><del>x</del></p>\n"
+    );
+}
@@ -0,0 +1,73 @@
+use markdown_wasm::{render_plain_text, MarkdownOptions, PlainTextOptions};
+
+fn plain(input: &str, max_length: usize) -> String {
+    let plain = PlainTextOptions {
+        max_length,
+        link_urls: false,
+    };
+    render_plain_text(input, &MarkdownOptions::gfm(), &plain)
+}
+
+#[test]
+fn blocks() {
+    assert_eq!(
+        plain(
+            "# Title\n\nSome *text* and `code`.\n\n- one\n- two\n\n1. first\n\n> quoted\n",
+            0
+        ),
+        "Title\n\nSome text and code.\n\n• one\n• two\n\n1. first\n\nquoted"
+    );
+    assert_eq!(plain("| a | b |\n|-|-|\n| 1 | 2 |\n", 0), "a\tb\n1\t2");
+    assert_eq!(
+        plain("Note[^n].\n\n[^n]: The note.\n", 0),
+        "Note[1].\n\n[1] The note."
+    );
+}
+
+#[test]
+fn link_urls() {
+    let plain = PlainTextOptions {
+        max_length: 0,
+        link_urls: true,
+    };
+    let input = "[text](https://example.com), <https://example.com>, [here](#top) and ![](/a.png)";
+    assert_eq!(
+        render_plain_text(input, &MarkdownOptions::gfm(), &plain),
+        "text (https://example.com), https://example.com, here and /a.png"
+    );
+}
+
+#[test]
+fn truncates_at_a_word_boundary() {
+    // The ellipsis counts towards the length.
+    assert_eq!(plain("one two three", 9), "one two…");
+    assert_eq!(plain("one two three", 8), "one two…");
+    assert_eq!(plain("one two three", 12), "one two…");
+    // Text that fits is left alone.
+    assert_eq!(plain("one two three", 13), "one two three");
+    assert_eq!(plain("one two three", 0), "one two three");
+    // Markup doesn't count.
+    assert_eq!(plain("**one** _two_ three", 8), "one two…");
+    // Line breaks are characters too.
+    assert_eq!(plain("one\n\ntwo three", 8), "one…");
+    assert_eq!(plain("one\n\ntwo three", 10), "one\n\ntwo…");
+}
+
+#[test]
+fn truncates_a_long_word_inside() {
+    assert_eq!(plain("abcdefghij", 5), "abcd…");
+    assert_eq!(plain("abcdefghij klm", 5), "abcd…");
+    assert_eq!(plain("abc", 1), "…");
+}
+
+#[test]
+fn truncates_multibyte_text_by_characters() {
+    assert_eq!(plain("héllo wörld ünïcode", 12), "héllo wörld…");
+    assert_eq!(plain("héllo wörld ünïcode", 11), "héllo…");
+    assert_eq!(plain("日本語のテキストです", 5), "日本語の…");
+    assert_eq!(plain("🦀🦀🦀 🦀🦀", 5), "🦀🦀🦀…");
+    for max in 1..30 {
+        let text = plain("Ünïcode — «quoted» 日本語 🦀 text", max);
+        assert!(text.chars().count() <= max, "{max}: {text:?}");
+    }
+}
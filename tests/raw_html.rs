@@ -0,0 +1,53 @@
+use markdown_wasm::{parse_markdown_with_options, HtmlPolicy, MarkdownOptions};
+
+const INPUT: &str =
+    "<div class=\"box\">\n*not* emphasis\n</div>\n\nInline <b>bold</b> & <!-- note --> text.\n";
+
+fn render(html: HtmlPolicy) -> String {
+    parse_markdown_with_options(
+        INPUT,
+        &MarkdownOptions {
+            html,
+            ..MarkdownOptions::new()
+        },
+    )
+}
+
+#[test]
+fn pass_keeps_raw_html() {
+    assert_eq!(
+        render(HtmlPolicy::Pass),
+        "<div class=\"box\">\n*not* emphasis\n</div>\n<p>Inline <b>bold</b> &amp; <!-- note --> text.</p>\n"
+    );
+}
+
+#[test]
+fn escape_shows_raw_html_as_text() {
+    assert_eq!(
+        render(HtmlPolicy::Escape),
+        "<p>&lt;div class=\"box\"&gt;\n*not* emphasis\n&lt;/div&gt;</p>\n\
+         <p>Inline &lt;b&gt;bold&lt;/b&gt; &amp; &lt;!-- note --&gt; text.</p>\n"
+    );
+}
+
+#[test]
+fn strip_drops_raw_html() {
+    assert_eq!(
+        render(HtmlPolicy::Strip),
+        "<p>Inline bold &amp;  text.</p>\n"
+    );
+}
+
+#[test]
+fn markdown_around_raw_html_is_unaffected() {
+    for html in [HtmlPolicy::Pass, HtmlPolicy::Escape, HtmlPolicy::Strip] {
+        let options = MarkdownOptions {
+            html,
+            ..MarkdownOptions::new()
+        };
+        assert_eq!(
+            parse_markdown_with_options("# *a* `<b>`\n\n    <i>code</i>\n", &options),
+            "<h1><em>a</em> <code>&lt;b&gt;</code></h1>\n<pre><code>&lt;i&gt;code&lt;/i&gt;\n</code></pre>\n"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use markdown_wasm::{parse_markdown_with_options, render, MarkdownOptions};
+use serde_json::{json, Value};
+
+fn metadata(input: &str, options: &MarkdownOptions) -> Value {
+    serde_json::from_str(&render(input, options).json()).unwrap()
+}
+
+#[test]
+fn html_matches_parse_markdown() {
+    let input = "# Title\n\nSee [a](/a) and www.example.com.\n";
+    for options in [
+        MarkdownOptions::new(),
+        MarkdownOptions::gfm(),
+        MarkdownOptions::all(),
+    ] {
+        assert_eq!(
+            render(input, &options).html,
+            parse_markdown_with_options(input, &options)
+        );
+    }
+}
+
+#[test]
+fn title_prefers_front_matter() {
+    let options = MarkdownOptions {
+        front_matter: true,
+        ..MarkdownOptions::new()
+    };
+    let document = "# Heading\n\n# Second\n";
+    assert_eq!(render(document, &options).title.as_deref(), Some("Heading"));
+    let document = "---\ntitle: From front matter\n---\n\n# Heading\n";
+    assert_eq!(
+        render(document, &options).title.as_deref(),
+        Some("From front matter")
+    );
+    assert_eq!(render("## Only level 2\n", &options).title, None);
+}
+
+#[test]
+fn headings_carry_the_rendered_ids() {
+    let input = "# One\n\n## Two\n\n# One\n";
+    let options = MarkdownOptions {
+        heading_ids: true,
+        ..MarkdownOptions::new()
+    };
+    let result = metadata(input, &options);
+    assert_eq!(
+        result["headings"],
+        json!([
+            {
+                "level": 1,
+                "text": "One",
+                "slug": "one",
+                "position": { "start": 0, "end": 6, "line": 1, "endLine": 1 },
+                "children": [{
+                    "level": 2,
+                    "text": "Two",
+                    "slug": "two",
+                    "position": { "start": 7, "end": 14, "line": 3, "endLine": 3 },
+                }],
+            },
+            {
+                "level": 1,
+                "text": "One",
+                "slug": "one-1",
+                "position": { "start": 15, "end": 21, "line": 5, "endLine": 5 },
+            },
+        ])
+    );
+    assert!(result["html"]
+        .as_str()
+        .unwrap()
+        .contains("<h1 id=\"one-1\">"));
+
+    // Without ids in the HTML, the outline has none either.
+    let result = metadata(input, &MarkdownOptions::new());
+    assert_eq!(result["headings"][0]["slug"], "");
+    assert!(!result["html"].as_str().unwrap().contains("id="));
+}
+
+#[test]
+fn links_list_text_and_kind() {
+    let input = "[text *em*](/a \"t\") ![alt](/i.png) [ref]\n\nwww.example.com\n\n[ref]: /r\n";
+    let result = metadata(input, &MarkdownOptions::gfm());
+    let links: Vec<_> = result["links"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|link| {
+            (
+                link["url"].as_str().unwrap(),
+                link["text"].as_str().unwrap(),
+                link["image"].as_bool().unwrap(),
+                link["linkType"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        links,
+        [
+            ("/a", "text em", false, "inline"),
+            ("/i.png", "alt", true, "inline"),
+            ("/r", "ref", false, "shortcut"),
+            (
+                "http://www.example.com",
+                "www.example.com",
+                false,
+                "autolink"
+            ),
+        ]
+    );
+    assert_eq!(result["links"][0]["title"], "t");
+    assert_eq!(result["links"][1].get("title"), None);
+}
+
+#[test]
+fn front_matter_is_parsed() {
+    let options = MarkdownOptions {
+        front_matter: true,
+        ..MarkdownOptions::new()
+    };
+    let result = metadata("+++\ntags = [\"a\"]\n+++\nText\n", &options);
+    assert_eq!(result["frontMatter"], json!({ "tags": ["a"] }));
+    assert_eq!(result["frontMatterFormat"], "toml");
+    assert_eq!(result["html"], "<p>Text</p>\n");
+
+    let result = metadata("+++\ntags = [\"a\"]\n+++\nText\n", &MarkdownOptions::new());
+    assert_eq!(result.get("frontMatter"), None);
+    assert_eq!(result.get("frontMatterFormat"), None);
+}
+
+#[test]
+fn diagnostics_are_sorted_by_position() {
+    let options = MarkdownOptions {
+        front_matter: true,
+        mathml: true,
+        ..MarkdownOptions::all()
+    };
+    let input = "---\ntitle: [unclosed\n---\n\n[a][missing] and $\\frac{1}$\n";
+    let result = metadata(input, &options);
+    let diagnostics: Vec<_> = result["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|diagnostic| {
+            (
+                diagnostic["severity"].as_str().unwrap(),
+                diagnostic["position"]["line"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(diagnostics, [("error", 3), ("warning", 5), ("warning", 5)]);
+    let messages = &result["diagnostics"];
+    assert!(messages[0]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("invalid front matter: "));
+    assert_eq!(messages[1]["message"], "undefined link reference [missing]");
+    assert!(messages[2]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("cannot render math: "));
+    assert_eq!(result.get("frontMatter"), None);
+    assert_eq!(result["frontMatterFormat"], "yaml");
+}
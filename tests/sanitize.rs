@@ -0,0 +1,123 @@
+use markdown_wasm::{
+    parse_markdown_with_options, sanitize_html, HighlightStyle, MarkdownOptions, SanitizerPolicy,
+};
+
+fn sanitized() -> MarkdownOptions {
+    MarkdownOptions {
+        sanitize: true,
+        ..MarkdownOptions::new()
+    }
+}
+
+#[test]
+fn scripts_are_dropped_with_their_content() {
+    assert_eq!(
+        sanitize_html("<p>a<script>alert(1)</script>b</p>"),
+        "<p>ab</p>"
+    );
+    assert_eq!(
+        parse_markdown_with_options("<script>alert(1)</script>\n\ntext\n", &sanitized()),
+        "\n<p>text</p>\n"
+    );
+}
+
+#[test]
+fn event_handlers_are_dropped() {
+    assert_eq!(
+        sanitize_html("<img src=\"x.png\" onerror=\"alert(1)\"><b onclick=\"alert(1)\" OnMouseOver=\"x\">b</b>"),
+        "<img src=\"x.png\"><b>b</b>"
+    );
+}
+
+#[test]
+fn javascript_urls_are_dropped() {
+    assert_eq!(
+        sanitize_html(
+            "<a href=\"JaVaScRiPt:alert(1)\">x</a><a href=\" javascript:alert(1)\">y</a>"
+        ),
+        "<a rel=\"noopener noreferrer\">x</a><a rel=\"noopener noreferrer\">y</a>"
+    );
+    assert_eq!(
+        parse_markdown_with_options(
+            "[x](javascript:alert(1)) ![y](data:text/html,z)\n",
+            &sanitized()
+        ),
+        "<p><a rel=\"noopener noreferrer\">x</a> <img alt=\"y\"></p>\n"
+    );
+}
+
+#[test]
+fn ids_are_prefixed_along_with_the_links_to_them() {
+    let options = MarkdownOptions {
+        heading_ids: true,
+        footnotes: true,
+        ..sanitized()
+    };
+    assert_eq!(
+        parse_markdown_with_options("# Title\n\nSee[^1] [x](#title).\n\n[^1]: note\n", &options),
+        "<h1 id=\"user-content-title\">Title</h1>\n<p>See<sup class=\"footnote-reference\">\
+         <a href=\"#user-content-1\" rel=\"noopener noreferrer\">1</a></sup> \
+         <a href=\"#user-content-title\" rel=\"noopener noreferrer\">x</a>.</p>\n\
+         <div class=\"footnote-definition\" id=\"user-content-1\"><sup class=\"footnote-definition-label\">1</sup>\n\
+         <p>note</p>\n</div>\n"
+    );
+    assert_eq!(
+        sanitize_html("<div id=\"location\"></div><div id=\"user-content-x\"></div><a href=\"#user-content-x\">x</a>"),
+        "<div id=\"user-content-location\"></div><div id=\"user-content-x\"></div>\
+         <a href=\"#user-content-x\" rel=\"noopener noreferrer\">x</a>"
+    );
+}
+
+#[test]
+fn the_id_prefix_can_be_changed_or_turned_off() {
+    let mut policy = SanitizerPolicy::new();
+    policy.set_id_prefix("doc-");
+    assert_eq!(
+        policy.clean("<h2 id=\"a\"><a href=\"#a\">a</a></h2>"),
+        "<h2 id=\"doc-a\"><a href=\"#doc-a\" rel=\"noopener noreferrer\">a</a></h2>"
+    );
+    policy.set_id_prefix("");
+    assert_eq!(policy.clean("<h2 id=\"a\"></h2>"), "<h2 id=\"a\"></h2>");
+}
+
+#[test]
+fn render_options_take_a_custom_policy() {
+    let mut policy = SanitizerPolicy::new();
+    policy.remove_tag("img");
+    policy.allow_attribute("span", "title");
+    let mut options = MarkdownOptions::new();
+    options.set_sanitizer_policy(&policy);
+    assert!(options.sanitize);
+    assert_eq!(
+        parse_markdown_with_options(
+            "![a](b.png) <span title=\"t\" data-x=\"1\">s</span>\n",
+            &options
+        ),
+        "<p> <span title=\"t\">s</span></p>\n"
+    );
+    assert_eq!(
+        parse_markdown_with_options("![a](b.png)\n", &sanitized()),
+        "<p><img src=\"b.png\" alt=\"a\"></p>\n"
+    );
+}
+
+#[test]
+fn inline_highlighting_survives_a_policy_allowing_span_styles() {
+    let mut policy = SanitizerPolicy::new();
+    policy.allow_attribute("span", "style");
+    let mut options = MarkdownOptions {
+        highlight: true,
+        highlight_style: HighlightStyle::Inline,
+        ..MarkdownOptions::new()
+    };
+    options.set_sanitizer_policy(&policy);
+    assert_eq!(
+        parse_markdown_with_options("```rust\nlet x; // y\n```\n", &options),
+        "<pre><code class=\"language-rust\"><span style=\"color:#d73a49\">let</span> x; \
+         <span style=\"color:#6a737d;font-style:italic\">// y</span>\n</code></pre>\n"
+    );
+    assert_eq!(
+        policy.clean("<span style=\"font-weight:bold;position:fixed\">x</span>"),
+        "<span style=\"font-weight:bold\">x</span>"
+    );
+}
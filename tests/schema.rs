@@ -0,0 +1,186 @@
+use markdown_wasm::FrontMatterSchema;
+use serde_json::{json, Value};
+
+fn diagnostics(schema: &Value, input: &str) -> Vec<Value> {
+    let schema = FrontMatterSchema::from_json(&schema.to_string()).unwrap();
+    serde_json::from_str(&schema.validate_json(input)).unwrap()
+}
+
+fn check(schema: &Value, input: &str) -> Vec<(String, String, String, u64)> {
+    diagnostics(schema, input)
+        .into_iter()
+        .map(|d| {
+            (
+                d["keyword"].as_str().unwrap().to_string(),
+                d["instancePath"].as_str().unwrap().to_string(),
+                d["schemaPath"].as_str().unwrap().to_string(),
+                d["line"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+fn diagnostic(
+    keyword: &str,
+    instance: &str,
+    schema: &str,
+    line: u64,
+) -> (String, String, String, u64) {
+    (
+        keyword.to_string(),
+        instance.to_string(),
+        schema.to_string(),
+        line,
+    )
+}
+
+#[test]
+fn schemas_must_be_objects_or_booleans() {
+    assert!(FrontMatterSchema::from_json("true").is_ok());
+    assert_eq!(
+        FrontMatterSchema::from_json("[]").unwrap_err(),
+        "a JSON Schema must be an object or a boolean"
+    );
+    assert!(FrontMatterSchema::from_json("{").is_err());
+}
+
+#[test]
+fn required() {
+    let schema = json!({"required": ["title", "date"]});
+    assert_eq!(
+        check(&schema, "---\ntitle: Post\n---\n"),
+        [diagnostic("required", "", "#/required", 1)]
+    );
+    assert!(check(&schema, "---\ntitle: Post\ndate: 2024-01-01\n---\n").is_empty());
+}
+
+#[test]
+fn type_mismatch() {
+    let schema =
+        json!({"properties": {"draft": {"type": "boolean"}, "weight": {"type": "integer"}}});
+    assert_eq!(
+        check(&schema, "---\ndraft: yes please\nweight: 1.5\n---\n"),
+        [
+            diagnostic("type", "/draft", "#/properties/draft/type", 2),
+            diagnostic("type", "/weight", "#/properties/weight/type", 3),
+        ]
+    );
+    assert!(check(&schema, "---\ndraft: true\nweight: 2\n---\n").is_empty());
+}
+
+#[test]
+fn enum_values() {
+    let schema = json!({"properties": {"kind": {"enum": ["post", "page"]}}});
+    assert_eq!(
+        check(&schema, "---\nkind: note\n---\n"),
+        [diagnostic("enum", "/kind", "#/properties/kind/enum", 2)]
+    );
+    assert!(check(&schema, "---\nkind: page\n---\n").is_empty());
+}
+
+#[test]
+fn pattern() {
+    let schema = json!({"properties": {"slug": {"type": "string", "pattern": "^[a-z-]+$"}}});
+    assert_eq!(
+        check(&schema, "---\nslug: Hello World\n---\n"),
+        [diagnostic(
+            "pattern",
+            "/slug",
+            "#/properties/slug/pattern",
+            2
+        )]
+    );
+    assert!(check(&schema, "---\nslug: hello-world\n---\n").is_empty());
+}
+
+#[test]
+fn formats() {
+    let matches = |format: &str, value: &str| {
+        let schema = json!({"properties": {"v": {"type": "string", "format": format}}});
+        check(&schema, &format!("---\nv: {}\n---\n", json!(value))).is_empty()
+    };
+    let valid = [
+        ("date", "2024-02-29"),
+        ("time", "23:59:60Z"),
+        ("time", "08:30:00.125+05:30"),
+        ("date-time", "2024-01-01T12:00:00-07:00"),
+        ("date-time", "2024-01-01 12:00:00z"),
+        ("email", "someone@example.com"),
+        ("uri", "https://example.com/a?b#c"),
+        ("uri-reference", "../a#b"),
+        ("uuid", "123e4567-e89b-12d3-a456-426614174000"),
+        ("unknown", "anything"),
+    ];
+    for (format, value) in valid {
+        assert!(matches(format, value), "{format} {value}");
+    }
+    let invalid = [
+        ("date", "2023-02-29"),
+        ("date", "2024-1-01"),
+        ("time", "10:00:00"),
+        ("time", "10:00:00.5"),
+        ("time", "24:00:00Z"),
+        ("time", "10:00:00+5:30"),
+        ("date-time", "2024-01-01T12:00:00"),
+        ("date-time", "2024-01-01"),
+        ("email", "someone@localhost"),
+        ("email", "@example.com"),
+        ("uri", "/relative/path"),
+        ("uri-reference", "a b"),
+        ("uuid", "123e4567-e89b-12d3-a456-42661417400g"),
+    ];
+    for (format, value) in invalid {
+        assert!(!matches(format, value), "{format} {value}");
+    }
+}
+
+#[test]
+fn format_diagnostic() {
+    let schema = json!({"properties": {"at": {"type": "string", "format": "time"}}});
+    assert_eq!(
+        check(&schema, "---\nat: \"10:00:00\"\n---\n"),
+        [diagnostic("format", "/at", "#/properties/at/format", 2)]
+    );
+}
+
+#[test]
+fn references() {
+    let schema = json!({
+        "properties": {"tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}}},
+        "$defs": {"tag": {"type": "string", "enum": ["rust", "wasm"]}}
+    });
+    assert_eq!(
+        check(&schema, "---\ntags:\n  - rust\n  - go\n---\n"),
+        [diagnostic("enum", "/tags/1", "#/$defs/tag/enum", 4)]
+    );
+    assert!(check(&schema, "---\ntags: [rust, wasm]\n---\n").is_empty());
+
+    let missing = diagnostics(&json!({"$ref": "#/$defs/nope"}), "---\na: 1\n---\n");
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0]["keyword"], "$ref");
+    assert_eq!(
+        missing[0]["message"],
+        "can't resolve reference #/$defs/nope"
+    );
+}
+
+#[test]
+fn recursive_reference_stops_at_max_depth() {
+    let nested = diagnostics(&json!({"$ref": "#"}), "---\na: 1\n---\n");
+    assert_eq!(nested.len(), 1);
+    assert_eq!(nested[0]["keyword"], "$ref");
+    assert_eq!(nested[0]["message"], "schema is nested too deeply");
+}
+
+#[test]
+fn unparseable_front_matter() {
+    assert_eq!(
+        check(&json!(true), "---\na: 1\n  b: 2\n---\n"),
+        [diagnostic("parse", "", "", 3)]
+    );
+    // A document without front matter is an empty object.
+    assert_eq!(
+        check(&json!({"required": ["a"]}), "text\n"),
+        [diagnostic("required", "", "#/required", 1)]
+    );
+}
@@ -0,0 +1,253 @@
+use markdown_wasm::{
+    edit_table, format_tables, parse_markdown_gfm, MarkdownOptions, TableOperation,
+};
+
+const TABLE: &str = "| a | b |\n|---|---|\n| 1 | 2 |\n| 3 | 4 |\n";
+
+/// The UTF-16 offset of the `2` cell in `TABLE`.
+const CELL: usize = 26;
+
+fn format(input: &str) -> String {
+    format_tables(input, &MarkdownOptions::gfm())
+}
+
+/// `TABLE` after `operation` with the cursor in the `2` cell, and the
+/// cursor's new line and column.
+fn edit(operation: TableOperation) -> (String, (usize, usize)) {
+    let edit = edit_table(TABLE, &MarkdownOptions::gfm(), CELL, operation)
+        .expect("the cursor is in the table");
+    let before = &edit.text[..edit.cursor];
+    let line = before.matches('\n').count();
+    let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1);
+    (edit.text, (line, column))
+}
+
+#[test]
+fn reflow() {
+    assert_eq!(
+        format("| a | b |\n|-|:-:|\n| y | long cell |\n|z|\n"),
+        "| a   |     b     |\n| --- | :-------: |\n| y   | long cell |\n| z   |           |\n"
+    );
+    assert_eq!(
+        format("a|b\n:-|-:\nleft|right\n"),
+        "| a    |     b |\n| :--- | ----: |\n| left | right |\n"
+    );
+    // Cells past the header's columns don't render and are kept as written.
+    assert_eq!(
+        format("| a |\n|-|\n| 1 | extra |\n"),
+        "| a   |\n| --- |\n| 1   | extra |\n"
+    );
+    // Text around the tables is left alone.
+    assert_eq!(
+        format("Before\n\n|a|\n|-|\n\nbetween  \n\n|b|\n|-|\n\nafter"),
+        "Before\n\n| a   |\n| --- |\n\nbetween  \n\n| b   |\n| --- |\n\nafter"
+    );
+}
+
+#[test]
+fn reflow_is_idempotent_and_keeps_the_html() {
+    for input in [
+        "| a | b |\n|-|:-:|\n| 中文 | x |\n| y | long cell |\n",
+        "| a | b |\n|--|--|\n| `x \\| y` | z\\|w |\n",
+        "- item\n\n  | a | b |\n  |-|-|\n  | 1 | 2 |\n",
+        "> | a | b |\n> |-|-|\n> | 1 | 2 |\n",
+        "- | a | b |\n  |-|-|\n  | 1 | 2 |\n",
+        "| a | b |\n|:-:|-:|\n| *x* | [link](/url) |\n| | |\n",
+    ] {
+        let formatted = format(input);
+        assert_eq!(format(&formatted), formatted, "{input:?}");
+        assert_eq!(
+            parse_markdown_gfm(&formatted),
+            parse_markdown_gfm(input),
+            "{input:?}"
+        );
+    }
+}
+
+#[test]
+fn wide_characters() {
+    // CJK characters take two columns each, others one.
+    assert_eq!(
+        format("| a | b |\n|-|-|\n| 中文 | x |\n| y | é |\n"),
+        "| a    | b   |\n| ---- | --- |\n| 中文 | x   |\n| y    | é   |\n"
+    );
+    // The cursor is a UTF-16 offset.
+    let input = "| 中文 | b |\n|-|-|\n| 𝒳 | 2 |\n";
+    let edit = edit_table(input, &MarkdownOptions::gfm(), 25, TableOperation::Format).unwrap();
+    assert_eq!(
+        edit.text,
+        "| 中文 | b   |\n| ---- | --- |\n| 𝒳    | 2   |\n"
+    );
+    let cell = edit.text.find("2   |").unwrap();
+    assert_eq!(edit.cursor, edit.text[..cell].encode_utf16().count());
+}
+
+#[test]
+fn escaped_pipes() {
+    assert_eq!(
+        format("| a | b |\n|--|--|\n| `x \\| y` | z\\|w |\n"),
+        "| a        | b    |\n| -------- | ---- |\n| `x \\| y` | z\\|w |\n"
+    );
+    let (text, cursor) = edit(TableOperation::Format);
+    assert_eq!(text, format(TABLE));
+    assert_eq!(cursor, (2, 8));
+    let input = "| a\\|b | c |\n|-|-|\n| 1 | 2 |\n";
+    let edit = edit_table(
+        input,
+        &MarkdownOptions::gfm(),
+        2,
+        TableOperation::MoveColumnRight,
+    )
+    .unwrap();
+    assert_eq!(
+        edit.text,
+        "| c   | a\\|b |\n| --- | ---- |\n| 2   | 1    |\n"
+    );
+}
+
+#[test]
+fn containers() {
+    assert_eq!(
+        format("- item\n\n  | a | b |\n  |-|-|\n  | 1 | 2 |\n"),
+        "- item\n\n  | a   | b   |\n  | --- | --- |\n  | 1   | 2   |\n"
+    );
+    assert_eq!(
+        format("- | a | b |\n  |-|-|\n  | 1 | 2 |\n"),
+        "- | a   | b   |\n  | --- | --- |\n  | 1   | 2   |\n"
+    );
+    assert_eq!(
+        format("> | a | b |\n> |-|-|\n> | 1 | 2 |\n"),
+        "> | a   | b   |\n> | --- | --- |\n> | 1   | 2   |\n"
+    );
+    assert_eq!(
+        format("> - | a |\n>   |-|\n>   | 1 |\n"),
+        "> - | a   |\n>   | --- |\n>   | 1   |\n"
+    );
+
+    let input = "> | a | b |\n> |-|-|\n> | 1 | 2 |\n";
+    let edit = edit_table(
+        input,
+        &MarkdownOptions::gfm(),
+        28,
+        TableOperation::InsertRowBelow,
+    )
+    .unwrap();
+    assert_eq!(
+        edit.text,
+        "> | a   | b   |\n> | --- | --- |\n> | 1   | 2   |\n> |     |     |\n"
+    );
+    let input = "- | a | b |\n  |-|-|\n  | 1 | 2 |\n";
+    let edit = edit_table(
+        input,
+        &MarkdownOptions::gfm(),
+        4,
+        TableOperation::DeleteColumn,
+    )
+    .unwrap();
+    assert_eq!(edit.text, "- | b   |\n  | --- |\n  | 2   |\n");
+}
+
+#[test]
+fn columns() {
+    assert_eq!(
+        edit(TableOperation::InsertColumnLeft),
+        (
+            "| a   |     | b   |\n| --- | --- | --- |\n| 1   |     | 2   |\n| 3   |     | 4   |\n"
+                .into(),
+            (2, 8)
+        )
+    );
+    assert_eq!(
+        edit(TableOperation::InsertColumnRight),
+        (
+            "| a   | b   |     |\n| --- | --- | --- |\n| 1   | 2   |     |\n| 3   | 4   |     |\n"
+                .into(),
+            (2, 14)
+        )
+    );
+    assert_eq!(
+        edit(TableOperation::DeleteColumn),
+        ("| a   |\n| --- |\n| 1   |\n| 3   |\n".into(), (2, 2))
+    );
+    assert_eq!(
+        edit(TableOperation::MoveColumnLeft),
+        (
+            "| b   | a   |\n| --- | --- |\n| 2   | 1   |\n| 4   | 3   |\n".into(),
+            (2, 2)
+        )
+    );
+    // The last column can't move further right.
+    assert_eq!(
+        edit(TableOperation::MoveColumnRight),
+        (format(TABLE), (2, 8))
+    );
+}
+
+#[test]
+fn rows() {
+    assert_eq!(
+        edit(TableOperation::InsertRowAbove),
+        (
+            "| a   | b   |\n| --- | --- |\n|     |     |\n| 1   | 2   |\n| 3   | 4   |\n".into(),
+            (2, 8)
+        )
+    );
+    assert_eq!(
+        edit(TableOperation::InsertRowBelow),
+        (
+            "| a   | b   |\n| --- | --- |\n| 1   | 2   |\n|     |     |\n| 3   | 4   |\n".into(),
+            (3, 8)
+        )
+    );
+    assert_eq!(
+        edit(TableOperation::DeleteRow),
+        (
+            "| a   | b   |\n| --- | --- |\n| 3   | 4   |\n".into(),
+            (2, 8)
+        )
+    );
+    // The header counts as the first row.
+    assert_eq!(
+        edit(TableOperation::MoveRowUp),
+        (
+            "| 1   | 2   |\n| --- | --- |\n| a   | b   |\n| 3   | 4   |\n".into(),
+            (0, 8)
+        )
+    );
+    assert_eq!(
+        edit(TableOperation::MoveRowDown),
+        (
+            "| a   | b   |\n| --- | --- |\n| 3   | 4   |\n| 1   | 2   |\n".into(),
+            (3, 8)
+        )
+    );
+}
+
+#[test]
+fn removing_the_last_row_or_column_removes_the_table() {
+    let options = MarkdownOptions::gfm();
+    let input = "Before\n\n| a |\n|-|\n\nAfter\n";
+    let edit = edit_table(input, &options, 10, TableOperation::DeleteColumn).unwrap();
+    assert_eq!(
+        (edit.text.as_str(), edit.cursor),
+        ("Before\n\n\nAfter\n", 8)
+    );
+    let edit = edit_table(input, &options, 10, TableOperation::DeleteRow).unwrap();
+    assert_eq!(edit.text, "Before\n\n\nAfter\n");
+}
+
+#[test]
+fn cursor_outside_a_table() {
+    let options = MarkdownOptions::gfm();
+    let input = "Before\n\n| a |\n|-|\n\nAfter\n";
+    assert!(edit_table(input, &options, 2, TableOperation::Format).is_none());
+    assert!(edit_table(input, &options, input.len(), TableOperation::Format).is_none());
+    // Tables are found whether or not `options` enables them.
+    assert!(edit_table(
+        input,
+        &MarkdownOptions::default(),
+        10,
+        TableOperation::Format
+    )
+    .is_some());
+}
@@ -0,0 +1,108 @@
+use markdown_wasm::{heading_outline_json, parse_markdown_with_options, MarkdownOptions};
+use serde_json::Value;
+
+fn toc() -> MarkdownOptions {
+    MarkdownOptions {
+        toc: true,
+        ..MarkdownOptions::new()
+    }
+}
+
+#[test]
+fn placeholder_becomes_a_nested_list() {
+    assert_eq!(
+        parse_markdown_with_options("[TOC]\n\n# A\n\n### Deep\n\n## B\n\n# C\n", &toc()),
+        "<ul>\n<li><a href=\"#a\">A</a>\n<ul>\n<li><a href=\"#deep\">Deep</a></li>\n\
+         <li><a href=\"#b\">B</a></li>\n</ul>\n</li>\n<li><a href=\"#c\">C</a></li>\n</ul>\n\
+         <h1 id=\"a\">A</h1>\n<h3 id=\"deep\">Deep</h3>\n<h2 id=\"b\">B</h2>\n<h1 id=\"c\">C</h1>\n"
+    );
+}
+
+#[test]
+fn comment_placeholder_and_levels() {
+    let options = MarkdownOptions {
+        toc_min_level: 2,
+        toc_max_level: 2,
+        ..toc()
+    };
+    assert_eq!(
+        parse_markdown_with_options(
+            "# Title\n\n<!--  TOC -->\n\n## One\n\n### Skipped\n\n## Two\n",
+            &options
+        ),
+        "<h1 id=\"title\">Title</h1>\n<ul>\n<li><a href=\"#one\">One</a></li>\n\
+         <li><a href=\"#two\">Two</a></li>\n</ul>\n<h2 id=\"one\">One</h2>\n\
+         <h3 id=\"skipped\">Skipped</h3>\n<h2 id=\"two\">Two</h2>\n"
+    );
+}
+
+#[test]
+fn placeholder_without_headings_is_removed() {
+    assert_eq!(
+        parse_markdown_with_options("[toc]\n\ntext\n", &toc()),
+        "<p>text</p>\n"
+    );
+}
+
+#[test]
+fn other_paragraphs_are_not_placeholders() {
+    assert_eq!(
+        parse_markdown_with_options("[TOC] here\n\n*[TOC]*\n\n# A\n", &toc()),
+        "<p>[TOC] here</p>\n<p><em>[TOC]</em></p>\n<h1 id=\"a\">A</h1>\n"
+    );
+    assert_eq!(
+        parse_markdown_with_options("[TOC]\n", &MarkdownOptions::new()),
+        "<p>[TOC]</p>\n"
+    );
+}
+
+#[test]
+fn entries_use_the_deduplicated_ids() {
+    assert_eq!(
+        parse_markdown_with_options("[TOC]\n\n# A\n\n# A\n", &toc()),
+        "<ul>\n<li><a href=\"#a\">A</a></li>\n<li><a href=\"#a-1\">A</a></li>\n</ul>\n\
+         <h1 id=\"a\">A</h1>\n<h1 id=\"a-1\">A</h1>\n"
+    );
+}
+
+fn outline(input: &str) -> Value {
+    serde_json::from_str(&heading_outline_json(input, &MarkdownOptions::new())).unwrap()
+}
+
+/// The slugs of `entries`, each followed by its children's.
+fn summary(entries: &Value) -> Vec<String> {
+    entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| match entry.get("children") {
+            None => entry["slug"].as_str().unwrap().to_string(),
+            Some(children) => format!(
+                "{} {:?}",
+                entry["slug"].as_str().unwrap(),
+                summary(children)
+            ),
+        })
+        .collect()
+}
+
+#[test]
+fn outline_nests_under_the_closest_lower_level() {
+    let outline = outline("## Skipped level\n\n# A\n\n### A.1\n\n## A.2\n\n# B *b*\n\n#### B.1\n");
+    assert_eq!(
+        summary(&outline),
+        ["skipped-level", "a [\"a1\", \"a2\"]", "b-b [\"b1\"]"]
+    );
+    assert_eq!(outline[2]["text"], "B b");
+    assert_eq!(outline[2]["level"], 1);
+}
+
+#[test]
+fn outline_positions_are_utf16() {
+    let outline = outline("é 😀\n\n## Two\n");
+    let position = &outline[0]["position"];
+    assert_eq!(
+        (&position["start"], &position["end"], &position["line"]),
+        (&Value::from(6), &Value::from(13), &Value::from(3))
+    );
+}
@@ -0,0 +1,297 @@
+mod common;
+
+use common::check;
+use markdown_wasm::{parse_markdown_with_options, HtmlPolicy, MarkdownOptions};
+
+const DOCUMENTS: &[&str] = &[
+    "# Title\n\nSome *emphasis*, **strong**, `code` and a [link](/a?b=1&c=2 \"t\").\n",
+    "Hard  \nbreak\n\n---\n\n![image](/img.png \"a & b\")\n",
+    "- [x] done\n- [ ] todo\n\n1. one\n2. two\n",
+    "| a | b |\n|:-|-:|\n| 1 | 2 |\n",
+    "> [!NOTE]\n> An alert.\n\n> quote\n",
+    "Footnote[^1].\n\n[^1]: The note.\n",
+    "```rust\nfn main() { let x = 1 < 2 && true; }\n```\n",
+    "Math $x^2 < y$ and\n\n$$\n\\frac{a}{b}\n$$\n",
+    "term\n: definition\n\nH~2~O and x^2^\n",
+    "[TOC]\n\n## One\n\n## Two\n",
+    "&copy; &nbsp; &amp; &#169; &#xA9; &bogus; AT&T\n",
+];
+
+const RAW_HTML: &[&str] = &[
+    "<div><p>unclosed\n\ntext",
+    "stray </span> end tag and </div>",
+    "<img src=x.png alt=hi> <br> <hr> <input type=checkbox disabled checked>",
+    "<a href=foo title='say \"hi\"' TITLE=dup>link</a>",
+    "&nbsp; &copy; &unknown; & &#0; &#x1F600; &#xD800; &#;",
+    "<!-- a -- b --->\n\n<!DOCTYPE html>\n\n<?php echo 1; ?>",
+    "<script>if (a < b && c) { x = '</div>'; }</script>",
+    "<style>p > a { color: red }</style>",
+    "<svg viewBox=\"0 0 1 1\"><use xlink:href=\"#a\"/><linearGradient id=g></linearGradient></svg>",
+    "<x:y>prefixed</x:y> <a@b> <3 and a<b, 1 < 2",
+    "<DIV CLASS=a>upper</DIV>",
+    "<![CDATA[ <raw> & ]]> <![CDATA[ unterminated",
+    "<span class=\"a\" class=\"b\" data-x=1 :bad=1 on:click=2>attrs</span>",
+    "<table><tr><td>cell</table>",
+    "<p>one<p>two</p></p>",
+    "<b><i>misnested</b></i>",
+    "<div\n",
+    "<a href=\"unterminated>text",
+    "\u{1}control\u{8} characters\u{fffe}",
+];
+
+fn presets() -> Vec<MarkdownOptions> {
+    let xhtml = |options: MarkdownOptions| MarkdownOptions {
+        xhtml: true,
+        ..options
+    };
+    vec![
+        xhtml(MarkdownOptions::new()),
+        xhtml(MarkdownOptions::gfm()),
+        xhtml(MarkdownOptions {
+            mathml: true,
+            subscript: true,
+            superscript: true,
+            toc: true,
+            heading_anchors: true,
+            highlight: true,
+            source_positions: true,
+            ..MarkdownOptions::all()
+        }),
+        xhtml(MarkdownOptions {
+            sanitize: true,
+            ..MarkdownOptions::all()
+        }),
+        xhtml(MarkdownOptions {
+            html: HtmlPolicy::Escape,
+            ..MarkdownOptions::gfm()
+        }),
+    ]
+}
+
+fn assert_well_formed(input: &str, options: &MarkdownOptions) {
+    let html = parse_markdown_with_options(input, options);
+    if let Err(error) = check(&html) {
+        panic!(
+            "{}\n\ninput: {:?}\noptions: {:?}\noutput:\n{}",
+            error, input, options, html
+        );
+    }
+}
+
+#[test]
+fn the_check_rejects_plain_html() {
+    let options = MarkdownOptions::new();
+    for html in [
+        "<br>",
+        "<p><b>x</p>",
+        "&nbsp;",
+        "a & b",
+        "<a href=x>",
+        "<!-- a -- b -->",
+    ] {
+        assert!(check(html).is_err(), "{:?}", html);
+    }
+    for html in &RAW_HTML[..4] {
+        assert!(
+            check(&parse_markdown_with_options(html, &options)).is_err(),
+            "{:?}",
+            html
+        );
+    }
+}
+
+#[test]
+fn documents_render_as_well_formed_xml() {
+    for options in presets() {
+        for document in DOCUMENTS {
+            assert_well_formed(document, &options);
+        }
+    }
+}
+
+#[test]
+fn raw_html_is_reserialized_as_well_formed_xml() {
+    for options in presets() {
+        for html in RAW_HTML {
+            assert_well_formed(html, &options);
+            // Raw HTML inline, and split across blocks around Markdown.
+            assert_well_formed(&format!("Text {} *more*\n", html), &options);
+            assert_well_formed(&format!("{}\n\n- *item*\n\n</div>\n", html), &options);
+        }
+    }
+}
+
+#[test]
+fn random_markup_is_well_formed() {
+    let pieces = [
+        "<div>",
+        "</div>",
+        "<p>",
+        "</p>",
+        "<span a=1>",
+        "</span>",
+        "<br>",
+        "<img src=x>",
+        "<b>",
+        "</i>",
+        "<!--",
+        "-->",
+        "<![CDATA[",
+        "]]>",
+        "&amp;",
+        "&nbsp;",
+        "&",
+        "<",
+        ">",
+        "\"",
+        "'",
+        "=",
+        "*",
+        "_",
+        "`",
+        "\n",
+        "\n\n",
+        " ",
+        "text",
+        "- ",
+        "> ",
+        "# ",
+        "|",
+        "<script>",
+        "</script>",
+        "<svg>",
+        "</svg>",
+        "<a",
+        "&#",
+        "x;",
+        "[",
+        "](",
+        ")",
+        "\\",
+    ];
+    let mut seed: u64 = 0x2545f4914f6cdd1d;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    let options = presets();
+    for _ in 0..500 {
+        let length = next() % 24;
+        let input: String = (0..length)
+            .map(|_| pieces[(next() % pieces.len() as u64) as usize])
+            .collect();
+        assert_well_formed(&input, &options[(next() % options.len() as u64) as usize]);
+    }
+}
+
+#[test]
+fn renderer_markup_is_unchanged() {
+    for document in DOCUMENTS.iter().filter(|document| !document.contains('&')) {
+        let options = MarkdownOptions::gfm();
+        let xhtml = MarkdownOptions {
+            xhtml: true,
+            ..options.clone()
+        };
+        // Only the spacing of self-closing tags differs.
+        assert_eq!(
+            parse_markdown_with_options(document, &xhtml),
+            parse_markdown_with_options(document, &options).replace("\"/>", "\" />"),
+            "{:?}",
+            document
+        );
+    }
+}
+
+#[test]
+fn named_references_become_numeric() {
+    let options = MarkdownOptions {
+        xhtml: true,
+        ..MarkdownOptions::new()
+    };
+    let input = "<div title=\"&eacute;\">&nbsp;&copy;&amp;&lt;&bogus;</div>\n";
+    let html = parse_markdown_with_options(input, &options);
+    assert_eq!(
+        html,
+        "<div title=\"&#233;\">&#160;&#169;&amp;&lt;&amp;bogus;</div>\n"
+    );
+}
+
+#[test]
+fn raw_html_is_repaired() {
+    let options = MarkdownOptions {
+        xhtml: true,
+        ..MarkdownOptions::new()
+    };
+    let cases = [
+        (
+            "<div>\n<img src=a.png alt=x>\n",
+            "<div>\n<img src=\"a.png\" alt=\"x\" />\n</div>",
+        ),
+        ("<input disabled>\n", "<input disabled=\"disabled\" />\n"),
+        ("a </span> b\n", "<p>a  b</p>\n"),
+        ("<b><i>x</b></i>\n", "<p><b><i>x</i></b></p>\n"),
+        ("<!-- a -- b -->\n", "<!-- a - - b -->\n"),
+        ("<script>a < b</script>\n", "<script>a &lt; b</script>\n"),
+        (
+            "<svg viewBox=\"0 0 1 1\"><use xlink:href=\"#a\"/></svg>\n",
+            "<p><svg viewBox=\"0 0 1 1\" xmlns=\"http://www.w3.org/2000/svg\"><use \
+             xmlns:xlink=\"http://www.w3.org/1999/xlink\" xlink:href=\"#a\" /></svg></p>\n",
+        ),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(
+            parse_markdown_with_options(input, &options),
+            expected,
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn unclosed_raw_html_ends_with_its_block() {
+    let options = MarkdownOptions {
+        xhtml: true,
+        ..MarkdownOptions::new()
+    };
+    let cases = [
+        (
+            "<div>\n<p>unclosed\n\n*text*\n",
+            "<div>\n<p>unclosed\n</p></div>\n<p><em>text</em></p>\n",
+        ),
+        (
+            "<div class=\"note\">\n\n*text*\n\n</div>\n",
+            "<div class=\"note\">\n</div>\n<p><em>text</em></p>\n",
+        ),
+        (
+            "<span>open *text*\n\nnext *paragraph*\n",
+            "<p><span>open <em>text</em></span></p>\n<p>next <em>paragraph</em></p>\n",
+        ),
+        ("*a <b>b* c</b>\n", "<p><em>a <b>b</b></em> c</p>\n"),
+        (
+            "a <span>b *c*</span> d\n",
+            "<p>a <span>b <em>c</em></span> d</p>\n",
+        ),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(
+            parse_markdown_with_options(input, &options),
+            expected,
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn escaped_html_stays_escaped() {
+    let options = MarkdownOptions {
+        xhtml: true,
+        html: HtmlPolicy::Escape,
+        ..MarkdownOptions::new()
+    };
+    let html = parse_markdown_with_options("<div>&nbsp;</div>\n", &options);
+    assert_eq!(html, "<p>&lt;div&gt;&amp;nbsp;&lt;/div&gt;</p>\n");
+}